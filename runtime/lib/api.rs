@@ -1,11 +1,23 @@
+mod call_graph;
+mod cfg;
+mod engine;
+mod exec_buffer;
+mod func;
 mod imports;
 mod instance;
 mod module;
 mod options;
+mod snapshot;
 mod store;
 
+pub use call_graph::*;
+pub use cfg::*;
+pub use engine::*;
+pub use exec_buffer::*;
+pub use func::*;
 pub use imports::*;
 pub use instance::*;
 pub use module::*;
 pub use options::*;
+pub use snapshot::*;
 pub use store::*;
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A directed graph of function dependencies in a module.
+///
+/// It is built by scanning function bodies for `call`, `call_indirect` and `ref.func` operators.
+/// Nodes are function indices, imported functions included.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct CallGraph {
+    pub(crate) inner: HashMap<u32, Vec<Dependency>>,
+}
+
+/// An outgoing edge of a function in the `CallGraph`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Dependency {
+    /// The function is called or referenced directly.
+    Function(u32),
+    /// Any funcref in the table can be called indirectly.
+    AnyInTable(u32),
+}
+
+impl CallGraph {
+    /// Gets the dependencies of the function with the given index.
+    pub fn dependencies(&self, function_index: u32) -> &[Dependency] {
+        self.inner
+            .get(&function_index)
+            .map(|deps| &deps[..])
+            .unwrap_or(&[])
+    }
+
+    /// Adds an edge from a function to its dependency, ignoring duplicates.
+    pub(crate) fn add(&mut self, function_index: u32, dependency: Dependency) {
+        let deps = self.inner.entry(function_index).or_default();
+        if !deps.contains(&dependency) {
+            deps.push(dependency);
+        }
+    }
+}
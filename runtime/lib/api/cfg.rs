@@ -0,0 +1,33 @@
+/// The control-flow graph of the LLVM code generated for a function.
+///
+/// Blocks are in the order they were appended to the function, so the entry block comes first.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cfg {
+    pub blocks: Vec<CfgBlock>,
+}
+
+/// A basic block in the `Cfg`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfgBlock {
+    /// The name of the block, which LLVM suffixes with a number when it is taken.
+    pub name: String,
+    /// Names of the blocks the terminator of the block can branch to.
+    pub successors: Vec<String>,
+    /// Whether the block ends with a terminator, which every block of valid code does.
+    pub is_terminated: bool,
+}
+
+impl Cfg {
+    /// Gets the block with the given name.
+    pub fn block(&self, name: &str) -> Option<&CfgBlock> {
+        self.blocks.iter().find(|block| block.name == name)
+    }
+
+    /// Gets the blocks that can branch to the block with the given name.
+    pub fn predecessors(&self, name: &str) -> Vec<&CfgBlock> {
+        self.blocks
+            .iter()
+            .filter(|block| block.successors.iter().any(|successor| successor == name))
+            .collect()
+    }
+}
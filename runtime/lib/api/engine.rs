@@ -0,0 +1,308 @@
+use std::{cell::Cell, mem, rc::Rc};
+
+use anyhow::Result;
+
+use crate::{
+    compiler::{
+        llvm::{
+            context::LLContext,
+            module::{
+                data_initializer_symbol, element_initializer_symbol, trampoline_symbol,
+                IMPORTED_FUNCTIONS_SYMBOL, MEMORY_BASE_SYMBOL, MEMORY_SIZE_SYMBOL, MEMORY_SYMBOL,
+                TABLE_BASE_SYMBOL, TABLE_SIZE_SYMBOL, TABLE_SLOTS_SYMBOL,
+            },
+            orc::LLJit,
+        },
+        DataKind, ElementKind, InitExpr,
+    },
+    errors::{CompilerError, TrapCode},
+    intrinsics::{
+        call_import, libc_symbols, memory_grow, raise_trap, table_fill, table_grow,
+        ImportedFunctions, CALL_IMPORT_SYMBOL, MEMORY_GROW_SYMBOL, RAISE_TRAP_SYMBOL,
+        TABLE_FILL_SYMBOL, TABLE_GROW_SYMBOL,
+    },
+    Memory, Module, Options,
+};
+
+/// An Engine JIT-compiles modules and links them against symbols defined by the host.
+///
+/// Imported globals are linked against the symbol named `module.field` of the import.
+#[derive(Debug)]
+pub struct Engine {
+    jit: LLJit,
+    /// Number of functions replaced so far, which keeps the symbols of replacements unique.
+    replacements: Cell<usize>,
+    /// The LLVM context shared by modules compiled with `compile`.
+    context: Rc<LLContext>,
+}
+
+impl Engine {
+    /// Creates a new `Engine` for the host.
+    pub fn new() -> Result<Self> {
+        let jit = LLJit::new()?;
+        jit.define_absolute_symbol(RAISE_TRAP_SYMBOL, raise_trap as *const () as usize)?;
+        jit.define_absolute_symbol(MEMORY_GROW_SYMBOL, memory_grow as *const () as usize)?;
+        jit.define_absolute_symbol(TABLE_GROW_SYMBOL, table_grow as *const () as usize)?;
+        jit.define_absolute_symbol(TABLE_FILL_SYMBOL, table_fill as *const () as usize)?;
+        jit.define_absolute_symbol(CALL_IMPORT_SYMBOL, call_import as *const () as usize)?;
+        for (name, address) in libc_symbols() {
+            jit.define_absolute_symbol(name, address)?;
+        }
+
+        Ok(Self {
+            jit,
+            replacements: Cell::new(0),
+            context: Rc::new(LLContext::new()),
+        })
+    }
+
+    /// Defines a symbol at `address` that modules loaded afterwards can link against.
+    ///
+    /// # Safety
+    /// The engine does not check what lives at `address`.
+    /// It must point to a value of the type the module expects and stay valid for as long as the module's code runs.
+    pub fn define_symbol(&self, name: &str, address: usize) -> Result<()> {
+        self.jit.define_absolute_symbol(name, address)
+    }
+
+    /// Compiles a module in a context pooled by the engine rather than in a context of its own.
+    ///
+    /// A context holds types and constants shared by its modules, so pooling it saves memory when compiling many modules.
+    /// Symbol names are scoped to their module so modules sharing the context never alias each other's symbols.
+    /// Loading modules that define the same symbols into one engine still needs them to be prefixed.
+    pub fn compile(&self, wasm: &[u8], options: Options) -> Result<Module> {
+        // TODO(appcypher): Prefix symbols so that modules sharing a context can be loaded together.
+        Module::new_in_context(wasm, options, Rc::clone(&self.context))
+    }
+
+    /// Adds the code of a compiled module to the engine.
+    ///
+    /// Symbols the module references must be defined before its functions are looked up.
+    ///
+    /// With `Options.hot_patching`, functions can be replaced with `replace_function` afterwards.
+    /// With `Options.lazy_compilation`, each function is compiled on its first call.
+    /// Otherwise the whole module is compiled when the first of its functions is looked up.
+    pub fn load(&self, module: &Module) -> Result<()> {
+        let llvm_module = module.compiler().llvm_module()?;
+        if module.options.hot_patching {
+            // TODO(appcypher): Compile patchable modules lazily as well.
+            self.jit.add_patchable_module(llvm_module)
+        } else if module.options.lazy_compilation {
+            self.jit.add_lazy_module(llvm_module)
+        } else {
+            self.jit.add_module(llvm_module)
+        }
+    }
+
+    /// Replaces the code of the exported function `name` of a loaded module with that of the same function in `wasm`.
+    ///
+    /// `wasm` is an updated version of the module, which must have the same imports, memories and functions in the same order,
+    /// with functions named the same in the name section.
+    /// Only the code of `name` is compiled and taken from it.
+    /// Calls made afterwards, including those through addresses looked up before, run the new code.
+    /// Memories are left as they are.
+    ///
+    /// The module must have been loaded with `Options.hot_patching`.
+    pub fn replace_function(&self, module: &Module, name: &str, wasm: &[u8]) -> Result<()> {
+        if !module.options.hot_patching {
+            return Err(CompilerError::ModuleNotPatchable.into());
+        }
+
+        let replacement = self.compile(wasm, module.options.clone())?;
+        let index = module.exported_function_index(name)?;
+        let replacement_index = replacement.exported_function_index(name)?;
+
+        let info = &module.compiler().info;
+        let replacement_info = &replacement.compiler().info;
+        let ty = &info.types[info.functions[index as usize].type_index as usize];
+        let replacement_ty = &replacement_info.types
+            [replacement_info.functions[replacement_index as usize].type_index as usize];
+
+        if ty != replacement_ty {
+            return Err(CompilerError::TypeMismatch(format!("replacement of {:?}", name)).into());
+        }
+
+        let generation = self.replacements.get() + 1;
+        self.jit.replace_function(
+            replacement.compiler().llvm_module()?,
+            &replacement_info.function_symbol(replacement_index),
+            &info.function_symbol(index),
+            generation,
+        )?;
+
+        self.replacements.set(generation);
+
+        Ok(())
+    }
+
+    /// Points memory 0 of the loaded module at `memory`, whose size accesses are checked against.
+    ///
+    /// `memory.grow` grows the memory in place and points the module at where it moved.
+    ///
+    /// # Safety
+    /// The memory must outlive calls into the module and must not be grown by the host while the module's code runs.
+    /// Growing it may move it, in which case the memory base has to be set again.
+    pub fn set_memory_base(&self, memory: &mut Memory) -> Result<()> {
+        let global = self.jit.lookup(MEMORY_SYMBOL)?;
+        unsafe { *(global as *mut *mut Memory) = memory };
+
+        let global = self.jit.lookup(MEMORY_BASE_SYMBOL)?;
+        unsafe { *(global as *mut *mut u8) = memory.as_mut_ptr() };
+
+        let global = self.jit.lookup(MEMORY_SIZE_SYMBOL)?;
+        unsafe { *(global as *mut u64) = memory.data().len() as u64 };
+
+        Ok(())
+    }
+
+    /// Points memory 0 of the loaded module at `base`, keeping the size `set_memory_base` set.
+    ///
+    /// # Safety
+    /// `base` must point to at least as many bytes as the memory last set and outlive calls into the module.
+    /// `memory.grow` points the module back at the memory it grows.
+    pub(crate) fn override_memory_base(&self, base: usize) -> Result<()> {
+        let global = self.jit.lookup(MEMORY_BASE_SYMBOL)?;
+        unsafe { *(global as *mut usize) = base };
+
+        Ok(())
+    }
+
+    /// Points table 0 of the loaded module at `slots`, whose length accesses are checked against.
+    ///
+    /// Each slot holds a funcref or the handle of an externref, depending on the element type of the table, and is zero
+    /// for a null reference. `table.grow` grows the vector in place.
+    ///
+    /// A funcref is the address of a constant of the module pairing the address of a function with the id of its type,
+    /// which calls through the slot check against the type they expect.
+    ///
+    /// # Safety
+    /// The slots must outlive calls into the module and must not be resized while the module's code runs.
+    pub fn set_table_base(&self, slots: &mut Vec<usize>) -> Result<()> {
+        let global = self.jit.lookup(TABLE_SLOTS_SYMBOL)?;
+        unsafe { *(global as *mut *mut Vec<usize>) = slots };
+
+        let global = self.jit.lookup(TABLE_BASE_SYMBOL)?;
+        unsafe { *(global as *mut *mut usize) = slots.as_mut_ptr() };
+
+        let global = self.jit.lookup(TABLE_SIZE_SYMBOL)?;
+        unsafe { *(global as *mut u64) = slots.len() as u64 };
+
+        Ok(())
+    }
+
+    /// Points the function imports of the loaded module at the host functions in `functions`.
+    ///
+    /// # Safety
+    /// The functions must outlive calls into the module.
+    pub(crate) fn set_imported_functions(&self, functions: &ImportedFunctions) -> Result<()> {
+        let global = self.jit.lookup(IMPORTED_FUNCTIONS_SYMBOL)?;
+        unsafe { *(global as *mut *const ImportedFunctions) = functions };
+
+        Ok(())
+    }
+
+    /// Copies the active data segments of a loaded module into memory 0, in order.
+    ///
+    /// The memory base must have been set. Segments copied before one that does not fit stay in the memory.
+    pub fn initialize_data(&self, module: &Module) -> Result<()> {
+        for (index, data) in module.compiler().info.data.iter().enumerate() {
+            match data.kind {
+                DataKind::Active {
+                    memory_index: 0,
+                    offset: InitExpr::I32Const(_) | InitExpr::I64Const(_),
+                } => {
+                    let address = self.jit.lookup(&data_initializer_symbol(0, index as u32))?;
+                    let initialize: extern "C" fn() -> i32 = unsafe { mem::transmute(address) };
+                    if initialize() == 0 {
+                        return Err(TrapCode::OutOfBoundsMemory.into());
+                    }
+                }
+                // TODO(appcypher): Support once imported globals are resolved.
+                DataKind::Active {
+                    offset: offset @ InitExpr::GlobalGet(_),
+                    ..
+                } => return Err(CompilerError::UnsupportedInitExpr(format!("{:?}", offset)).into()),
+                DataKind::Active { memory_index, .. } => {
+                    return Err(CompilerError::UnsupportedDataSegment(format!(
+                        "segment targets memory {}",
+                        memory_index
+                    ))
+                    .into())
+                }
+                DataKind::Passive => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the references of the active element segments of a loaded module into table 0, in order.
+    ///
+    /// The table base must have been set. Segments copied before one that does not fit stay in the table.
+    pub fn initialize_elements(&self, module: &Module) -> Result<()> {
+        let info = &module.compiler().info;
+        for (index, element) in info.elements.iter().enumerate() {
+            match element.kind {
+                // TODO(appcypher): Support once item expressions other than `ref.func` and `ref.null` are evaluated.
+                ElementKind::Active { .. } if !element.is_compiled() => {
+                    return Err(CompilerError::UnsupportedElementSegment(format!(
+                        "segment {} has items that are not compiled",
+                        index
+                    ))
+                    .into())
+                }
+                ElementKind::Active {
+                    table_index: 0,
+                    offset: InitExpr::I32Const(_),
+                } => {
+                    let address = self
+                        .jit
+                        .lookup(&element_initializer_symbol(0, index as u32))?;
+                    let initialize: extern "C" fn() -> i32 = unsafe { mem::transmute(address) };
+                    if initialize() == 0 {
+                        return Err(TrapCode::OutOfBoundsTable.into());
+                    }
+                }
+                // TODO(appcypher): Support once imported globals are resolved.
+                ElementKind::Active {
+                    offset: offset @ (InitExpr::GlobalGet(_) | InitExpr::I64Const(_)),
+                    ..
+                } => return Err(CompilerError::UnsupportedInitExpr(format!("{:?}", offset)).into()),
+                ElementKind::Active { table_index, .. } => {
+                    return Err(CompilerError::UnsupportedElementSegment(format!(
+                        "segment targets table {}",
+                        table_index
+                    ))
+                    .into())
+                }
+                ElementKind::Passive | ElementKind::Declared => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the number of LLVM modules the engine has compiled so far.
+    ///
+    /// Each function is compiled as a module of its own with `Options.lazy_compilation`.
+    pub fn compile_count(&self) -> usize {
+        self.jit.compile_count()
+    }
+
+    /// Gets the address of the exported function with the given name.
+    ///
+    /// The module must have been loaded into the engine. Imported functions cannot be looked up.
+    pub fn get_function(&self, module: &Module, name: &str) -> Result<usize> {
+        let index = module.exported_function_index(name)?;
+        self.jit
+            .lookup(&module.compiler().info.function_symbol(index))
+    }
+
+    /// Gets the address of the trampoline the function at `index` is called through, which must be exported or the
+    /// start function of the loaded module.
+    ///
+    /// The trampoline takes pointers to arrays of 8-byte slots, one holding the arguments and one receiving the results.
+    pub(crate) fn get_trampoline(&self, index: u32) -> Result<usize> {
+        self.jit.lookup(&trampoline_symbol(index))
+    }
+}
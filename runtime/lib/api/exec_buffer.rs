@@ -0,0 +1,27 @@
+use anyhow::Result;
+
+use crate::{compiler::llvm::exec_buffer::LLExecBuffer, Module};
+
+/// Native code of a module generated at once into memory mapped executable.
+///
+/// It is a lighter alternative to an [`Engine`](struct.Engine.html) for embedding, without a JIT session behind it.
+/// Only intrinsics like the one raising traps are linked against the code, so the module must not import anything.
+#[derive(Debug)]
+pub struct ExecBuffer {
+    buffer: LLExecBuffer,
+}
+
+impl ExecBuffer {
+    pub(crate) fn new(buffer: LLExecBuffer) -> Self {
+        Self { buffer }
+    }
+
+    /// Gets the entry point of the exported function with the given name.
+    ///
+    /// The buffer must have been emitted for `module`. Imported functions cannot be looked up.
+    pub fn get_function(&self, module: &Module, name: &str) -> Result<usize> {
+        let index = module.exported_function_index(name)?;
+        self.buffer
+            .get_function_address(&module.compiler().info.function_symbol(index))
+    }
+}
@@ -0,0 +1,191 @@
+use std::marker::PhantomData;
+
+use anyhow::Result;
+
+use crate::{
+    types::{NumType, ValType},
+    CompilerError, Instance, NumVal, Value,
+};
+
+/// A handle to an exported function of an instance.
+///
+/// The handle borrows the instance, whose memories the function accesses when called.
+#[derive(Debug)]
+pub struct Func<'i, 'a> {
+    instance: &'i mut Instance<'a>,
+    index: u32,
+}
+
+impl<'i, 'a> Func<'i, 'a> {
+    pub(crate) fn new(instance: &'i mut Instance<'a>, index: u32) -> Self {
+        Self { instance, index }
+    }
+
+    /// Gets the index of the function in the function index space of the module.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Calls the function with `args`, returning its results.
+    ///
+    /// See [`Instance::call`](struct.Instance.html#method.call)
+    pub fn call(&mut self, args: &[Value]) -> Result<Vec<Value>> {
+        self.instance.call_function(self.index, args)
+    }
+}
+
+/// A handle to an exported function of an instance whose type was checked against `Params` and `Results`.
+///
+/// Created with `Instance::get_typed_func`, it takes and returns Rust values instead of `Value`s.
+#[derive(Debug)]
+pub struct TypedFunc<'i, 'a, Params, Results> {
+    func: Func<'i, 'a>,
+    _signature: PhantomData<fn(Params) -> Results>,
+}
+
+impl<'i, 'a, Params, Results> TypedFunc<'i, 'a, Params, Results>
+where
+    Params: WasmParams,
+    Results: WasmResults,
+{
+    pub(crate) fn new(func: Func<'i, 'a>) -> Self {
+        Self {
+            func,
+            _signature: PhantomData,
+        }
+    }
+
+    /// Calls the function with `params`, returning its results.
+    ///
+    /// See [`Instance::call`](struct.Instance.html#method.call)
+    pub fn call(&mut self, params: Params) -> Result<Results> {
+        let results = self.func.call(&params.into_values())?;
+
+        Results::from_values(&results).ok_or_else(|| {
+            CompilerError::TypeMismatch(format!("results of function {}", self.func.index())).into()
+        })
+    }
+}
+
+/// A Rust type that maps to a wasm num type.
+pub trait WasmTy: Sized {
+    /// Gets the wasm type of values of this type.
+    fn valtype() -> ValType;
+
+    /// Converts the value to a wasm value.
+    fn into_value(self) -> Value;
+
+    /// Converts a wasm value of the right type back.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+/// The params of a typed function, which is nothing, a single type or a tuple of types.
+pub trait WasmParams: Sized {
+    /// Gets the wasm types of the params in order.
+    fn valtypes() -> Vec<ValType>;
+
+    /// Converts the params to wasm values in order.
+    fn into_values(self) -> Vec<Value>;
+}
+
+/// The results of a typed function, which is nothing, a single type or a tuple of types.
+pub trait WasmResults: Sized {
+    /// Gets the wasm types of the results in order.
+    fn valtypes() -> Vec<ValType>;
+
+    /// Converts wasm values of the right types back, in order.
+    fn from_values(values: &[Value]) -> Option<Self>;
+}
+
+/// Implements `WasmTy` for Rust types and the num types and values they map to.
+macro_rules! impl_wasm_ty {
+    ($($ty:ty => $num_type:ident),* $(,)?) => {
+        $(
+            impl WasmTy for $ty {
+                fn valtype() -> ValType {
+                    ValType::Num(NumType::$num_type)
+                }
+
+                fn into_value(self) -> Value {
+                    Value::Num(NumVal::$num_type(self))
+                }
+
+                fn from_value(value: &Value) -> Option<Self> {
+                    match value {
+                        Value::Num(NumVal::$num_type(value)) => Some(*value),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_wasm_ty! {
+    i32 => I32,
+    i64 => I64,
+    f32 => F32,
+    f64 => F64,
+}
+
+impl<T: WasmTy> WasmParams for T {
+    fn valtypes() -> Vec<ValType> {
+        vec![T::valtype()]
+    }
+
+    fn into_values(self) -> Vec<Value> {
+        vec![self.into_value()]
+    }
+}
+
+impl<T: WasmTy> WasmResults for T {
+    fn valtypes() -> Vec<ValType> {
+        vec![T::valtype()]
+    }
+
+    fn from_values(values: &[Value]) -> Option<Self> {
+        match values {
+            [value] => T::from_value(value),
+            _ => None,
+        }
+    }
+}
+
+/// Implements `WasmParams` and `WasmResults` for tuples of the given type parameters.
+macro_rules! impl_wasm_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: WasmTy),*> WasmParams for ($($name,)*) {
+            fn valtypes() -> Vec<ValType> {
+                vec![$($name::valtype()),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Vec<Value> {
+                let ($($name,)*) = self;
+                vec![$($name.into_value()),*]
+            }
+        }
+
+        impl<$($name: WasmTy),*> WasmResults for ($($name,)*) {
+            fn valtypes() -> Vec<ValType> {
+                vec![$($name::valtype()),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn from_values(values: &[Value]) -> Option<Self> {
+                match values {
+                    [$($name),*] => Some(($($name::from_value($name)?,)*)),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_wasm_tuple!();
+impl_wasm_tuple!(A);
+impl_wasm_tuple!(A, B);
+impl_wasm_tuple!(A, B, C);
+impl_wasm_tuple!(A, B, C, D);
+impl_wasm_tuple!(A, B, C, D, E);
+impl_wasm_tuple!(A, B, C, D, E, F);
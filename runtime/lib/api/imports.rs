@@ -1,7 +1,11 @@
+mod function;
 mod imports;
 mod memory;
 mod table;
+mod wasi;
 
+pub use function::*;
 pub use imports::*;
 pub use memory::*;
 pub use table::*;
+pub use wasi::*;
@@ -0,0 +1,51 @@
+use std::{
+    fmt::Debug,
+    panic::{self, AssertUnwindSafe},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{errors::TrapCode, Value};
+
+/// The signature of functions provided by the host.
+type HostFunc = dyn Fn(&[Value]) -> Result<Vec<Value>>;
+
+/// A host-provided function that can be imported by an instance.
+pub struct HostFunction {
+    func: Box<HostFunc>,
+}
+
+impl HostFunction {
+    pub fn new(func: impl Fn(&[Value]) -> Result<Vec<Value>> + 'static) -> Self {
+        Self {
+            func: Box::new(func),
+        }
+    }
+
+    /// Calls the function the way a guest does.
+    ///
+    /// Host errors and panics must not unwind through guest frames, so they are caught here and surfaced as a `TrapCode::HostError`.
+    /// The original error is kept as the source of the trap. An error that is a `TrapCode` traps with that code instead.
+    pub fn call(&self, args: &[Value]) -> Result<Vec<Value>> {
+        match panic::catch_unwind(AssertUnwindSafe(|| (self.func)(args))) {
+            Ok(Ok(results)) => Ok(results),
+            Ok(Err(err)) if err.is::<TrapCode>() => Err(err),
+            Ok(Err(err)) => Err(err.context(TrapCode::HostError)),
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "host function panicked".to_string());
+
+                Err(anyhow!(message).context(TrapCode::HostError))
+            }
+        }
+    }
+}
+
+impl Debug for HostFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostFunction").finish_non_exhaustive()
+    }
+}
@@ -1,4 +1,90 @@
+use std::{
+    cell::{RefCell, RefMut},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use anyhow::Result;
+
+use super::{HostFunction, Memory, Table, WasiCtx, WASI_FUNCTIONS, WASI_MODULE};
+use crate::{intrinsics::with_caller_memory, Value};
+
 /// `Imports` is a set of user-supplied objects that are exposed to a WebAssembly `Instance`.
 ///
 /// It is different from compiler `Imports` type because it does not necessarily contain a resolution of all the imports an Instance needs.
-pub struct Imports {}
+#[derive(Default)]
+pub struct Imports {
+    memories: HashMap<(String, String), RefCell<Memory>>,
+    tables: HashMap<(String, String), Table>,
+    functions: HashMap<(String, String), HostFunction>,
+    wasi: Option<Rc<RefCell<WasiCtx>>>,
+}
+
+impl Imports {
+    /// Defines a memory that can be imported as `module.name`.
+    pub fn define_memory(&mut self, module: &str, name: &str, memory: Memory) {
+        self.memories
+            .insert((module.to_string(), name.to_string()), RefCell::new(memory));
+    }
+
+    /// Gets the memory defined as `module.name`.
+    ///
+    /// Instances importing the memory write to and grow it in place, so the host sees their changes after a call.
+    pub fn get_memory(&self, module: &str, name: &str) -> Option<&RefCell<Memory>> {
+        self.memories.get(&(module.to_string(), name.to_string()))
+    }
+
+    /// Defines a table that can be imported as `module.name`.
+    pub fn define_table(&mut self, module: &str, name: &str, table: Table) {
+        self.tables
+            .insert((module.to_string(), name.to_string()), table);
+    }
+
+    /// Gets the table defined as `module.name`.
+    pub fn get_table(&self, module: &str, name: &str) -> Option<&Table> {
+        self.tables.get(&(module.to_string(), name.to_string()))
+    }
+
+    /// Defines a function that can be imported as `module.name`.
+    pub fn define_function(&mut self, module: &str, name: &str, function: HostFunction) {
+        self.functions
+            .insert((module.to_string(), name.to_string()), function);
+    }
+
+    /// Defines the closure `func` as a function that can be imported as `module.name`.
+    ///
+    /// Compiled code calling the import passes its arguments to `func`, whose results must match the type of the import.
+    pub fn define_func(
+        &mut self,
+        module: &str,
+        name: &str,
+        func: impl Fn(&[Value]) -> Result<Vec<Value>> + 'static,
+    ) {
+        self.define_function(module, name, HostFunction::new(func));
+    }
+
+    /// Gets the function defined as `module.name`.
+    pub fn get_function(&self, module: &str, name: &str) -> Option<&HostFunction> {
+        self.functions.get(&(module.to_string(), name.to_string()))
+    }
+
+    /// Provides the WASI functions listed in `WASI_FUNCTIONS` under `WASI_MODULE`, backed by `ctx`.
+    ///
+    /// The functions access memory 0 of the instance calling them. `proc_exit` traps with `TrapCode::Exit`.
+    pub fn define_wasi(&mut self, ctx: WasiCtx) {
+        let ctx = Rc::new(RefCell::new(ctx));
+        for name in WASI_FUNCTIONS {
+            let ctx = Rc::clone(&ctx);
+            self.define_func(WASI_MODULE, name, move |args| {
+                with_caller_memory(|memory| ctx.borrow_mut().call(name, args, memory))
+            });
+        }
+
+        self.wasi = Some(ctx);
+    }
+
+    /// Gets the context behind the WASI functions.
+    pub fn wasi_mut(&mut self) -> Option<RefMut<'_, WasiCtx>> {
+        self.wasi.as_ref().map(|ctx| ctx.borrow_mut())
+    }
+}
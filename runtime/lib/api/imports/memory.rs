@@ -1,6 +1,150 @@
-use crate::types::Limits;
+use std::{fmt::Debug, rc::Rc};
 
+use anyhow::Result;
+
+use crate::{
+    errors::CompilerError,
+    types::{Limits, NumType, MAX_PAGES, PAGE_SIZE},
+};
+
+/// The byte poisoned memories are filled with.
+pub const POISON_BYTE: u8 = 0xCD;
+
+/// A callback invoked with the old and new sizes in pages whenever a memory grows.
+#[derive(Clone)]
+pub struct MemoryGrowHook(pub Rc<dyn Fn(u32, u32)>);
+
+/// A host-provided linear memory that can be imported by an instance.
 pub struct Memory {
     pub limits: Limits,
-    // pub size: T, // TODO(appcypher): Make this machine-dependent
+    pub is_shared: bool,
+    /// The type of addresses into the memory, `I64` for memories of the memory64 proposal.
+    pub index_type: NumType,
+    data: Vec<u8>,
+    /// The byte new pages are filled with.
+    fill: u8,
+    /// Called after each successful grow.
+    on_grow: Option<MemoryGrowHook>,
+}
+
+impl Memory {
+    /// Creates a 32-bit memory with `min` pages allocated.
+    ///
+    /// Fails with `LimitExceeded` if `min` is more than `MAX_PAGES`.
+    pub fn new(min: u64, max: Option<u64>, is_shared: bool) -> Result<Self> {
+        let length = Some(min)
+            .filter(|min| *min <= MAX_PAGES)
+            .and_then(|min| min.checked_mul(PAGE_SIZE as u64))
+            .and_then(|length| usize::try_from(length).ok())
+            .ok_or_else(|| CompilerError::LimitExceeded(format!("memory of {} pages", min)))?;
+
+        Ok(Self {
+            limits: Limits::new(min, max),
+            is_shared,
+            index_type: NumType::I32,
+            data: vec![0; length],
+            fill: 0,
+            on_grow: None,
+        })
+    }
+
+    /// Makes the memory one with addresses of `index_type`, so that it can be imported as a 64-bit memory.
+    pub fn with_index_type(mut self, index_type: NumType) -> Self {
+        self.index_type = index_type;
+        self
+    }
+
+    /// Fills the memory and pages it grows by with `POISON_BYTE` so that uninitialized reads stand out.
+    ///
+    /// This is meant for debugging only. It breaks wasm semantics which expect new memory to be zeroed.
+    pub fn poisoned(mut self) -> Self {
+        self.data.fill(POISON_BYTE);
+        self.fill = POISON_BYTE;
+        self
+    }
+
+    /// Calls `hook` with the old and new sizes in pages whenever the memory grows, so that hosts can track usage.
+    pub fn with_grow_hook(mut self, hook: MemoryGrowHook) -> Self {
+        self.on_grow = Some(hook);
+        self
+    }
+
+    /// Gets the current size of the memory in pages.
+    pub fn size(&self) -> u64 {
+        self.data.len() as u64 / PAGE_SIZE as u64
+    }
+
+    /// Grows the memory by `delta` pages and returns the previous size in pages.
+    ///
+    /// Returns `None` if the memory cannot grow past its maximum, or if the host cannot allocate the new pages.
+    ///
+    /// # Safety
+    /// Growing may reallocate the memory, so base addresses obtained before a grow must not be used after it.
+    /// Generated code has to re-read the base address and size at each access rather than caching them across a `memory.grow`.
+    pub fn grow(&mut self, delta: u64) -> Option<u64> {
+        let size = self.size();
+        let new_size = size.checked_add(delta)?;
+        if new_size > self.limits.max.unwrap_or(MAX_PAGES).min(MAX_PAGES) {
+            return None;
+        }
+
+        // The length may not fit in a `usize` on 32-bit hosts. And `resize` aborts when allocation fails, whereas a
+        // failed grow has to be reported to the guest.
+        let length = usize::try_from(new_size.checked_mul(PAGE_SIZE as u64)?).ok()?;
+        self.data.try_reserve_exact(length - self.data.len()).ok()?;
+        self.data.resize(length, self.fill);
+
+        // Sizes are at most `MAX_PAGES`, which fits in a `u32`.
+        if let Some(MemoryGrowHook(hook)) = &self.on_grow {
+            hook(size as u32, new_size as u32);
+        }
+
+        Some(size)
+    }
+
+    /// Gets the base address of the memory.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.data.as_ptr()
+    }
+
+    /// Gets the base address of the memory for code that writes to it.
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.data.as_mut_ptr()
+    }
+
+    /// Gets the contents of the memory.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Gets the contents of the memory mutably.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Replaces the contents of the memory with `data`, which may shrink it.
+    ///
+    /// # Safety
+    /// Like growing, this may reallocate the memory.
+    pub(crate) fn restore(&mut self, data: &[u8]) {
+        self.data.clear();
+        self.data.extend_from_slice(data);
+    }
+}
+
+impl Debug for MemoryGrowHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MemoryGrowHook")
+    }
+}
+
+impl Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Memory")
+            .field("limits", &self.limits)
+            .field("is_shared", &self.is_shared)
+            .field("index_type", &self.index_type)
+            .field("size", &self.size())
+            .finish()
+    }
 }
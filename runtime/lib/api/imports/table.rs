@@ -1 +1,73 @@
+use std::{any::Any, fmt::Debug, rc::Rc};
 
+use anyhow::Result;
+
+use crate::{errors::TrapCode, types::Limits, Store};
+
+/// A host-provided table of externref values that can be imported by an instance.
+///
+/// Each slot holds an `Rc` to the host object it refers to, incremented when the slot is set and decremented when it is overwritten.
+pub struct Table {
+    pub limits: Limits,
+    elements: Vec<Option<(u64, Rc<dyn Any>)>>,
+}
+
+impl Table {
+    /// Creates a table with `min` null elements.
+    pub fn new(min: u32, max: Option<u32>) -> Self {
+        Self {
+            limits: Limits::new(min as u64, max.map(|max| max as u64)),
+            elements: vec![None; min as usize],
+        }
+    }
+
+    /// Gets the current number of elements in the table.
+    pub fn size(&self) -> u32 {
+        self.elements.len() as u32
+    }
+
+    /// Gets the handle of the externref at `index`, like `table.get` does.
+    ///
+    /// Returns `None` for a null externref.
+    pub fn get(&self, index: u32) -> Result<Option<u64>> {
+        let element = self
+            .elements
+            .get(index as usize)
+            .ok_or(TrapCode::OutOfBoundsTable)?;
+
+        Ok(element.as_ref().map(|(handle, _)| *handle))
+    }
+
+    /// Sets the element at `index` to the externref `handle` of `store`, like `table.set` does.
+    ///
+    /// `None` sets a null externref. The object previously at `index` is released by the table.
+    pub fn set(&mut self, store: &Store, index: u32, handle: Option<u64>) -> Result<()> {
+        let element = match handle {
+            Some(handle) => {
+                let object = store
+                    .externrefs()
+                    .get(handle)
+                    .ok_or(TrapCode::InvalidExternRef)?;
+
+                Some((handle, Rc::clone(object)))
+            }
+            None => None,
+        };
+
+        *self
+            .elements
+            .get_mut(index as usize)
+            .ok_or(TrapCode::OutOfBoundsTable)? = element;
+
+        Ok(())
+    }
+}
+
+impl Debug for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Table")
+            .field("limits", &self.limits)
+            .field("size", &self.size())
+            .finish()
+    }
+}
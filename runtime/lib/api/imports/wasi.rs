@@ -0,0 +1,300 @@
+use std::io::{self, Read, Write};
+
+use anyhow::Result;
+
+use super::Memory;
+use crate::{
+    compiler::value::{NumVal, Value},
+    errors::{CompilerError, TrapCode},
+};
+
+/// The module name WASI functions are imported from.
+pub const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+/// The WASI functions wasmo provides.
+pub const WASI_FUNCTIONS: &[&str] = &[
+    "args_get",
+    "args_sizes_get",
+    "environ_get",
+    "environ_sizes_get",
+    "fd_read",
+    "fd_write",
+    "proc_exit",
+];
+
+/// WASI error numbers returned by WASI functions.
+///
+/// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-errno-variant
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+    Success = 0,
+    Badf = 8,
+    Fault = 21,
+    Io = 29,
+}
+
+/// The host state behind the WASI functions of an instance.
+///
+/// It only supports the standard streams. Other file descriptors are reported as bad.
+pub struct WasiCtx {
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub stdin: Box<dyn Read>,
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+    /// The code passed to `proc_exit`, if the module has exited.
+    pub exit_code: Option<u32>,
+}
+
+impl Default for WasiCtx {
+    fn default() -> Self {
+        Self {
+            args: vec![],
+            env: vec![],
+            stdin: Box::new(io::stdin()),
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+            exit_code: None,
+        }
+    }
+}
+
+impl WasiCtx {
+    /// Writes the data of `iovs_len` iovecs at `iovs` to `fd` and stores the number of bytes written at `nwritten`.
+    pub fn fd_write(
+        &mut self,
+        memory: &mut Memory,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nwritten: u32,
+    ) -> Errno {
+        let sink = match fd {
+            1 => &mut self.stdout,
+            2 => &mut self.stderr,
+            _ => return Errno::Badf,
+        };
+
+        let mut written = 0_u32;
+        for index in 0..iovs_len {
+            let (buf, buf_len) = match read_iovec(memory, iovs, index) {
+                Some(iovec) => iovec,
+                None => return Errno::Fault,
+            };
+
+            let data = match memory.data().get(buf..buf + buf_len as usize) {
+                Some(data) => data,
+                None => return Errno::Fault,
+            };
+
+            if sink.write_all(data).is_err() {
+                return Errno::Io;
+            }
+
+            written = written.saturating_add(buf_len);
+        }
+
+        write_u32(memory, nwritten as usize, written)
+    }
+
+    /// Reads from `fd` into `iovs_len` iovecs at `iovs` and stores the number of bytes read at `nread`.
+    pub fn fd_read(
+        &mut self,
+        memory: &mut Memory,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nread: u32,
+    ) -> Errno {
+        if fd != 0 {
+            return Errno::Badf;
+        }
+
+        let mut read = 0_u32;
+        for index in 0..iovs_len {
+            let (buf, buf_len) = match read_iovec(memory, iovs, index) {
+                Some(iovec) => iovec,
+                None => return Errno::Fault,
+            };
+
+            let data = match memory.data_mut().get_mut(buf..buf + buf_len as usize) {
+                Some(data) => data,
+                None => return Errno::Fault,
+            };
+
+            let count = match self.stdin.read(data) {
+                Ok(count) => count as u32,
+                Err(_) => return Errno::Io,
+            };
+
+            read += count;
+
+            // Stop at the end of the stream or when the stream has nothing more for now.
+            if count < buf_len {
+                break;
+            }
+        }
+
+        write_u32(memory, nread as usize, read)
+    }
+
+    /// Records the exit code of the module.
+    ///
+    /// The caller is responsible for unwinding the module once this returns, which the `proc_exit` import does by
+    /// trapping with `TrapCode::Exit`.
+    pub fn proc_exit(&mut self, code: u32) {
+        self.exit_code = Some(code);
+    }
+
+    /// Stores the number of args at `argc` and the size of their null-terminated strings at `argv_buf_size`.
+    pub fn args_sizes_get(&self, memory: &mut Memory, argc: u32, argv_buf_size: u32) -> Errno {
+        let strings = self.args.iter().map(|arg| arg.as_bytes().to_vec());
+        sizes_get(memory, strings, argc, argv_buf_size)
+    }
+
+    /// Stores pointers to the args at `argv` and their null-terminated strings at `argv_buf`.
+    pub fn args_get(&self, memory: &mut Memory, argv: u32, argv_buf: u32) -> Errno {
+        let strings = self.args.iter().map(|arg| arg.as_bytes().to_vec());
+        strings_get(memory, strings, argv, argv_buf)
+    }
+
+    /// Stores the number of env variables at `count` and the size of their null-terminated strings at `buf_size`.
+    pub fn environ_sizes_get(&self, memory: &mut Memory, count: u32, buf_size: u32) -> Errno {
+        sizes_get(memory, self.environ(), count, buf_size)
+    }
+
+    /// Stores pointers to the env variables at `environ` and their null-terminated `KEY=VALUE` strings at `environ_buf`.
+    pub fn environ_get(&self, memory: &mut Memory, environ: u32, environ_buf: u32) -> Errno {
+        strings_get(memory, self.environ(), environ, environ_buf)
+    }
+
+    /// Calls the function `name` of `WASI_FUNCTIONS` for a module with `args`, where `memory` is memory 0 of the module.
+    ///
+    /// Functions other than `proc_exit` return an errno, which is `Fault` if the module has no memory.
+    pub(crate) fn call(
+        &mut self,
+        name: &str,
+        args: &[Value],
+        memory: Option<&mut Memory>,
+    ) -> Result<Vec<Value>> {
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                Value::Num(NumVal::I32(value)) => Some(*value as u32),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| CompilerError::TypeMismatch(format!("arguments of {}", name)))?;
+
+        if let ("proc_exit", &[code]) = (name, args.as_slice()) {
+            self.proc_exit(code);
+            return Err(TrapCode::Exit.into());
+        }
+
+        let memory = match memory {
+            Some(memory) => memory,
+            None => return Ok(vec![Value::Num(NumVal::I32(Errno::Fault as i32))]),
+        };
+
+        let errno = match (name, args.as_slice()) {
+            ("args_get", &[argv, argv_buf]) => self.args_get(memory, argv, argv_buf),
+            ("args_sizes_get", &[argc, argv_buf_size]) => {
+                self.args_sizes_get(memory, argc, argv_buf_size)
+            }
+            ("environ_get", &[environ, environ_buf]) => {
+                self.environ_get(memory, environ, environ_buf)
+            }
+            ("environ_sizes_get", &[count, buf_size]) => {
+                self.environ_sizes_get(memory, count, buf_size)
+            }
+            ("fd_read", &[fd, iovs, iovs_len, nread]) => {
+                self.fd_read(memory, fd, iovs, iovs_len, nread)
+            }
+            ("fd_write", &[fd, iovs, iovs_len, nwritten]) => {
+                self.fd_write(memory, fd, iovs, iovs_len, nwritten)
+            }
+            _ => return Err(CompilerError::TypeMismatch(format!("arguments of {}", name)).into()),
+        };
+
+        Ok(vec![Value::Num(NumVal::I32(errno as i32))])
+    }
+
+    fn environ(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        self.env
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value).into_bytes())
+    }
+}
+
+/// Reads the pointer and length of the iovec at `index` in the array at `iovs`.
+///
+/// Addresses are computed as `usize` so that they cannot overflow.
+fn read_iovec(memory: &Memory, iovs: u32, index: u32) -> Option<(usize, u32)> {
+    let iovec = iovs as usize + index as usize * 8;
+    Some((
+        read_u32(memory, iovec)? as usize,
+        read_u32(memory, iovec + 4)?,
+    ))
+}
+
+fn read_u32(memory: &Memory, address: usize) -> Option<u32> {
+    let bytes = memory.data().get(address..address + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn write_u32(memory: &mut Memory, address: usize, value: u32) -> Errno {
+    write_bytes(memory, address, &value.to_le_bytes())
+}
+
+fn write_bytes(memory: &mut Memory, address: usize, bytes: &[u8]) -> Errno {
+    match memory.data_mut().get_mut(address..address + bytes.len()) {
+        Some(data) => {
+            data.copy_from_slice(bytes);
+            Errno::Success
+        }
+        None => Errno::Fault,
+    }
+}
+
+fn sizes_get(
+    memory: &mut Memory,
+    strings: impl Iterator<Item = Vec<u8>>,
+    count: u32,
+    buf_size: u32,
+) -> Errno {
+    let (strings_count, strings_size) = strings.fold((0, 0), |(count, size), string| {
+        (count + 1, size + string.len() as u32 + 1)
+    });
+
+    match write_u32(memory, count as usize, strings_count) {
+        Errno::Success => write_u32(memory, buf_size as usize, strings_size),
+        errno => errno,
+    }
+}
+
+fn strings_get(
+    memory: &mut Memory,
+    strings: impl Iterator<Item = Vec<u8>>,
+    pointers: u32,
+    buf: u32,
+) -> Errno {
+    let mut address = buf as usize;
+    for (index, mut string) in strings.enumerate() {
+        string.push(0);
+
+        let pointer = pointers as usize + index * 4;
+        let errno = match write_u32(memory, pointer, address as u32) {
+            Errno::Success => write_bytes(memory, address, &string),
+            errno => errno,
+        };
+
+        if errno != Errno::Success {
+            return errno;
+        }
+
+        address += string.len();
+    }
+
+    Errno::Success
+}
@@ -1,6 +1,12 @@
-use super::Store;
-use crate::compiler::value::Value;
-use crate::{Imports, Module};
+use std::{cell::RefCell, mem, ptr};
+
+use super::{Func, Snapshot, Store, TypedFunc, WasmParams, WasmResults};
+use crate::compiler::{
+    llvm::module::{IMPORTED_FUNCTIONS_SYMBOL, MEMORY_BASE_SYMBOL, TABLE_BASE_SYMBOL},
+    value::Value,
+};
+use crate::intrinsics::{call_with_memory, ImportedFunctions};
+use crate::{catch_traps, types::ValType, CompilerError, Engine, Imports, Memory, Module};
 use anyhow::Result;
 
 /// An Instance is a fully resolved wasm runtime context.
@@ -9,18 +15,269 @@ use anyhow::Result;
 #[derive(Debug, Default)]
 pub struct Instance<'a> {
     _module: Option<&'a Module>,
-    _store: Option<Store>,
+    /// The store data section of the instance, which embedders may patch addresses into.
+    store: Option<Store>,
+    /// Host functions the function imports are resolved to, boxed so that compiled code can keep their address.
+    imported_functions: Box<ImportedFunctions<'a>>,
+    /// Memories shared with the imports, which compiled code writes to and grows in place.
+    imported_memories: Vec<&'a RefCell<Memory>>,
+    /// Memories created for the instance.
+    local_memories: Vec<Memory>,
+    /// Slots of the tables created for the instance, filled from active element segments on the first call.
+    local_tables: Vec<Vec<usize>>,
+    /// The engine running the code of the module, created on the first call.
+    engine: Option<Engine>,
 }
 
 impl<'a> Instance<'a> {
     /// Creates a WebAssembly instance.
-    pub fn new(module: &'a Module, imports: &Imports) -> Result<Self> {
+    pub fn new(module: &'a Module, imports: &'a Imports) -> Result<Self> {
         module.initialize(imports, Default::default())
     }
 
-    /// Invokes the function with the given name.
-    pub fn invoke(_name: String, _params: &[Value]) -> Result<Value> {
-        // TODO(appcypher): Implement this.
-        todo!()
+    pub(crate) fn from_parts(
+        module: &'a Module,
+        store: Store,
+        imported_functions: ImportedFunctions<'a>,
+        imported_memories: Vec<&'a RefCell<Memory>>,
+        local_memories: Vec<Memory>,
+        local_tables: Vec<Vec<usize>>,
+    ) -> Self {
+        Self {
+            _module: Some(module),
+            store: Some(store),
+            imported_functions: Box::new(imported_functions),
+            imported_memories,
+            local_memories,
+            local_tables,
+            engine: None,
+        }
+    }
+
+    /// Gets the memory at `index` in the memory index space, where imported memories come first.
+    ///
+    /// Only memories created for the instance are available. Imported ones are shared through `Imports::get_memory`.
+    pub fn memory(&self, index: u32) -> Option<&Memory> {
+        let index = (index as usize).checked_sub(self.imported_memories.len())?;
+        self.local_memories.get(index)
+    }
+
+    /// Gets the memory at `index` for growing or writing to it.
+    ///
+    /// Only memories created for the instance are available. Imported ones are shared through `Imports::get_memory`.
+    pub fn memory_mut(&mut self, index: u32) -> Option<&mut Memory> {
+        let index = (index as usize).checked_sub(self.imported_memories.len())?;
+        self.local_memories.get_mut(index)
+    }
+
+    /// Gets the store of the instance for patching addresses into its data section.
+    ///
+    /// See [`Store::data_section_mut`](struct.Store.html#method.data_section_mut) for the addresses calls pick up.
+    pub fn store_mut(&mut self) -> Option<&mut Store> {
+        self.store.as_mut()
+    }
+
+    /// Captures the memories and globals of the instance, to roll back to them later with `restore`.
+    ///
+    /// Imported memories are left out since they are shared with the `Imports`, which other instances may use too.
+    // TODO(appcypher): Capture tables once instances hold them.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memories: self
+                .local_memories
+                .iter()
+                .map(|memory| memory.data().to_vec())
+                .collect(),
+            data_section: self
+                .store
+                .as_ref()
+                .map(|store| store.data_section().to_vec()),
+        }
+    }
+
+    /// Rolls the instance back to the state captured by `snapshot`, undoing writes and grows since.
+    ///
+    /// Fails if the snapshot was not captured from an instance of the same module.
+    ///
+    /// # Safety
+    /// Memories that grew since the snapshot shrink back, so their base addresses must be re-read afterwards.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        let data_section_size = self.store.as_ref().map(|store| store.data_section().len());
+        if snapshot.memories.len() != self.local_memories.len()
+            || snapshot.data_section.as_ref().map(Vec::len) != data_section_size
+        {
+            return Err(CompilerError::SnapshotMismatch.into());
+        }
+
+        for (memory, data) in self.local_memories.iter_mut().zip(&snapshot.memories) {
+            memory.restore(data);
+        }
+
+        if let (Some(store), Some(data_section)) = (&mut self.store, &snapshot.data_section) {
+            store.data_section_mut().copy_from_slice(data_section);
+        }
+
+        Ok(())
+    }
+
+    /// Calls the exported function `name` with `args`, returning its results.
+    ///
+    /// The module is JIT-compiled on the first call, and its code accesses memory 0 and table 0 of the instance.
+    /// Only functions whose params and results are `i32`, `i64`, `f32` or `f64` can be called.
+    /// A trap is returned as an error that downcasts to its `TrapCode`.
+    pub fn call(&mut self, name: &str, args: &[Value]) -> Result<Vec<Value>> {
+        let module = self._module.ok_or(CompilerError::ModuleNotCompiled)?;
+        let index = module.exported_function_index(name)?;
+
+        self.call_function(index, args)
+    }
+
+    /// Gets a handle to the exported function `name` for calling it with `Func::call`.
+    pub fn get_func(&mut self, name: &str) -> Result<Func<'_, 'a>> {
+        let module = self._module.ok_or(CompilerError::ModuleNotCompiled)?;
+        let index = module.exported_function_index(name)?;
+
+        Ok(Func::new(self, index))
+    }
+
+    /// Gets a handle to the exported function `name` that takes `Params` and returns `Results`.
+    ///
+    /// Fails if the type of the function does not have exactly those params and results, like
+    /// `get_typed_func::<(i32, i32), i32>` for a function of type `[i32 i32] -> [i32]`.
+    pub fn get_typed_func<Params, Results>(
+        &mut self,
+        name: &str,
+    ) -> Result<TypedFunc<'_, 'a, Params, Results>>
+    where
+        Params: WasmParams,
+        Results: WasmResults,
+    {
+        let module = self._module.ok_or(CompilerError::ModuleNotCompiled)?;
+        let index = module.exported_function_index(name)?;
+
+        let info = &module.compiler().info;
+        let ty = &info.types[info.functions[index as usize].type_index as usize];
+        if ty.params != Params::valtypes() || ty.results != Results::valtypes() {
+            return Err(CompilerError::TypeMismatch(format!("signature of {:?}", name)).into());
+        }
+
+        Ok(TypedFunc::new(Func::new(self, index)))
+    }
+
+    /// Calls the local function at `index`, which must be exported or the start function.
+    ///
+    /// See [`call`](#method.call)
+    pub(crate) fn call_function(&mut self, index: u32, args: &[Value]) -> Result<Vec<Value>> {
+        let module = self._module.ok_or(CompilerError::ModuleNotCompiled)?;
+
+        let info = &module.compiler().info;
+        let ty = &info.types[info.functions[index as usize].type_index as usize];
+
+        if !ty
+            .params
+            .iter()
+            .chain(&ty.results)
+            .all(|ty| matches!(ty, ValType::Num(_)))
+        {
+            return Err(CompilerError::UnsupportedWasmoValType(format!(
+                "{:?} of function {}",
+                ty, index
+            ))
+            .into());
+        }
+
+        if args.len() != ty.params.len() {
+            return Err(
+                CompilerError::TypeMismatch(format!("arguments of function {}", index)).into(),
+            );
+        }
+
+        let args = args
+            .iter()
+            .zip(&ty.params)
+            .map(|(arg, ty)| arg.to_slot(ty))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                CompilerError::TypeMismatch(format!("arguments of function {}", index))
+            })?;
+
+        // TODO(appcypher): Point at imported tables too once instances resolve them.
+        let llvm_module = module.compiler().llvm_module()?;
+        let uses_table =
+            llvm_module.get_global(TABLE_BASE_SYMBOL)?.is_some() && info.imports.tables.is_empty();
+
+        let engine = match &mut self.engine {
+            Some(engine) => engine,
+            engine => {
+                let new_engine = Engine::new()?;
+                new_engine.load(module)?;
+
+                // Funcrefs are addresses in the loaded code, so element segments can only be copied once it is loaded.
+                if let (true, Some(slots)) = (uses_table, self.local_tables.first_mut()) {
+                    new_engine.set_table_base(slots)?;
+                    new_engine.initialize_elements(module)?;
+                }
+
+                engine.insert(new_engine)
+            }
+        };
+
+        if !self.imported_functions.is_empty()
+            && llvm_module.get_global(IMPORTED_FUNCTIONS_SYMBOL)?.is_some()
+        {
+            engine.set_imported_functions(&self.imported_functions)?;
+        }
+
+        // The base is set on every call since the memory may have moved while growing since the last one.
+        // An imported memory is only borrowed while pointing at it, as it stays in place in the `Imports` after.
+        if llvm_module.get_global(MEMORY_BASE_SYMBOL)?.is_some() {
+            match (
+                self.imported_memories.first(),
+                self.local_memories.first_mut(),
+            ) {
+                (Some(memory), _) => engine.set_memory_base(&mut memory.borrow_mut())?,
+                (None, Some(memory)) => engine.set_memory_base(memory)?,
+                (None, None) => (),
+            }
+
+            // A base an embedder patched into the store data section takes the place of the one resolved above.
+            if let Some(base) = self
+                .store
+                .as_ref()
+                .and_then(|store| store.memory_base(0))
+                .filter(|base| *base != 0)
+            {
+                engine.override_memory_base(base)?;
+            }
+        }
+
+        if let (true, Some(slots)) = (uses_table, self.local_tables.first_mut()) {
+            engine.set_table_base(slots)?;
+        }
+
+        let trampoline = engine.get_trampoline(index)?;
+        let trampoline: extern "C-unwind" fn(*const u64, *mut u64) =
+            unsafe { mem::transmute(trampoline) };
+
+        // Host functions access the memory of the instance calling them, which outlives the call.
+        let memory = match (
+            self.imported_memories.first(),
+            self.local_memories.first_mut(),
+        ) {
+            (Some(memory), _) => memory.as_ptr(),
+            (None, Some(memory)) => memory,
+            (None, None) => ptr::null_mut(),
+        };
+
+        let mut results = vec![0; ty.results.len()];
+        catch_traps(|| unsafe {
+            call_with_memory(memory, || trampoline(args.as_ptr(), results.as_mut_ptr()))
+        })?;
+
+        Ok(results
+            .into_iter()
+            .zip(&ty.results)
+            .filter_map(|(slot, ty)| Value::from_slot(slot, ty))
+            .collect())
     }
 }
@@ -1,6 +1,24 @@
-use crate::{compiler::Compiler, Imports, Instance, Options, Store};
+use crate::{
+    compiler::{
+        exports::ExportKind,
+        llvm::{context::LLContext, LLVM},
+        Compiler, DataKind, ElementKind,
+    },
+    errors::{CompilerError, TrapCode},
+    intrinsics::{
+        call_import, memory_grow, raise_trap, table_fill, table_grow, ImportedFunctions,
+        CALL_IMPORT_SYMBOL, MEMORY_GROW_SYMBOL, RAISE_TRAP_SYMBOL, TABLE_FILL_SYMBOL,
+        TABLE_GROW_SYMBOL,
+    },
+    types::Limits,
+    CallGraph, Cfg, ExecBuffer, Imports, Instance, Memory, Options, Store, StoreLayout,
+};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, io::Write, path::Path, rc::Rc};
+
+/// Version of the layout of precompiled artifacts, to be bumped whenever it or the serialized `Module` changes.
+const PRECOMPILED_ABI_VERSION: u32 = 12;
 
 /// A WebAssembly module with compiled code but with unresolved external references.
 /// Memories and tables are also not created yet.
@@ -12,6 +30,15 @@ pub struct Module {
     compiler: Compiler,
 }
 
+/// The size of the code generated for a function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionStats {
+    /// Number of basic blocks in the function.
+    pub basic_blocks: usize,
+    /// Number of instructions across all basic blocks, terminators included.
+    pub instructions: usize,
+}
+
 /// Options available for initialiazing a module.
 #[derive(Debug, Default)]
 pub struct InitializeOpts {
@@ -21,15 +48,222 @@ pub struct InitializeOpts {
 impl Module {
     /// Creates a new `Module` with the given options.
     pub fn new(wasm: &[u8], options: Options) -> Result<Self> {
+        Self::new_in_context(wasm, options, Rc::new(LLContext::new()))
+    }
+
+    /// Creates a new `Module` whose LLVM code lives in `context`, which may be shared with other modules.
+    pub(crate) fn new_in_context(
+        wasm: &[u8],
+        options: Options,
+        context: Rc<LLContext>,
+    ) -> Result<Self> {
         // Create compiler and compile wasm bytes.
-        let mut compiler = Compiler::new(options.liftoff);
+        let mut compiler = Compiler::new(
+            options.liftoff,
+            options.limits,
+            options.opt_level,
+            options.preserve_frame_pointer,
+            options.debug_info,
+            options.static_memory_image,
+        );
 
         // Compile wasm bytes.
-        compiler.compile(wasm)?;
+        compiler.compile(wasm, context)?;
 
         Ok(Self { options, compiler })
     }
 
+    /// Generates and verifies the LLVM IR for `wasm` without optimizing or keeping it.
+    ///
+    /// This is meant for profiling the code generator, whose cost is otherwise mixed with that of the LLVM backend.
+    pub fn compile_to_ir_only(wasm: &[u8], options: Options) -> Result<()> {
+        let mut compiler = Compiler::new(
+            options.liftoff,
+            options.limits,
+            options.opt_level,
+            options.preserve_frame_pointer,
+            options.debug_info,
+            options.static_memory_image,
+        );
+
+        compiler.compile_to_ir_only(wasm, Rc::new(LLContext::new()))?;
+
+        Ok(())
+    }
+
+    /// Writes the textual LLVM IR of the module to `writer`.
+    ///
+    /// The IR is only available in a module that has been compiled rather than deserialized.
+    pub fn write_ir<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.compiler.write_ir(writer)
+    }
+
+    /// Gets the textual LLVM IR of the module.
+    ///
+    /// Unlike `write_ir`, the whole IR is buffered in memory, which is convenient for small modules.
+    pub fn ir_to_string(&self) -> Result<String> {
+        self.compiler.ir_to_string()
+    }
+
+    /// Gets the size of the LLVM code generated for the function at `index`.
+    ///
+    /// This is useful for spotting pathological lowerings. Imported functions have no code, and like the IR,
+    /// the stats are only available in a module that has been compiled rather than deserialized.
+    pub fn function_stats(&self, index: u32) -> Result<FunctionStats> {
+        self.compiler.function_stats(index)
+    }
+
+    /// Serializes the module into a single precompiled artifact, its LLVM bitcode with the module's metadata embedded.
+    ///
+    /// The metadata, such as types and exports, lives in a section of its own so no other file is needed to restore the module.
+    pub fn to_precompiled(&self) -> Result<Vec<u8>> {
+        let llvm = self
+            .compiler
+            .llvm
+            .as_ref()
+            .ok_or(CompilerError::ModuleNotCompiled)?;
+
+        let mut metadata = PRECOMPILED_ABI_VERSION.to_le_bytes().to_vec();
+        metadata.extend(bincode::serialize(self)?);
+
+        llvm.to_precompiled(&metadata)
+    }
+
+    /// Restores a module from an artifact created by `to_precompiled` without compiling any wasm.
+    ///
+    /// The module can be loaded into an engine like a compiled one, but its stats and control-flow graphs are not available.
+    pub fn from_precompiled(precompiled: &[u8]) -> Result<Self> {
+        let (llvm, metadata) = LLVM::from_precompiled(precompiled, Rc::new(LLContext::new()))?;
+
+        let (version, metadata) = metadata.split_at(metadata.len().min(4));
+        if version != PRECOMPILED_ABI_VERSION.to_le_bytes() {
+            return Err(CompilerError::IncompatiblePrecompiled(format!(
+                "expected ABI version {}",
+                PRECOMPILED_ABI_VERSION
+            ))
+            .into());
+        }
+
+        let mut module: Module = bincode::deserialize(metadata)?;
+        module.compiler.llvm = Some(llvm);
+
+        Ok(module)
+    }
+
+    /// Writes the native code of the module to `path` as a static archive that C or Rust programs can link against.
+    ///
+    /// Exported functions are visible to the linker under their export names, and other functions are hidden.
+    /// The program must define the symbols the module links against, such as `__wasmo_raise_trap` and imported globals.
+    pub fn emit_static_archive(&self, path: impl AsRef<Path>) -> Result<()> {
+        let llvm = self
+            .compiler
+            .llvm
+            .as_ref()
+            .ok_or(CompilerError::ModuleNotCompiled)?;
+
+        llvm.emit_static_archive(path.as_ref(), &self.linked_exports())
+    }
+
+    /// Writes the native code of the module to `path` as an object file, for `triple` or the host's target triple.
+    ///
+    /// Symbols are visible to the linker like in [`emit_static_archive`](#method.emit_static_archive).
+    /// Only triples of the host's architecture family, like `i686-unknown-linux-gnu` on x86-64, are supported.
+    pub fn emit_object_file(&self, path: impl AsRef<Path>, triple: Option<&str>) -> Result<()> {
+        let llvm = self
+            .compiler
+            .llvm
+            .as_ref()
+            .ok_or(CompilerError::ModuleNotCompiled)?;
+
+        llvm.emit_object_file(path.as_ref(), triple, &self.linked_exports())
+    }
+
+    /// Gets the local functions exported by the module, as pairs of their name in the LLVM module and their export name.
+    fn linked_exports(&self) -> Vec<(String, String)> {
+        let info = &self.compiler.info;
+        let mut exports = info
+            .exports
+            .inner
+            .iter()
+            .filter(|(_, export)| matches!(export.kind, ExportKind::Function))
+            .filter(|(_, export)| export.index as usize >= info.imports.functions.len())
+            .map(|(name, export)| (info.function_symbol(export.index), name.clone()))
+            .collect::<Vec<_>>();
+
+        exports.sort();
+
+        exports
+    }
+
+    /// Gets the control-flow graph of the LLVM code generated for the function at `index`.
+    ///
+    /// This makes lowering bugs like missing terminators or wrong loop edges inspectable.
+    /// Like the stats, the graph is only available in a module that has been compiled rather than deserialized.
+    pub fn function_cfg(&self, index: u32) -> Result<Cfg> {
+        self.compiler.function_cfg(index)
+    }
+
+    /// Generates native code for the module into memory mapped executable, without a JIT session.
+    pub fn emit_to_executable_buffer(&self) -> Result<ExecBuffer> {
+        let llvm = self
+            .compiler
+            .llvm
+            .as_ref()
+            .ok_or(CompilerError::ModuleNotCompiled)?;
+
+        let symbols = [
+            (RAISE_TRAP_SYMBOL, raise_trap as *const () as usize),
+            (MEMORY_GROW_SYMBOL, memory_grow as *const () as usize),
+            (TABLE_GROW_SYMBOL, table_grow as *const () as usize),
+            (TABLE_FILL_SYMBOL, table_fill as *const () as usize),
+            (CALL_IMPORT_SYMBOL, call_import as *const () as usize),
+        ];
+
+        Ok(ExecBuffer::new(llvm.emit_to_executable_buffer(&symbols)?))
+    }
+
+    pub(crate) fn compiler(&self) -> &Compiler {
+        &self.compiler
+    }
+
+    /// Gets the index of the exported function with the given name, which must not be imported.
+    pub(crate) fn exported_function_index(&self, name: &str) -> Result<u32> {
+        let info = &self.compiler.info;
+        let export = info
+            .exports
+            .inner
+            .get(name)
+            .filter(|export| matches!(export.kind, ExportKind::Function))
+            .ok_or_else(|| CompilerError::UnknownExport(format!("function {:?}", name)))?;
+
+        if (export.index as usize) < info.imports.functions.len() {
+            return Err(
+                CompilerError::UnknownExport(format!("imported function {:?}", name)).into(),
+            );
+        }
+
+        Ok(export.index)
+    }
+
+    /// Gets the layout of the store data section instances of the module use.
+    pub fn store_layout(&self) -> StoreLayout {
+        let info = &self.compiler.info;
+
+        // TODO(appcypher): Count intrinsics once generated code calls them.
+        StoreLayout::new(
+            0,
+            info.functions.len() as u32,
+            info.memories.len() as u32,
+            info.tables.len() as u32,
+            info.globals.len() as u32,
+        )
+    }
+
+    /// Gets the dependencies between functions of the module.
+    pub fn call_graph(&self) -> &CallGraph {
+        &self.compiler.info.call_graph
+    }
+
     /// Creates a WebAssembly instance.
     ///
     /// Resolves and initialises the instance.
@@ -40,9 +274,242 @@ impl Module {
     /// 3. Create local memories, tables and globals.
     /// 4. Populate memories, tables and globals.
     /// 5. Call start function.
-    pub fn initialize(&self, _imports: &Imports, _opts: InitializeOpts) -> Result<Instance> {
-        // TODO(appcypher): Create Store or use the one in opts.
-        // TODO(appcypher): Implement.
-        todo!()
+    pub fn initialize<'a>(
+        &'a self,
+        imports: &'a Imports,
+        opts: InitializeOpts,
+    ) -> Result<Instance<'a>> {
+        let store = opts
+            .store
+            .unwrap_or_else(|| Store::new(self.store_layout()));
+        let imported_functions = self.resolve_imported_functions(imports)?;
+        let imported_memories = self.resolve_imported_memories(imports)?;
+        let mut local_memories = self.create_local_memories()?;
+        let local_tables = self.create_local_tables();
+
+        self.initialize_data(&imported_memories, &mut local_memories)?;
+        self.check_element_segments()?;
+
+        // TODO(appcypher): Resolve imported tables, and resolve and create globals.
+        let mut instance = Instance::from_parts(
+            self,
+            store,
+            imported_functions,
+            imported_memories,
+            local_memories,
+            local_tables,
+        );
+        self.call_start_function(imports, &mut instance)?;
+
+        Ok(instance)
+    }
+
+    /// Resolves the imported functions in order.
+    ///
+    /// Compiled code calls them through the host, which checks arguments and results against the type of the import.
+    fn resolve_imported_functions<'b>(
+        &self,
+        imports: &'b Imports,
+    ) -> Result<ImportedFunctions<'b>> {
+        let info = &self.compiler.info;
+        let mut functions = vec![];
+
+        for import in info.imports.functions.iter() {
+            let field = import.field.as_deref().unwrap_or_default();
+            let function = imports
+                .get_function(&import.module, field)
+                .ok_or_else(|| CompilerError::UnresolvedImport(import.symbol_name()))?;
+
+            let ty = &info.types[info.functions[import.index as usize].type_index as usize];
+            functions.push((function, ty.clone()));
+        }
+
+        Ok(ImportedFunctions::new(functions))
+    }
+
+    /// Resolves the imported memories in order.
+    ///
+    /// Memories are aliased, not copied, so the instance sees the same memory as the host.
+    fn resolve_imported_memories<'b>(
+        &self,
+        imports: &'b Imports,
+    ) -> Result<Vec<&'b RefCell<Memory>>> {
+        let info = &self.compiler.info;
+        let mut memories = vec![];
+
+        for import in info.imports.memories.iter() {
+            let field = import.field.as_deref().unwrap_or_default();
+            let name = format!("{}.{}", import.module, field);
+
+            let memory = imports
+                .get_memory(&import.module, field)
+                .ok_or_else(|| CompilerError::UnresolvedImport(name.clone()))?;
+
+            let expected = &info.memories[import.index as usize];
+            let is_compatible = {
+                let memory = memory.borrow();
+                Limits::new(memory.size(), memory.limits.max).matches(&expected.limits)
+                    && memory.is_shared == expected.is_shared
+                    && memory.index_type == expected.index_type
+            };
+            if !is_compatible {
+                return Err(CompilerError::IncompatibleImport(name).into());
+            }
+
+            memories.push(memory);
+        }
+
+        Ok(memories)
+    }
+
+    /// Calls the start function, if the module has one.
+    ///
+    /// A start index in the import space dispatches to the host function resolved for that import.
+    /// A local start function runs compiled code with the memories of `instance`.
+    fn call_start_function(&self, imports: &Imports, instance: &mut Instance) -> Result<()> {
+        let info = &self.compiler.info;
+        let index = match info.start_function {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        match info.imports.functions.get(index as usize) {
+            Some(import) => {
+                let field = import.field.as_deref().unwrap_or_default();
+                let function = imports
+                    .get_function(&import.module, field)
+                    .ok_or_else(|| CompilerError::UnresolvedImport(import.symbol_name()))?;
+
+                function.call(&[])?;
+            }
+            None => {
+                instance.call_function(index, &[])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the memories defined by the module.
+    ///
+    /// Fails with `LimitExceeded` if the minimum size of a memory cannot be allocated.
+    fn create_local_memories(&self) -> Result<Vec<Memory>> {
+        let info = &self.compiler.info;
+
+        info.memories[info.imports.memories.len()..]
+            .iter()
+            .map(|memory| {
+                let mut local =
+                    Memory::new(memory.limits.min, memory.limits.max, memory.is_shared)?
+                        .with_index_type(memory.index_type);
+                if self.options.poison_memory {
+                    local = local.poisoned();
+                }
+
+                Ok(match &self.options.on_memory_grow {
+                    Some(hook) => local.with_grow_hook(hook.clone()),
+                    None => local,
+                })
+            })
+            .collect()
+    }
+
+    /// Checks that every active element segment targets the table instances copy segments into.
+    ///
+    /// Segments are copied into a local table 0 on the first call, so those targeting other tables would be dropped.
+    fn check_element_segments(&self) -> Result<()> {
+        let info = &self.compiler.info;
+
+        for element in info.elements.iter() {
+            // TODO(appcypher): Support other tables and imported tables.
+            if let ElementKind::Active { table_index, .. } = element.kind {
+                if table_index != 0 || !info.imports.tables.is_empty() {
+                    return Err(CompilerError::UnsupportedElementSegment(format!(
+                        "segment targets table {}",
+                        table_index
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the slots of the tables defined by the module, all null references.
+    fn create_local_tables(&self) -> Vec<Vec<usize>> {
+        let info = &self.compiler.info;
+
+        info.tables[info.imports.tables.len()..]
+            .iter()
+            .map(|table| vec![0; table.limits.min as usize])
+            .collect()
+    }
+
+    /// Copies active data segments into the memories they target, as a single image for memories that have one.
+    ///
+    /// `local_memories` are the memories defined by the module, which come after `imported_memories` in the memory index space.
+    fn initialize_data(
+        &self,
+        imported_memories: &[&RefCell<Memory>],
+        local_memories: &mut [Memory],
+    ) -> Result<()> {
+        let info = &self.compiler.info;
+        let imported_count = info.imports.memories.len();
+
+        // Images only exist for local memories whose segments all fit.
+        for image in info.memory_images.iter() {
+            let memory = &mut local_memories[image.memory_index as usize - imported_count];
+            memory.data_mut()[..image.bytes.len()].copy_from_slice(&image.bytes);
+        }
+
+        for data in info.data.iter() {
+            let (memory_index, offset) = match data.kind {
+                DataKind::Active {
+                    memory_index,
+                    offset,
+                } => (memory_index as usize, offset),
+                DataKind::Passive => continue,
+            };
+
+            if info
+                .memory_images
+                .iter()
+                .any(|image| image.memory_index as usize == memory_index)
+            {
+                continue;
+            }
+
+            // TODO(appcypher): Support offsets from imported globals once they are resolved.
+            let offset = offset
+                .const_offset()
+                .ok_or_else(|| CompilerError::UnsupportedInitExpr(format!("{:?}", offset)))?;
+
+            let mut imported_memory;
+            let memory = match imported_memories.get(memory_index) {
+                Some(memory) => {
+                    imported_memory = memory.borrow_mut();
+                    &mut *imported_memory
+                }
+                None => local_memories
+                    .get_mut(memory_index - imported_count)
+                    .ok_or_else(|| {
+                        CompilerError::IndexOutOfBounds(format!("memory {}", memory_index))
+                    })?,
+            };
+
+            // Offsets of 64-bit memories may not fit in a `usize`, let alone with the segment added.
+            let start = usize::try_from(offset).map_err(|_| TrapCode::OutOfBoundsMemory)?;
+            let end = start
+                .checked_add(data.bytes.len())
+                .ok_or(TrapCode::OutOfBoundsMemory)?;
+            memory
+                .data_mut()
+                .get_mut(start..end)
+                .ok_or(TrapCode::OutOfBoundsMemory)?
+                .copy_from_slice(&data.bytes);
+        }
+
+        Ok(())
     }
 }
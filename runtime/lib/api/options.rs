@@ -1,8 +1,79 @@
 use serde::{Deserialize, Serialize};
 
+use crate::MemoryGrowHook;
+
 /// The different options for configuring the runtime.
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct Options {
     /// Whether to use the Liftoff compiler.
     pub liftoff: bool,
+    /// Limits guarding the compiler against untrusted modules.
+    pub limits: ResourceLimits,
+    /// Whether to fill created and grown memories with `POISON_BYTE` instead of zeros.
+    ///
+    /// This is meant for debugging uninitialized reads and deviates from wasm semantics.
+    pub poison_memory: bool,
+    /// Called with the old and new sizes in pages whenever a memory created for an instance grows, whether by the host
+    /// or by `memory.grow`.
+    ///
+    /// Imported memories are grown by the host, which can give them a hook of its own with `Memory::with_grow_hook`.
+    #[serde(skip)]
+    pub on_memory_grow: Option<MemoryGrowHook>,
+    /// Whether the initial contents of memories are built from data segments at compile time.
+    ///
+    /// Memories are then initialized with a single copy of their image, which is also emitted with the code
+    /// for ahead-of-time compiled modules. Segments with offsets only known at instantiation are still copied one by one.
+    pub static_memory_image: bool,
+    /// Whether an engine compiles each function on its first call instead of the whole module on load.
+    ///
+    /// This trades throughput for faster startup of large modules.
+    pub lazy_compilation: bool,
+    /// How hard the compiler optimizes the generated code.
+    pub opt_level: OptLevel,
+    /// Whether compiled functions keep frame pointers so that sampling profilers like perf can walk the stack.
+    pub preserve_frame_pointer: bool,
+    /// Whether compiled functions carry DWARF debug info so that debuggers can step through them.
+    ///
+    /// Wasm has no source lines, so the line numbers debuggers show are byte offsets in the wasm binary.
+    pub debug_info: bool,
+    /// Whether an engine lets functions be replaced after the module is loaded.
+    ///
+    /// Calls to functions then go through a pointer the engine can redirect, which costs an indirect jump per call.
+    pub hot_patching: bool,
+}
+
+/// Optimization levels, mirroring the `-O` flags of clang.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimization.
+    #[default]
+    None,
+    /// Like `-O1`.
+    Less,
+    /// Like `-O2`.
+    Default,
+    /// Like `-O3`.
+    Aggressive,
+    /// Like `-Os`, favoring code size over speed.
+    Size,
+    /// Like `-Oz`, optimizing for code size at any cost.
+    SizeMin,
+}
+
+/// Upper bounds on resources a module can ask the compiler for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ResourceLimits {
+    /// Maximum number of locals a function can declare, excluding params.
+    pub max_locals: u32,
+    /// Maximum number of blocks, loops and ifs that can be nested in each other within a function.
+    pub max_control_depth: u32,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_locals: 50_000,
+            max_control_depth: 1_000,
+        }
+    }
 }
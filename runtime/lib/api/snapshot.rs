@@ -0,0 +1,18 @@
+/// The state of an instance captured by `Instance::snapshot` to roll it back to with `Instance::restore`.
+///
+/// Memories are copied on capture, so a snapshot costs as much memory as the state it holds.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Contents of the memories created for the instance, which determine their sizes.
+    pub(crate) memories: Vec<Vec<u8>>,
+    /// The store data section, which holds the values of globals.
+    pub(crate) data_section: Option<Vec<u8>>,
+}
+
+impl Snapshot {
+    /// Gets the number of bytes the snapshot holds.
+    pub fn size(&self) -> usize {
+        let data_section_size = self.data_section.as_ref().map_or(0, Vec::len);
+        self.memories.iter().map(Vec::len).sum::<usize>() + data_section_size
+    }
+}
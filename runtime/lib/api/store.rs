@@ -1,3 +1,7 @@
+mod externrefs;
+mod layout;
 mod store;
 
+pub use externrefs::*;
+pub use layout::*;
 pub use store::*;
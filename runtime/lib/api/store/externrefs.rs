@@ -0,0 +1,39 @@
+use std::{any::Any, collections::HashMap, fmt::Debug, rc::Rc};
+
+/// Host objects that externref values refer to by handle.
+///
+/// The store only roots an object until it is removed. Tables keep their own `Rc` to the objects in their slots,
+/// so an object a table still references outlives its removal from here.
+#[derive(Default)]
+pub struct ExternRefs {
+    objects: HashMap<u64, Rc<dyn Any>>,
+    /// The last handle given out. Handle 0 is never given out since it stands for a null externref.
+    last_handle: u64,
+}
+
+impl ExternRefs {
+    /// Roots `object` and returns the handle externref values refer to it by.
+    pub fn insert(&mut self, object: Rc<dyn Any>) -> u64 {
+        self.last_handle += 1;
+        self.objects.insert(self.last_handle, object);
+        self.last_handle
+    }
+
+    /// Gets the object behind `handle`.
+    pub fn get(&self, handle: u64) -> Option<&Rc<dyn Any>> {
+        self.objects.get(&handle)
+    }
+
+    /// Unroots the object behind `handle`, which is freed once no table references it either.
+    pub fn remove(&mut self, handle: u64) -> Option<Rc<dyn Any>> {
+        self.objects.remove(&handle)
+    }
+}
+
+impl Debug for ExternRefs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternRefs")
+            .field("handles", &self.objects.keys())
+            .finish()
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::context::{LENGTH_SIZE, POINTER_SIZE};
+
+/// The length of a region is stored as a `u32`.
+pub(crate) const REGION_LENGTH_SIZE: usize = LENGTH_SIZE as usize;
+
+/// Offsets of the regions of the store data section.
+///
+/// The store data section holds the addresses generated code needs to reach the runtime context.
+/// Every region starts with its number of entries, padded to pointer size, followed by pointer-sized entries.
+///
+/// - `function_resolver -> func_addr`
+/// - `intrinsics -> (length, func_addr*)`
+/// - `functions -> (length, (type, func_addr)*)`
+/// - `memories -> (length, memory_base_addr*)`
+/// - `tables -> (length, table_base_addr*)`
+/// - `globals -> (length, global_addr*)`
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub struct StoreLayout {
+    pub function_resolver: usize,
+    pub intrinsics: usize,
+    pub functions: usize,
+    pub memories: usize,
+    pub tables: usize,
+    pub globals: usize,
+    /// Size of the whole data section in bytes.
+    pub size: usize,
+    lengths: [u32; 5],
+}
+
+impl StoreLayout {
+    /// Lays out a data section with room for the given number of entries.
+    pub fn new(intrinsics: u32, functions: u32, memories: u32, tables: u32, globals: u32) -> Self {
+        let pointer_size = POINTER_SIZE as usize;
+        let region_size =
+            |entries: u32, entry_size: usize| pointer_size + entries as usize * entry_size;
+
+        let function_resolver = 0;
+        let intrinsics_offset = function_resolver + pointer_size;
+        let functions_offset = intrinsics_offset + region_size(intrinsics, pointer_size);
+        let memories_offset = functions_offset + region_size(functions, 2 * pointer_size);
+        let tables_offset = memories_offset + region_size(memories, pointer_size);
+        let globals_offset = tables_offset + region_size(tables, pointer_size);
+        let size = globals_offset + region_size(globals, pointer_size);
+
+        Self {
+            function_resolver,
+            intrinsics: intrinsics_offset,
+            functions: functions_offset,
+            memories: memories_offset,
+            tables: tables_offset,
+            globals: globals_offset,
+            size,
+            lengths: [intrinsics, functions, memories, tables, globals],
+        }
+    }
+
+    /// Gets the offset of the address of the intrinsic at `index`.
+    pub fn intrinsic(&self, index: u32) -> usize {
+        self.intrinsics + Self::entry(index, 1)
+    }
+
+    /// Gets the offset of the type and address of the function at `index`.
+    pub fn function(&self, index: u32) -> usize {
+        self.functions + Self::entry(index, 2)
+    }
+
+    /// Gets the offset of the base address of the memory at `index`.
+    pub fn memory_base(&self, index: u32) -> usize {
+        self.memories + Self::entry(index, 1)
+    }
+
+    /// Gets the offset of the base address of the table at `index`.
+    pub fn table_base(&self, index: u32) -> usize {
+        self.tables + Self::entry(index, 1)
+    }
+
+    /// Gets the offset of the address of the global at `index`.
+    pub fn global(&self, index: u32) -> usize {
+        self.globals + Self::entry(index, 1)
+    }
+
+    /// Gets the offsets of the region lengths and the lengths to store there.
+    pub(crate) fn lengths(&self) -> [(usize, u32); 5] {
+        let [intrinsics, functions, memories, tables, globals] = self.lengths;
+        [
+            (self.intrinsics, intrinsics),
+            (self.functions, functions),
+            (self.memories, memories),
+            (self.tables, tables),
+            (self.globals, globals),
+        ]
+    }
+
+    /// Gets the offset of an entry of `pointers` pointer-sized fields within its region.
+    fn entry(index: u32, pointers: usize) -> usize {
+        POINTER_SIZE as usize + index as usize * pointers * POINTER_SIZE as usize
+    }
+}
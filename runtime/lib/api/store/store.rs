@@ -1,16 +1,90 @@
 use serde::{Deserialize, Serialize};
 
+use super::{ExternRefs, StoreLayout, REGION_LENGTH_SIZE};
+use crate::context::POINTER_SIZE;
+
 /// Store manages the entire global state accessible to a WebAssembly instance.
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// The store data section holds addresses of that state, which embedders can patch in place of the resolved ones.
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Store {
-// Imported Memories
-// Imported Tables
-// Imported Globals
-// Local Memories
-// Local Tables
-// Local Globals
-
-// Imported Functions
-// Intrinsics
-// Version
+    layout: StoreLayout,
+    data_section: Vec<u8>,
+    /// Host objects referenced by externref values.
+    #[serde(skip)]
+    externrefs: ExternRefs,
+    // Imported Memories
+    // Imported Tables
+    // Imported Globals
+    // Local Memories
+    // Local Tables
+    // Local Globals
+
+    // Imported Functions
+    // Intrinsics
+    // Version
+}
+
+impl Store {
+    /// Creates a store with a zeroed data section laid out by `layout`.
+    pub fn new(layout: StoreLayout) -> Self {
+        let mut data_section = vec![0; layout.size];
+        for (offset, length) in layout.lengths() {
+            data_section[offset..offset + REGION_LENGTH_SIZE]
+                .copy_from_slice(&length.to_ne_bytes());
+        }
+
+        Self {
+            layout,
+            data_section,
+            externrefs: ExternRefs::default(),
+        }
+    }
+
+    /// Gets the layout of the store data section.
+    pub fn layout(&self) -> &StoreLayout {
+        &self.layout
+    }
+
+    /// Gets the store data section.
+    pub fn data_section(&self) -> &[u8] {
+        &self.data_section
+    }
+
+    /// Gets the store data section for patching addresses in place of the default resolver.
+    ///
+    /// This is an escape hatch for embedders doing their own linking.
+    /// Offsets are given by the [`StoreLayout`](struct.StoreLayout.html) and addresses are stored in native byte order.
+    /// The section cannot be resized, so patching it cannot invalidate addresses generated code holds.
+    ///
+    /// Only the base address of memory 0 is picked up so far. An instance points its code at a non-zero base on each
+    /// call, in place of the base of its memory, until the code grows the memory. The base must point to at least as
+    /// many bytes as the memory has.
+    pub fn data_section_mut(&mut self) -> &mut [u8] {
+        &mut self.data_section
+    }
+
+    /// Gets the host objects referenced by externref values.
+    pub fn externrefs(&self) -> &ExternRefs {
+        &self.externrefs
+    }
+
+    /// Gets the host objects referenced by externref values for rooting and unrooting them.
+    pub fn externrefs_mut(&mut self) -> &mut ExternRefs {
+        &mut self.externrefs
+    }
+
+    /// Gets the base address of the memory at `index` from the data section.
+    pub fn memory_base(&self, index: u32) -> Option<usize> {
+        let offset = self.layout.memory_base(index);
+        if offset >= self.layout.tables {
+            return None;
+        }
+
+        let bytes = self
+            .data_section
+            .get(offset..offset + POINTER_SIZE as usize)?;
+
+        Some(usize::from_ne_bytes(bytes.try_into().ok()?))
+    }
 }
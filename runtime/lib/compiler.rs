@@ -1,12 +1,15 @@
 mod compiler;
 mod data;
 mod elem;
-mod exports;
+pub(crate) mod exports;
 mod function;
+mod generator;
 mod global;
 mod imports;
-mod llvm;
+mod init_expr;
+pub(crate) mod llvm;
 mod memory;
+mod names;
 mod table;
 mod utils;
 pub(crate) mod value;
@@ -16,5 +19,7 @@ pub use data::*;
 pub use elem::*;
 pub use function::*;
 pub use global::*;
+pub use init_expr::*;
 pub use memory::*;
+pub use names::*;
 pub use table::*;
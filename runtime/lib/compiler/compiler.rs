@@ -1,28 +1,42 @@
-use std::pin::Pin;
+use std::{collections::HashMap, io::Write, pin::Pin, rc::Rc};
 
 use serde::{Deserialize, Serialize};
 
 use anyhow::Result;
-use log::debug;
+use log::{debug, warn};
 use wasmparser::{
     DataSectionReader, ElementSectionReader, ExportSectionReader, FunctionBody,
     FunctionSectionReader, GlobalSectionReader, ImportSectionEntryType, ImportSectionReader,
-    MemorySectionReader, Parser, Payload, TableSectionReader, TypeDef, TypeSectionReader,
+    MemorySectionReader, Operator, Parser, Payload, TableSectionReader, TypeDef, TypeSectionReader,
 };
 
 use crate::{
     compiler::exports::ExportKind,
     errors::CompilerError,
-    types::{FuncType, Limits},
+    types::{FuncType, Limits, ValType, PAGE_SIZE},
+    CallGraph, Cfg, CfgBlock, Dependency, FunctionStats, OptLevel, ResourceLimits,
 };
 
 use super::{
     exports::{Export, Exports},
+    generator::{
+        DataInitializerGenerator, ElementInitializerGenerator, FunctionBodyGenerator,
+        ImportFunctionGenerator, TrampolineGenerator,
+    },
     imports::{Import, Imports},
-    llvm::LLVM,
+    llvm::{
+        basic_block::LLBasicBlock,
+        context::LLContext,
+        di_builder::LLDIBuilder,
+        function::LLFunction,
+        module::{function_reference_symbol, LLModule, MEMORY_IMAGE_SYMBOL_PREFIX},
+        values::LLGlobal,
+        LLVM,
+    },
     utils::convert,
     value::Value,
-    Data, Element, Function, Global, Memory, Table,
+    Data, DataKind, Element, ElementKind, Function, Global, InitExpr, Memory, MemoryImage, Names,
+    Table,
 };
 
 /// The compiler is responsible for compiling a module.
@@ -33,6 +47,16 @@ pub struct Compiler {
     pub(crate) llvm: Option<Pin<Box<LLVM>>>,
     /// Option for enabling lift-off compilation.
     pub liftoff: bool,
+    /// Limits guarding against untrusted modules.
+    pub limits: ResourceLimits,
+    /// The optimization level of the generated code.
+    pub opt_level: OptLevel,
+    /// Whether functions keep frame pointers for profilers.
+    pub preserve_frame_pointer: bool,
+    /// Whether functions carry debug info for debuggers.
+    pub debug_info: bool,
+    /// Whether initial memory images are built from the data segments at compile time.
+    pub static_memory_image: bool,
     /// Compiler data.
     pub info: ModuleInfo,
 }
@@ -59,10 +83,42 @@ pub struct ModuleInfo {
     pub elements: Vec<Element>,
     /// An ordered list of data from the data section.
     pub data: Vec<Data>,
+    /// The number of data segments from the data count section, which comes before the code referring to them.
+    pub data_count: Option<u32>,
     /// Represents the current function being compiled.
     pub current_frame: Option<FunctionFrame>,
     /// The start function.
     pub start_function: Option<u32>,
+    /// Dependencies between functions.
+    pub call_graph: CallGraph,
+    /// Initial contents of local memories, only built with `Options.static_memory_image`.
+    pub memory_images: Vec<MemoryImage>,
+    /// Names of functions and locals from the name section.
+    pub names: Names,
+}
+
+impl ModuleInfo {
+    /// Gets the symbol of the function generated for the function at `index`, which calls the host for an imported one.
+    ///
+    /// It is the name the name section gives the function, or `func_{index}` if it has no name that can be a symbol.
+    pub(crate) fn function_symbol(&self, index: u32) -> String {
+        self.names
+            .functions
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("func_{}", index))
+    }
+
+    /// Gets the id of the type at `type_index`, which is the index of the first type with the same params and results.
+    ///
+    /// Types are compared by structure, so indirect calls compare the ids of types rather than their indices.
+    pub(crate) fn type_id(&self, type_index: u32) -> u32 {
+        let ty = &self.types[type_index as usize];
+        self.types
+            .iter()
+            .position(|other| other == ty)
+            .unwrap_or(type_index as usize) as u32
+    }
 }
 
 /// Represents the current function being compiled.
@@ -76,16 +132,75 @@ pub struct FunctionFrame {
 
 impl Compiler {
     /// Creates a new `Compiler` with the given options.
-    pub fn new(liftoff: bool) -> Self {
+    pub fn new(
+        liftoff: bool,
+        limits: ResourceLimits,
+        opt_level: OptLevel,
+        preserve_frame_pointer: bool,
+        debug_info: bool,
+        static_memory_image: bool,
+    ) -> Self {
         Self {
             liftoff,
+            limits,
+            opt_level,
+            preserve_frame_pointer,
+            debug_info,
+            static_memory_image,
             ..Default::default()
         }
     }
 
-    /// Compiles provided wasm bytes.
-    pub fn compile(&mut self, wasm: &[u8]) -> Result<()> {
-        let mut llvm = LLVM::new()?;
+    /// Compiles provided wasm bytes into a module living in `context`, which may be shared with other compilers.
+    pub(crate) fn compile(&mut self, wasm: &[u8], context: Rc<LLContext>) -> Result<()> {
+        let llvm = self.generate(wasm, context)?;
+
+        debug!(
+            "module: {}",
+            llvm.module.as_ref().unwrap().print_to_string()
+        );
+
+        llvm.module.as_ref().unwrap().verify()?;
+
+        llvm.module.as_ref().unwrap().optimize(self.opt_level);
+
+        self.llvm = Some(llvm);
+
+        Ok(())
+    }
+
+    /// Generates and verifies the LLVM IR for provided wasm bytes, stopping before optimization.
+    ///
+    /// The returned code is not kept by the compiler. This separates the cost of code generation from that of
+    /// the LLVM backend in benchmarks.
+    pub(crate) fn compile_to_ir_only(
+        &mut self,
+        wasm: &[u8],
+        context: Rc<LLContext>,
+    ) -> Result<Pin<Box<LLVM>>> {
+        let llvm = self.generate(wasm, context)?;
+        llvm.module.as_ref().unwrap().verify()?;
+
+        Ok(llvm)
+    }
+
+    /// Parses provided wasm bytes, generating LLVM IR for their functions.
+    fn generate(&mut self, wasm: &[u8], context: Rc<LLContext>) -> Result<Pin<Box<LLVM>>> {
+        let mut llvm = LLVM::new(context)?;
+        let mut body_index = 0;
+
+        self.info.names = Names::parse(wasm);
+
+        if self.debug_info {
+            let module = llvm.module.as_ref().unwrap();
+            let is_optimized = self.opt_level != OptLevel::None;
+            llvm.di_builder = Some(LLDIBuilder::new(
+                module,
+                &llvm.context,
+                "module.wasm",
+                is_optimized,
+            ));
+        }
 
         for payload in Parser::new(0).parse_all(wasm) {
             match payload? {
@@ -96,11 +211,11 @@ impl Compiler {
                 }
                 Payload::ImportSection(reader) => {
                     debug!("======= ImportSection =======");
-                    self.compile_imports(reader)?;
+                    self.compile_imports(reader, &mut llvm)?;
                 }
                 Payload::FunctionSection(reader) => {
                     debug!("======= FunctionSection =======");
-                    self.compile_functions(reader)?;
+                    self.compile_functions(reader, &mut llvm)?;
                 }
                 Payload::TableSection(reader) => {
                     debug!("======= TableSection =======");
@@ -124,14 +239,15 @@ impl Compiler {
                 }
                 Payload::ElementSection(reader) => {
                     debug!("======= ElementSection =======");
-                    self.compile_elements(reader)?;
+                    self.compile_elements(reader, &mut llvm)?;
                 }
-                Payload::DataCountSection { .. } => {
+                Payload::DataCountSection { count, .. } => {
                     debug!("======= DataCountSection =======");
+                    self.info.data_count = Some(count);
                 }
                 Payload::DataSection(reader) => {
                     debug!("======= DataSection =======");
-                    self.compile_data(reader)?;
+                    self.compile_data(reader, &mut llvm)?;
                 }
                 Payload::CustomSection { name, .. } => {
                     debug!("======= CustomSection =======");
@@ -142,7 +258,9 @@ impl Compiler {
                 }
                 Payload::CodeSectionEntry(body) => {
                     debug!("======= CodeSectionEntry =======");
-                    self.compile_function_body(body)?;
+                    let function_index = self.info.imports.functions.len() as u32 + body_index;
+                    self.compile_function_body(body, function_index, &llvm)?;
+                    body_index += 1;
                 }
                 Payload::ModuleSectionStart { .. } => {
                     debug!("======= ModuleSectionStart =======");
@@ -162,18 +280,93 @@ impl Compiler {
             }
         }
 
-        // Print module.
-        llvm.module.as_ref().unwrap().print();
+        // Data segments come after function bodies so images are only complete at the end.
+        if self.static_memory_image {
+            self.compile_memory_images(&llvm)?;
+        }
+
+        self.compile_trampolines(&mut llvm)?;
 
-        self.llvm = Some(llvm);
+        if let Some(di_builder) = llvm.di_builder.take() {
+            di_builder.finalize();
+        }
 
-        Ok(())
+        Ok(llvm)
     }
 }
 
 impl Compiler {
+    /// Writes the textual LLVM IR of the compiled module to `writer`.
+    pub fn write_ir<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.llvm_module()?.write_ir(writer)
+    }
+
+    /// Gets the textual LLVM IR of the compiled module.
+    pub fn ir_to_string(&self) -> Result<String> {
+        Ok(self.llvm_module()?.print_to_string())
+    }
+
+    /// Gets the size of the code generated for a non-imported function.
+    pub(crate) fn function_stats(&self, function_index: u32) -> Result<FunctionStats> {
+        let basic_blocks = self.llvm_function(function_index)?.basic_blocks();
+        let instructions = basic_blocks
+            .iter()
+            .map(LLBasicBlock::instruction_count)
+            .sum();
+
+        Ok(FunctionStats {
+            basic_blocks: basic_blocks.len(),
+            instructions,
+        })
+    }
+
+    /// Gets the control-flow graph of the code generated for a non-imported function.
+    pub(crate) fn function_cfg(&self, function_index: u32) -> Result<Cfg> {
+        let blocks = self
+            .llvm_function(function_index)?
+            .basic_blocks()
+            .iter()
+            .map(|basic_block| CfgBlock {
+                name: basic_block.name(),
+                successors: basic_block
+                    .successors()
+                    .iter()
+                    .map(LLBasicBlock::name)
+                    .collect(),
+                is_terminated: basic_block.has_terminator(),
+            })
+            .collect();
+
+        Ok(Cfg { blocks })
+    }
+
+    /// Gets the LLVM function generated for a non-imported function.
+    fn llvm_function(&self, function_index: u32) -> Result<&LLFunction> {
+        let llvm = self.llvm.as_ref().ok_or(CompilerError::ModuleNotCompiled)?;
+        let function = (function_index as usize)
+            .checked_sub(self.info.imports.functions.len())
+            .and_then(|local_index| llvm.info.functions.get(local_index))
+            .ok_or_else(|| {
+                CompilerError::IndexOutOfBounds(format!("function {}", function_index))
+            })?;
+
+        Ok(function)
+    }
+
+    /// Gets the LLVM module, which only a compiler that compiled it rather than deserialized it has.
+    pub(crate) fn llvm_module(&self) -> Result<&LLModule> {
+        let llvm = self.llvm.as_ref().ok_or(CompilerError::ModuleNotCompiled)?;
+
+        llvm.module
+            .as_ref()
+            .ok_or_else(|| CompilerError::ModuleNotCompiled.into())
+    }
+
     /// Compiles function types in type section.
+    ///
+    /// Entries with the same signature share one LLVM function type, so functions of either get the same one.
     pub(crate) fn compile_types(&mut self, reader: TypeSectionReader, llvm: &mut LLVM) -> Result<()> {
+        let mut llvm_func_types = HashMap::new();
         for result in reader.into_iter() {
             let typedef = result?;
 
@@ -182,8 +375,14 @@ impl Compiler {
             match typedef {
                 TypeDef::Func(ty) => {
                     let wasmo_func_ty = convert::to_wasmo_functype(&ty)?;
-                    let llvm_func_ty = convert::to_llvm_functype(&llvm.context, &wasmo_func_ty);
-                    // TODO(appcypher): Store llvm func type in llvm.types.
+                    let llvm_func_ty =
+                        llvm_func_types
+                            .entry(wasmo_func_ty.clone())
+                            .or_insert_with(|| {
+                                Rc::new(convert::to_llvm_functype(&llvm.context, &wasmo_func_ty))
+                            });
+
+                    llvm.info.types.push(Rc::clone(llvm_func_ty));
                     self.info.types.push(wasmo_func_ty);
                 }
                 t => {
@@ -198,7 +397,11 @@ impl Compiler {
     }
 
     /// Compiles imports in import section.
-    pub fn compile_imports(&mut self, reader: ImportSectionReader) -> Result<()> {
+    pub(crate) fn compile_imports(
+        &mut self,
+        reader: ImportSectionReader,
+        llvm: &mut LLVM,
+    ) -> Result<()> {
         for result in reader.into_iter() {
             let import = result?;
 
@@ -227,32 +430,33 @@ impl Compiler {
                     ));
                 }
                 ImportSectionEntryType::Memory(ty) => {
-                    // TODO(appcypher): Wasmo does not support memory64 proposal yet.
-                    if ty.memory64 {
-                        return Err(CompilerError::UnsupportedMemory64Proposal.into());
-                    }
-
                     self.info.imports.memories.push(Import::new(
                         import.module.to_string(),
                         import.field.map(|s| s.to_string()),
                         self.info.memories.len() as u32,
                     ));
 
-                    self.info
-                        .memories
-                        .push(Memory::new(Limits::new(ty.initial, ty.maximum), ty.shared));
+                    self.info.memories.push(convert::to_wasmo_memory(&ty));
                 }
                 ImportSectionEntryType::Global(ty) => {
-                    self.info.imports.globals.push(Import::new(
+                    let import = Import::new(
                         import.module.to_string(),
                         import.field.map(|s| s.to_string()),
                         self.info.globals.len() as u32,
-                    ));
+                    );
 
-                    self.info.globals.push(Global::new(
-                        convert::to_wasmo_valtype(&ty.content_type)?,
-                        ty.mutable,
-                    ));
+                    let content_type = convert::to_wasmo_valtype(&ty.content_type)?;
+
+                    // Imported globals are linked against host symbols by name.
+                    llvm.module.as_ref().unwrap().add_external_global(
+                        &convert::to_llvm_valtype(&llvm.context, &content_type),
+                        &import.symbol_name(),
+                    )?;
+
+                    self.info.imports.globals.push(import);
+                    self.info
+                        .globals
+                        .push(Global::new(content_type, ty.mutable));
                 }
                 t => {
                     return Err(
@@ -266,12 +470,47 @@ impl Compiler {
     }
 
     /// Compiles functions in function section.
-    pub fn compile_functions(&mut self, reader: FunctionSectionReader) -> Result<()> {
+    pub(crate) fn compile_functions(
+        &mut self,
+        reader: FunctionSectionReader,
+        llvm: &mut LLVM,
+    ) -> Result<()> {
         for result in reader.into_iter() {
             let type_index = result?;
 
             debug!("function type_index: {:?}", type_index);
 
+            let function_type =
+                llvm.info.types.get(type_index as usize).ok_or_else(|| {
+                    CompilerError::IndexOutOfBounds(format!("type {}", type_index))
+                })?;
+
+            let function = LLFunction::new(
+                &self.info.function_symbol(self.info.functions.len() as u32),
+                llvm.module.as_mut().unwrap(),
+                Rc::clone(function_type),
+            )?;
+
+            // Size levels also tell the backend to pick smaller instruction sequences.
+            match self.opt_level {
+                OptLevel::Size => function.add_attribute(&llvm.context, "optsize"),
+                OptLevel::SizeMin => {
+                    function.add_attribute(&llvm.context, "optsize");
+                    function.add_attribute(&llvm.context, "minsize");
+                }
+                _ => (),
+            }
+
+            if self.preserve_frame_pointer {
+                function.add_string_attribute(&llvm.context, "frame-pointer", "all");
+            }
+
+            // Names may be those of C library functions, which calls must not be taken for calls into the library.
+            if !self.info.names.functions.is_empty() {
+                function.add_string_attribute(&llvm.context, "no-builtins", "");
+            }
+
+            llvm.info.functions.push(function);
             self.info.functions.push(Function::new(type_index));
         }
 
@@ -301,9 +540,7 @@ impl Compiler {
 
             debug!("memory type: {:?}", ty);
 
-            self.info
-                .memories
-                .push(Memory::new(Limits::new(ty.initial, ty.maximum), ty.shared));
+            self.info.memories.push(convert::to_wasmo_memory(&ty));
         }
 
         Ok(())
@@ -328,39 +565,238 @@ impl Compiler {
     }
 
     /// Compiles data in data section.
-    pub fn compile_data(&mut self, reader: DataSectionReader) -> Result<()> {
+    pub(crate) fn compile_data(
+        &mut self,
+        reader: DataSectionReader,
+        llvm: &mut LLVM,
+    ) -> Result<()> {
         for result in reader.into_iter() {
             let data = result?;
 
             debug!("data: {:?}", data);
 
-            self.info
-                .data
-                .push(Data::new(convert::to_wasmo_data_kind(&data.kind)));
+            let index = self.info.data.len() as u32;
+            let kind = convert::to_wasmo_data_kind(&data.kind)?;
+            match kind {
+                // Active segments are dropped once instantiation copies them, so `memory.init` never reads their bytes.
+                DataKind::Passive => llvm
+                    .module
+                    .as_ref()
+                    .unwrap()
+                    .define_data_segment(index, data.data)?,
+                // TODO(appcypher): Support other memories and offsets from imported globals.
+                DataKind::Active {
+                    memory_index: 0,
+                    offset,
+                } => {
+                    if let Some(offset) = offset.const_offset() {
+                        DataInitializerGenerator::new(llvm, index, offset, data.data).generate()?
+                    }
+                }
+                DataKind::Active { .. } => (),
+            }
 
-            // llvm.codegen_data(reader)?;
+            self.info.data.push(Data::new(kind, data.data.to_vec()));
+        }
+
+        Ok(())
+    }
+
+    /// Builds the initial images of local memories, each emitted as a constant global for linked code to copy from.
+    pub(crate) fn compile_memory_images(&mut self, llvm: &LLVM) -> Result<()> {
+        let imported_count = self.info.imports.memories.len();
+        let images = self.info.memories[imported_count..]
+            .iter()
+            .zip(imported_count as u32..)
+            .filter_map(|(memory, index)| {
+                // Memories too large to allocate fail instantiation before any image is copied.
+                let size = memory.limits.min.checked_mul(PAGE_SIZE as u64)?;
+                let size = usize::try_from(size).ok()?;
+                MemoryImage::build(index, size, &self.info.data)
+            })
+            .collect::<Vec<_>>();
+
+        for image in images.iter() {
+            let name = format!("{}{}", MEMORY_IMAGE_SYMBOL_PREFIX, image.memory_index);
+            llvm.module
+                .as_ref()
+                .unwrap()
+                .add_constant_data(&name, &image.bytes)?;
+        }
+
+        self.info.memory_images = images;
+
+        Ok(())
+    }
+
+    /// Generates the trampolines the host calls exported functions and the start function through.
+    ///
+    /// Only local functions whose params and results are all numbers get one.
+    pub(crate) fn compile_trampolines(&mut self, llvm: &mut LLVM) -> Result<()> {
+        let imported_count = self.info.imports.functions.len();
+        let mut indices = self
+            .info
+            .exports
+            .inner
+            .values()
+            .filter(|export| matches!(export.kind, ExportKind::Function))
+            .map(|export| export.index)
+            .chain(self.info.start_function)
+            .filter(|index| *index as usize >= imported_count)
+            .collect::<Vec<_>>();
+
+        // Exports are kept in a map, so they are sorted for the module to come out the same every time.
+        indices.sort_unstable();
+        indices.dedup();
+
+        for index in indices {
+            let ty = &self.info.types[self.info.functions[index as usize].type_index as usize];
+            if !ty
+                .params
+                .iter()
+                .chain(&ty.results)
+                .all(|ty| matches!(ty, ValType::Num(_)))
+            {
+                continue;
+            }
+
+            let function = Rc::clone(&llvm.info.functions[index as usize - imported_count]);
+            TrampolineGenerator::new(llvm, index, function, ty).generate()?;
         }
 
         Ok(())
     }
 
     /// Compiles elements in element section.
-    pub fn compile_elements(&mut self, reader: ElementSectionReader) -> Result<()> {
+    pub(crate) fn compile_elements(
+        &mut self,
+        reader: ElementSectionReader,
+        llvm: &mut LLVM,
+    ) -> Result<()> {
         for result in reader.into_iter() {
             let elem = result?;
 
             debug!("elem items: {:?}", elem.items);
 
-            self.info
-                .elements
-                .push(Element::new(convert::to_wasmo_element_kind(&elem.kind)));
+            if let wasmparser::ElementKind::Active { table_index, .. } = elem.kind {
+                let table = self.info.tables.get(table_index as usize).ok_or_else(|| {
+                    CompilerError::IndexOutOfBounds(format!("table {}", table_index))
+                })?;
+
+                let ty = convert::to_wasmo_valtype(&elem.ty)?;
+                if ty != table.element_type {
+                    return Err(CompilerError::TypeMismatch(format!(
+                        "element segment of type {:?} in table {} of type {:?}",
+                        ty, table_index, table.element_type
+                    ))
+                    .into());
+                }
+            }
+
+            let items = elem
+                .items
+                .get_items_reader()?
+                .into_iter()
+                .map(|item| convert::to_wasmo_element_item(&item?))
+                .collect::<Result<Vec<_>>>();
+            let items = match items {
+                Ok(items) => Some(items),
+                Err(error)
+                    if matches!(
+                        error.downcast_ref(),
+                        Some(CompilerError::UnsupportedInitExpr(_))
+                    ) =>
+                {
+                    warn!(
+                        "unsupported element item, segment will not initialize: {}",
+                        error
+                    );
+                    None
+                }
+                Err(error) => return Err(error),
+            };
 
-            // llvm.codegen_element(reader)?;
+            for function_index in items.iter().flatten().flatten() {
+                if *function_index as usize >= self.info.functions.len() {
+                    return Err(CompilerError::IndexOutOfBounds(format!(
+                        "function {}",
+                        function_index
+                    ))
+                    .into());
+                }
+            }
+
+            let index = self.info.elements.len() as u32;
+            let element = Element::new(convert::to_wasmo_element_kind(&elem.kind)?, items);
+
+            // The functions are already declared, so segments referencing them can be compiled now.
+            if let Some(items) = &element.items {
+                let ty =
+                    convert::to_llvm_valtype(&llvm.context, &convert::to_wasmo_valtype(&elem.ty)?);
+                let references = items
+                    .iter()
+                    .map(|item| {
+                        item.map(|function_index| {
+                            self.compile_function_reference(function_index, llvm)
+                        })
+                        .transpose()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                match element.kind {
+                    ElementKind::Passive => llvm.module.as_ref().unwrap().define_element_segment(
+                        index,
+                        &ty,
+                        &llvm.context.i8_type().pointer_type(),
+                        &llvm.context.i64_type(),
+                        &references,
+                    )?,
+                    // TODO(appcypher): Support other tables and offsets from imported globals.
+                    ElementKind::Active {
+                        table_index: 0,
+                        offset: InitExpr::I32Const(offset),
+                    } => ElementInitializerGenerator::new(
+                        llvm,
+                        index,
+                        offset as u32,
+                        ty,
+                        &references,
+                    )
+                    .generate()?,
+                    ElementKind::Active { .. } | ElementKind::Declared => (),
+                }
+            }
+
+            self.info.elements.push(element);
         }
 
         Ok(())
     }
 
+    /// Gets the global funcrefs to the function at `function_index` point to, defining it if needed.
+    ///
+    /// Imported functions are referenced through a function calling the host, generated along with the global.
+    fn compile_function_reference(&self, function_index: u32, llvm: &mut LLVM) -> Result<LLGlobal> {
+        let type_index = self.info.functions[function_index as usize].type_index;
+        let type_id = self.info.type_id(type_index);
+        let symbol = function_reference_symbol(function_index);
+        if let Some(global) = llvm.module.as_ref().unwrap().get_global(&symbol)? {
+            return Ok(global);
+        }
+
+        let function =
+            match (function_index as usize).checked_sub(self.info.imports.functions.len()) {
+                Some(local_index) => Rc::clone(&llvm.info.functions[local_index]),
+                None => ImportFunctionGenerator::new(llvm, &self.info, function_index, self.limits)
+                    .generate()?,
+            };
+
+        llvm.module
+            .as_ref()
+            .unwrap()
+            .add_function_reference(function_index, &function, type_id)
+    }
+
     /// Compiles exports in export section.
     pub fn compile_exports(&mut self, reader: ExportSectionReader) -> Result<()> {
         for result in reader.into_iter() {
@@ -405,28 +841,69 @@ impl Compiler {
     }
 
     /// Compiles start function.
-    pub fn compile_start_function(&mut self, _func: u32) -> Result<()> {
-        self.info.start_function = Some(_func);
+    ///
+    /// The index is in the function index space, so the start function can be an imported one.
+    pub fn compile_start_function(&mut self, func: u32) -> Result<()> {
+        if func as usize >= self.info.functions.len() {
+            return Err(CompilerError::IndexOutOfBounds(format!("start function {}", func)).into());
+        }
+
+        self.info.start_function = Some(func);
         // llvm.codegen_start_function(reader)?;
         Ok(())
     }
 
     /// Compiles function body.
-    pub fn compile_function_body(&mut self, body: FunctionBody) -> Result<()> {
+    pub(crate) fn compile_function_body(
+        &mut self,
+        body: FunctionBody,
+        function_index: u32,
+        llvm: &LLVM,
+    ) -> Result<()> {
         debug!("function body: {:?}", body);
 
-        body.get_locals_reader().into_iter().for_each(|r| {
-            r.into_iter().for_each(|i| {
-                debug!("local: {:?}", i);
-            });
-        });
-
-        body.get_operators_reader().into_iter().for_each(|r| {
-            r.into_iter().for_each(|i| {
-                debug!("operator: {:?}", i);
-            });
-        });
+        for operator in body.get_operators_reader()? {
+            match operator? {
+                Operator::Call {
+                    function_index: callee,
+                }
+                | Operator::ReturnCall {
+                    function_index: callee,
+                }
+                | Operator::RefFunc {
+                    function_index: callee,
+                } => {
+                    self.info
+                        .call_graph
+                        .add(function_index, Dependency::Function(callee));
+                }
+                Operator::CallIndirect { table_index, .. }
+                | Operator::ReturnCallIndirect { table_index, .. } => {
+                    self.info
+                        .call_graph
+                        .add(function_index, Dependency::AnyInTable(table_index));
+                }
+                _ => (),
+            }
+        }
 
-        Ok(())
+        let local_index = function_index as usize - self.info.imports.functions.len();
+        let function = llvm.info.functions.get(local_index).ok_or_else(|| {
+            CompilerError::IndexOutOfBounds(format!("function {}", function_index))
+        })?;
+
+        let type_index = self.info.functions[function_index as usize].type_index;
+        let func_type = &self.info.types[type_index as usize];
+
+        FunctionBodyGenerator::new(
+            llvm,
+            &self.info,
+            function_index,
+            Rc::clone(function),
+            func_type,
+            body,
+            self.limits,
+        )
+        .generate()
     }
 }
@@ -1,9 +1,13 @@
 use serde::{Deserialize, Serialize};
 
+use super::InitExpr;
+
 /// The `Data` section contains the initial values of the linear memory.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Data {
     pub kind: DataKind,
+    /// The bytes the segment initializes memory with.
+    pub bytes: Vec<u8>,
 }
 
 /// The kind of data segment.
@@ -15,12 +19,59 @@ pub enum DataKind {
     Passive,
     /// Active represents a data segment that is initialized by the program.
     ///
-    /// `memory_index` is the index of the memory to use.
-    Active { memory_index: u32 },
+    /// `memory_index` is the index of the memory to use and `offset` is where the bytes go in it.
+    Active { memory_index: u32, offset: InitExpr },
+}
+
+/// The initial contents of a memory, built at compile time from the active data segments targeting it.
+///
+/// Bytes past the end of the image are zero.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryImage {
+    pub memory_index: u32,
+    pub bytes: Vec<u8>,
 }
 
 impl Data {
-    pub fn new(kind: DataKind) -> Self {
-        Self { kind }
+    pub fn new(kind: DataKind, bytes: Vec<u8>) -> Self {
+        Self { kind, bytes }
+    }
+}
+
+impl MemoryImage {
+    /// Builds the image of the memory at `memory_index` from `data`, with `size` the size of the memory in bytes.
+    ///
+    /// Returns `None` if no segment targets the memory, or if one has an offset only known at instantiation or
+    /// does not fit, in which case instantiation initializes the memory segment by segment.
+    pub fn build(memory_index: u32, size: usize, data: &[Data]) -> Option<Self> {
+        let mut bytes = vec![];
+        for segment in data {
+            let offset = match segment.kind {
+                DataKind::Active {
+                    memory_index: index,
+                    offset,
+                } if index == memory_index => usize::try_from(offset.const_offset()?).ok()?,
+                _ => continue,
+            };
+
+            // Later segments overwrite earlier ones where they overlap.
+            let end = offset
+                .checked_add(segment.bytes.len())
+                .filter(|end| *end <= size)?;
+            if bytes.len() < end {
+                bytes.resize(end, 0);
+            }
+
+            bytes[offset..end].copy_from_slice(&segment.bytes);
+        }
+
+        if bytes.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            memory_index,
+            bytes,
+        })
     }
 }
@@ -1,19 +1,31 @@
 use serde::{Deserialize, Serialize};
 
+use super::InitExpr;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Element {
     pub kind: ElementKind,
+    /// The indices of the functions the segment references, with `None` for a null reference.
+    ///
+    /// This is `None` itself if an item is an expression other than `ref.func` and `ref.null`, which cannot be
+    /// evaluated yet.
+    pub items: Option<Vec<Option<u32>>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ElementKind {
     Passive,
-    Active { table_index: u32 },
+    Active { table_index: u32, offset: InitExpr },
     Declared,
 }
 
 impl Element {
-    pub fn new(kind: ElementKind) -> Self {
-        Self { kind }
+    pub fn new(kind: ElementKind, items: Option<Vec<Option<u32>>>) -> Self {
+        Self { kind, items }
+    }
+
+    /// Whether the references of the segment are compiled, which they are unless its items cannot be evaluated yet.
+    pub fn is_compiled(&self) -> bool {
+        self.items.is_some()
     }
 }
@@ -0,0 +1,15 @@
+mod control;
+mod data;
+mod elem;
+mod function;
+mod import;
+mod operator;
+mod trampoline;
+
+pub(crate) use control::*;
+pub(crate) use data::*;
+pub(crate) use elem::*;
+pub(crate) use function::*;
+pub(crate) use import::*;
+pub(crate) use operator::*;
+pub(crate) use trampoline::*;
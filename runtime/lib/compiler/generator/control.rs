@@ -0,0 +1,72 @@
+use std::rc::Rc;
+
+use crate::compiler::llvm::{basic_block::LLBasicBlock, types::LLNumType, values::LLValue};
+
+/// A block, loop or if whose `end` has not been reached yet.
+///
+/// Each keeps the height of the value stack at its start so that values it leaves behind can be dropped at its end.
+pub(crate) enum Control {
+    Block {
+        end: LLBasicBlock,
+        stack_height: usize,
+        results: ControlResults,
+    },
+    Loop {
+        begin: LLBasicBlock,
+        end: LLBasicBlock,
+        stack_height: usize,
+        results: ControlResults,
+    },
+    If {
+        /// The block taken when the condition is false, until an `else` or the `end` appends it.
+        else_block: Option<LLBasicBlock>,
+        end: LLBasicBlock,
+        stack_height: usize,
+        results: ControlResults,
+    },
+}
+
+/// The results of a control, which are merged from the values each branch to its end carries.
+pub(crate) struct ControlResults {
+    pub(crate) types: Vec<LLNumType>,
+    /// The values carried by each branch to the end, along with the block branching.
+    ///
+    /// A block branching to the end several times, like a `br_table` with several targets, appears once per branch.
+    pub(crate) incoming: Vec<(Vec<Rc<dyn LLValue>>, LLBasicBlock)>,
+}
+
+impl Control {
+    /// Gets the block a branch to this control jumps to.
+    ///
+    /// Branching to a loop continues it, while branching to anything else leaves it.
+    pub(crate) fn branch_target(&self) -> &LLBasicBlock {
+        match self {
+            Control::Loop { begin, .. } => begin,
+            _ => self.end(),
+        }
+    }
+
+    /// Gets the block following the control.
+    pub(crate) fn end(&self) -> &LLBasicBlock {
+        match self {
+            Control::Block { end, .. } | Control::Loop { end, .. } | Control::If { end, .. } => end,
+        }
+    }
+
+    /// Gets the results a branch to this control carries, which there are none of for a loop.
+    pub(crate) fn branch_results_mut(&mut self) -> Option<&mut ControlResults> {
+        match self {
+            Control::Loop { .. } => None,
+            Control::Block { results, .. } | Control::If { results, .. } => Some(results),
+        }
+    }
+}
+
+impl ControlResults {
+    pub(crate) fn new(types: Vec<LLNumType>) -> Self {
+        Self {
+            types,
+            incoming: vec![],
+        }
+    }
+}
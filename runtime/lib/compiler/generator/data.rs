@@ -0,0 +1,112 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use llvm_sys::LLVMIntPredicate;
+
+use crate::{
+    compiler::llvm::{
+        basic_block::LLBasicBlock,
+        builder::LLBuilder,
+        function::LLFunction,
+        module::{data_initializer_symbol, DATA_BYTES_SYMBOL_PREFIX},
+        types::LLResultType,
+        LLVM,
+    },
+    errors::CompilerError,
+};
+
+/// Generates the function copying an active data segment into memory 0 at its offset.
+///
+/// The function takes no arguments and returns an `i32` that is zero if the segment does not fit in the memory, in
+/// which case nothing is copied.
+pub(crate) struct DataInitializerGenerator<'a> {
+    llvm: &'a mut LLVM,
+    data_index: u32,
+    offset: u64,
+    bytes: &'a [u8],
+}
+
+impl<'a> DataInitializerGenerator<'a> {
+    pub(crate) fn new(llvm: &'a mut LLVM, data_index: u32, offset: u64, bytes: &'a [u8]) -> Self {
+        Self {
+            llvm,
+            data_index,
+            offset,
+            bytes,
+        }
+    }
+
+    /// Generates the initializer, named after the memory and segment it copies.
+    pub(crate) fn generate(self) -> Result<()> {
+        let context = &self.llvm.context;
+        let i1_type = context.i1_type();
+        let i32_type = context.i32_type();
+        let i64_type = context.i64_type();
+        let byte_type = context.i8_type();
+
+        let module = self.llvm.module.as_mut().unwrap();
+        let data = module.add_constant_data(
+            &format!("{}{}", DATA_BYTES_SYMBOL_PREFIX, self.data_index),
+            self.bytes,
+        )?;
+        let function = LLFunction::new(
+            &data_initializer_symbol(0, self.data_index),
+            module,
+            Rc::new(context.function_type(&[], &LLResultType::Num(i32_type), false)),
+        )?;
+
+        let builder = LLBuilder::new(context);
+        let entry = LLBasicBlock::new("entry", context)?;
+        let out_of_bounds = LLBasicBlock::new("out_of_bounds", context)?;
+        let copy = LLBasicBlock::new("copy", context)?;
+
+        // Offsets of 64-bit memories can be large enough for the end to wrap, and such a segment never fits.
+        function.append_basic_block(&entry);
+        builder.position_at_end(&entry);
+        let end = self.offset.saturating_add(self.bytes.len() as u64);
+        // The host memory is defined along with the other globals of the memory, which the engine sets together.
+        module.add_or_get_memory(&byte_type.pointer_type())?;
+        let size = module.add_or_get_memory_size(&i64_type)?;
+        let size = builder.build_load(&i64_type, &size, "memory_size")?;
+        let is_out_of_bounds = builder.build_int_cmp(
+            LLVMIntPredicate::LLVMIntUGT,
+            &i64_type.const_int(end),
+            &size,
+            "",
+        )?;
+        builder.build_cond_br(&is_out_of_bounds, &out_of_bounds, &copy);
+
+        function.append_basic_block(&out_of_bounds);
+        builder.position_at_end(&out_of_bounds);
+        builder.build_ret(&i32_type.zero());
+
+        function.append_basic_block(&copy);
+        builder.position_at_end(&copy);
+        let base = module.add_or_get_memory_base(&byte_type.pointer_type())?;
+        let base = builder.build_load(&byte_type.pointer_type(), &base, "memory_base")?;
+        let destination =
+            builder.build_gep(&byte_type, &base, &i64_type.const_int(self.offset), "")?;
+        let source = builder.build_bitcast(&data, &byte_type.pointer_type(), "")?;
+
+        let byte_pointer_type = byte_type.pointer_type();
+        let memcpy = module
+            .add_or_get_intrinsic_function(
+                "llvm.memcpy",
+                &[byte_pointer_type, byte_pointer_type, i64_type],
+            )
+            .ok_or_else(|| CompilerError::UnsupportedIntrinsic("llvm.memcpy".to_string()))?;
+        builder.build_call_intrinsic(
+            &memcpy,
+            &[
+                &destination,
+                &source,
+                &i64_type.const_int(self.bytes.len() as u64),
+                &i1_type.const_int(0),
+            ],
+            "",
+        )?;
+        builder.build_ret(&i32_type.const_int(1));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use llvm_sys::LLVMIntPredicate;
+
+use crate::{
+    compiler::llvm::{
+        basic_block::LLBasicBlock,
+        builder::LLBuilder,
+        function::LLFunction,
+        module::{element_initializer_symbol, ELEM_ITEMS_SYMBOL_PREFIX},
+        types::{LLNumType, LLResultType},
+        values::LLGlobal,
+        LLVM,
+    },
+    errors::CompilerError,
+};
+
+/// Generates the function copying the references of an active element segment into table 0 at its offset.
+///
+/// The function takes no arguments and returns an `i32` that is zero if the segment does not fit in the table, in
+/// which case nothing is copied.
+pub(crate) struct ElementInitializerGenerator<'a> {
+    llvm: &'a mut LLVM,
+    elem_index: u32,
+    offset: u32,
+    ty: LLNumType,
+    references: &'a [Option<LLGlobal>],
+}
+
+impl<'a> ElementInitializerGenerator<'a> {
+    pub(crate) fn new(
+        llvm: &'a mut LLVM,
+        elem_index: u32,
+        offset: u32,
+        ty: LLNumType,
+        references: &'a [Option<LLGlobal>],
+    ) -> Self {
+        Self {
+            llvm,
+            elem_index,
+            offset,
+            ty,
+            references,
+        }
+    }
+
+    /// Generates the initializer, named after the table and segment it copies.
+    pub(crate) fn generate(self) -> Result<()> {
+        let context = &self.llvm.context;
+        let i1_type = context.i1_type();
+        let i32_type = context.i32_type();
+        let i64_type = context.i64_type();
+        let byte_pointer_type = context.i8_type().pointer_type();
+
+        let module = self.llvm.module.as_mut().unwrap();
+        let items = module.add_constant_references(
+            &format!("{}{}", ELEM_ITEMS_SYMBOL_PREFIX, self.elem_index),
+            &self.ty,
+            self.references,
+        )?;
+        let function = LLFunction::new(
+            &element_initializer_symbol(0, self.elem_index),
+            module,
+            Rc::new(context.function_type(&[], &LLResultType::Num(i32_type), false)),
+        )?;
+
+        let builder = LLBuilder::new(context);
+        let entry = LLBasicBlock::new("entry", context)?;
+        let out_of_bounds = LLBasicBlock::new("out_of_bounds", context)?;
+        let copy = LLBasicBlock::new("copy", context)?;
+
+        // The offset and length both fit in 32 bits, so their sum cannot wrap.
+        function.append_basic_block(&entry);
+        builder.position_at_end(&entry);
+        let end = self.offset as u64 + self.references.len() as u64;
+        // The slots are defined along with the other globals of the table, which the engine sets together.
+        module.add_or_get_table_slots(&byte_pointer_type)?;
+        let size = module.add_or_get_table_size(&i64_type)?;
+        let size = builder.build_load(&i64_type, &size, "table_size")?;
+        let is_out_of_bounds = builder.build_int_cmp(
+            LLVMIntPredicate::LLVMIntUGT,
+            &i64_type.const_int(end),
+            &size,
+            "",
+        )?;
+        builder.build_cond_br(&is_out_of_bounds, &out_of_bounds, &copy);
+
+        function.append_basic_block(&out_of_bounds);
+        builder.position_at_end(&out_of_bounds);
+        builder.build_ret(&i32_type.zero());
+
+        function.append_basic_block(&copy);
+        builder.position_at_end(&copy);
+        let base = module.add_or_get_table_base(&self.ty.pointer_type())?;
+        let base = builder.build_load(&self.ty.pointer_type(), &base, "table_base")?;
+        let destination =
+            builder.build_gep(&self.ty, &base, &i64_type.const_int(self.offset as u64), "")?;
+        let destination = builder.build_bitcast(&destination, &byte_pointer_type, "")?;
+        let source = builder.build_bitcast(&items, &byte_pointer_type, "")?;
+
+        let memcpy = module
+            .add_or_get_intrinsic_function(
+                "llvm.memcpy",
+                &[byte_pointer_type, byte_pointer_type, i64_type],
+            )
+            .ok_or_else(|| CompilerError::UnsupportedIntrinsic("llvm.memcpy".to_string()))?;
+        builder.build_call_intrinsic(
+            &memcpy,
+            &[
+                &destination,
+                &source,
+                &i64_type.const_int(self.references.len() as u64 * self.ty.byte_size()),
+                &i1_type.const_int(0),
+            ],
+            "",
+        )?;
+        builder.build_ret(&i32_type.const_int(1));
+
+        Ok(())
+    }
+}
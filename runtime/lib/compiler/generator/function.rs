@@ -0,0 +1,199 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use log::debug;
+use wasmparser::{FunctionBody, Operator};
+
+use crate::{
+    compiler::{
+        llvm::{basic_block::LLBasicBlock, di_builder::LLDISubprogram, function::LLFunction, LLVM},
+        utils::convert,
+        ModuleInfo,
+    },
+    errors::CompilerError,
+    types::FuncType,
+    ResourceLimits,
+};
+
+use super::OperatorGenerator;
+
+/// Generates LLVM IR for a function body.
+///
+/// Params and locals are kept in stack slots created in the entry block.
+pub(crate) struct FunctionBodyGenerator<'a> {
+    llvm: &'a LLVM,
+    info: &'a ModuleInfo,
+    function_index: u32,
+    function: Rc<LLFunction>,
+    func_type: &'a FuncType,
+    body: FunctionBody<'a>,
+    limits: ResourceLimits,
+}
+
+impl<'a> FunctionBodyGenerator<'a> {
+    pub(crate) fn new(
+        llvm: &'a LLVM,
+        info: &'a ModuleInfo,
+        function_index: u32,
+        function: Rc<LLFunction>,
+        func_type: &'a FuncType,
+        body: FunctionBody<'a>,
+        limits: ResourceLimits,
+    ) -> Self {
+        Self {
+            llvm,
+            info,
+            function_index,
+            function,
+            func_type,
+            body,
+            limits,
+        }
+    }
+
+    /// Generates the function body.
+    pub(crate) fn generate(&self) -> Result<()> {
+        let entry = LLBasicBlock::new("entry", &self.llvm.context)?;
+        self.function.append_basic_block(&entry);
+
+        let mut generator = OperatorGenerator::new(
+            &self.llvm.context,
+            self.llvm.module.as_ref().unwrap(),
+            &self.llvm.info.functions,
+            self.info,
+            self.function_index,
+            Rc::clone(&self.function),
+            self.limits,
+        );
+        generator.builder.position_at_end(&entry);
+
+        let subprogram = self.llvm.di_builder.as_ref().map(|di_builder| {
+            let subprogram = di_builder.create_subprogram(
+                &self.info.function_symbol(self.function_index),
+                self.body.range().start as u32,
+            );
+            self.function.set_subprogram(subprogram);
+            subprogram
+        });
+
+        let result = self.generate_body(&mut generator, subprogram);
+        generator.release_blocks();
+
+        result
+    }
+
+    /// Generates the locals and operators of the function body.
+    ///
+    /// With a `subprogram`, the code of each operator is located at the byte offset of the operator.
+    fn generate_body(
+        &self,
+        generator: &mut OperatorGenerator,
+        subprogram: Option<LLDISubprogram>,
+    ) -> Result<()> {
+        self.generate_locals(generator)?;
+
+        let mut operator_count = 0;
+        for item in self.body.get_operators_reader()?.into_iter_with_offsets() {
+            let (operator, offset) = item?;
+            operator_count += 1;
+
+            // A body holding nothing but its `end` returns zeros rather than underflowing the value stack.
+            if operator_count == 1 && matches!(operator, Operator::End) {
+                for ty in self.func_type.results.iter() {
+                    let ty = convert::to_llvm_valtype(&self.llvm.context, ty);
+                    generator.push(ty.zero());
+                }
+            }
+
+            if let (Some(di_builder), Some(subprogram)) = (&self.llvm.di_builder, subprogram) {
+                let location =
+                    di_builder.create_location(&self.llvm.context, offset as u32, subprogram);
+                generator.builder.set_current_debug_location(location);
+            }
+
+            generator.generate(&operator, offset)?;
+
+            // The rest of the function is never reached.
+            if generator.is_unsupported {
+                return Ok(());
+            }
+        }
+
+        // Blocks of controls that are never closed would be left out of the function.
+        let unclosed = generator.control_stack.len() + usize::from(!generator.is_body_ended);
+        if unclosed > 0 {
+            return Err(CompilerError::UnbalancedControl(format!(
+                "{} control(s) missing an end, counting the function body",
+                unclosed
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Creates stack slots for params and locals.
+    fn generate_locals(&self, generator: &mut OperatorGenerator) -> Result<()> {
+        let context = &self.llvm.context;
+
+        for (index, ty) in self.func_type.params.iter().enumerate() {
+            let ty = convert::to_llvm_valtype(context, ty);
+            let alloca = generator
+                .builder
+                .build_alloca(&ty, &self.local_name(index as u32))?;
+
+            generator
+                .builder
+                .build_store(&self.function.get_param(index as u32), &alloca);
+
+            generator.locals.push((alloca, ty));
+        }
+
+        // Sum local counts before anything gets allocated for them.
+        let mut locals_count = 0_u64;
+        let mut locals = vec![];
+        for local in self.body.get_locals_reader()? {
+            let (count, ty) = local?;
+
+            debug!("local: {:?}", (count, ty));
+
+            locals_count += count as u64;
+            if locals_count > self.limits.max_locals as u64 {
+                return Err(CompilerError::LimitExceeded(format!(
+                    "function declares more than {} locals",
+                    self.limits.max_locals
+                ))
+                .into());
+            }
+
+            locals.push((count, ty));
+        }
+
+        // Locals are zero-initialized.
+        for (count, ty) in locals {
+            let ty = convert::to_llvm_valtype(context, &convert::to_wasmo_valtype(&ty)?);
+            for _ in 0..count {
+                let index = generator.locals.len();
+                let alloca = generator
+                    .builder
+                    .build_alloca(&ty, &self.local_name(index as u32))?;
+
+                generator.builder.build_store(&ty.zero(), &alloca);
+                generator.locals.push((alloca, ty));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the name of the stack slot of the param or local at `index`, from the name section if it names it.
+    fn local_name(&self, index: u32) -> String {
+        self.info
+            .names
+            .locals
+            .get(&self.function_index)
+            .and_then(|locals| locals.get(&index))
+            .cloned()
+            .unwrap_or_else(|| format!("local_{}", index))
+    }
+}
@@ -0,0 +1,79 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use wasmparser::Operator;
+
+use crate::{
+    compiler::{
+        llvm::{basic_block::LLBasicBlock, function::LLFunction, LLVM},
+        ModuleInfo,
+    },
+    ResourceLimits,
+};
+
+use super::OperatorGenerator;
+
+/// Generates a function of the type of an imported function that calls the host function the import is resolved to.
+///
+/// Funcrefs point to functions of the module, so this is what references to the import in tables point to.
+pub(crate) struct ImportFunctionGenerator<'a> {
+    llvm: &'a mut LLVM,
+    info: &'a ModuleInfo,
+    function_index: u32,
+    limits: ResourceLimits,
+}
+
+impl<'a> ImportFunctionGenerator<'a> {
+    pub(crate) fn new(
+        llvm: &'a mut LLVM,
+        info: &'a ModuleInfo,
+        function_index: u32,
+        limits: ResourceLimits,
+    ) -> Self {
+        Self {
+            llvm,
+            info,
+            function_index,
+            limits,
+        }
+    }
+
+    /// Generates the function, named like a local function at the index of the import.
+    pub(crate) fn generate(self) -> Result<Rc<LLFunction>> {
+        let type_index = self.info.functions[self.function_index as usize].type_index;
+        let function = LLFunction::new(
+            &self.info.function_symbol(self.function_index),
+            self.llvm.module.as_mut().unwrap(),
+            Rc::clone(&self.llvm.info.types[type_index as usize]),
+        )?;
+
+        let entry = LLBasicBlock::new("entry", &self.llvm.context)?;
+        function.append_basic_block(&entry);
+
+        let mut generator = OperatorGenerator::new(
+            &self.llvm.context,
+            self.llvm.module.as_ref().unwrap(),
+            &self.llvm.info.functions,
+            self.info,
+            self.function_index,
+            Rc::clone(&function),
+            self.limits,
+        );
+        generator.builder.position_at_end(&entry);
+
+        let param_count = self.info.types[type_index as usize].params.len();
+        for index in 0..param_count {
+            generator.push(function.get_param(index as u32));
+        }
+
+        let call = Operator::Call {
+            function_index: self.function_index,
+        };
+        generator.generate_call_import(&call, self.function_index)?;
+        if !generator.is_unsupported {
+            generator.generate_return()?;
+        }
+
+        Ok(function)
+    }
+}
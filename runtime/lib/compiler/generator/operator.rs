@@ -0,0 +1,2684 @@
+use std::{mem, rc::Rc};
+
+use anyhow::Result;
+use llvm_sys::{LLVMIntPredicate, LLVMRealPredicate};
+use log::{debug, warn};
+use wasmparser::{MemoryImmediate, Operator, Type, TypeOrFuncType};
+
+use super::{Control, ControlResults};
+use crate::{
+    compiler::llvm::{
+        basic_block::LLBasicBlock,
+        builder::LLBuilder,
+        context::LLContext,
+        function::LLFunction,
+        intrinsic::LLIntrinsic,
+        module::LLModule,
+        types::{LLFunctionType, LLNumType, LLResultType},
+        values::{LLAlloca, LLAnd, LLBitCast, LLCall, LLGep, LLGlobal, LLIntZExt, LLLoad, LLValue},
+    },
+    compiler::{utils::convert, ModuleInfo},
+    errors::{CompilerError, TrapCode},
+    intrinsics::{
+        CALL_IMPORT_SYMBOL, MEMORY_GROW_SYMBOL, RAISE_TRAP_SYMBOL, TABLE_FILL_SYMBOL,
+        TABLE_GROW_SYMBOL,
+    },
+    types::{NumType, RefType, ValType, PAGE_SIZE},
+    ResourceLimits,
+};
+
+/// An `LLBuilder` method building a binary instruction.
+type BuildBinOp<T> = fn(&LLBuilder, &dyn LLValue, &dyn LLValue, &str) -> Result<T>;
+
+/// Generates LLVM IR for the operators of a function body.
+pub(crate) struct OperatorGenerator<'a> {
+    pub(crate) llvm_context: &'a LLContext,
+    pub(crate) llvm_module: &'a LLModule,
+    /// LLVM functions of the non-imported functions, which calls are resolved to.
+    pub(crate) llvm_functions: &'a [Rc<LLFunction>],
+    pub(crate) info: &'a ModuleInfo,
+    /// Index of the function in the function index space, imported functions included.
+    pub(crate) function_index: u32,
+    pub(crate) function: Rc<LLFunction>,
+    pub(crate) limits: ResourceLimits,
+    pub(crate) builder: LLBuilder,
+    /// Stack slots of params and locals, and their types.
+    pub(crate) locals: Vec<(LLAlloca, LLNumType)>,
+    /// The implicit operand stack of the function, where the results of comparisons are `i1` booleans.
+    pub(crate) value_stack: Vec<Rc<dyn LLValue>>,
+    /// Blocks, loops and ifs enclosing the current operator, innermost last.
+    pub(crate) control_stack: Vec<Control>,
+    /// Set when an operator without codegen support is reached.
+    pub(crate) is_unsupported: bool,
+    /// Set when the `end` of the function body itself is reached.
+    pub(crate) is_body_ended: bool,
+    /// Set after an operator that never falls through, such as `return`, until the end of the enclosing control.
+    pub(crate) is_unreachable: bool,
+    /// Number of controls opened since the code became unreachable, which are skipped along with their contents.
+    pub(crate) unreachable_depth: u32,
+    /// Byte offset of the operator being generated in the wasm binary, which traps report.
+    pub(crate) offset: usize,
+    /// Stack slots of the arguments and results of calls to imported functions, created on the first such call.
+    pub(crate) import_slots: Option<(LLAlloca, LLAlloca)>,
+}
+
+impl<'a> OperatorGenerator<'a> {
+    pub(crate) fn new(
+        llvm_context: &'a LLContext,
+        llvm_module: &'a LLModule,
+        llvm_functions: &'a [Rc<LLFunction>],
+        info: &'a ModuleInfo,
+        function_index: u32,
+        function: Rc<LLFunction>,
+        limits: ResourceLimits,
+    ) -> Self {
+        Self {
+            llvm_context,
+            llvm_module,
+            llvm_functions,
+            info,
+            function_index,
+            function,
+            limits,
+            builder: LLBuilder::new(llvm_context),
+            locals: vec![],
+            value_stack: vec![],
+            control_stack: vec![],
+            is_unsupported: false,
+            is_body_ended: false,
+            is_unreachable: false,
+            unreachable_depth: 0,
+            offset: 0,
+            import_slots: None,
+        }
+    }
+
+    /// Generates LLVM IR for the operator at byte `offset` of the wasm binary.
+    ///
+    /// Compiler errors are located at the operator so that they can be mapped back to the source.
+    pub(crate) fn generate(&mut self, operator: &Operator, offset: usize) -> Result<()> {
+        self.offset = offset;
+        self.generate_operator(operator)
+            .map_err(|err| match err.downcast::<CompilerError>() {
+                Ok(error) => CompilerError::AtOperator {
+                    function_index: self.function_index,
+                    offset,
+                    error: Box::new(error),
+                }
+                .into(),
+                Err(err) => err,
+            })
+    }
+
+    /// Generates LLVM IR for an operator.
+    fn generate_operator(&mut self, operator: &Operator) -> Result<()> {
+        use LLVMIntPredicate::*;
+        use LLVMRealPredicate::*;
+
+        debug!("operator: {:?}", operator);
+
+        if self.skip_unreachable(operator) {
+            return Ok(());
+        }
+
+        let i32_type = self.llvm_context.i32_type();
+        let i64_type = self.llvm_context.i64_type();
+        let f32_type = self.llvm_context.f32_type();
+        let f64_type = self.llvm_context.f64_type();
+
+        match operator {
+            Operator::Block { ty } | Operator::Loop { ty } | Operator::If { ty }
+                if self.has_block_params(ty) =>
+            {
+                self.generate_unsupported(operator)
+            }
+            Operator::Block { ty } => {
+                self.check_control_depth()?;
+                self.control_stack.push(Control::Block {
+                    end: LLBasicBlock::new("block_end", self.llvm_context)?,
+                    stack_height: self.value_stack.len(),
+                    results: ControlResults::new(self.get_block_results(ty)?),
+                });
+            }
+            Operator::Loop { ty } => {
+                self.check_control_depth()?;
+                let begin = LLBasicBlock::new("loop_begin", self.llvm_context)?;
+                self.builder.build_br(&begin);
+                self.function.append_basic_block(&begin);
+                self.builder.position_at_end(&begin);
+
+                self.control_stack.push(Control::Loop {
+                    begin,
+                    end: LLBasicBlock::new("loop_end", self.llvm_context)?,
+                    stack_height: self.value_stack.len(),
+                    results: ControlResults::new(self.get_block_results(ty)?),
+                });
+            }
+            Operator::If { ty } => {
+                self.check_control_depth()?;
+                let condition = self.pop_condition()?;
+                let then = LLBasicBlock::new("if_then", self.llvm_context)?;
+                let else_block = LLBasicBlock::new("if_else", self.llvm_context)?;
+                self.builder
+                    .build_cond_br(condition.as_ref(), &then, &else_block);
+                self.function.append_basic_block(&then);
+                self.builder.position_at_end(&then);
+
+                self.control_stack.push(Control::If {
+                    else_block: Some(else_block),
+                    end: LLBasicBlock::new("if_end", self.llvm_context)?,
+                    stack_height: self.value_stack.len(),
+                    results: ControlResults::new(self.get_block_results(ty)?),
+                });
+            }
+            Operator::Else => match self.control_stack.pop() {
+                Some(Control::If {
+                    else_block: Some(else_block),
+                    end,
+                    stack_height,
+                    mut results,
+                }) => {
+                    self.generate_fallthrough(&end, &mut results)?;
+                    self.value_stack.truncate(stack_height);
+                    self.function.append_basic_block(&else_block);
+                    self.builder.position_at_end(&else_block);
+
+                    self.control_stack.push(Control::If {
+                        else_block: None,
+                        end,
+                        stack_height,
+                        results,
+                    });
+                }
+                _ => {
+                    return Err(CompilerError::InvalidModule(
+                        "else without a matching if".to_string(),
+                    )
+                    .into())
+                }
+            },
+            Operator::Br { relative_depth } => {
+                self.add_branch_results(*relative_depth)?;
+                match self.get_control(*relative_depth) {
+                    Some(control) => {
+                        self.builder.build_br(control.branch_target());
+                        self.is_unreachable = true;
+                    }
+                    // TODO(appcypher): Return when branching out of the function body.
+                    None => self.generate_unsupported(operator),
+                }
+            }
+            Operator::BrIf { relative_depth } => {
+                let condition = self.pop_condition()?;
+                self.add_branch_results(*relative_depth)?;
+                match self.get_control(*relative_depth) {
+                    Some(control) => {
+                        let next = LLBasicBlock::new("br_if_next", self.llvm_context)?;
+                        self.builder.build_cond_br(
+                            condition.as_ref(),
+                            control.branch_target(),
+                            &next,
+                        );
+                        self.function.append_basic_block(&next);
+                        self.builder.position_at_end(&next);
+                    }
+                    // TODO(appcypher): Return when branching out of the function body.
+                    None => self.generate_unsupported(operator),
+                }
+            }
+            Operator::BrTable { table } => {
+                let index = self.pop()?;
+                if !index.has_type(&i32_type) {
+                    return Err(CompilerError::TypeMismatch("br_table index".to_string()).into());
+                }
+
+                let targets = table.targets().collect::<Result<Vec<_>, _>>()?;
+                let default = table.default();
+
+                // TODO(appcypher): Return when branching out of the function body.
+                if targets
+                    .iter()
+                    .chain([&default])
+                    .any(|depth| self.get_control(*depth).is_none())
+                {
+                    self.generate_unsupported(operator);
+                    return Ok(());
+                }
+
+                // The switch branches once for every case, even those with the same target.
+                for depth in targets.iter().chain([&default]) {
+                    self.add_branch_results(*depth)?;
+                }
+
+                let target = |depth| self.get_control(depth).unwrap().branch_target();
+                let switch =
+                    self.builder
+                        .build_switch(index.as_ref(), target(default), table.len());
+                for (case, depth) in targets.iter().enumerate() {
+                    switch.add_case(&i32_type.const_int(case as u64), target(*depth));
+                }
+
+                self.is_unreachable = true;
+            }
+            Operator::Unreachable => {
+                // The block is terminated, so the `end` closing it must not branch out of it.
+                self.generate_trap(TrapCode::Unreachable, None)?;
+                self.is_unreachable = true;
+            }
+            Operator::Return => {
+                self.generate_return()?;
+                self.is_unreachable = true;
+            }
+            Operator::Call { function_index } => self.generate_call(operator, *function_index)?,
+            Operator::CallIndirect { index, table_index } => {
+                self.generate_call_indirect(operator, *index, *table_index)?
+            }
+            Operator::Drop => {
+                // An `i1` boolean is dropped as is rather than widened first.
+                self.value_stack
+                    .pop()
+                    .ok_or(CompilerError::ValueStackUnderflow)?;
+            }
+            Operator::LocalGet { local_index } => {
+                let (alloca, ty) = self.get_local(*local_index)?;
+                let value = self.builder.build_load(ty, alloca, "")?;
+                self.push(value);
+            }
+            Operator::LocalSet { local_index } => {
+                let value = self.pop()?;
+                let (alloca, _) = self.get_local(*local_index)?;
+                self.builder.build_store(value.as_ref(), alloca);
+            }
+            Operator::LocalTee { local_index } => {
+                let value = self.pop()?;
+                let (alloca, _) = self.get_local(*local_index)?;
+                self.builder.build_store(value.as_ref(), alloca);
+                self.value_stack.push(value);
+            }
+            Operator::GlobalGet { global_index } => {
+                match self.get_imported_global(*global_index)? {
+                    Some((global, ty)) => {
+                        let value = self.builder.build_load(&ty, &global, "")?;
+                        self.push(value);
+                    }
+                    // TODO(appcypher): Generate once local globals are available at runtime.
+                    None => self.generate_unsupported(operator),
+                }
+            }
+            Operator::GlobalSet { global_index } => {
+                match self.get_imported_global(*global_index)? {
+                    Some((global, _)) => {
+                        let value = self.pop()?;
+                        self.builder.build_store(value.as_ref(), &global);
+                    }
+                    // TODO(appcypher): Generate once local globals are available at runtime.
+                    None => self.generate_unsupported(operator),
+                }
+            }
+            Operator::I32Load { memarg } => self.generate_load(operator, &i32_type, memarg)?,
+            Operator::I64Load { memarg } => self.generate_load(operator, &i64_type, memarg)?,
+            Operator::F32Load { memarg } => self.generate_load(operator, &f32_type, memarg)?,
+            Operator::F64Load { memarg } => self.generate_load(operator, &f64_type, memarg)?,
+            Operator::I32Load8S { memarg } | Operator::I32Load8U { memarg } => {
+                let is_signed = matches!(operator, Operator::I32Load8S { .. });
+                let i8_type = self.llvm_context.i8_type();
+                self.generate_extending_load(operator, &i32_type, &i8_type, is_signed, memarg)?
+            }
+            Operator::I32Load16S { memarg } | Operator::I32Load16U { memarg } => {
+                let is_signed = matches!(operator, Operator::I32Load16S { .. });
+                let i16_type = self.llvm_context.i16_type();
+                self.generate_extending_load(operator, &i32_type, &i16_type, is_signed, memarg)?
+            }
+            Operator::I64Load8S { memarg } | Operator::I64Load8U { memarg } => {
+                let is_signed = matches!(operator, Operator::I64Load8S { .. });
+                let i8_type = self.llvm_context.i8_type();
+                self.generate_extending_load(operator, &i64_type, &i8_type, is_signed, memarg)?
+            }
+            Operator::I64Load16S { memarg } | Operator::I64Load16U { memarg } => {
+                let is_signed = matches!(operator, Operator::I64Load16S { .. });
+                let i16_type = self.llvm_context.i16_type();
+                self.generate_extending_load(operator, &i64_type, &i16_type, is_signed, memarg)?
+            }
+            Operator::I64Load32S { memarg } | Operator::I64Load32U { memarg } => {
+                let is_signed = matches!(operator, Operator::I64Load32S { .. });
+                self.generate_extending_load(operator, &i64_type, &i32_type, is_signed, memarg)?
+            }
+            Operator::I32Store { memarg } => {
+                self.generate_store(operator, &i32_type, None, memarg)?
+            }
+            Operator::I64Store { memarg } => {
+                self.generate_store(operator, &i64_type, None, memarg)?
+            }
+            Operator::F32Store { memarg } => {
+                self.generate_store(operator, &f32_type, None, memarg)?
+            }
+            Operator::F64Store { memarg } => {
+                self.generate_store(operator, &f64_type, None, memarg)?
+            }
+            Operator::I32Store8 { memarg } => {
+                let i8_type = self.llvm_context.i8_type();
+                self.generate_store(operator, &i32_type, Some(&i8_type), memarg)?
+            }
+            Operator::I32Store16 { memarg } => {
+                let i16_type = self.llvm_context.i16_type();
+                self.generate_store(operator, &i32_type, Some(&i16_type), memarg)?
+            }
+            Operator::I64Store8 { memarg } => {
+                let i8_type = self.llvm_context.i8_type();
+                self.generate_store(operator, &i64_type, Some(&i8_type), memarg)?
+            }
+            Operator::I64Store16 { memarg } => {
+                let i16_type = self.llvm_context.i16_type();
+                self.generate_store(operator, &i64_type, Some(&i16_type), memarg)?
+            }
+            Operator::I64Store32 { memarg } => {
+                self.generate_store(operator, &i64_type, Some(&i32_type), memarg)?
+            }
+            // TODO(appcypher): Support multiple memories.
+            Operator::MemorySize { mem: 0, .. } => self.generate_memory_size()?,
+            Operator::MemoryGrow { mem: 0, .. } => self.generate_memory_grow()?,
+            Operator::MemoryFill { mem: 0 } => self.generate_memory_fill()?,
+            Operator::MemoryCopy { src: 0, dst: 0 } => self.generate_memory_copy()?,
+            Operator::MemoryInit { segment, mem: 0 } => self.generate_memory_init(*segment)?,
+            Operator::DataDrop { segment } => {
+                let (_, size) = self.get_data_segment(*segment)?;
+                self.builder.build_store(&i64_type.zero(), &size);
+            }
+            Operator::I32AtomicLoad { memarg }
+            | Operator::I32AtomicLoad16U { memarg }
+            | Operator::I32AtomicLoad8U { memarg }
+            | Operator::I64AtomicLoad { memarg }
+            | Operator::I64AtomicLoad32U { memarg }
+            | Operator::I64AtomicLoad16U { memarg }
+            | Operator::I64AtomicLoad8U { memarg }
+            | Operator::I32AtomicStore { memarg }
+            | Operator::I32AtomicStore16 { memarg }
+            | Operator::I32AtomicStore8 { memarg }
+            | Operator::I64AtomicStore { memarg }
+            | Operator::I64AtomicStore32 { memarg }
+            | Operator::I64AtomicStore16 { memarg }
+            | Operator::I64AtomicStore8 { memarg }
+            | Operator::I32AtomicRmwAdd { memarg }
+            | Operator::I32AtomicRmwSub { memarg }
+            | Operator::I32AtomicRmwAnd { memarg }
+            | Operator::I32AtomicRmwOr { memarg }
+            | Operator::I32AtomicRmwXor { memarg }
+            | Operator::I32AtomicRmwXchg { memarg }
+            | Operator::I32AtomicRmwCmpxchg { memarg }
+            | Operator::I32AtomicRmw16AddU { memarg }
+            | Operator::I32AtomicRmw16SubU { memarg }
+            | Operator::I32AtomicRmw16AndU { memarg }
+            | Operator::I32AtomicRmw16OrU { memarg }
+            | Operator::I32AtomicRmw16XorU { memarg }
+            | Operator::I32AtomicRmw16XchgU { memarg }
+            | Operator::I32AtomicRmw16CmpxchgU { memarg }
+            | Operator::I32AtomicRmw8AddU { memarg }
+            | Operator::I32AtomicRmw8SubU { memarg }
+            | Operator::I32AtomicRmw8AndU { memarg }
+            | Operator::I32AtomicRmw8OrU { memarg }
+            | Operator::I32AtomicRmw8XorU { memarg }
+            | Operator::I32AtomicRmw8XchgU { memarg }
+            | Operator::I32AtomicRmw8CmpxchgU { memarg }
+            | Operator::I64AtomicRmwAdd { memarg }
+            | Operator::I64AtomicRmwSub { memarg }
+            | Operator::I64AtomicRmwAnd { memarg }
+            | Operator::I64AtomicRmwOr { memarg }
+            | Operator::I64AtomicRmwXor { memarg }
+            | Operator::I64AtomicRmwXchg { memarg }
+            | Operator::I64AtomicRmwCmpxchg { memarg }
+            | Operator::I64AtomicRmw32AddU { memarg }
+            | Operator::I64AtomicRmw32SubU { memarg }
+            | Operator::I64AtomicRmw32AndU { memarg }
+            | Operator::I64AtomicRmw32OrU { memarg }
+            | Operator::I64AtomicRmw32XorU { memarg }
+            | Operator::I64AtomicRmw32XchgU { memarg }
+            | Operator::I64AtomicRmw32CmpxchgU { memarg }
+            | Operator::I64AtomicRmw16AddU { memarg }
+            | Operator::I64AtomicRmw16SubU { memarg }
+            | Operator::I64AtomicRmw16AndU { memarg }
+            | Operator::I64AtomicRmw16OrU { memarg }
+            | Operator::I64AtomicRmw16XorU { memarg }
+            | Operator::I64AtomicRmw16XchgU { memarg }
+            | Operator::I64AtomicRmw16CmpxchgU { memarg }
+            | Operator::I64AtomicRmw8AddU { memarg }
+            | Operator::I64AtomicRmw8SubU { memarg }
+            | Operator::I64AtomicRmw8AndU { memarg }
+            | Operator::I64AtomicRmw8OrU { memarg }
+            | Operator::I64AtomicRmw8XorU { memarg }
+            | Operator::I64AtomicRmw8XchgU { memarg }
+            | Operator::I64AtomicRmw8CmpxchgU { memarg }
+            | Operator::MemoryAtomicNotify { memarg }
+            | Operator::MemoryAtomicWait32 { memarg }
+            | Operator::MemoryAtomicWait64 { memarg } => {
+                self.check_shared_memory(memarg.memory)?;
+                // TODO(appcypher): Generate atomic accesses.
+                self.generate_unsupported(operator);
+            }
+            Operator::I32Const { value } => {
+                let value = self.llvm_context.i32_type().const_int(*value as u64);
+                self.push(value);
+            }
+            Operator::I64Const { value } => {
+                let value = self.llvm_context.i64_type().const_int(*value as u64);
+                self.push(value);
+            }
+            Operator::F32Const { value } => {
+                let value = f32::from_bits(value.bits()) as f64;
+                let value = self.llvm_context.f32_type().const_float(value);
+                self.push(value);
+            }
+            Operator::F64Const { value } => {
+                let value = f64::from_bits(value.bits());
+                let value = self.llvm_context.f64_type().const_float(value);
+                self.push(value);
+            }
+            Operator::I32Eqz => self.generate_int_eqz(i32_type)?,
+            Operator::I64Eqz => self.generate_int_eqz(i64_type)?,
+            Operator::I32Eq => self.generate_int_cmp(i32_type, LLVMIntEQ)?,
+            Operator::I32Ne => self.generate_int_cmp(i32_type, LLVMIntNE)?,
+            Operator::I32LtS => self.generate_int_cmp(i32_type, LLVMIntSLT)?,
+            Operator::I32LtU => self.generate_int_cmp(i32_type, LLVMIntULT)?,
+            Operator::I32GtS => self.generate_int_cmp(i32_type, LLVMIntSGT)?,
+            Operator::I32GtU => self.generate_int_cmp(i32_type, LLVMIntUGT)?,
+            Operator::I32LeS => self.generate_int_cmp(i32_type, LLVMIntSLE)?,
+            Operator::I32LeU => self.generate_int_cmp(i32_type, LLVMIntULE)?,
+            Operator::I32GeS => self.generate_int_cmp(i32_type, LLVMIntSGE)?,
+            Operator::I32GeU => self.generate_int_cmp(i32_type, LLVMIntUGE)?,
+            Operator::I64Eq => self.generate_int_cmp(i64_type, LLVMIntEQ)?,
+            Operator::I64Ne => self.generate_int_cmp(i64_type, LLVMIntNE)?,
+            Operator::I64LtS => self.generate_int_cmp(i64_type, LLVMIntSLT)?,
+            Operator::I64LtU => self.generate_int_cmp(i64_type, LLVMIntULT)?,
+            Operator::I64GtS => self.generate_int_cmp(i64_type, LLVMIntSGT)?,
+            Operator::I64GtU => self.generate_int_cmp(i64_type, LLVMIntUGT)?,
+            Operator::I64LeS => self.generate_int_cmp(i64_type, LLVMIntSLE)?,
+            Operator::I64LeU => self.generate_int_cmp(i64_type, LLVMIntULE)?,
+            Operator::I64GeS => self.generate_int_cmp(i64_type, LLVMIntSGE)?,
+            Operator::I64GeU => self.generate_int_cmp(i64_type, LLVMIntUGE)?,
+            Operator::I32Add => self.generate_binop(i32_type, LLBuilder::build_int_add)?,
+            Operator::I32Sub => self.generate_binop(i32_type, LLBuilder::build_int_sub)?,
+            Operator::I32Mul => self.generate_binop(i32_type, LLBuilder::build_int_mul)?,
+            Operator::I32DivS => self.generate_int_div(i32_type, true, LLBuilder::build_sdiv)?,
+            Operator::I32DivU => self.generate_int_div(i32_type, false, LLBuilder::build_udiv)?,
+            Operator::I32RemS => self.generate_int_rem(i32_type, true, LLBuilder::build_srem)?,
+            Operator::I32RemU => self.generate_int_rem(i32_type, false, LLBuilder::build_urem)?,
+            Operator::I32And => self.generate_binop(i32_type, LLBuilder::build_and)?,
+            Operator::I32Or => self.generate_binop(i32_type, LLBuilder::build_or)?,
+            Operator::I32Xor => self.generate_binop(i32_type, LLBuilder::build_xor)?,
+            Operator::I32Shl => self.generate_int_shift(i32_type, LLBuilder::build_shl)?,
+            Operator::I32ShrS => self.generate_int_shift(i32_type, LLBuilder::build_ashr)?,
+            Operator::I32ShrU => self.generate_int_shift(i32_type, LLBuilder::build_lshr)?,
+            Operator::I32Clz => self.generate_count_zeros(i32_type, "llvm.ctlz")?,
+            Operator::I32Ctz => self.generate_count_zeros(i32_type, "llvm.cttz")?,
+            Operator::I32Rotl => self.generate_int_rotate(i32_type, true)?,
+            Operator::I32Rotr => self.generate_int_rotate(i32_type, false)?,
+            Operator::I64Add => self.generate_binop(i64_type, LLBuilder::build_int_add)?,
+            Operator::I64Sub => self.generate_binop(i64_type, LLBuilder::build_int_sub)?,
+            Operator::I64Mul => self.generate_binop(i64_type, LLBuilder::build_int_mul)?,
+            Operator::I64DivS => self.generate_int_div(i64_type, true, LLBuilder::build_sdiv)?,
+            Operator::I64DivU => self.generate_int_div(i64_type, false, LLBuilder::build_udiv)?,
+            Operator::I64RemS => self.generate_int_rem(i64_type, true, LLBuilder::build_srem)?,
+            Operator::I64RemU => self.generate_int_rem(i64_type, false, LLBuilder::build_urem)?,
+            Operator::I64And => self.generate_binop(i64_type, LLBuilder::build_and)?,
+            Operator::I64Or => self.generate_binop(i64_type, LLBuilder::build_or)?,
+            Operator::I64Xor => self.generate_binop(i64_type, LLBuilder::build_xor)?,
+            Operator::I64Shl => self.generate_int_shift(i64_type, LLBuilder::build_shl)?,
+            Operator::I64ShrS => self.generate_int_shift(i64_type, LLBuilder::build_ashr)?,
+            Operator::I64ShrU => self.generate_int_shift(i64_type, LLBuilder::build_lshr)?,
+            Operator::I64Clz => self.generate_count_zeros(i64_type, "llvm.ctlz")?,
+            Operator::I64Ctz => self.generate_count_zeros(i64_type, "llvm.cttz")?,
+            Operator::I64Rotl => self.generate_int_rotate(i64_type, true)?,
+            Operator::I64Rotr => self.generate_int_rotate(i64_type, false)?,
+            Operator::I32WrapI64 => {
+                let operand = self.pop()?;
+                if !operand.has_type(&i64_type) {
+                    return Err(CompilerError::TypeMismatch("wrap operand".to_string()).into());
+                }
+
+                let value = self
+                    .builder
+                    .build_int_trunc(operand.as_ref(), &i32_type, "")?;
+
+                self.push(value);
+            }
+            Operator::I32Extend8S => {
+                let narrow_type = self.llvm_context.i8_type();
+                self.generate_sign_extend(i32_type, narrow_type)?
+            }
+            Operator::I32Extend16S => {
+                let narrow_type = self.llvm_context.i16_type();
+                self.generate_sign_extend(i32_type, narrow_type)?
+            }
+            Operator::I64Extend8S => {
+                let narrow_type = self.llvm_context.i8_type();
+                self.generate_sign_extend(i64_type, narrow_type)?
+            }
+            Operator::I64Extend16S => {
+                let narrow_type = self.llvm_context.i16_type();
+                self.generate_sign_extend(i64_type, narrow_type)?
+            }
+            Operator::I64Extend32S => self.generate_sign_extend(i64_type, i32_type)?,
+            Operator::I64ExtendI32S => {
+                let operand = self.pop_extend_operand()?;
+                let value = self
+                    .builder
+                    .build_int_sext(operand.as_ref(), &i64_type, "")?;
+
+                self.push(value);
+            }
+            Operator::I64ExtendI32U => {
+                let operand = self.pop_extend_operand()?;
+                let value = self
+                    .builder
+                    .build_int_zext(operand.as_ref(), &i64_type, "")?;
+
+                self.push(value);
+            }
+            Operator::I32TruncF32S => self.generate_float_to_int(f32_type, i32_type, true)?,
+            Operator::I32TruncF32U => self.generate_float_to_int(f32_type, i32_type, false)?,
+            Operator::I32TruncF64S => self.generate_float_to_int(f64_type, i32_type, true)?,
+            Operator::I32TruncF64U => self.generate_float_to_int(f64_type, i32_type, false)?,
+            Operator::I64TruncF32S => self.generate_float_to_int(f32_type, i64_type, true)?,
+            Operator::I64TruncF32U => self.generate_float_to_int(f32_type, i64_type, false)?,
+            Operator::I64TruncF64S => self.generate_float_to_int(f64_type, i64_type, true)?,
+            Operator::I64TruncF64U => self.generate_float_to_int(f64_type, i64_type, false)?,
+            Operator::I32TruncSatF32S => {
+                self.generate_float_to_int_sat(f32_type, i32_type, true)?
+            }
+            Operator::I32TruncSatF32U => {
+                self.generate_float_to_int_sat(f32_type, i32_type, false)?
+            }
+            Operator::I32TruncSatF64S => {
+                self.generate_float_to_int_sat(f64_type, i32_type, true)?
+            }
+            Operator::I32TruncSatF64U => {
+                self.generate_float_to_int_sat(f64_type, i32_type, false)?
+            }
+            Operator::I64TruncSatF32S => {
+                self.generate_float_to_int_sat(f32_type, i64_type, true)?
+            }
+            Operator::I64TruncSatF32U => {
+                self.generate_float_to_int_sat(f32_type, i64_type, false)?
+            }
+            Operator::I64TruncSatF64S => {
+                self.generate_float_to_int_sat(f64_type, i64_type, true)?
+            }
+            Operator::I64TruncSatF64U => {
+                self.generate_float_to_int_sat(f64_type, i64_type, false)?
+            }
+            Operator::F32ConvertI32S => self.generate_int_to_float(i32_type, f32_type, true)?,
+            Operator::F32ConvertI32U => self.generate_int_to_float(i32_type, f32_type, false)?,
+            Operator::F32ConvertI64S => self.generate_int_to_float(i64_type, f32_type, true)?,
+            Operator::F32ConvertI64U => self.generate_int_to_float(i64_type, f32_type, false)?,
+            Operator::F64ConvertI32S => self.generate_int_to_float(i32_type, f64_type, true)?,
+            Operator::F64ConvertI32U => self.generate_int_to_float(i32_type, f64_type, false)?,
+            Operator::F64ConvertI64S => self.generate_int_to_float(i64_type, f64_type, true)?,
+            Operator::F64ConvertI64U => self.generate_int_to_float(i64_type, f64_type, false)?,
+            Operator::I32ReinterpretF32 => self.generate_reinterpret(f32_type, i32_type)?,
+            Operator::I64ReinterpretF64 => self.generate_reinterpret(f64_type, i64_type)?,
+            Operator::F32ReinterpretI32 => self.generate_reinterpret(i32_type, f32_type)?,
+            Operator::F64ReinterpretI64 => self.generate_reinterpret(i64_type, f64_type)?,
+            Operator::F32Eq => self.generate_float_cmp(f32_type, LLVMRealOEQ)?,
+            Operator::F64Eq => self.generate_float_cmp(f64_type, LLVMRealOEQ)?,
+            Operator::F32Ne => self.generate_float_cmp(f32_type, LLVMRealUNE)?,
+            Operator::F64Ne => self.generate_float_cmp(f64_type, LLVMRealUNE)?,
+            Operator::F32Lt => self.generate_float_cmp(f32_type, LLVMRealOLT)?,
+            Operator::F64Lt => self.generate_float_cmp(f64_type, LLVMRealOLT)?,
+            Operator::F32Gt => self.generate_float_cmp(f32_type, LLVMRealOGT)?,
+            Operator::F64Gt => self.generate_float_cmp(f64_type, LLVMRealOGT)?,
+            Operator::F32Le => self.generate_float_cmp(f32_type, LLVMRealOLE)?,
+            Operator::F64Le => self.generate_float_cmp(f64_type, LLVMRealOLE)?,
+            Operator::F32Ge => self.generate_float_cmp(f32_type, LLVMRealOGE)?,
+            Operator::F64Ge => self.generate_float_cmp(f64_type, LLVMRealOGE)?,
+            Operator::F32Add => self.generate_binop(f32_type, LLBuilder::build_float_add)?,
+            Operator::F32Sub => self.generate_binop(f32_type, LLBuilder::build_float_sub)?,
+            Operator::F32Mul => self.generate_binop(f32_type, LLBuilder::build_float_mul)?,
+            Operator::F32Div => self.generate_binop(f32_type, LLBuilder::build_float_div)?,
+            Operator::F64Add => self.generate_binop(f64_type, LLBuilder::build_float_add)?,
+            Operator::F64Sub => self.generate_binop(f64_type, LLBuilder::build_float_sub)?,
+            Operator::F64Mul => self.generate_binop(f64_type, LLBuilder::build_float_mul)?,
+            Operator::F64Div => self.generate_binop(f64_type, LLBuilder::build_float_div)?,
+            Operator::F32Min => self.generate_float_min_max(f32_type, i32_type, false)?,
+            Operator::F32Max => self.generate_float_min_max(f32_type, i32_type, true)?,
+            Operator::F64Min => self.generate_float_min_max(f64_type, i64_type, false)?,
+            Operator::F64Max => self.generate_float_min_max(f64_type, i64_type, true)?,
+            Operator::F32Neg => self.generate_float_neg(f32_type)?,
+            Operator::F64Neg => self.generate_float_neg(f64_type)?,
+            Operator::F32Copysign => self.generate_float_copysign(f32_type)?,
+            Operator::F64Copysign => self.generate_float_copysign(f64_type)?,
+            Operator::F32Nearest => {
+                // 2^23, from which on every f32 is an integer.
+                self.generate_float_nearest(f32_type, 8_388_608.0)?
+            }
+            Operator::F64Nearest => {
+                // 2^52, from which on every f64 is an integer.
+                self.generate_float_nearest(f64_type, 4_503_599_627_370_496.0)?
+            }
+            Operator::RefNull { ty } => {
+                let ty =
+                    convert::to_llvm_valtype(self.llvm_context, &convert::to_wasmo_valtype(ty)?);
+                self.push(ty.zero());
+            }
+            Operator::RefIsNull => {
+                let operand = self.pop()?;
+                let is_null = self.builder.build_is_null(operand.as_ref(), "")?;
+                self.push(is_null);
+            }
+            Operator::TableGet { table } => match self.pop_table_pointer(*table)? {
+                Some((pointer, ty)) => {
+                    let value = self.builder.build_load(&ty, &pointer, "")?;
+                    self.push(value);
+                }
+                None => self.generate_unsupported(operator),
+            },
+            Operator::TableSet { table } => {
+                self.check_table_ref(*table, 0)?;
+                let value = self.pop()?;
+                match self.pop_table_pointer(*table)? {
+                    Some((pointer, _)) => {
+                        self.builder.build_store(value.as_ref(), &pointer);
+                    }
+                    None => self.generate_unsupported(operator),
+                }
+            }
+            // TODO(appcypher): Support multiple tables.
+            Operator::TableSize { table: 0 } => {
+                let (_, _, size) = self.get_table_globals()?;
+                let size = self.builder.build_load(&i64_type, &size, "table_size")?;
+                let size = self.builder.build_int_trunc(&size, &i32_type, "")?;
+                self.push(size);
+            }
+            Operator::TableGrow { table } => {
+                self.check_table_ref(*table, 1)?;
+                match table {
+                    0 => self.generate_table_grow()?,
+                    _ => self.generate_unsupported(operator),
+                }
+            }
+            Operator::TableFill { table } => {
+                self.check_table_ref(*table, 1)?;
+                match table {
+                    0 => self.generate_table_fill()?,
+                    _ => self.generate_unsupported(operator),
+                }
+            }
+            Operator::TableInit { segment, table: 0 } => {
+                match self.get_element_segment(*segment)? {
+                    Some(segment) => self.generate_table_init(segment)?,
+                    None => self.generate_unsupported(operator),
+                }
+            }
+            Operator::ElemDrop { segment } => {
+                if let Some((_, size)) = self.get_element_segment(*segment)? {
+                    self.builder.build_store(&i64_type.zero(), &size);
+                }
+            }
+            Operator::Nop => {}
+            Operator::End => {
+                // With no enclosing control, this is the end of the function body, which returns.
+                match self.control_stack.pop() {
+                    Some(control) => self.generate_end(control)?,
+                    None => self.generate_body_end()?,
+                }
+            }
+            operator => self.generate_unsupported(operator),
+        }
+
+        Ok(())
+    }
+
+    /// Checks if a block type has params.
+    fn has_block_params(&self, ty: &TypeOrFuncType) -> bool {
+        // TODO(appcypher): Support block params.
+        match ty {
+            TypeOrFuncType::Type(_) => false,
+            TypeOrFuncType::FuncType(index) => self
+                .info
+                .types
+                .get(*index as usize)
+                .is_some_and(|func_type| !func_type.params.is_empty()),
+        }
+    }
+
+    /// Gets the types of the results of a block type.
+    fn get_block_results(&self, ty: &TypeOrFuncType) -> Result<Vec<LLNumType>> {
+        let results = match ty {
+            TypeOrFuncType::Type(Type::EmptyBlockType) => vec![],
+            TypeOrFuncType::Type(ty) => vec![convert::to_wasmo_valtype(ty)?],
+            TypeOrFuncType::FuncType(index) => self
+                .info
+                .types
+                .get(*index as usize)
+                .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("type {}", index)))?
+                .results
+                .clone(),
+        };
+
+        Ok(results
+            .iter()
+            .map(|ty| convert::to_llvm_valtype(self.llvm_context, ty))
+            .collect())
+    }
+
+    /// Checks if `operator` can never run and should be skipped, updating the unreachable state.
+    ///
+    /// Code becomes reachable again at the `else` or `end` of the control it became unreachable in,
+    /// except for the `end` of the function body, after which there is no code.
+    fn skip_unreachable(&mut self, operator: &Operator) -> bool {
+        if !self.is_unreachable {
+            return false;
+        }
+
+        match operator {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                self.unreachable_depth += 1;
+                true
+            }
+            Operator::Else if self.unreachable_depth == 0 => {
+                self.is_unreachable = false;
+                false
+            }
+            Operator::End if self.unreachable_depth > 0 => {
+                self.unreachable_depth -= 1;
+                true
+            }
+            Operator::End => {
+                self.is_unreachable = self.control_stack.is_empty();
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the results of the function from the top of the value stack.
+    ///
+    /// Values below the results are left on the stack since the return may be in the middle of the function,
+    /// where controls enclosing it still expect their values when their `else` or `end` is reached.
+    pub(crate) fn generate_return(&mut self) -> Result<()> {
+        let type_index = self.info.functions[self.function_index as usize].type_index;
+        let result_count = self.info.types[type_index as usize].results.len();
+
+        let mut values = vec![];
+        for _ in 0..result_count {
+            values.push(self.pop()?);
+        }
+
+        values.reverse();
+
+        match &values[..] {
+            [] => self.builder.build_ret_void(),
+            [value] => self.builder.build_ret(value.as_ref()),
+            values => {
+                let values = values
+                    .iter()
+                    .map(|v| v.as_ref())
+                    .collect::<Vec<&dyn LLValue>>();
+                self.builder.build_aggregate_ret(&values);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls the function at `function_index`, passing it the params of its type from the value stack.
+    fn generate_call(&mut self, operator: &Operator, function_index: u32) -> Result<()> {
+        let type_index = self
+            .info
+            .functions
+            .get(function_index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("function {}", function_index)))?
+            .type_index;
+        let func_type = &self.info.types[type_index as usize];
+
+        // Imported functions precede the local ones in the index space, and are called through the host.
+        let function = match (function_index as usize)
+            .checked_sub(self.info.imports.functions.len())
+            .and_then(|local_index| self.llvm_functions.get(local_index))
+        {
+            Some(function) => Rc::clone(function),
+            None => return self.generate_call_import(operator, function_index),
+        };
+
+        let mut args = vec![];
+        for _ in 0..func_type.params.len() {
+            args.push(self.pop()?);
+        }
+
+        args.reverse();
+
+        let args = args
+            .iter()
+            .map(|v| v.as_ref())
+            .collect::<Vec<&dyn LLValue>>();
+        let value = self.builder.build_call(&function, &args, "")?;
+        self.push_call_results(value, func_type.results.len())
+    }
+
+    /// Calls the imported function at `function_index` through the call import intrinsic, which dispatches to the host
+    /// function the instance resolved the import to.
+    ///
+    /// Arguments and results are passed in 8-byte stack slots laid out like those of a trampoline.
+    pub(crate) fn generate_call_import(
+        &mut self,
+        operator: &Operator,
+        function_index: u32,
+    ) -> Result<()> {
+        let type_index = self.info.functions[function_index as usize].type_index;
+        let func_type = &self.info.types[type_index as usize];
+
+        // TODO(appcypher): Pass references once the host can resolve them.
+        if !func_type
+            .params
+            .iter()
+            .chain(&func_type.results)
+            .all(|ty| matches!(ty, ValType::Num(_)))
+        {
+            self.generate_unsupported(operator);
+            return Ok(());
+        }
+
+        let mut args = vec![];
+        for _ in 0..func_type.params.len() {
+            args.push(self.pop()?);
+        }
+
+        args.reverse();
+
+        let i32_type = self.llvm_context.i32_type();
+        let i64_type = self.llvm_context.i64_type();
+        let byte_pointer_type = self.llvm_context.i8_type().pointer_type();
+        let slots_type = i64_type.pointer_type();
+        let call_import = self.llvm_module.add_or_get_host_function(
+            CALL_IMPORT_SYMBOL,
+            &LLFunctionType::new(
+                &[byte_pointer_type, i32_type, slots_type, slots_type],
+                &LLResultType::Void(self.llvm_context.void_type()),
+                false,
+            ),
+        )?;
+
+        let functions = self
+            .llvm_module
+            .add_or_get_imported_functions(&byte_pointer_type)?;
+        let functions =
+            self.builder
+                .build_load(&byte_pointer_type, &functions, "imported_functions")?;
+
+        self.add_import_slots()?;
+        let (arg_slots, result_slots) = self.import_slots.as_ref().unwrap();
+        for (index, (arg, ty)) in args.iter().zip(&func_type.params).enumerate() {
+            let ty = convert::to_llvm_valtype(self.llvm_context, ty);
+            let slot = self.get_slot(arg_slots, index, &ty)?;
+            self.builder.build_store(arg.as_ref(), &slot);
+        }
+
+        self.builder.build_call_host_function(
+            &call_import,
+            &[
+                &functions,
+                &i32_type.const_int(function_index as u64),
+                arg_slots,
+                result_slots,
+            ],
+            "",
+        )?;
+
+        let mut results = vec![];
+        for (index, ty) in func_type.results.iter().enumerate() {
+            let ty = convert::to_llvm_valtype(self.llvm_context, ty);
+            let slot = self.get_slot(result_slots, index, &ty)?;
+            results.push(self.builder.build_load(&ty, &slot, "")?);
+        }
+
+        for result in results {
+            self.push(result);
+        }
+
+        Ok(())
+    }
+
+    /// Creates the stack slots of the arguments and results of calls to imported functions, if not done yet.
+    ///
+    /// They are created at the start of the entry block, with room for the imported function with the most params or
+    /// results, so that calls in loops do not grow the stack.
+    fn add_import_slots(&mut self) -> Result<()> {
+        if self.import_slots.is_none() {
+            let count = self
+                .info
+                .imports
+                .functions
+                .iter()
+                .map(|import| {
+                    let ty = &self.info.types
+                        [self.info.functions[import.index as usize].type_index as usize];
+                    ty.params.len().max(ty.results.len())
+                })
+                .max()
+                .unwrap_or_default()
+                .max(1);
+
+            let i64_type = self.llvm_context.i64_type();
+            let count = i64_type.const_int(count as u64);
+            let builder = LLBuilder::new(self.llvm_context);
+            builder.position_at_start(&self.function.basic_blocks()[0]);
+
+            self.import_slots = Some((
+                builder.build_array_alloca(&i64_type, &count, "import_args")?,
+                builder.build_array_alloca(&i64_type, &count, "import_results")?,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Gets a pointer to slot `index` of `slots`, cast to hold a value of type `ty`.
+    fn get_slot(&self, slots: &LLAlloca, index: usize, ty: &LLNumType) -> Result<LLBitCast> {
+        let i64_type = self.llvm_context.i64_type();
+        let slot =
+            self.builder
+                .build_gep(&i64_type, slots, &i64_type.const_int(index as u64), "")?;
+
+        self.builder.build_bitcast(&slot, &ty.pointer_type(), "")
+    }
+
+    /// Calls the function in a slot of table `table_index`, whose index is popped off the value stack before the
+    /// arguments of a function of type `type_index`.
+    ///
+    /// Traps with `OutOfBoundsTable` if the index is not less than the size of the table, with `UndefinedElement` if
+    /// the slot is null, and with `IndirectCallTypeMismatch` if the type id of the function the slot references is not
+    /// that of the expected type.
+    fn generate_call_indirect(
+        &mut self,
+        operator: &Operator,
+        type_index: u32,
+        table_index: u32,
+    ) -> Result<()> {
+        let func_type = self
+            .info
+            .types
+            .get(type_index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("type {}", type_index)))?;
+
+        let table = self
+            .info
+            .tables
+            .get(table_index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("table {}", table_index)))?;
+        if table.element_type != ValType::Ref(RefType::FuncRef) {
+            return Err(CompilerError::TypeMismatch(format!(
+                "indirect call through table {} of non-function references",
+                table_index
+            ))
+            .into());
+        }
+
+        let (pointer, ty) = match self.pop_table_pointer(table_index)? {
+            Some(slot) => slot,
+            None => {
+                self.generate_unsupported(operator);
+                return Ok(());
+            }
+        };
+
+        let callee = self.builder.build_load(&ty, &pointer, "callee")?;
+        let is_null = self.builder.build_is_null(&callee, "")?;
+        self.generate_trap_if(&is_null, TrapCode::UndefinedElement, None)?;
+
+        // Funcrefs live in their own address space, which has to be left before reading the function reference.
+        let i64_type = self.llvm_context.i64_type();
+        let byte_pointer_type = self.llvm_context.i8_type().pointer_type();
+        let function_type = convert::to_llvm_functype(self.llvm_context, func_type);
+        let reference = self
+            .builder
+            .build_addrspace_cast(&callee, &byte_pointer_type, "")?;
+
+        // The reference holds the address of the function followed by the id of its type.
+        let fields = self
+            .builder
+            .build_bitcast(&reference, &i64_type.pointer_type(), "")?;
+        let type_id = self
+            .builder
+            .build_gep(&i64_type, &fields, &i64_type.const_int(1), "")?;
+        let type_id = self.builder.build_load(&i64_type, &type_id, "type_id")?;
+        let expected_id = i64_type.const_int(self.info.type_id(type_index) as u64);
+        let is_mismatch =
+            self.builder
+                .build_int_cmp(LLVMIntPredicate::LLVMIntNE, &type_id, &expected_id, "")?;
+        self.generate_trap_if(&is_mismatch, TrapCode::IndirectCallTypeMismatch, None)?;
+
+        let function_pointer_type = function_type.pointer_type();
+        let callee =
+            self.builder
+                .build_bitcast(&reference, &function_pointer_type.pointer_type(), "")?;
+        let callee = self
+            .builder
+            .build_load(&function_pointer_type, &callee, "callee_function")?;
+
+        let mut args = vec![];
+        for _ in 0..func_type.params.len() {
+            args.push(self.pop()?);
+        }
+
+        args.reverse();
+
+        let args = args
+            .iter()
+            .map(|v| v.as_ref())
+            .collect::<Vec<&dyn LLValue>>();
+        let value = self
+            .builder
+            .build_call_pointer(&function_type, &callee, &args, "")?;
+        self.push_call_results(value, func_type.results.len())
+    }
+
+    /// Pushes the `result_count` results of a call, which come back as a struct when there are several.
+    fn push_call_results(&mut self, value: LLCall, result_count: usize) -> Result<()> {
+        match result_count {
+            0 => (),
+            1 => self.push(value),
+            result_count => {
+                for index in 0..result_count {
+                    let result = self.builder.build_extract_value(&value, index as u32, "")?;
+                    self.push(result);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that another block, loop or if can be entered, guarding against nesting deep enough to exhaust memory.
+    fn check_control_depth(&self) -> Result<()> {
+        if self.control_stack.len() >= self.limits.max_control_depth as usize {
+            return Err(CompilerError::LimitExceeded(format!(
+                "function nests more than {} controls",
+                self.limits.max_control_depth
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Gets the control `depth` levels out from the innermost one.
+    ///
+    /// Returns `None` for the function body itself, which is just outside the outermost control.
+    fn get_control(&self, depth: u32) -> Option<&Control> {
+        self.control_stack.iter().rev().nth(depth as usize)
+    }
+
+    /// Pops an `i32` condition off the value stack and compares it against zero.
+    ///
+    /// A boolean left by a comparison is already an `i1` and is used as is.
+    fn pop_condition(&mut self) -> Result<Rc<dyn LLValue>> {
+        let value = self
+            .value_stack
+            .pop()
+            .ok_or(CompilerError::ValueStackUnderflow)?;
+        if value.has_type(&self.llvm_context.i1_type()) {
+            return Ok(value);
+        }
+
+        let i32_type = self.llvm_context.i32_type();
+        if !value.has_type(&i32_type) {
+            return Err(CompilerError::TypeMismatch("condition".to_string()).into());
+        }
+
+        let cmp = self.builder.build_int_cmp(
+            LLVMIntPredicate::LLVMIntNE,
+            value.as_ref(),
+            &i32_type.zero(),
+            "",
+        )?;
+
+        Ok(Rc::new(cmp))
+    }
+
+    /// Branches to `destination` unless the current block already ends with a branch.
+    ///
+    /// The branch carries the values on top of the value stack as the `results` of the control ending there.
+    fn generate_fallthrough(
+        &mut self,
+        destination: &LLBasicBlock,
+        results: &mut ControlResults,
+    ) -> Result<()> {
+        if self.builder.get_insert_block().has_terminator() {
+            return Ok(());
+        }
+
+        if !results.types.is_empty() {
+            let values = self.peek_results(results.types.len())?;
+            results
+                .incoming
+                .push((values, self.builder.get_insert_block()));
+        }
+
+        self.builder.build_br(destination);
+
+        Ok(())
+    }
+
+    /// Records the values on top of the value stack as carried by a branch from the current block to the control
+    /// `depth` levels out, before the branch is built.
+    fn add_branch_results(&mut self, depth: u32) -> Result<()> {
+        let count = match self
+            .control_stack
+            .iter_mut()
+            .rev()
+            .nth(depth as usize)
+            .and_then(Control::branch_results_mut)
+        {
+            Some(results) if !results.types.is_empty() => results.types.len(),
+            _ => return Ok(()),
+        };
+
+        let values = self.peek_results(count)?;
+        let block = self.builder.get_insert_block();
+        if let Some(results) = self
+            .control_stack
+            .iter_mut()
+            .rev()
+            .nth(depth as usize)
+            .and_then(Control::branch_results_mut)
+        {
+            results.incoming.push((values, block));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the `count` values on top of the value stack, in stack order, without popping them.
+    ///
+    /// Booleans among them are extended on the stack, since they leave the block they are in as `i32`.
+    fn peek_results(&mut self, count: usize) -> Result<Vec<Rc<dyn LLValue>>> {
+        let mut values = vec![];
+        for _ in 0..count {
+            values.push(self.pop()?);
+        }
+
+        values.reverse();
+        self.value_stack.extend(values.iter().cloned());
+
+        Ok(values)
+    }
+
+    /// Returns from the end of the function body, unless it already returned because the end is unreachable.
+    ///
+    /// Operators following it are never reached, and there are none in a valid body.
+    fn generate_body_end(&mut self) -> Result<()> {
+        if !self.is_unreachable {
+            self.generate_return()?;
+        }
+
+        self.value_stack.clear();
+        self.is_body_ended = true;
+        self.is_unreachable = true;
+
+        Ok(())
+    }
+
+    /// Closes a control, continuing after it with its results merged on the value stack.
+    fn generate_end(&mut self, control: Control) -> Result<()> {
+        let (end, stack_height, mut results, else_block) = match control {
+            Control::Block {
+                end,
+                stack_height,
+                results,
+            }
+            | Control::Loop {
+                end,
+                stack_height,
+                results,
+                ..
+            } => (end, stack_height, results, None),
+            Control::If {
+                else_block,
+                end,
+                stack_height,
+                results,
+            } => (end, stack_height, results, else_block),
+        };
+
+        self.generate_fallthrough(&end, &mut results)?;
+
+        // An if without an else does nothing when its condition is false, so it cannot have results.
+        let is_else_missing = else_block.is_some();
+        if let Some(else_block) = else_block {
+            self.function.append_basic_block(&else_block);
+            self.builder.position_at_end(&else_block);
+            self.builder.build_br(&end);
+        }
+
+        self.value_stack.truncate(stack_height);
+        self.function.append_basic_block(&end);
+        self.builder.position_at_end(&end);
+
+        if is_else_missing && !results.types.is_empty() {
+            return Err(
+                CompilerError::InvalidModule("if with results but no else".to_string()).into(),
+            );
+        }
+
+        for (index, ty) in results.types.iter().enumerate() {
+            // Nothing branches to the end, so it is never reached and any value will do.
+            if results.incoming.is_empty() {
+                self.push(ty.zero());
+                continue;
+            }
+
+            let mut incoming = vec![];
+            for (values, block) in results.incoming.iter() {
+                let value = values[index].as_ref();
+                if !value.has_type(ty) {
+                    return Err(CompilerError::TypeMismatch("block result".to_string()).into());
+                }
+
+                incoming.push((value, block));
+            }
+
+            let mut phi = self.builder.build_phi(ty, "")?;
+            phi.add_incoming(&incoming);
+            self.push(phi);
+        }
+
+        Ok(())
+    }
+
+    /// Traps in place of an operator without codegen support.
+    fn generate_unsupported(&mut self, operator: &Operator) {
+        // TODO(appcypher): Remove once all operators are supported.
+        warn!("unsupported operator {:?}, function will trap", operator);
+        self.builder.build_unreachable();
+        self.is_unsupported = true;
+        self.release_blocks();
+    }
+
+    /// Appends the blocks of controls that are still open to the function, ending each with an `unreachable`.
+    ///
+    /// Blocks not yet appended are owned by nothing and are never freed, so this must be called once the function
+    /// is done with, including when generating it fails. Blocks that earlier branches target must also end up
+    /// in the function for it to be well-formed.
+    pub(crate) fn release_blocks(&mut self) {
+        for control in mem::take(&mut self.control_stack) {
+            if let Control::If {
+                else_block: Some(else_block),
+                ..
+            } = &control
+            {
+                self.function.append_basic_block(else_block);
+                self.builder.position_at_end(else_block);
+                self.builder.build_unreachable();
+            }
+
+            self.function.append_basic_block(control.end());
+            self.builder.position_at_end(control.end());
+            self.builder.build_unreachable();
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: impl LLValue + 'static) {
+        self.value_stack.push(Rc::new(value));
+    }
+
+    /// Pops a value off the value stack, extending a boolean to the `i32` wasm represents it as.
+    ///
+    /// Booleans stay `i1` on the stack so that those only used as conditions are never extended.
+    pub(crate) fn pop(&mut self) -> Result<Rc<dyn LLValue>> {
+        let value = self
+            .value_stack
+            .pop()
+            .ok_or(CompilerError::ValueStackUnderflow)?;
+        if !value.has_type(&self.llvm_context.i1_type()) {
+            return Ok(value);
+        }
+
+        let value =
+            self.builder
+                .build_int_zext(value.as_ref(), &self.llvm_context.i32_type(), "")?;
+
+        Ok(Rc::new(value))
+    }
+
+    /// Gets the value `depth` entries below the top of the value stack without popping it.
+    fn peek(&self, depth: usize) -> Result<&Rc<dyn LLValue>> {
+        self.value_stack
+            .iter()
+            .rev()
+            .nth(depth)
+            .ok_or_else(|| CompilerError::ValueStackUnderflow.into())
+    }
+
+    /// Checks that the reference `depth` entries below the top of the value stack can be stored in a table.
+    ///
+    /// Funcref and externref have distinct LLVM types so a mismatch shows in the type of the value.
+    fn check_table_ref(&self, table_index: u32, depth: usize) -> Result<()> {
+        let table = self
+            .info
+            .tables
+            .get(table_index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("table {}", table_index)))?;
+
+        let ty = convert::to_llvm_valtype(self.llvm_context, &table.element_type);
+        if !self.peek(depth)?.has_type(&ty) {
+            return Err(CompilerError::TypeMismatch(format!(
+                "reference stored in table {} of type {:?}",
+                table_index, table.element_type
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Pops an index off the value stack and gets a pointer to the slot it refers to in table `table_index`, along with
+    /// the type of the slot.
+    ///
+    /// Traps with `OutOfBoundsTable` if the index is not less than the size of the table.
+    ///
+    /// Returns `None` for tables other than table 0, which is the only one with a base address yet.
+    fn pop_table_pointer(&mut self, table_index: u32) -> Result<Option<(LLGep, LLNumType)>> {
+        let index = self.pop()?;
+        if !index.has_type(&self.llvm_context.i32_type()) {
+            return Err(CompilerError::TypeMismatch("table index".to_string()).into());
+        }
+
+        let ty = self.get_table_type(table_index)?;
+
+        // TODO(appcypher): Support multiple tables.
+        if table_index != 0 {
+            return Ok(None);
+        }
+
+        let (_, base, size) = self.get_table_globals()?;
+        let base = self
+            .builder
+            .build_load(&ty.pointer_type(), &base, "table_base")?;
+
+        let i64_type = self.llvm_context.i64_type();
+        let index = self.builder.build_int_zext(index.as_ref(), &i64_type, "")?;
+        let size = self.builder.build_load(&i64_type, &size, "table_size")?;
+        let is_out_of_bounds =
+            self.builder
+                .build_int_cmp(LLVMIntPredicate::LLVMIntUGE, &index, &size, "")?;
+        self.generate_trap_if(&is_out_of_bounds, TrapCode::OutOfBoundsTable, None)?;
+
+        let pointer = self.builder.build_gep(&ty, &base, &index, "")?;
+
+        Ok(Some((pointer, ty)))
+    }
+
+    /// Gets the globals holding the host slot vector, base address and size of table 0.
+    ///
+    /// All three are defined together so the engine can set them whichever table operators a module uses.
+    fn get_table_globals(&self) -> Result<(LLGlobal, LLGlobal, LLGlobal)> {
+        let ty = self.get_table_type(0)?;
+        let slots = self
+            .llvm_module
+            .add_or_get_table_slots(&self.llvm_context.i8_type().pointer_type())?;
+        let base = self.llvm_module.add_or_get_table_base(&ty.pointer_type())?;
+        let size = self
+            .llvm_module
+            .add_or_get_table_size(&self.llvm_context.i64_type())?;
+
+        Ok((slots, base, size))
+    }
+
+    /// Gets the LLVM type of the references table `index` holds.
+    fn get_table_type(&self, index: u32) -> Result<LLNumType> {
+        let table = self
+            .info
+            .tables
+            .get(index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("table {}", index)))?;
+
+        Ok(convert::to_llvm_valtype(
+            self.llvm_context,
+            &table.element_type,
+        ))
+    }
+
+    /// Grows table 0 by the number of slots on top of the value stack, set to the reference below it.
+    ///
+    /// Pushes the old size of the table, or -1 if it cannot grow.
+    fn generate_table_grow(&mut self) -> Result<()> {
+        let i32_type = self.llvm_context.i32_type();
+        let i64_type = self.llvm_context.i64_type();
+        let delta = self.pop()?;
+        if !delta.has_type(&i32_type) {
+            return Err(CompilerError::TypeMismatch("table grow delta".to_string()).into());
+        }
+        let init = self.pop()?;
+
+        let ty = self.get_table_type(0)?;
+        let byte_pointer_type = self.llvm_context.i8_type().pointer_type();
+        let table_grow = self.llvm_module.add_or_get_host_function(
+            TABLE_GROW_SYMBOL,
+            &LLFunctionType::new(
+                &[
+                    byte_pointer_type,
+                    i32_type,
+                    ty,
+                    i64_type,
+                    ty.pointer_type().pointer_type(),
+                    i64_type.pointer_type(),
+                ],
+                &LLResultType::Num(i32_type),
+                false,
+            ),
+        )?;
+
+        let (slots, base, size) = self.get_table_globals()?;
+        let slots = self
+            .builder
+            .build_load(&byte_pointer_type, &slots, "table_slots")?;
+        let max = self.info.tables[0].limits.max.unwrap_or(u64::MAX);
+
+        let old_size = self.builder.build_call_host_function(
+            &table_grow,
+            &[
+                &slots,
+                delta.as_ref(),
+                init.as_ref(),
+                &i64_type.const_int(max),
+                &base,
+                &size,
+            ],
+            "",
+        )?;
+        self.push(old_size);
+
+        Ok(())
+    }
+
+    /// Sets a number of slots of table 0 from an index on to a reference, all popped off the value stack.
+    ///
+    /// Traps before setting anything if any of the slots is out of bounds.
+    fn generate_table_fill(&mut self) -> Result<()> {
+        let i32_type = self.llvm_context.i32_type();
+        let i64_type = self.llvm_context.i64_type();
+        let count = self.pop()?;
+        let value = self.pop()?;
+        let index = self.pop()?;
+        if !count.has_type(&i32_type) || !index.has_type(&i32_type) {
+            return Err(CompilerError::TypeMismatch("table fill operand".to_string()).into());
+        }
+
+        let ty = self.get_table_type(0)?;
+        let (_, base, size) = self.get_table_globals()?;
+        let size = self.builder.build_load(&i64_type, &size, "table_size")?;
+        let start = self.builder.build_int_zext(index.as_ref(), &i64_type, "")?;
+        let length = self.builder.build_int_zext(count.as_ref(), &i64_type, "")?;
+        self.generate_bounds_check(
+            &start,
+            &length,
+            &size,
+            TrapCode::OutOfBoundsTable,
+            None,
+            false,
+        )?;
+
+        let table_fill = self.llvm_module.add_or_get_host_function(
+            TABLE_FILL_SYMBOL,
+            &LLFunctionType::new(
+                &[ty.pointer_type(), i32_type, ty, i32_type],
+                &LLResultType::Void(self.llvm_context.void_type()),
+                false,
+            ),
+        )?;
+        let base = self
+            .builder
+            .build_load(&ty.pointer_type(), &base, "table_base")?;
+        self.builder.build_call_host_function(
+            &table_fill,
+            &[&base, index.as_ref(), value.as_ref(), count.as_ref()],
+            "",
+        )?;
+
+        Ok(())
+    }
+
+    /// Checks that the memory at `index` is shared, which atomic accesses require.
+    fn check_shared_memory(&self, index: u32) -> Result<()> {
+        let memory = self
+            .info
+            .memories
+            .get(index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("memory {}", index)))?;
+
+        if !memory.is_shared {
+            return Err(CompilerError::AtomicOnUnsharedMemory(format!("memory {}", index)).into());
+        }
+
+        Ok(())
+    }
+
+    fn get_local(&self, index: u32) -> Result<&(LLAlloca, LLNumType)> {
+        self.locals
+            .get(index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("local {}", index)).into())
+    }
+
+    /// Gets the declaration and type of an imported global, or `None` if the global is defined in the module.
+    fn get_imported_global(&self, index: u32) -> Result<Option<(LLGlobal, LLNumType)>> {
+        let import = match self.info.imports.globals.get(index as usize) {
+            Some(import) => import,
+            None => return Ok(None),
+        };
+
+        let global = self
+            .llvm_module
+            .get_global(&import.symbol_name())?
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("global {}", index)))?;
+
+        let ty = convert::to_llvm_valtype(
+            self.llvm_context,
+            &self.info.globals[index as usize].content_type,
+        );
+
+        Ok(Some((global, ty)))
+    }
+
+    /// Loads a value of type `ty` from the address on top of the value stack.
+    fn generate_load(
+        &mut self,
+        operator: &Operator,
+        ty: &LLNumType,
+        memarg: &MemoryImmediate,
+    ) -> Result<()> {
+        if let Some(value) = self.build_memory_load(operator, ty, memarg)? {
+            self.push(value);
+        }
+
+        Ok(())
+    }
+
+    /// Loads an integer of the narrower type `narrow_ty` from the address on top of the value stack and extends it
+    /// to `ty`.
+    fn generate_extending_load(
+        &mut self,
+        operator: &Operator,
+        ty: &LLNumType,
+        narrow_ty: &LLNumType,
+        is_signed: bool,
+        memarg: &MemoryImmediate,
+    ) -> Result<()> {
+        if let Some(narrow) = self.build_memory_load(operator, narrow_ty, memarg)? {
+            if is_signed {
+                let value = self.builder.build_int_sext(&narrow, ty, "")?;
+                self.push(value);
+            } else {
+                let value = self.builder.build_int_zext(&narrow, ty, "")?;
+                self.push(value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores the value of type `ty` on top of the value stack to the address below it.
+    ///
+    /// Integers are truncated to `narrow_ty` first if given.
+    fn generate_store(
+        &mut self,
+        operator: &Operator,
+        ty: &LLNumType,
+        narrow_ty: Option<&LLNumType>,
+        memarg: &MemoryImmediate,
+    ) -> Result<()> {
+        let value = self.pop()?;
+        if !value.has_type(ty) {
+            return Err(CompilerError::TypeMismatch("stored value".to_string()).into());
+        }
+
+        let (value, store_ty) = match narrow_ty {
+            Some(narrow_ty) => {
+                let narrow = self
+                    .builder
+                    .build_int_trunc(value.as_ref(), narrow_ty, "")?;
+                (Rc::new(narrow) as Rc<dyn LLValue>, narrow_ty)
+            }
+            None => (value, ty),
+        };
+
+        match self.pop_memory_pointer(store_ty, memarg)? {
+            Some(pointer) => {
+                let store = self.builder.build_store(value.as_ref(), &pointer);
+                store.set_alignment(1 << memarg.align);
+            }
+            None => self.generate_unsupported(operator),
+        }
+
+        Ok(())
+    }
+
+    /// Loads a value of type `ty` from the address popped off the value stack.
+    ///
+    /// Returns `None` after trapping in place of accesses to memories without a base address yet.
+    fn build_memory_load(
+        &mut self,
+        operator: &Operator,
+        ty: &LLNumType,
+        memarg: &MemoryImmediate,
+    ) -> Result<Option<LLLoad>> {
+        let pointer = match self.pop_memory_pointer(ty, memarg)? {
+            Some(pointer) => pointer,
+            None => {
+                self.generate_unsupported(operator);
+                return Ok(None);
+            }
+        };
+
+        let value = self.builder.build_load(ty, &pointer, "")?;
+        // The alignment is encoded as a power of two, which validation limits to the size of `ty`.
+        value.set_alignment(1 << memarg.align);
+
+        Ok(Some(value))
+    }
+
+    /// Pops an address off the value stack and gets a pointer to the value of type `ty` it accesses with `memarg`.
+    ///
+    /// Traps with `OutOfBoundsMemory` if the value does not fit in the memory.
+    ///
+    /// Returns `None` for memories other than memory 0, which is the only one with a base address yet.
+    fn pop_memory_pointer(
+        &mut self,
+        ty: &LLNumType,
+        memarg: &MemoryImmediate,
+    ) -> Result<Option<LLBitCast>> {
+        let address = self.pop_address_operand(memarg.memory)?;
+
+        // TODO(appcypher): Support multiple memories.
+        if memarg.memory != 0 {
+            return Ok(None);
+        }
+
+        // The address is unsigned and adding the offset to it must not wrap around.
+        let i64_type = self.llvm_context.i64_type();
+        let offset = i64_type.const_int(memarg.offset);
+        let address = self.builder.build_int_add(address.as_ref(), &offset, "")?;
+
+        // Only the 64-bit addresses and offsets of memory64 can get that large.
+        if self.is_memory_64(0)? && memarg.offset != 0 {
+            let is_wrapped =
+                self.builder
+                    .build_int_cmp(LLVMIntPredicate::LLVMIntULT, &address, &offset, "")?;
+            self.generate_trap_if(&is_wrapped, TrapCode::OutOfBoundsMemory, Some(&address))?;
+        }
+
+        let pointer = self.build_memory_pointer(&address, &i64_type.const_int(ty.byte_size()))?;
+        let pointer = self
+            .builder
+            .build_bitcast(&pointer, &ty.pointer_type(), "")?;
+
+        Ok(Some(pointer))
+    }
+
+    /// Copies the `length` bytes at a source offset of data segment `index` to a destination address, all popped off
+    /// the value stack.
+    ///
+    /// Traps before writing anything if any of the bytes is out of bounds of either the segment or the memory.
+    fn generate_memory_init(&mut self, index: u32) -> Result<()> {
+        let length = self.pop_memory_operand()?;
+        let source = self.pop_memory_operand()?;
+        let destination = self.pop_address_operand(0)?;
+
+        // Dropped segments have a size of zero.
+        let i64_type = self.llvm_context.i64_type();
+        let byte_type = self.llvm_context.i8_type();
+        let (base, size) = self.get_data_segment(index)?;
+        let size = self.builder.build_load(&i64_type, &size, "data_size")?;
+        self.generate_bounds_check(
+            &source,
+            &length,
+            &size,
+            TrapCode::OutOfBoundsMemory,
+            None,
+            false,
+        )?;
+
+        let destination = self.build_memory_pointer(destination.as_ref(), &length)?;
+        let base = self
+            .builder
+            .build_load(&byte_type.pointer_type(), &base, "data_base")?;
+        let source = self.builder.build_gep(&byte_type, &base, &source, "")?;
+
+        let byte_pointer_type = byte_type.pointer_type();
+        let memcpy = self.get_intrinsic(
+            "llvm.memcpy",
+            &[byte_pointer_type, byte_pointer_type, i64_type],
+        )?;
+        let is_volatile = self.llvm_context.i1_type().const_int(0);
+        self.builder.build_call_intrinsic(
+            &memcpy,
+            &[&destination, &source, &length, &is_volatile],
+            "",
+        )?;
+
+        Ok(())
+    }
+
+    /// Copies the references at a source index of an element segment to a destination index of table 0, all popped
+    /// off the value stack along with their number.
+    ///
+    /// `segment` holds the globals with the base address and size of the segment. Traps before writing anything if
+    /// any of the references is out of bounds of either the segment or the table.
+    fn generate_table_init(&mut self, segment: (LLGlobal, LLGlobal)) -> Result<()> {
+        let length = self.pop_memory_operand()?;
+        let source = self.pop_memory_operand()?;
+        let destination = self.pop_memory_operand()?;
+
+        // Dropped segments have a size of zero.
+        let i64_type = self.llvm_context.i64_type();
+        let (base, size) = segment;
+        let size = self.builder.build_load(&i64_type, &size, "elem_size")?;
+        self.generate_bounds_check(
+            &source,
+            &length,
+            &size,
+            TrapCode::OutOfBoundsTable,
+            None,
+            false,
+        )?;
+
+        let (_, table_base, table_size) = self.get_table_globals()?;
+        let table_size = self
+            .builder
+            .build_load(&i64_type, &table_size, "table_size")?;
+        self.generate_bounds_check(
+            &destination,
+            &length,
+            &table_size,
+            TrapCode::OutOfBoundsTable,
+            None,
+            false,
+        )?;
+
+        let ty = self.get_table_type(0)?;
+        let byte_pointer_type = self.llvm_context.i8_type().pointer_type();
+        let base = self
+            .builder
+            .build_load(&byte_pointer_type, &base, "elem_base")?;
+        let base = self.builder.build_bitcast(&base, &ty.pointer_type(), "")?;
+        let source = self.builder.build_gep(&ty, &base, &source, "")?;
+        let source = self
+            .builder
+            .build_bitcast(&source, &byte_pointer_type, "")?;
+        let table_base = self
+            .builder
+            .build_load(&ty.pointer_type(), &table_base, "table_base")?;
+        let destination = self.builder.build_gep(&ty, &table_base, &destination, "")?;
+        let destination = self
+            .builder
+            .build_bitcast(&destination, &byte_pointer_type, "")?;
+        let byte_length =
+            self.builder
+                .build_int_mul(&length, &i64_type.const_int(ty.byte_size()), "")?;
+
+        let memcpy = self.get_intrinsic(
+            "llvm.memcpy",
+            &[byte_pointer_type, byte_pointer_type, i64_type],
+        )?;
+        let is_volatile = self.llvm_context.i1_type().const_int(0);
+        self.builder.build_call_intrinsic(
+            &memcpy,
+            &[&destination, &source, &byte_length, &is_volatile],
+            "",
+        )?;
+
+        Ok(())
+    }
+
+    /// Gets the globals holding the base address and `i64` size of element segment `index`.
+    ///
+    /// Returns `None` for a segment whose references are not compiled yet.
+    fn get_element_segment(&self, index: u32) -> Result<Option<(LLGlobal, LLGlobal)>> {
+        let element = self
+            .info
+            .elements
+            .get(index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("element {}", index)))?;
+
+        if !element.is_compiled() {
+            return Ok(None);
+        }
+
+        self.llvm_module
+            .add_or_get_element_segment(
+                index,
+                &self.llvm_context.i8_type().pointer_type(),
+                &self.llvm_context.i64_type(),
+            )
+            .map(Some)
+    }
+
+    /// Gets the globals holding the base address and `i64` size of data segment `index`.
+    fn get_data_segment(&self, index: u32) -> Result<(LLGlobal, LLGlobal)> {
+        if !matches!(self.info.data_count, Some(count) if index < count) {
+            return Err(CompilerError::IndexOutOfBounds(format!("data {}", index)).into());
+        }
+
+        self.llvm_module.add_or_get_data_segment(
+            index,
+            &self.llvm_context.i8_type().pointer_type(),
+            &self.llvm_context.i64_type(),
+        )
+    }
+
+    /// Pops an `i32` index or length off the value stack and extends it to the `i64` memory pointers are indexed
+    /// with.
+    fn pop_memory_operand(&mut self) -> Result<LLIntZExt> {
+        let operand = self.pop()?;
+        if !operand.has_type(&self.llvm_context.i32_type()) {
+            return Err(CompilerError::TypeMismatch("memory operand".to_string()).into());
+        }
+
+        self.builder
+            .build_int_zext(operand.as_ref(), &self.llvm_context.i64_type(), "")
+    }
+
+    /// Pops an address or length in the memory at `index` off the value stack as the `i64` memory pointers are
+    /// indexed with.
+    ///
+    /// They are `i64`s for 64-bit memories and `i32`s that get extended for the others.
+    fn pop_address_operand(&mut self, index: u32) -> Result<Rc<dyn LLValue>> {
+        if !self.is_memory_64(index)? {
+            return Ok(Rc::new(self.pop_memory_operand()?));
+        }
+
+        let operand = self.pop()?;
+        if !operand.has_type(&self.llvm_context.i64_type()) {
+            return Err(CompilerError::TypeMismatch("memory operand".to_string()).into());
+        }
+
+        Ok(operand)
+    }
+
+    /// Checks if the memory at `index` is a 64-bit memory of the memory64 proposal.
+    fn is_memory_64(&self, index: u32) -> Result<bool> {
+        let memory = self
+            .info
+            .memories
+            .get(index as usize)
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("memory {}", index)))?;
+
+        Ok(memory.index_type == NumType::I64)
+    }
+
+    /// Gets an `i8` pointer to the `length` bytes at the `i64` `address` of memory 0.
+    ///
+    /// Traps with `OutOfBoundsMemory` if the bytes do not fit in the memory.
+    fn build_memory_pointer(&self, address: &dyn LLValue, length: &dyn LLValue) -> Result<LLGep> {
+        let byte_type = self.llvm_context.i8_type();
+        let (_, base, size) = self.get_memory_globals()?;
+        let base = self
+            .builder
+            .build_load(&byte_type.pointer_type(), &base, "memory_base")?;
+
+        // The size is read at each access since the memory may have grown since the last one.
+        // TODO(appcypher): Skip the check for memories behind guard pages once faults in them are turned into traps.
+        let i64_type = self.llvm_context.i64_type();
+        let size = self.builder.build_load(&i64_type, &size, "memory_size")?;
+        self.generate_bounds_check(
+            address,
+            length,
+            &size,
+            TrapCode::OutOfBoundsMemory,
+            Some(address),
+            self.is_memory_64(0)?,
+        )?;
+
+        self.builder.build_gep(&byte_type, &base, address, "")
+    }
+
+    /// Gets the globals holding the host memory, base address and size of memory 0.
+    ///
+    /// All three are defined together so the engine can set them whichever memory operators a module uses.
+    fn get_memory_globals(&self) -> Result<(LLGlobal, LLGlobal, LLGlobal)> {
+        let byte_pointer_type = self.llvm_context.i8_type().pointer_type();
+        let memory = self.llvm_module.add_or_get_memory(&byte_pointer_type)?;
+        let base = self
+            .llvm_module
+            .add_or_get_memory_base(&byte_pointer_type)?;
+        let size = self
+            .llvm_module
+            .add_or_get_memory_size(&self.llvm_context.i64_type())?;
+
+        Ok((memory, base, size))
+    }
+
+    /// Pushes the size of memory 0 in pages, as an `i64` for 64-bit memories and an `i32` for the others.
+    fn generate_memory_size(&mut self) -> Result<()> {
+        let i64_type = self.llvm_context.i64_type();
+        let (_, _, size) = self.get_memory_globals()?;
+        let size = self.builder.build_load(&i64_type, &size, "memory_size")?;
+        let size = self
+            .builder
+            .build_udiv(&size, &i64_type.const_int(PAGE_SIZE as u64), "")?;
+        if self.is_memory_64(0)? {
+            self.push(size);
+            return Ok(());
+        }
+
+        let size = self
+            .builder
+            .build_int_trunc(&size, &self.llvm_context.i32_type(), "")?;
+        self.push(size);
+
+        Ok(())
+    }
+
+    /// Grows memory 0 by the number of pages on top of the value stack.
+    ///
+    /// Pushes the old size of the memory in pages, or -1 if it cannot grow. Like the delta, it is an `i64` for 64-bit
+    /// memories and an `i32` for the others.
+    fn generate_memory_grow(&mut self) -> Result<()> {
+        let i64_type = self.llvm_context.i64_type();
+        let delta = self.pop_address_operand(0)?;
+
+        let byte_pointer_type = self.llvm_context.i8_type().pointer_type();
+        let memory_grow = self.llvm_module.add_or_get_host_function(
+            MEMORY_GROW_SYMBOL,
+            &LLFunctionType::new(
+                &[
+                    byte_pointer_type,
+                    i64_type,
+                    byte_pointer_type.pointer_type(),
+                    i64_type.pointer_type(),
+                ],
+                &LLResultType::Num(i64_type),
+                false,
+            ),
+        )?;
+
+        // The base and size globals are updated by the intrinsic, and accesses after it read them again.
+        let (memory, base, size) = self.get_memory_globals()?;
+        let memory = self
+            .builder
+            .build_load(&byte_pointer_type, &memory, "memory")?;
+        let old_size = self.builder.build_call_host_function(
+            &memory_grow,
+            &[&memory, delta.as_ref(), &base, &size],
+            "",
+        )?;
+        if self.is_memory_64(0)? {
+            self.push(old_size);
+            return Ok(());
+        }
+
+        let old_size =
+            self.builder
+                .build_int_trunc(&old_size, &self.llvm_context.i32_type(), "")?;
+        self.push(old_size);
+
+        Ok(())
+    }
+
+    /// Traps with `code` unless the `length` items from `start` fit in the `size` items of a memory, table or
+    /// segment, all given as `i64`s.
+    ///
+    /// Starts and lengths from `i32` operands are both at most 33 bits wide, so adding them cannot wrap. Those of
+    /// 64-bit memories can, which is checked for if `may_wrap` is set. `address` is passed on to the trap for
+    /// out-of-bounds memory accesses.
+    fn generate_bounds_check(
+        &self,
+        start: &dyn LLValue,
+        length: &dyn LLValue,
+        size: &dyn LLValue,
+        code: TrapCode,
+        address: Option<&dyn LLValue>,
+        may_wrap: bool,
+    ) -> Result<()> {
+        let end = self.builder.build_int_add(start, length, "")?;
+        let is_out_of_bounds =
+            self.builder
+                .build_int_cmp(LLVMIntPredicate::LLVMIntUGT, &end, size, "")?;
+
+        if !may_wrap {
+            return self.generate_trap_if(&is_out_of_bounds, code, address);
+        }
+
+        let is_wrapped =
+            self.builder
+                .build_int_cmp(LLVMIntPredicate::LLVMIntULT, &end, start, "")?;
+        let is_out_of_bounds = self.builder.build_or(&is_out_of_bounds, &is_wrapped, "")?;
+
+        self.generate_trap_if(&is_out_of_bounds, code, address)
+    }
+
+    /// Sets the `length` bytes at a destination address to a value, all popped off the value stack.
+    ///
+    /// Traps before writing anything if any of the bytes is out of bounds.
+    fn generate_memory_fill(&mut self) -> Result<()> {
+        let length = self.pop_address_operand(0)?;
+        let value = self.pop()?;
+        if !value.has_type(&self.llvm_context.i32_type()) {
+            return Err(CompilerError::TypeMismatch("memory fill value".to_string()).into());
+        }
+        let destination = self.pop_address_operand(0)?;
+
+        let destination = self.build_memory_pointer(destination.as_ref(), length.as_ref())?;
+        let byte_type = self.llvm_context.i8_type();
+        let value = self
+            .builder
+            .build_int_trunc(value.as_ref(), &byte_type, "")?;
+
+        let memset = self.get_intrinsic(
+            "llvm.memset",
+            &[byte_type.pointer_type(), self.llvm_context.i64_type()],
+        )?;
+        let is_volatile = self.llvm_context.i1_type().const_int(0);
+        self.builder.build_call_intrinsic(
+            &memset,
+            &[&destination, &value, length.as_ref(), &is_volatile],
+            "",
+        )?;
+
+        Ok(())
+    }
+
+    /// Copies the `length` bytes at a source address to a destination address, all popped off the value stack.
+    ///
+    /// The ranges may overlap. Traps before writing anything if any of the bytes of either is out of bounds.
+    fn generate_memory_copy(&mut self) -> Result<()> {
+        let length = self.pop_address_operand(0)?;
+        let source = self.pop_address_operand(0)?;
+        let destination = self.pop_address_operand(0)?;
+
+        let source = self.build_memory_pointer(source.as_ref(), length.as_ref())?;
+        let destination = self.build_memory_pointer(destination.as_ref(), length.as_ref())?;
+
+        let byte_pointer_type = self.llvm_context.i8_type().pointer_type();
+        let memmove = self.get_intrinsic(
+            "llvm.memmove",
+            &[
+                byte_pointer_type,
+                byte_pointer_type,
+                self.llvm_context.i64_type(),
+            ],
+        )?;
+        let is_volatile = self.llvm_context.i1_type().const_int(0);
+        self.builder.build_call_intrinsic(
+            &memmove,
+            &[&destination, &source, length.as_ref(), &is_volatile],
+            "",
+        )?;
+
+        Ok(())
+    }
+
+    /// Pops the operands of a binary operator, checking that both are of the operator's type `ty`.
+    ///
+    /// Operands get their types from the operators that pushed them so nothing else guarantees they match.
+    fn pop_operands(&mut self, ty: &LLNumType) -> Result<(Rc<dyn LLValue>, Rc<dyn LLValue>)> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        if !lhs.has_type(ty) || !rhs.has_type(ty) {
+            return Err(CompilerError::TypeMismatch("binary operator operands".to_string()).into());
+        }
+
+        Ok((lhs, rhs))
+    }
+
+    /// Pops the `i32` operand of an extension to `i64`.
+    fn pop_extend_operand(&mut self) -> Result<Rc<dyn LLValue>> {
+        let operand = self.pop()?;
+        if !operand.has_type(&self.llvm_context.i32_type()) {
+            return Err(CompilerError::TypeMismatch("extend operand".to_string()).into());
+        }
+
+        Ok(operand)
+    }
+
+    /// Sign-extends the low bits of an integer operand of type `ty`, as wide as `narrow_ty`, back to `ty`.
+    fn generate_sign_extend(&mut self, ty: LLNumType, narrow_ty: LLNumType) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&ty) {
+            return Err(CompilerError::TypeMismatch("sign-extend operand".to_string()).into());
+        }
+
+        let narrow = self
+            .builder
+            .build_int_trunc(operand.as_ref(), &narrow_ty, "")?;
+        let value = self.builder.build_int_sext(&narrow, &ty, "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Applies a binary integer or float instruction to operands of type `ty`.
+    fn generate_binop<T: LLValue + 'static>(
+        &mut self,
+        ty: LLNumType,
+        build: BuildBinOp<T>,
+    ) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        let value = build(&self.builder, lhs.as_ref(), rhs.as_ref(), "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Shifts an integer operand of type `ty`.
+    ///
+    /// Wasm takes the shift count modulo the bit width whereas LLVM gives poison for counts as large as the width.
+    fn generate_int_shift<T: LLValue + 'static>(
+        &mut self,
+        ty: LLNumType,
+        build: BuildBinOp<T>,
+    ) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        let mask = ty.const_int(ty.int_width() as u64 - 1);
+        let count = self.builder.build_and(rhs.as_ref(), &mask, "")?;
+        let value = build(&self.builder, lhs.as_ref(), &count, "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Counts the leading or trailing zero bits of an integer operand of type `ty` with the intrinsic `name`.
+    ///
+    /// The intrinsic is told that a zero operand is not poison, so that it gives the bit width for it like wasm does.
+    fn generate_count_zeros(&mut self, ty: LLNumType, name: &str) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&ty) {
+            return Err(CompilerError::TypeMismatch("counted operand".to_string()).into());
+        }
+
+        let intrinsic = self.get_intrinsic(name, &[ty])?;
+        let is_zero_poison = self.llvm_context.i1_type().const_int(0);
+        let value = self.builder.build_call_intrinsic(
+            &intrinsic,
+            &[operand.as_ref(), &is_zero_poison],
+            "",
+        )?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Rotates an integer operand of type `ty` left, or right if `is_left` is not set.
+    ///
+    /// A funnel shift of a value with itself is a rotation, and it takes the count modulo the bit width like wasm does.
+    fn generate_int_rotate(&mut self, ty: LLNumType, is_left: bool) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        let name = if is_left { "llvm.fshl" } else { "llvm.fshr" };
+        let intrinsic = self.get_intrinsic(name, &[ty])?;
+        let value = self.builder.build_call_intrinsic(
+            &intrinsic,
+            &[lhs.as_ref(), lhs.as_ref(), rhs.as_ref()],
+            "",
+        )?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Divides integer operands of type `ty`, trapping where LLVM would give undefined behavior instead.
+    fn generate_int_div<T: LLValue + 'static>(
+        &mut self,
+        ty: LLNumType,
+        is_signed: bool,
+        build: BuildBinOp<T>,
+    ) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        self.generate_divide_by_zero_check(&ty, rhs.as_ref())?;
+
+        if is_signed {
+            let is_overflow = self.build_signed_overflow_cmp(&ty, lhs.as_ref(), rhs.as_ref())?;
+            self.generate_trap_if(&is_overflow, TrapCode::IntegerOverflow, None)?;
+        }
+
+        let value = build(&self.builder, lhs.as_ref(), rhs.as_ref(), "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Takes the remainder of integer operands of type `ty`, trapping on a zero divisor.
+    ///
+    /// Unlike the division, the signed remainder of the minimum integer by -1 is defined in wasm and is 0.
+    /// LLVM gives undefined behavior for it so the divisor is swapped for 1, which has the same remainder.
+    fn generate_int_rem<T: LLValue + 'static>(
+        &mut self,
+        ty: LLNumType,
+        is_signed: bool,
+        build: BuildBinOp<T>,
+    ) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        self.generate_divide_by_zero_check(&ty, rhs.as_ref())?;
+
+        let value = if is_signed {
+            let is_overflow = self.build_signed_overflow_cmp(&ty, lhs.as_ref(), rhs.as_ref())?;
+            let divisor =
+                self.builder
+                    .build_select(&is_overflow, &ty.const_int(1), rhs.as_ref(), "")?;
+
+            build(&self.builder, lhs.as_ref(), &divisor, "")?
+        } else {
+            build(&self.builder, lhs.as_ref(), rhs.as_ref(), "")?
+        };
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Traps if the divisor `rhs` of type `ty` is zero.
+    fn generate_divide_by_zero_check(&self, ty: &LLNumType, rhs: &dyn LLValue) -> Result<()> {
+        let is_zero =
+            self.builder
+                .build_int_cmp(LLVMIntPredicate::LLVMIntEQ, rhs, &ty.zero(), "")?;
+
+        self.generate_trap_if(&is_zero, TrapCode::IntegerDivideByZero, None)
+    }
+
+    /// Checks if a signed division of `lhs` by `rhs` overflows, which is only the case for the minimum integer by -1.
+    fn build_signed_overflow_cmp(
+        &self,
+        ty: &LLNumType,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+    ) -> Result<LLAnd> {
+        let min = ty.const_int(1 << (ty.int_width() - 1));
+        let minus_one = ty.const_int(u64::MAX);
+        let is_min = self
+            .builder
+            .build_int_cmp(LLVMIntPredicate::LLVMIntEQ, lhs, &min, "")?;
+        let is_minus_one =
+            self.builder
+                .build_int_cmp(LLVMIntPredicate::LLVMIntEQ, rhs, &minus_one, "")?;
+
+        self.builder.build_and(&is_min, &is_minus_one, "")
+    }
+
+    /// Calls into the runtime to raise a trap with `code` if the `i1` `condition` holds.
+    fn generate_trap_if(
+        &self,
+        condition: &dyn LLValue,
+        code: TrapCode,
+        address: Option<&dyn LLValue>,
+    ) -> Result<()> {
+        let trap = LLBasicBlock::new("trap", self.llvm_context)?;
+        let next = LLBasicBlock::new("trap_next", self.llvm_context)?;
+        self.builder.build_cond_br(condition, &trap, &next);
+
+        self.function.append_basic_block(&trap);
+        self.builder.position_at_end(&trap);
+        self.generate_trap(code, address)?;
+
+        self.function.append_basic_block(&next);
+        self.builder.position_at_end(&next);
+
+        Ok(())
+    }
+
+    /// Raises a trap with `code` at the current operator, terminating the current block.
+    ///
+    /// `address` is the `i64` address of an out-of-bounds memory access.
+    fn generate_trap(&self, code: TrapCode, address: Option<&dyn LLValue>) -> Result<()> {
+        let i32_type = self.llvm_context.i32_type();
+        let i64_type = self.llvm_context.i64_type();
+        let raise_trap = self.llvm_module.add_or_get_host_function(
+            RAISE_TRAP_SYMBOL,
+            &LLFunctionType::new(
+                &[i32_type, i32_type, i32_type, i64_type],
+                &LLResultType::Void(self.llvm_context.void_type()),
+                false,
+            ),
+        )?;
+
+        let zero = i64_type.zero();
+        self.builder.build_call_host_function(
+            &raise_trap,
+            &[
+                &i32_type.const_int(code as u64),
+                &i32_type.const_int(self.function_index as u64),
+                &i32_type.const_int(self.offset as u64),
+                address.unwrap_or(&zero),
+            ],
+            "",
+        )?;
+        self.builder.build_unreachable();
+
+        Ok(())
+    }
+
+    /// Reinterprets the bits of an operand of type `from` as a value of type `to`, which has the same width.
+    fn generate_reinterpret(&mut self, from: LLNumType, to: LLNumType) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&from) {
+            return Err(CompilerError::TypeMismatch("reinterpreted operand".to_string()).into());
+        }
+
+        let value = self.builder.build_bitcast(operand.as_ref(), &to, "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Converts an integer operand of type `int_ty` to the nearest float of type `float_ty`.
+    fn generate_int_to_float(
+        &mut self,
+        int_ty: LLNumType,
+        float_ty: LLNumType,
+        is_signed: bool,
+    ) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&int_ty) {
+            return Err(CompilerError::TypeMismatch("converted operand".to_string()).into());
+        }
+
+        if is_signed {
+            let value = self
+                .builder
+                .build_si_to_fp(operand.as_ref(), &float_ty, "")?;
+            self.push(value);
+        } else {
+            let value = self
+                .builder
+                .build_ui_to_fp(operand.as_ref(), &float_ty, "")?;
+            self.push(value);
+        }
+
+        Ok(())
+    }
+
+    /// Truncates a float operand of type `float_ty` towards zero into an integer of type `int_ty`.
+    fn generate_float_to_int(
+        &mut self,
+        float_ty: LLNumType,
+        int_ty: LLNumType,
+        is_signed: bool,
+    ) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&float_ty) {
+            return Err(CompilerError::TypeMismatch("truncated operand".to_string()).into());
+        }
+
+        self.trap_if_out_of_range(operand.as_ref(), &float_ty, &int_ty, is_signed)?;
+
+        if is_signed {
+            let value = self.builder.build_fp_to_si(operand.as_ref(), &int_ty, "")?;
+            self.push(value);
+        } else {
+            let value = self.builder.build_fp_to_ui(operand.as_ref(), &int_ty, "")?;
+            self.push(value);
+        }
+
+        Ok(())
+    }
+
+    /// Truncates a float operand of type `float_ty` towards zero into an integer of type `int_ty`,
+    /// clamping out of range values to the integer's minimum or maximum and NaN to zero.
+    ///
+    /// `llvm.fptosi.sat` and `llvm.fptoui.sat` have the same semantics as the wasm operators.
+    fn generate_float_to_int_sat(
+        &mut self,
+        float_ty: LLNumType,
+        int_ty: LLNumType,
+        is_signed: bool,
+    ) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&float_ty) {
+            return Err(CompilerError::TypeMismatch("truncated operand".to_string()).into());
+        }
+
+        let name = if is_signed {
+            "llvm.fptosi.sat"
+        } else {
+            "llvm.fptoui.sat"
+        };
+        let intrinsic = self.get_intrinsic(name, &[int_ty, float_ty])?;
+        let value = self
+            .builder
+            .build_call_intrinsic(&intrinsic, &[operand.as_ref()], "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Traps unless the float `operand` truncates to an integer that fits in `int_ty`, which LLVM gives poison for.
+    ///
+    /// NaN traps as an invalid conversion and anything else out of range as an integer overflow.
+    /// The bounds are exclusive so that fractions truncating to the minimum or maximum pass.
+    fn trap_if_out_of_range(
+        &self,
+        operand: &dyn LLValue,
+        float_ty: &LLNumType,
+        int_ty: &LLNumType,
+        is_signed: bool,
+    ) -> Result<()> {
+        use LLVMRealPredicate::*;
+
+        let is_nan = self
+            .builder
+            .build_float_cmp(LLVMRealUNO, operand, operand, "")?;
+        self.generate_trap_if(&is_nan, TrapCode::InvalidConversionToInteger, None)?;
+
+        // Floats are rounded to `float_ty`, which only matters for the bounds below the minimum signed integer.
+        let is_f32 = operand.has_type(&self.llvm_context.f32_type());
+        let round = |value: f64| if is_f32 { value as f32 as f64 } else { value };
+
+        let width = int_ty.int_width() as i32;
+        let (min, max) = if is_signed {
+            (-(2f64.powi(width - 1)), 2f64.powi(width - 1))
+        } else {
+            (0.0, 2f64.powi(width))
+        };
+
+        // The float just below the minimum integer is one less if it can be represented, otherwise it is the
+        // minimum itself and the comparison includes it.
+        let below_min = round(min - 1.0);
+        let (predicate, lower_bound) = if below_min < min {
+            (LLVMRealOLE, below_min)
+        } else {
+            (LLVMRealOLT, min)
+        };
+
+        let is_too_small = self.builder.build_float_cmp(
+            predicate,
+            operand,
+            &float_ty.const_float(lower_bound),
+            "",
+        )?;
+        let is_too_large =
+            self.builder
+                .build_float_cmp(LLVMRealOGE, operand, &float_ty.const_float(max), "")?;
+        let is_out_of_range = self.builder.build_or(&is_too_small, &is_too_large, "")?;
+
+        self.generate_trap_if(&is_out_of_range, TrapCode::IntegerOverflow, None)
+    }
+
+    /// Compares integer operands of type `ty`, pushing the result as a boolean.
+    fn generate_int_cmp(&mut self, ty: LLNumType, predicate: LLVMIntPredicate) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        let cmp = self
+            .builder
+            .build_int_cmp(predicate, lhs.as_ref(), rhs.as_ref(), "")?;
+
+        self.push(cmp);
+
+        Ok(())
+    }
+
+    /// Compares float operands of type `ty`, pushing the result as a boolean.
+    fn generate_float_cmp(&mut self, ty: LLNumType, predicate: LLVMRealPredicate) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        let cmp = self
+            .builder
+            .build_float_cmp(predicate, lhs.as_ref(), rhs.as_ref(), "")?;
+
+        self.push(cmp);
+
+        Ok(())
+    }
+
+    /// Compares an integer operand of type `ty` against a zero of the same type.
+    fn generate_int_eqz(&mut self, ty: LLNumType) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&ty) {
+            return Err(CompilerError::TypeMismatch("eqz operand".to_string()).into());
+        }
+
+        let cmp = self.builder.build_int_cmp(
+            LLVMIntPredicate::LLVMIntEQ,
+            operand.as_ref(),
+            &ty.zero(),
+            "",
+        )?;
+
+        self.push(cmp);
+
+        Ok(())
+    }
+
+    /// Takes the minimum, or the maximum if `is_max` is set, of float operands of type `ty`.
+    ///
+    /// Wasm propagates NaN and orders -0.0 below +0.0, like `llvm.minimum` and `llvm.maximum`, whereas
+    /// `llvm.minnum` and `llvm.maxnum` return the operand that is not NaN and may return either zero.
+    /// Where the former are not lowered this falls back to comparisons, with `int_ty` the integer type as wide as `ty`.
+    fn generate_float_min_max(
+        &mut self,
+        ty: LLNumType,
+        int_ty: LLNumType,
+        is_max: bool,
+    ) -> Result<()> {
+        use LLVMRealPredicate::*;
+
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        let name = if is_max {
+            "llvm.maximum"
+        } else {
+            "llvm.minimum"
+        };
+
+        if let Some(intrinsic) = self.llvm_module.add_or_get_intrinsic_function(name, &[ty]) {
+            let value =
+                self.builder
+                    .build_call_intrinsic(&intrinsic, &[lhs.as_ref(), rhs.as_ref()], "")?;
+
+            self.push(value);
+            return Ok(());
+        }
+
+        let (lhs, rhs) = (lhs.as_ref(), rhs.as_ref());
+        let is_nan = self.builder.build_float_cmp(LLVMRealUNO, lhs, rhs, "")?;
+        let nan = self.builder.build_float_add(lhs, rhs, "")?;
+
+        // Zeros of different signs are equal. Or-ing their bits gives -0.0 for the minimum,
+        // and and-ing them gives +0.0 for the maximum, while other equal operands are unchanged.
+        let is_equal = self.builder.build_float_cmp(LLVMRealOEQ, lhs, rhs, "")?;
+        let lhs_bits = self.builder.build_bitcast(lhs, &int_ty, "")?;
+        let rhs_bits = self.builder.build_bitcast(rhs, &int_ty, "")?;
+        let merged = if is_max {
+            let bits = self.builder.build_and(&lhs_bits, &rhs_bits, "")?;
+            self.builder.build_bitcast(&bits, &ty, "")?
+        } else {
+            let bits = self.builder.build_or(&lhs_bits, &rhs_bits, "")?;
+            self.builder.build_bitcast(&bits, &ty, "")?
+        };
+
+        let predicate = if is_max { LLVMRealOGT } else { LLVMRealOLT };
+        let is_lhs = self.builder.build_float_cmp(predicate, lhs, rhs, "")?;
+        let value = self.builder.build_select(&is_lhs, lhs, rhs, "")?;
+        let value = self.builder.build_select(&is_equal, &merged, &value, "")?;
+        let value = self.builder.build_select(&is_nan, &nan, &value, "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Flips the sign of a float operand of type `ty`, which NaNs are no exception to.
+    fn generate_float_neg(&mut self, ty: LLNumType) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&ty) {
+            return Err(CompilerError::TypeMismatch("negated operand".to_string()).into());
+        }
+
+        let value = self.builder.build_float_neg(operand.as_ref(), "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Combines the magnitude of the first float operand of type `ty` with the sign of the second.
+    fn generate_float_copysign(&mut self, ty: LLNumType) -> Result<()> {
+        let (lhs, rhs) = self.pop_operands(&ty)?;
+        let intrinsic = self.get_intrinsic("llvm.copysign", &[ty])?;
+        let value =
+            self.builder
+                .build_call_intrinsic(&intrinsic, &[lhs.as_ref(), rhs.as_ref()], "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Rounds a float operand of type `ty` to the nearest integer, with ties to even.
+    ///
+    /// `llvm.roundeven` is not lowered by every target so this falls back to adding and subtracting `threshold`,
+    /// the smallest power of two from which on floats of `ty` have no fractional part.
+    /// The addition rounds to the nearest integer with ties to even in the default rounding mode.
+    fn generate_float_nearest(&mut self, ty: LLNumType, threshold: f64) -> Result<()> {
+        let operand = self.pop()?;
+        if !operand.has_type(&ty) {
+            return Err(CompilerError::TypeMismatch("nearest operand".to_string()).into());
+        }
+
+        if let Some(roundeven) = self
+            .llvm_module
+            .add_or_get_intrinsic_function("llvm.roundeven", &[ty])
+        {
+            let value = self
+                .builder
+                .build_call_intrinsic(&roundeven, &[operand.as_ref()], "")?;
+
+            self.push(value);
+            return Ok(());
+        }
+
+        let fabs = self.get_intrinsic("llvm.fabs", &[ty])?;
+        let copysign = self.get_intrinsic("llvm.copysign", &[ty])?;
+        let threshold = ty.const_float(threshold);
+
+        let magnitude = self
+            .builder
+            .build_call_intrinsic(&fabs, &[operand.as_ref()], "")?;
+        let sum = self.builder.build_float_add(&magnitude, &threshold, "")?;
+        let rounded = self.builder.build_float_sub(&sum, &threshold, "")?;
+        let rounded =
+            self.builder
+                .build_call_intrinsic(&copysign, &[&rounded, operand.as_ref()], "")?;
+
+        // Large floats, infinities and NaNs are returned as they are.
+        let has_fraction = self.builder.build_float_cmp(
+            LLVMRealPredicate::LLVMRealOLT,
+            &magnitude,
+            &threshold,
+            "",
+        )?;
+        let value = self
+            .builder
+            .build_select(&has_fraction, &rounded, operand.as_ref(), "")?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Gets an intrinsic that every target is expected to support.
+    fn get_intrinsic(&self, name: &str, types: &[LLNumType]) -> Result<LLIntrinsic> {
+        self.llvm_module
+            .add_or_get_intrinsic_function(name, types)
+            .ok_or_else(|| CompilerError::UnsupportedIntrinsic(name.to_string()).into())
+    }
+}
@@ -0,0 +1,100 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::{
+    compiler::{
+        llvm::{
+            basic_block::LLBasicBlock, builder::LLBuilder, function::LLFunction,
+            module::trampoline_symbol, types::LLResultType, LLVM,
+        },
+        utils::convert,
+    },
+    types::FuncType,
+};
+
+/// Generates the trampoline through which the host calls an exported function without knowing its type statically.
+///
+/// The trampoline takes a pointer to the arguments and a pointer to where the results go. Both are arrays of 8-byte
+/// slots, each holding an `i32`, `i64`, `f32` or `f64` at its start, in the order of the params and results of the function.
+pub(crate) struct TrampolineGenerator<'a> {
+    llvm: &'a mut LLVM,
+    function_index: u32,
+    function: Rc<LLFunction>,
+    ty: &'a FuncType,
+}
+
+impl<'a> TrampolineGenerator<'a> {
+    pub(crate) fn new(
+        llvm: &'a mut LLVM,
+        function_index: u32,
+        function: Rc<LLFunction>,
+        ty: &'a FuncType,
+    ) -> Self {
+        Self {
+            llvm,
+            function_index,
+            function,
+            ty,
+        }
+    }
+
+    /// Generates the trampoline, named after the function it calls.
+    pub(crate) fn generate(self) -> Result<()> {
+        let context = &self.llvm.context;
+        let i64_type = context.i64_type();
+        let slots_type = i64_type.pointer_type();
+
+        let module = self.llvm.module.as_mut().unwrap();
+        let trampoline = LLFunction::new(
+            &trampoline_symbol(self.function_index),
+            module,
+            Rc::new(context.function_type(
+                &[slots_type, slots_type],
+                &LLResultType::Void(context.void_type()),
+                false,
+            )),
+        )?;
+
+        let builder = LLBuilder::new(context);
+        let entry = LLBasicBlock::new("entry", context)?;
+        trampoline.append_basic_block(&entry);
+        builder.position_at_end(&entry);
+
+        let arg_slots = trampoline.get_param(0);
+        let mut args = vec![];
+        for (index, ty) in self.ty.params.iter().enumerate() {
+            let ty = convert::to_llvm_valtype(context, ty);
+            let slot =
+                builder.build_gep(&i64_type, &arg_slots, &i64_type.const_int(index as u64), "")?;
+            let slot = builder.build_bitcast(&slot, &ty.pointer_type(), "")?;
+            args.push(builder.build_load(&ty, &slot, "")?);
+        }
+
+        let args = args.iter().map(|arg| arg as _).collect::<Vec<_>>();
+        let call = builder.build_call(&self.function, &args, "")?;
+
+        // A function with several results returns them as the fields of a struct.
+        let result_slots = trampoline.get_param(1);
+        for (index, ty) in self.ty.results.iter().enumerate() {
+            let ty = convert::to_llvm_valtype(context, ty);
+            let slot = builder.build_gep(
+                &i64_type,
+                &result_slots,
+                &i64_type.const_int(index as u64),
+                "",
+            )?;
+            let slot = builder.build_bitcast(&slot, &ty.pointer_type(), "")?;
+            if self.ty.results.len() == 1 {
+                builder.build_store(&call, &slot);
+            } else {
+                let result = builder.build_extract_value(&call, index as u32, "")?;
+                builder.build_store(&result, &slot);
+            }
+        }
+
+        builder.build_ret_void();
+
+        Ok(())
+    }
+}
@@ -23,4 +23,12 @@ impl Import {
             index,
         }
     }
+
+    /// Gets the name of the symbol the import is linked against, `module.field`.
+    pub fn symbol_name(&self) -> String {
+        match &self.field {
+            Some(field) => format!("{}.{}", self.module, field),
+            None => self.module.clone(),
+        }
+    }
 }
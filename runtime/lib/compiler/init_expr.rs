@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// A constant expression computing the offset of an active segment.
+///
+/// https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum InitExpr {
+    I32Const(i32),
+    /// The offset of a segment of a 64-bit memory.
+    I64Const(i64),
+    /// The value of an imported global.
+    GlobalGet(u32),
+}
+
+impl InitExpr {
+    /// Gets the offset a constant expression evaluates to, or `None` if it is only known at instantiation.
+    ///
+    /// Offsets are unsigned, so `i32` ones are zero-extended.
+    pub fn const_offset(&self) -> Option<u64> {
+        match *self {
+            InitExpr::I32Const(value) => Some(value as u32 as u64),
+            InitExpr::I64Const(value) => Some(value as u64),
+            InitExpr::GlobalGet(_) => None,
+        }
+    }
+}
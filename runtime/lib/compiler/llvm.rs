@@ -1,8 +1,16 @@
+pub(crate) mod archive;
 pub(crate) mod basic_block;
+pub(crate) mod builder;
 pub(crate) mod context;
+pub(crate) mod di_builder;
+pub(crate) mod exec_buffer;
 pub(crate) mod function;
+pub(crate) mod intrinsic;
 pub(crate) mod llvm;
 pub(crate) mod module;
+pub(crate) mod orc;
+pub(crate) mod target_machine;
 pub(crate) mod types;
+pub(crate) mod values;
 
 pub(crate) use llvm::*;
@@ -0,0 +1,66 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+/// Writes a static archive in the GNU `ar` format holding a single object file.
+///
+/// The archive starts with a symbol table so that linkers can find the `symbols` the object defines without an index of their own.
+/// Member names must be at most 15 bytes long since there is no table of long names.
+///
+/// - https://www.freebsd.org/cgi/man.cgi?query=ar&sektion=5
+pub(crate) fn write_archive<W: Write>(
+    writer: &mut W,
+    object_name: &str,
+    object: &[u8],
+    symbols: &[String],
+) -> Result<()> {
+    // The symbol table is a count followed by the offset of the member defining each symbol and then their names,
+    // with numbers in big-endian.
+    let names_size = symbols.iter().map(|name| name.len() + 1).sum::<usize>();
+    let symbol_table_size = 4 + 4 * symbols.len() + names_size;
+    let object_offset = 8 + 60 + padded(symbol_table_size);
+
+    let mut symbol_table = Vec::with_capacity(symbol_table_size);
+    symbol_table.extend_from_slice(&(symbols.len() as u32).to_be_bytes());
+    for _ in symbols {
+        symbol_table.extend_from_slice(&(object_offset as u32).to_be_bytes());
+    }
+
+    for name in symbols {
+        symbol_table.extend_from_slice(name.as_bytes());
+        symbol_table.push(0);
+    }
+
+    writer.write_all(b"!<arch>\n")?;
+    write_member(writer, "/", &symbol_table)?;
+    write_member(writer, &format!("{}/", object_name), object)?;
+
+    Ok(())
+}
+
+/// Writes a member header followed by the member, padded to an even size.
+fn write_member<W: Write>(writer: &mut W, name: &str, content: &[u8]) -> Result<()> {
+    // Name, modification time, owner, group, mode and size, all left-aligned and padded with spaces.
+    writeln!(
+        writer,
+        "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`",
+        name,
+        0,
+        0,
+        0,
+        644,
+        content.len()
+    )?;
+
+    writer.write_all(content)?;
+    if padded(content.len()) != content.len() {
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+/// Rounds the size of a member up to the even size it takes in the archive.
+fn padded(size: usize) -> usize {
+    (size + 1) & !1
+}
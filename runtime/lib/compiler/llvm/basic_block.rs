@@ -1,5 +1,83 @@
-use llvm_sys::prelude::LLVMBasicBlockRef;
+use std::ffi::{CStr, CString};
 
-pub(crate) struct BasicBlock {
+use anyhow::Result;
+use llvm_sys::{
+    core::{
+        LLVMCreateBasicBlockInContext, LLVMGetBasicBlockName, LLVMGetBasicBlockTerminator,
+        LLVMGetFirstInstruction, LLVMGetNextInstruction, LLVMGetNumSuccessors, LLVMGetSuccessor,
+    },
+    prelude::LLVMBasicBlockRef,
+};
+
+use super::context::LLContext;
+
+/// A wrapper for LLVM Basic Block.
+///
+/// # Safety
+/// A basic block created here is not attached to any function.
+/// It gets owned and freed by the function it is appended to, so it must be appended eventually.
+///
+/// - https://llvm.org/doxygen/BasicBlock_8h_source.html#l00099
+#[derive(Debug)]
+pub(crate) struct LLBasicBlock {
     basic_block_ref: LLVMBasicBlockRef,
 }
+
+impl LLBasicBlock {
+    /// Creates a basic block that is not yet attached to a function.
+    ///
+    /// # Safety
+    /// A temporary `CString` name is safe to use here because it is copied into the LLVM Basic Block.
+    pub(crate) fn new(name: &str, context: &LLContext) -> Result<Self> {
+        Ok(Self {
+            basic_block_ref: unsafe {
+                LLVMCreateBasicBlockInContext(context.as_ptr(), CString::new(name)?.as_ptr())
+            },
+        })
+    }
+
+    /// Wraps a basic block that already exists, such as the one a builder is positioned at.
+    pub(super) unsafe fn from_ptr(basic_block_ref: LLVMBasicBlockRef) -> Self {
+        Self { basic_block_ref }
+    }
+
+    /// Checks if the basic block ends with a terminator instruction.
+    pub(crate) fn has_terminator(&self) -> bool {
+        unsafe { !LLVMGetBasicBlockTerminator(self.basic_block_ref).is_null() }
+    }
+
+    /// Counts the instructions in the basic block, including its terminator.
+    pub(crate) fn instruction_count(&self) -> usize {
+        let mut count = 0;
+        let mut instruction = unsafe { LLVMGetFirstInstruction(self.basic_block_ref) };
+        while !instruction.is_null() {
+            count += 1;
+            instruction = unsafe { LLVMGetNextInstruction(instruction) };
+        }
+
+        count
+    }
+
+    /// Gets the name of the basic block.
+    pub(crate) fn name(&self) -> String {
+        unsafe { CStr::from_ptr(LLVMGetBasicBlockName(self.basic_block_ref)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Gets the basic blocks the terminator can branch to, which there are none of without a terminator.
+    pub(crate) fn successors(&self) -> Vec<LLBasicBlock> {
+        let terminator = unsafe { LLVMGetBasicBlockTerminator(self.basic_block_ref) };
+        if terminator.is_null() {
+            return vec![];
+        }
+
+        (0..unsafe { LLVMGetNumSuccessors(terminator) })
+            .map(|index| unsafe { Self::from_ptr(LLVMGetSuccessor(terminator, index)) })
+            .collect()
+    }
+
+    pub(crate) unsafe fn as_ptr(&self) -> LLVMBasicBlockRef {
+        self.basic_block_ref
+    }
+}
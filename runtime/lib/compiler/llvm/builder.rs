@@ -0,0 +1,885 @@
+use std::ffi::CString;
+
+use anyhow::Result;
+use llvm_sys::{
+    core::{
+        LLVMBuildAShr, LLVMBuildAdd, LLVMBuildAddrSpaceCast, LLVMBuildAggregateRet,
+        LLVMBuildAlloca, LLVMBuildAnd, LLVMBuildArrayAlloca, LLVMBuildBitCast, LLVMBuildBr,
+        LLVMBuildCall2, LLVMBuildCondBr, LLVMBuildExtractValue, LLVMBuildFAdd, LLVMBuildFCmp,
+        LLVMBuildFDiv, LLVMBuildFMul, LLVMBuildFNeg, LLVMBuildFPToSI, LLVMBuildFPToUI,
+        LLVMBuildFSub, LLVMBuildGEP2, LLVMBuildICmp, LLVMBuildInsertValue, LLVMBuildIsNull,
+        LLVMBuildLShr, LLVMBuildLoad2, LLVMBuildMul, LLVMBuildNeg, LLVMBuildNot, LLVMBuildOr,
+        LLVMBuildPhi, LLVMBuildRet, LLVMBuildRetVoid, LLVMBuildSDiv, LLVMBuildSExt,
+        LLVMBuildSIToFP, LLVMBuildSRem, LLVMBuildSelect, LLVMBuildShl, LLVMBuildStore,
+        LLVMBuildSub, LLVMBuildSwitch, LLVMBuildTrunc, LLVMBuildUDiv, LLVMBuildUIToFP,
+        LLVMBuildURem, LLVMBuildUnreachable, LLVMBuildXor, LLVMBuildZExt,
+        LLVMCreateBuilderInContext, LLVMDisposeBuilder, LLVMGetFirstInstruction,
+        LLVMGetInsertBlock, LLVMPositionBuilderAtEnd, LLVMPositionBuilderBefore,
+        LLVMSetCurrentDebugLocation2,
+    },
+    prelude::{LLVMBuilderRef, LLVMValueRef},
+    LLVMIntPredicate, LLVMRealPredicate,
+};
+
+use super::{
+    basic_block::LLBasicBlock,
+    context::LLContext,
+    di_builder::LLDILocation,
+    function::{LLFunction, LLHostFunction},
+    intrinsic::LLIntrinsic,
+    types::{LLFunctionType, LLNumType},
+    values::{
+        LLAShr, LLAddrSpaceCast, LLAlloca, LLAnd, LLBitCast, LLCall, LLExtractValue, LLFPToSI,
+        LLFPToUI, LLFloatAdd, LLFloatCmp, LLFloatDiv, LLFloatMul, LLFloatNeg, LLFloatSub, LLGep,
+        LLInsertValue, LLIntAdd, LLIntCmp, LLIntMul, LLIntNeg, LLIntNot, LLIntSExt, LLIntSub,
+        LLIntTrunc, LLIntZExt, LLLShr, LLLoad, LLOr, LLPhi, LLSDiv, LLSIToFP, LLSRem, LLSelect,
+        LLShl, LLStore, LLSwitch, LLUDiv, LLUIToFP, LLURem, LLValue, LLXor,
+    },
+};
+
+/// A wrapper for LLVM IR Builder.
+///
+/// # Safety
+/// The builder is created in and must not outlive its `LLContext`.
+/// Instructions it builds are owned by the basic block it is positioned at.
+///
+/// Names passed to the builder are copied into the created values so temporary `CString`s are safe to use.
+///
+/// - https://llvm.org/doxygen/IRBuilder_8h_source.html
+#[derive(Debug)]
+pub(crate) struct LLBuilder {
+    builder_ref: LLVMBuilderRef,
+}
+
+impl LLBuilder {
+    pub(crate) fn new(context: &LLContext) -> Self {
+        Self {
+            builder_ref: unsafe { LLVMCreateBuilderInContext(context.as_ptr()) },
+        }
+    }
+
+    /// Moves the builder to the end of the basic block.
+    pub(crate) fn position_at_end(&self, basic_block: &LLBasicBlock) {
+        unsafe { LLVMPositionBuilderAtEnd(self.builder_ref, basic_block.as_ptr()) }
+    }
+
+    /// Positions the builder before the first instruction of the basic block, or at its end if it is empty.
+    pub(crate) fn position_at_start(&self, basic_block: &LLBasicBlock) {
+        unsafe {
+            let instruction = LLVMGetFirstInstruction(basic_block.as_ptr());
+            if instruction.is_null() {
+                LLVMPositionBuilderAtEnd(self.builder_ref, basic_block.as_ptr())
+            } else {
+                LLVMPositionBuilderBefore(self.builder_ref, instruction)
+            }
+        }
+    }
+
+    /// Attaches `location` to the instructions built from now on.
+    pub(crate) fn set_current_debug_location(&self, location: LLDILocation) {
+        unsafe { LLVMSetCurrentDebugLocation2(self.builder_ref, location.as_ptr()) }
+    }
+
+    /// Gets the basic block the builder is positioned at.
+    pub(crate) fn get_insert_block(&self) -> LLBasicBlock {
+        unsafe { LLBasicBlock::from_ptr(LLVMGetInsertBlock(self.builder_ref)) }
+    }
+
+    pub(crate) fn build_alloca(&self, ty: &LLNumType, name: &str) -> Result<LLAlloca> {
+        Ok(LLAlloca(unsafe {
+            LLVMBuildAlloca(self.builder_ref, ty.as_ptr(), CString::new(name)?.as_ptr())
+        }))
+    }
+
+    /// Allocates `count` consecutive values of type `ty` on the stack, returning a pointer to the first.
+    pub(crate) fn build_array_alloca(
+        &self,
+        ty: &LLNumType,
+        count: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLAlloca> {
+        Ok(LLAlloca(unsafe {
+            LLVMBuildArrayAlloca(
+                self.builder_ref,
+                ty.as_ptr(),
+                count.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_load(
+        &self,
+        ty: &LLNumType,
+        pointer: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLLoad> {
+        Ok(LLLoad(unsafe {
+            LLVMBuildLoad2(
+                self.builder_ref,
+                ty.as_ptr(),
+                pointer.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_store(&self, value: &dyn LLValue, pointer: &dyn LLValue) -> LLStore {
+        LLStore(unsafe { LLVMBuildStore(self.builder_ref, value.as_ptr(), pointer.as_ptr()) })
+    }
+
+    pub(crate) fn build_int_cmp(
+        &self,
+        predicate: LLVMIntPredicate,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLIntCmp> {
+        Ok(LLIntCmp(unsafe {
+            LLVMBuildICmp(
+                self.builder_ref,
+                predicate,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_float_cmp(
+        &self,
+        predicate: LLVMRealPredicate,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLFloatCmp> {
+        Ok(LLFloatCmp(unsafe {
+            LLVMBuildFCmp(
+                self.builder_ref,
+                predicate,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Checks if a value is null, producing an `i1`.
+    pub(crate) fn build_is_null(&self, value: &dyn LLValue, name: &str) -> Result<LLIntCmp> {
+        Ok(LLIntCmp(unsafe {
+            LLVMBuildIsNull(
+                self.builder_ref,
+                value.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_int_zext(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLIntZExt> {
+        Ok(LLIntZExt(unsafe {
+            LLVMBuildZExt(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_int_sext(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLIntSExt> {
+        Ok(LLIntSExt(unsafe {
+            LLVMBuildSExt(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_int_trunc(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLIntTrunc> {
+        Ok(LLIntTrunc(unsafe {
+            LLVMBuildTrunc(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_fp_to_si(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLFPToSI> {
+        Ok(LLFPToSI(unsafe {
+            LLVMBuildFPToSI(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_fp_to_ui(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLFPToUI> {
+        Ok(LLFPToUI(unsafe {
+            LLVMBuildFPToUI(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_si_to_fp(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLSIToFP> {
+        Ok(LLSIToFP(unsafe {
+            LLVMBuildSIToFP(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_ui_to_fp(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLUIToFP> {
+        Ok(LLUIToFP(unsafe {
+            LLVMBuildUIToFP(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_int_add(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLIntAdd> {
+        Ok(LLIntAdd(unsafe {
+            LLVMBuildAdd(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_int_sub(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLIntSub> {
+        Ok(LLIntSub(unsafe {
+            LLVMBuildSub(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_int_mul(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLIntMul> {
+        Ok(LLIntMul(unsafe {
+            LLVMBuildMul(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_sdiv(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLSDiv> {
+        Ok(LLSDiv(unsafe {
+            LLVMBuildSDiv(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_udiv(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLUDiv> {
+        Ok(LLUDiv(unsafe {
+            LLVMBuildUDiv(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_srem(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLSRem> {
+        Ok(LLSRem(unsafe {
+            LLVMBuildSRem(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_urem(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLURem> {
+        Ok(LLURem(unsafe {
+            LLVMBuildURem(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_and(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLAnd> {
+        Ok(LLAnd(unsafe {
+            LLVMBuildAnd(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_or(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLOr> {
+        Ok(LLOr(unsafe {
+            LLVMBuildOr(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_xor(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLXor> {
+        Ok(LLXor(unsafe {
+            LLVMBuildXor(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_shl(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLShl> {
+        Ok(LLShl(unsafe {
+            LLVMBuildShl(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_ashr(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLAShr> {
+        Ok(LLAShr(unsafe {
+            LLVMBuildAShr(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_lshr(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLLShr> {
+        Ok(LLLShr(unsafe {
+            LLVMBuildLShr(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_float_add(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLFloatAdd> {
+        Ok(LLFloatAdd(unsafe {
+            LLVMBuildFAdd(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_float_sub(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLFloatSub> {
+        Ok(LLFloatSub(unsafe {
+            LLVMBuildFSub(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_float_mul(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLFloatMul> {
+        Ok(LLFloatMul(unsafe {
+            LLVMBuildFMul(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_float_div(
+        &self,
+        lhs: &dyn LLValue,
+        rhs: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLFloatDiv> {
+        Ok(LLFloatDiv(unsafe {
+            LLVMBuildFDiv(
+                self.builder_ref,
+                lhs.as_ptr(),
+                rhs.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_float_neg(&self, value: &dyn LLValue, name: &str) -> Result<LLFloatNeg> {
+        Ok(LLFloatNeg(unsafe {
+            LLVMBuildFNeg(
+                self.builder_ref,
+                value.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Negates an integer, which LLVM builds as a subtraction from zero.
+    pub(crate) fn build_int_neg(&self, value: &dyn LLValue, name: &str) -> Result<LLIntNeg> {
+        Ok(LLIntNeg(unsafe {
+            LLVMBuildNeg(
+                self.builder_ref,
+                value.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Complements the bits of an integer, which LLVM builds as an `xor` with all ones.
+    pub(crate) fn build_int_not(&self, value: &dyn LLValue, name: &str) -> Result<LLIntNot> {
+        Ok(LLIntNot(unsafe {
+            LLVMBuildNot(
+                self.builder_ref,
+                value.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Gets a pointer `index` elements of type `ty` past `pointer`.
+    pub(crate) fn build_gep(
+        &self,
+        ty: &LLNumType,
+        pointer: &dyn LLValue,
+        index: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLGep> {
+        let mut indices = [unsafe { index.as_ptr() }];
+        Ok(LLGep(unsafe {
+            LLVMBuildGEP2(
+                self.builder_ref,
+                ty.as_ptr(),
+                pointer.as_ptr(),
+                indices.as_mut_ptr(),
+                indices.len() as u32,
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_bitcast(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLBitCast> {
+        Ok(LLBitCast(unsafe {
+            LLVMBuildBitCast(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Casts a pointer to a pointer in another address space, like a funcref to a plain pointer.
+    pub(crate) fn build_addrspace_cast(
+        &self,
+        value: &dyn LLValue,
+        ty: &LLNumType,
+        name: &str,
+    ) -> Result<LLAddrSpaceCast> {
+        Ok(LLAddrSpaceCast(unsafe {
+            LLVMBuildAddrSpaceCast(
+                self.builder_ref,
+                value.as_ptr(),
+                ty.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Gets the field at `index` of a struct, like one of the results of a multi-value call.
+    pub(crate) fn build_extract_value(
+        &self,
+        aggregate: &dyn LLValue,
+        index: u32,
+        name: &str,
+    ) -> Result<LLExtractValue> {
+        Ok(LLExtractValue(unsafe {
+            LLVMBuildExtractValue(
+                self.builder_ref,
+                aggregate.as_ptr(),
+                index,
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Sets the field at `index` of a struct to `value`, giving the updated struct.
+    pub(crate) fn build_insert_value(
+        &self,
+        aggregate: &dyn LLValue,
+        value: &dyn LLValue,
+        index: u32,
+        name: &str,
+    ) -> Result<LLInsertValue> {
+        Ok(LLInsertValue(unsafe {
+            LLVMBuildInsertValue(
+                self.builder_ref,
+                aggregate.as_ptr(),
+                value.as_ptr(),
+                index,
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Picks `then` if `condition` is true, `otherwise` if not.
+    pub(crate) fn build_select(
+        &self,
+        condition: &dyn LLValue,
+        then: &dyn LLValue,
+        otherwise: &dyn LLValue,
+        name: &str,
+    ) -> Result<LLSelect> {
+        Ok(LLSelect(unsafe {
+            LLVMBuildSelect(
+                self.builder_ref,
+                condition.as_ptr(),
+                then.as_ptr(),
+                otherwise.as_ptr(),
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_call_intrinsic(
+        &self,
+        intrinsic: &LLIntrinsic,
+        args: &[&dyn LLValue],
+        name: &str,
+    ) -> Result<LLCall> {
+        let mut args = args
+            .iter()
+            .map(|v| unsafe { v.as_ptr() })
+            .collect::<Vec<LLVMValueRef>>();
+
+        Ok(LLCall(unsafe {
+            LLVMBuildCall2(
+                self.builder_ref,
+                intrinsic.function_type_ref,
+                intrinsic.as_ptr(),
+                args.as_mut_ptr(),
+                args.len() as u32,
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Calls a function of the module.
+    pub(crate) fn build_call(
+        &self,
+        function: &LLFunction,
+        args: &[&dyn LLValue],
+        name: &str,
+    ) -> Result<LLCall> {
+        let mut args = args
+            .iter()
+            .map(|v| unsafe { v.as_ptr() })
+            .collect::<Vec<LLVMValueRef>>();
+
+        Ok(LLCall(unsafe {
+            LLVMBuildCall2(
+                self.builder_ref,
+                function.function_type().as_ptr(),
+                function.as_ptr(),
+                args.as_mut_ptr(),
+                args.len() as u32,
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Calls the function of type `function_type` that `pointer` points to.
+    pub(crate) fn build_call_pointer(
+        &self,
+        function_type: &LLFunctionType,
+        pointer: &dyn LLValue,
+        args: &[&dyn LLValue],
+        name: &str,
+    ) -> Result<LLCall> {
+        let mut args = args
+            .iter()
+            .map(|v| unsafe { v.as_ptr() })
+            .collect::<Vec<LLVMValueRef>>();
+
+        Ok(LLCall(unsafe {
+            LLVMBuildCall2(
+                self.builder_ref,
+                function_type.as_ptr(),
+                pointer.as_ptr(),
+                args.as_mut_ptr(),
+                args.len() as u32,
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    /// Calls a function the host provides.
+    pub(crate) fn build_call_host_function(
+        &self,
+        function: &LLHostFunction,
+        args: &[&dyn LLValue],
+        name: &str,
+    ) -> Result<LLCall> {
+        let mut args = args
+            .iter()
+            .map(|v| unsafe { v.as_ptr() })
+            .collect::<Vec<LLVMValueRef>>();
+
+        Ok(LLCall(unsafe {
+            LLVMBuildCall2(
+                self.builder_ref,
+                function.function_type_ref,
+                function.function_ref,
+                args.as_mut_ptr(),
+                args.len() as u32,
+                CString::new(name)?.as_ptr(),
+            )
+        }))
+    }
+
+    pub(crate) fn build_br(&self, destination: &LLBasicBlock) {
+        unsafe {
+            LLVMBuildBr(self.builder_ref, destination.as_ptr());
+        }
+    }
+
+    /// Builds a phi of type `ty` with no incoming values, which have to be added for every predecessor of its block.
+    ///
+    /// Phis must come before the other instructions of their block.
+    pub(crate) fn build_phi(&self, ty: &LLNumType, name: &str) -> Result<LLPhi> {
+        Ok(LLPhi(unsafe {
+            LLVMBuildPhi(self.builder_ref, ty.as_ptr(), CString::new(name)?.as_ptr())
+        }))
+    }
+
+    /// Jumps to the destination of the case matching `condition`, or to `default` if none does.
+    ///
+    /// `case_count` is only a hint for allocating the cases, which are added to the returned switch.
+    pub(crate) fn build_switch(
+        &self,
+        condition: &dyn LLValue,
+        default: &LLBasicBlock,
+        case_count: u32,
+    ) -> LLSwitch {
+        LLSwitch(unsafe {
+            LLVMBuildSwitch(
+                self.builder_ref,
+                condition.as_ptr(),
+                default.as_ptr(),
+                case_count,
+            )
+        })
+    }
+
+    /// Branches to `then` if `condition` is true, to `otherwise` if not.
+    pub(crate) fn build_cond_br(
+        &self,
+        condition: &dyn LLValue,
+        then: &LLBasicBlock,
+        otherwise: &LLBasicBlock,
+    ) {
+        unsafe {
+            LLVMBuildCondBr(
+                self.builder_ref,
+                condition.as_ptr(),
+                then.as_ptr(),
+                otherwise.as_ptr(),
+            );
+        }
+    }
+
+    pub(crate) fn build_ret(&self, value: &dyn LLValue) {
+        unsafe {
+            LLVMBuildRet(self.builder_ref, value.as_ptr());
+        }
+    }
+
+    pub(crate) fn build_ret_void(&self) {
+        unsafe {
+            LLVMBuildRetVoid(self.builder_ref);
+        }
+    }
+
+    /// Returns multiple values as a single struct.
+    pub(crate) fn build_aggregate_ret(&self, values: &[&dyn LLValue]) {
+        let mut values = values
+            .iter()
+            .map(|v| unsafe { v.as_ptr() })
+            .collect::<Vec<LLVMValueRef>>();
+
+        unsafe {
+            LLVMBuildAggregateRet(self.builder_ref, values.as_mut_ptr(), values.len() as u32);
+        }
+    }
+
+    pub(crate) fn build_unreachable(&self) {
+        unsafe {
+            LLVMBuildUnreachable(self.builder_ref);
+        }
+    }
+}
+
+impl Drop for LLBuilder {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeBuilder(self.builder_ref) }
+    }
+}
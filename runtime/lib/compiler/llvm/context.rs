@@ -1,12 +1,18 @@
 use anyhow::Result;
 use llvm_sys::{
-    core::{LLVMContextCreate, LLVMContextDispose},
+    core::{LLVMConstArray, LLVMConstStringInContext, LLVMContextCreate, LLVMContextDispose},
     prelude::LLVMContextRef,
+    target::{LLVMDisposeTargetData, LLVMIntPtrTypeInContext},
 };
 
 use super::{
     module::LLModule,
-    types::{LLFunctionType, LLNumType, LLNumTypeKind, LLResultType, LLStructType, LLVoidType},
+    target_machine::LLTargetMachine,
+    types::{
+        LLArrayType, LLFunctionType, LLNumType, LLNumTypeKind, LLResultType, LLStructType,
+        LLVectorType, LLVoidType,
+    },
+    values::{LLConstArray, LLValue},
 };
 
 /// This a wrapper for LLVM Context.
@@ -33,6 +39,18 @@ impl LLContext {
         self.context_ref
     }
 
+    pub(crate) fn i1_type(&self) -> LLNumType {
+        LLNumType::new(self, LLNumTypeKind::I1)
+    }
+
+    pub(crate) fn i8_type(&self) -> LLNumType {
+        LLNumType::new(self, LLNumTypeKind::I8)
+    }
+
+    pub(crate) fn i16_type(&self) -> LLNumType {
+        LLNumType::new(self, LLNumTypeKind::I16)
+    }
+
     pub(crate) fn i32_type(&self) -> LLNumType {
         LLNumType::new(self, LLNumTypeKind::I32)
     }
@@ -53,12 +71,77 @@ impl LLContext {
         LLNumType::new(self, LLNumTypeKind::F64)
     }
 
+    /// Gets the integer type as wide as pointers on `target`, like `i32` on 32-bit targets.
+    ///
+    /// Reference types are pointers, which LLVM already sizes for the target. This is for integers holding addresses.
+    pub(crate) fn ptr_sized_int_type(&self, target: &LLTargetMachine) -> LLNumType {
+        unsafe {
+            let data_layout = target.create_data_layout();
+            let ty = LLVMIntPtrTypeInContext(self.context_ref, data_layout);
+            LLVMDisposeTargetData(data_layout);
+
+            LLNumType::from_ptr(ty)
+        }
+    }
+
+    pub(crate) fn funcref_type(&self) -> LLNumType {
+        LLNumType::new(self, LLNumTypeKind::FuncRef)
+    }
+
+    pub(crate) fn externref_type(&self) -> LLNumType {
+        LLNumType::new(self, LLNumTypeKind::ExternRef)
+    }
+
     pub(crate) fn void_type(&self) -> LLVoidType {
         LLVoidType::new(self)
     }
 
     pub(crate) fn struct_type(&self, types: &[LLNumType], is_packed: bool) -> LLStructType {
-        LLStructType::new(types, is_packed)
+        LLStructType::new(self, types, is_packed)
+    }
+
+    pub(crate) fn named_struct_type(&self, name: &str) -> Result<LLStructType> {
+        LLStructType::new_named(self, name)
+    }
+
+    pub(crate) fn vector_type(&self, element: &LLNumType, count: u32) -> LLVectorType {
+        LLVectorType::new(element, count)
+    }
+
+    pub(crate) fn array_type(&self, element: &LLNumType, count: u32) -> LLArrayType {
+        LLArrayType::new(element, count)
+    }
+
+    /// Creates a constant array of `elements`, which must all be of type `element_ty`.
+    pub(crate) fn const_array(
+        &self,
+        element_ty: &LLNumType,
+        elements: &[&dyn LLValue],
+    ) -> LLConstArray {
+        let mut elements = elements
+            .iter()
+            .map(|e| unsafe { e.as_ptr() })
+            .collect::<Vec<_>>();
+
+        LLConstArray(unsafe {
+            LLVMConstArray(
+                element_ty.as_ptr(),
+                elements.as_mut_ptr(),
+                elements.len() as u32,
+            )
+        })
+    }
+
+    /// Creates a constant array of `bytes`, with an extra zero byte at the end if `null_terminate` is true.
+    pub(crate) fn const_string(&self, bytes: &[u8], null_terminate: bool) -> LLConstArray {
+        LLConstArray(unsafe {
+            LLVMConstStringInContext(
+                self.context_ref,
+                bytes.as_ptr() as *const _,
+                bytes.len() as u32,
+                !null_terminate as i32,
+            )
+        })
     }
 
     pub(crate) fn function_type(
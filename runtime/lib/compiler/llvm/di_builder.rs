@@ -0,0 +1,184 @@
+use std::ptr;
+
+use llvm_sys::{
+    core::{LLVMAddModuleFlag, LLVMConstInt, LLVMInt32TypeInContext, LLVMValueAsMetadata},
+    debuginfo::{
+        LLVMCreateDIBuilder, LLVMDIBuilderCreateCompileUnit, LLVMDIBuilderCreateDebugLocation,
+        LLVMDIBuilderCreateFile, LLVMDIBuilderCreateFunction, LLVMDIBuilderCreateSubroutineType,
+        LLVMDIBuilderFinalize, LLVMDIFlagZero, LLVMDWARFEmissionKind, LLVMDWARFSourceLanguage,
+        LLVMDebugMetadataVersion, LLVMDisposeDIBuilder,
+    },
+    prelude::{LLVMDIBuilderRef, LLVMMetadataRef},
+    LLVMModuleFlagBehavior,
+};
+
+use super::{context::LLContext, module::LLModule};
+
+/// The module flag without which the verifier strips debug info from the module.
+const DEBUG_INFO_VERSION_FLAG: &str = "Debug Info Version";
+
+/// A wrapper for LLVM DIBuilder, which describes the generated functions to debuggers as DWARF.
+///
+/// Wasm has no source lines to map code to, so the module is described as a single file whose line numbers are
+/// byte offsets in the wasm binary.
+///
+/// # Safety
+/// The builder is created for and must not outlive its `LLModule`.
+/// Metadata it creates is owned by the context of the module.
+///
+/// - https://llvm.org/doxygen/DIBuilder_8h_source.html
+#[derive(Debug)]
+pub(crate) struct LLDIBuilder {
+    builder_ref: LLVMDIBuilderRef,
+    file: LLVMMetadataRef,
+    is_optimized: bool,
+}
+
+/// A wrapper for the `DISubprogram` describing a function.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LLDISubprogram(LLVMMetadataRef);
+
+/// A wrapper for the `DILocation` of an instruction within a function.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LLDILocation(LLVMMetadataRef);
+
+impl LLDIBuilder {
+    /// Creates a builder with the compile unit of `module`, whose code comes from the file `file_name`.
+    ///
+    /// `is_optimized` tells debuggers that variables may not live where they expect.
+    pub(crate) fn new(
+        module: &LLModule,
+        context: &LLContext,
+        file_name: &str,
+        is_optimized: bool,
+    ) -> Self {
+        let producer = concat!("wasmo ", env!("CARGO_PKG_VERSION"));
+        unsafe {
+            let builder_ref = LLVMCreateDIBuilder(module.as_ptr());
+            let file = LLVMDIBuilderCreateFile(
+                builder_ref,
+                file_name.as_ptr() as _,
+                file_name.len(),
+                ".".as_ptr() as _,
+                1,
+            );
+
+            LLVMDIBuilderCreateCompileUnit(
+                builder_ref,
+                LLVMDWARFSourceLanguage::LLVMDWARFSourceLanguageC,
+                file,
+                producer.as_ptr() as _,
+                producer.len(),
+                is_optimized as _,
+                ptr::null(),
+                0,
+                0,
+                ptr::null(),
+                0,
+                LLVMDWARFEmissionKind::LLVMDWARFEmissionKindFull,
+                0,
+                0,
+                0,
+                ptr::null(),
+                0,
+                ptr::null(),
+                0,
+            );
+
+            let version = LLVMConstInt(
+                LLVMInt32TypeInContext(context.as_ptr()),
+                LLVMDebugMetadataVersion() as u64,
+                0,
+            );
+            LLVMAddModuleFlag(
+                module.as_ptr(),
+                LLVMModuleFlagBehavior::LLVMModuleFlagBehaviorWarning,
+                DEBUG_INFO_VERSION_FLAG.as_ptr() as _,
+                DEBUG_INFO_VERSION_FLAG.len(),
+                LLVMValueAsMetadata(version),
+            );
+
+            Self {
+                builder_ref,
+                file,
+                is_optimized,
+            }
+        }
+    }
+
+    /// Creates the subprogram of a function named `name` whose body starts at byte offset `offset`.
+    ///
+    /// Params and results are left out of its type, since debuggers only need it to step through the function.
+    pub(crate) fn create_subprogram(&self, name: &str, offset: u32) -> LLDISubprogram {
+        unsafe {
+            let ty = LLVMDIBuilderCreateSubroutineType(
+                self.builder_ref,
+                self.file,
+                ptr::null_mut(),
+                0,
+                LLVMDIFlagZero,
+            );
+
+            LLDISubprogram(LLVMDIBuilderCreateFunction(
+                self.builder_ref,
+                self.file,
+                name.as_ptr() as _,
+                name.len(),
+                name.as_ptr() as _,
+                name.len(),
+                self.file,
+                offset,
+                ty,
+                0,
+                1,
+                offset,
+                LLVMDIFlagZero,
+                self.is_optimized as _,
+            ))
+        }
+    }
+
+    /// Creates the location of the operator at byte offset `offset` within the function of `subprogram`.
+    pub(crate) fn create_location(
+        &self,
+        context: &LLContext,
+        offset: u32,
+        subprogram: LLDISubprogram,
+    ) -> LLDILocation {
+        LLDILocation(unsafe {
+            LLVMDIBuilderCreateDebugLocation(
+                context.as_ptr(),
+                offset,
+                0,
+                subprogram.0,
+                ptr::null_mut(),
+            )
+        })
+    }
+
+    /// Resolves the metadata the builder deferred, which must be done before the module is verified.
+    pub(crate) fn finalize(self) {
+        unsafe { LLVMDIBuilderFinalize(self.builder_ref) }
+    }
+}
+
+impl LLDISubprogram {
+    pub(crate) unsafe fn as_ptr(&self) -> LLVMMetadataRef {
+        self.0
+    }
+}
+
+impl LLDILocation {
+    pub(crate) unsafe fn as_ptr(&self) -> LLVMMetadataRef {
+        self.0
+    }
+}
+
+impl Drop for LLDIBuilder {
+    fn drop(&mut self) {
+        // Dispose of the builder, leaving the metadata it created to the context.
+        unsafe {
+            LLVMDisposeDIBuilder(self.builder_ref);
+        }
+    }
+}
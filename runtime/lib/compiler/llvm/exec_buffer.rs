@@ -0,0 +1,221 @@
+use std::{
+    ffi::{c_void, CStr, CString},
+    mem, ptr,
+};
+
+use anyhow::Result;
+use libc::{c_char, c_uint, uintptr_t};
+use llvm_sys::{
+    bit_reader::LLVMParseBitcodeInContext2,
+    bit_writer::LLVMWriteBitcodeToMemoryBuffer,
+    core::{LLVMDisposeMemoryBuffer, LLVMDisposeMessage, LLVMGetNamedFunction},
+    execution_engine::{
+        LLVMAddGlobalMapping, LLVMCreateMCJITCompilerForModule, LLVMCreateSimpleMCJITMemoryManager,
+        LLVMDisposeExecutionEngine, LLVMExecutionEngineRef, LLVMGetFunctionAddress,
+        LLVMInitializeMCJITCompilerOptions, LLVMLinkInMCJIT, LLVMMCJITCompilerOptions,
+    },
+    prelude::LLVMBool,
+};
+
+use crate::errors::CompilerError;
+
+use super::{context::LLContext, module::LLModule, LLVM};
+
+/// Native code of a module generated by MCJIT into memory mapped for execution.
+///
+/// This is a lighter alternative to `LLJit` that generates the code of the whole module at once
+/// and has no session that other code can be linked into.
+///
+/// # Ownership
+/// Owns a copy of the module in a context of its own.
+/// The execution engine owns the module and the memory manager, which unmaps the code when the engine is disposed.
+///
+/// - https://llvm.org/docs/MCJITDesignAndImplementation.html
+#[derive(Debug)]
+pub(crate) struct LLExecBuffer {
+    engine_ref: LLVMExecutionEngineRef,
+    /// The context the module is copied into. It must outlive the engine.
+    _context: LLContext,
+}
+
+/// The memory mapped for the sections of a module.
+#[derive(Default)]
+struct Mappings {
+    mappings: Vec<Mapping>,
+}
+
+struct Mapping {
+    address: *mut c_void,
+    length: usize,
+    is_code: bool,
+}
+
+impl LLExecBuffer {
+    /// Generates native code for a copy of `module`, linking functions it declares against the addresses in `symbols`.
+    ///
+    /// The module is copied through bitcode since the engine takes ownership of it.
+    pub(crate) fn new(module: &LLModule, symbols: &[(&str, usize)]) -> Result<Self> {
+        LLVM::initialize_native_target();
+
+        let context = LLContext::new();
+        unsafe {
+            LLVMLinkInMCJIT();
+
+            let buffer = LLVMWriteBitcodeToMemoryBuffer(module.as_ptr());
+            let mut module_ref = ptr::null_mut();
+            let failed = LLVMParseBitcodeInContext2(context.as_ptr(), buffer, &mut module_ref) != 0;
+            LLVMDisposeMemoryBuffer(buffer);
+
+            if failed {
+                return Err(CompilerError::JitError("cannot copy module".to_string()).into());
+            }
+
+            let mappings = Box::into_raw(Box::new(Mappings::default()));
+            let mut options: LLVMMCJITCompilerOptions = mem::zeroed();
+            LLVMInitializeMCJITCompilerOptions(&mut options, mem::size_of_val(&options));
+            options.MCJMM = LLVMCreateSimpleMCJITMemoryManager(
+                mappings as *mut c_void,
+                allocate_code_section,
+                allocate_data_section,
+                finalize_memory,
+                Some(destroy_mappings),
+            );
+
+            // The module and memory manager are disposed even when creating the engine fails.
+            let mut engine_ref = ptr::null_mut();
+            let mut message = ptr::null_mut();
+            if LLVMCreateMCJITCompilerForModule(
+                &mut engine_ref,
+                module_ref,
+                &mut options,
+                mem::size_of_val(&options),
+                &mut message,
+            ) != 0
+            {
+                let description = CStr::from_ptr(message).to_string_lossy().into_owned();
+                LLVMDisposeMessage(message);
+                return Err(CompilerError::JitError(description).into());
+            }
+
+            // Dropping `this` on error disposes the engine.
+            let this = Self {
+                engine_ref,
+                _context: context,
+            };
+
+            for (name, address) in symbols {
+                let function = LLVMGetNamedFunction(module_ref, CString::new(*name)?.as_ptr());
+                if !function.is_null() {
+                    LLVMAddGlobalMapping(engine_ref, function, *address as *mut c_void);
+                }
+            }
+
+            Ok(this)
+        }
+    }
+
+    /// Gets the address of the function with the given name, generating the code of the module if needed.
+    pub(crate) fn get_function_address(&self, name: &str) -> Result<usize> {
+        let address =
+            unsafe { LLVMGetFunctionAddress(self.engine_ref, CString::new(name)?.as_ptr()) };
+
+        if address == 0 {
+            return Err(CompilerError::JitError(format!("symbol {:?} not found", name)).into());
+        }
+
+        Ok(address as usize)
+    }
+}
+
+impl Drop for LLExecBuffer {
+    fn drop(&mut self) {
+        unsafe { LLVMDisposeExecutionEngine(self.engine_ref) }
+    }
+}
+
+impl Mappings {
+    /// Maps writable memory for a section, which is page-aligned.
+    ///
+    /// Returns null, which fails the allocation, if the section needs a larger alignment or the mapping fails.
+    unsafe fn map(&mut self, size: uintptr_t, alignment: c_uint, is_code: bool) -> *mut u8 {
+        let page_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+        if alignment as usize > page_size {
+            return ptr::null_mut();
+        }
+
+        // Empty sections still need an address of their own.
+        let length = size.max(1);
+        let address = libc::mmap(
+            ptr::null_mut(),
+            length,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+
+        if address == libc::MAP_FAILED {
+            return ptr::null_mut();
+        }
+
+        self.mappings.push(Mapping {
+            address,
+            length,
+            is_code,
+        });
+
+        address as *mut u8
+    }
+}
+
+extern "C" fn allocate_code_section(
+    mappings: *mut c_void,
+    size: uintptr_t,
+    alignment: c_uint,
+    _section_id: c_uint,
+    _section_name: *const c_char,
+) -> *mut u8 {
+    unsafe { (*(mappings as *mut Mappings)).map(size, alignment, true) }
+}
+
+extern "C" fn allocate_data_section(
+    mappings: *mut c_void,
+    size: uintptr_t,
+    alignment: c_uint,
+    _section_id: c_uint,
+    _section_name: *const c_char,
+    _is_read_only: LLVMBool,
+) -> *mut u8 {
+    unsafe { (*(mappings as *mut Mappings)).map(size, alignment, false) }
+}
+
+/// Makes code sections executable once the engine has relocated them, which also makes them read-only.
+extern "C" fn finalize_memory(mappings: *mut c_void, message: *mut *mut c_char) -> LLVMBool {
+    let mappings = unsafe { &*(mappings as *const Mappings) };
+    for mapping in mappings.mappings.iter().filter(|mapping| mapping.is_code) {
+        let failed = unsafe {
+            libc::mprotect(
+                mapping.address,
+                mapping.length,
+                libc::PROT_READ | libc::PROT_EXEC,
+            ) != 0
+        };
+
+        if failed {
+            // The engine frees the message with `free`.
+            let description = CString::new("cannot make code executable").unwrap();
+            unsafe { *message = libc::strdup(description.as_ptr()) };
+            return 1;
+        }
+    }
+
+    0
+}
+
+/// Unmaps the sections and frees the mappings when the engine is disposed.
+extern "C" fn destroy_mappings(mappings: *mut c_void) {
+    let mappings = unsafe { Box::from_raw(mappings as *mut Mappings) };
+    for mapping in mappings.mappings.iter() {
+        unsafe { libc::munmap(mapping.address, mapping.length) };
+    }
+}
@@ -1,9 +1,25 @@
 use anyhow::Result;
 use std::{ffi::CString, rc::Rc};
 
-use llvm_sys::{core::LLVMAddFunction, prelude::LLVMValueRef};
+use llvm_sys::{
+    core::{
+        LLVMAddAttributeAtIndex, LLVMAddFunction, LLVMAppendExistingBasicBlock,
+        LLVMCreateEnumAttribute, LLVMCreateStringAttribute, LLVMGetEnumAttributeKindForName,
+        LLVMGetFirstBasicBlock, LLVMGetNextBasicBlock, LLVMGetParam,
+    },
+    debuginfo::LLVMSetSubprogram,
+    prelude::{LLVMTypeRef, LLVMValueRef},
+    LLVMAttributeFunctionIndex,
+};
 
-use super::{module::LLModule, types::LLFunctionType};
+use super::{
+    basic_block::LLBasicBlock,
+    context::LLContext,
+    di_builder::LLDISubprogram,
+    module::LLModule,
+    types::LLFunctionType,
+    values::{LLParam, LLValue},
+};
 
 /// This is a wrapper for LLVM Function.
 ///
@@ -24,6 +40,16 @@ pub(crate) struct LLFunction {
     function_type: Rc<LLFunctionType>,
 }
 
+/// This is a wrapper for the declaration of a function the host provides, like the one raising traps.
+///
+/// # Safety
+/// The declaration is owned by the module it is added to.
+#[derive(Debug)]
+pub(crate) struct LLHostFunction {
+    pub(super) function_ref: LLVMValueRef,
+    pub(super) function_type_ref: LLVMTypeRef,
+}
+
 impl LLFunction {
     /// Creates a new LLVM function.
     ///
@@ -59,4 +85,74 @@ impl LLFunction {
 
         Ok(function)
     }
+
+    /// Gets the parameter at the given index.
+    pub(crate) fn get_param(&self, index: u32) -> LLParam {
+        LLParam(unsafe { LLVMGetParam(self.function_ref, index) })
+    }
+
+    /// Adds a valueless function attribute like `optsize`.
+    ///
+    /// # Safety
+    /// Attributes are uniqued in the context, so the function does not own the created attribute.
+    pub(crate) fn add_attribute(&self, context: &LLContext, name: &str) {
+        unsafe {
+            let kind = LLVMGetEnumAttributeKindForName(name.as_ptr() as _, name.len());
+            let attribute = LLVMCreateEnumAttribute(context.as_ptr(), kind, 0);
+            LLVMAddAttributeAtIndex(self.function_ref, LLVMAttributeFunctionIndex, attribute);
+        }
+    }
+
+    /// Adds a function attribute with a string value like `"frame-pointer"="all"`.
+    pub(crate) fn add_string_attribute(&self, context: &LLContext, key: &str, value: &str) {
+        unsafe {
+            let attribute = LLVMCreateStringAttribute(
+                context.as_ptr(),
+                key.as_ptr() as _,
+                key.len() as u32,
+                value.as_ptr() as _,
+                value.len() as u32,
+            );
+            LLVMAddAttributeAtIndex(self.function_ref, LLVMAttributeFunctionIndex, attribute);
+        }
+    }
+
+    /// Attaches the debug info of the function.
+    pub(crate) fn set_subprogram(&self, subprogram: LLDISubprogram) {
+        unsafe { LLVMSetSubprogram(self.function_ref, subprogram.as_ptr()) }
+    }
+
+    /// Appends a basic block to the end of the function.
+    ///
+    /// # Safety
+    /// The function takes ownership of the basic block so it must not be appended more than once.
+    pub(crate) fn append_basic_block(&self, basic_block: &LLBasicBlock) {
+        unsafe { LLVMAppendExistingBasicBlock(self.function_ref, basic_block.as_ptr()) }
+    }
+
+    /// Gets the basic blocks appended to the function in order.
+    pub(crate) fn basic_blocks(&self) -> Vec<LLBasicBlock> {
+        let mut basic_blocks = vec![];
+        let mut basic_block_ref = unsafe { LLVMGetFirstBasicBlock(self.function_ref) };
+        while !basic_block_ref.is_null() {
+            basic_blocks.push(unsafe { LLBasicBlock::from_ptr(basic_block_ref) });
+            basic_block_ref = unsafe { LLVMGetNextBasicBlock(basic_block_ref) };
+        }
+
+        basic_blocks
+    }
+
+    pub(super) fn function_type(&self) -> &LLFunctionType {
+        &self.function_type
+    }
+
+    pub(crate) unsafe fn as_ptr(&self) -> LLVMValueRef {
+        self.function_ref
+    }
+}
+
+impl LLValue for LLFunction {
+    unsafe fn as_ptr(&self) -> LLVMValueRef {
+        self.function_ref
+    }
 }
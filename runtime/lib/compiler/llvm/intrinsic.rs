@@ -0,0 +1,49 @@
+use llvm_sys::prelude::{LLVMTypeRef, LLVMValueRef};
+
+/// Intrinsics that not every target lowers natively, and the architectures known to lower them.
+///
+/// Using them anywhere else may end in a libcall the host does not provide or a crash in the backend.
+///
+/// | Intrinsic         | Since LLVM | Native on           |
+/// |-------------------|------------|---------------------|
+/// | `llvm.roundeven`  | 11         | aarch64             |
+/// | `llvm.minimum`    | 8          | aarch64             |
+/// | `llvm.maximum`    | 8          | aarch64             |
+///
+/// x86_64 only lowers `llvm.roundeven` natively with SSE4.1 which cannot be told from the target triple.
+/// It has no lowering at all for `llvm.minimum` and `llvm.maximum` before LLVM 17, and the backend aborts on them.
+const TARGET_DEPENDENT_INTRINSICS: &[(&str, &[&str])] = &[
+    ("llvm.roundeven", &["aarch64"]),
+    ("llvm.minimum", &["aarch64"]),
+    ("llvm.maximum", &["aarch64"]),
+];
+
+/// This is a wrapper for the declaration of an LLVM intrinsic function.
+///
+/// # Safety
+/// The declaration is owned by the module it is added to.
+///
+/// - https://llvm.org/docs/LangRef.html#intrinsic-functions
+#[derive(Debug)]
+pub(crate) struct LLIntrinsic {
+    pub(super) function_ref: LLVMValueRef,
+    pub(super) function_type_ref: LLVMTypeRef,
+}
+
+impl LLIntrinsic {
+    /// Checks if an intrinsic can be lowered for the target with the given triple.
+    ///
+    /// Intrinsics not listed in `TARGET_DEPENDENT_INTRINSICS` are assumed to be supported by all targets.
+    pub(crate) fn is_supported(name: &str, target_triple: &str) -> bool {
+        let arch = target_triple.split('-').next().unwrap_or_default();
+
+        TARGET_DEPENDENT_INTRINSICS
+            .iter()
+            .filter(|(intrinsic, _)| *intrinsic == name)
+            .all(|(_, archs)| archs.contains(&arch))
+    }
+
+    pub(crate) unsafe fn as_ptr(&self) -> LLVMValueRef {
+        self.function_ref
+    }
+}
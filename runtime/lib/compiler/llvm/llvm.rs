@@ -1,8 +1,25 @@
-use std::pin::Pin;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    rc::Rc,
+    sync::Once,
+};
 
-use super::{context::LLContext, module::LLModule, types::LLFunctionType};
+use super::{
+    archive, context::LLContext, di_builder::LLDIBuilder, exec_buffer::LLExecBuffer,
+    function::LLFunction, module::LLModule, target_machine::LLTargetMachine, types::LLFunctionType,
+};
+use crate::errors::CompilerError;
 use anyhow::Result;
-use llvm_sys::core::LLVMShutdown;
+use llvm_sys::target::{LLVM_InitializeNativeAsmPrinter, LLVM_InitializeNativeTarget};
+
+/// The symbol of the global holding the metadata of a precompiled module.
+const METADATA_SYMBOL: &str = "__wasmo_metadata";
+
+/// The object file section the metadata of a precompiled module is placed in.
+const METADATA_SECTION: &str = ".wasmo_metadata";
 
 /// Converts WebAssembly semantics to LLVM code and handles materialization.
 ///
@@ -25,7 +42,8 @@ use llvm_sys::core::LLVMShutdown;
 /// - `setup_local_globals(store_ref_addr)` // call intrinsics, call initializers, save content to designated data section
 ///
 /// #### The Initializer Functions
-/// - `initialize_mem_0_data_0(store_ref_addr)` // call intrinsics, save content to designated data section
+/// - `initialize_mem_0_data_0()` // copy the active segment into memory 0, return 0 if it does not fit
+/// - `initialize_table_0_elem_0()` // copy the active segment into table 0, return 0 if it does not fit
 /// - ...
 ///
 /// #### The Start Function
@@ -48,25 +66,33 @@ use llvm_sys::core::LLVMShutdown;
 /// - loading important values like memory address into registers from the store data section
 #[derive(Debug)]
 pub(crate) struct LLVM {
-    pub(crate) context: LLContext,
+    /// Describes the generated functions to debuggers while the module is generated with `Options.debug_info`.
+    pub(crate) di_builder: Option<LLDIBuilder>,
+    /// Fields are dropped in order, which disposes of the module before its context.
     pub(crate) module: Option<LLModule>,
+    /// The context can be shared with other instances to save the memory of a context per module.
+    pub(crate) context: Rc<LLContext>,
     pub(crate) info: LLVMInfo,
 }
 
 /// Compilation information about an LLVM Module.
 #[derive(Debug, Default)]
 pub(crate) struct LLVMInfo {
-    types: Vec<LLFunctionType>,
+    /// Function types in the same order as the type section, shared by entries with the same signature.
+    pub(crate) types: Vec<Rc<LLFunctionType>>,
+    /// Non-imported functions in the same order as the function section.
+    pub(crate) functions: Vec<Rc<LLFunction>>,
 }
 
 impl LLVM {
-    /// Creates pinned LLVM instance.
-    pub(crate) fn new() -> Result<Pin<Box<Self>>> {
+    /// Creates pinned LLVM instance whose module lives in `context`.
+    pub(crate) fn new(context: Rc<LLContext>) -> Result<Pin<Box<Self>>> {
         // TODO(appcypher): Initialize target, asm printer.
 
         let mut this = Box::pin(Self {
-            context: LLContext::new(),
+            di_builder: None,
             module: None,
+            context,
             info: LLVMInfo::default(),
         });
 
@@ -75,10 +101,125 @@ impl LLVM {
 
         Ok(this)
     }
-}
 
-impl Drop for LLVM {
-    fn drop(&mut self) {
-        unsafe { LLVMShutdown() }
+    /// Initializes the target and asm printer of the host, which generating native code needs.
+    ///
+    /// This only does anything the first time it is called.
+    pub(crate) fn initialize_native_target() {
+        static INIT: Once = Once::new();
+
+        INIT.call_once(|| unsafe {
+            LLVM_InitializeNativeTarget();
+            LLVM_InitializeNativeAsmPrinter();
+        });
+    }
+
+    /// Generates native code for the module into memory that is mapped executable.
+    ///
+    /// Functions the module declares are linked against the addresses in `symbols`.
+    pub(crate) fn emit_to_executable_buffer(
+        &self,
+        symbols: &[(&str, usize)],
+    ) -> Result<LLExecBuffer> {
+        LLExecBuffer::new(self.module.as_ref().unwrap(), symbols)
+    }
+
+    /// Writes the module as bitcode with `metadata` embedded in it, which `from_precompiled` gives back.
+    pub(crate) fn to_precompiled(&self, metadata: &[u8]) -> Result<Vec<u8>> {
+        // The copy is declared last so that it is dropped before its context.
+        let context = LLContext::new();
+        let module = self.module.as_ref().unwrap().copy_into(&context)?;
+        module.add_section_data(METADATA_SYMBOL, METADATA_SECTION, metadata)?;
+
+        Ok(module.to_bitcode())
+    }
+
+    /// Creates pinned LLVM instance from bitcode written by `to_precompiled`, in `context`.
+    ///
+    /// The embedded metadata is removed from the module and returned with it.
+    pub(crate) fn from_precompiled(
+        bitcode: &[u8],
+        context: Rc<LLContext>,
+    ) -> Result<(Pin<Box<Self>>, Vec<u8>)> {
+        let mut this = Box::pin(Self {
+            di_builder: None,
+            module: None,
+            context,
+            info: LLVMInfo::default(),
+        });
+
+        let module = LLModule::from_bitcode(bitcode, &this.context)?;
+        let metadata = module
+            .take_section_data(METADATA_SYMBOL)?
+            .ok_or_else(|| CompilerError::IncompatiblePrecompiled("no metadata".to_string()))?;
+
+        // The module field references the context field so this is self-referential.
+        this.module = Some(module);
+
+        Ok((this, metadata))
+    }
+
+    /// Generates an object file for the module and writes it to `path` as a static archive that programs can link against.
+    ///
+    /// Functions get internal linkage. Only the `exports`, pairs of a function name in the module and the symbol it is
+    /// exported as, are visible to the linker, along with the globals the module defines.
+    /// The program linking against the archive has to define the symbols the module declares, such as those of intrinsics.
+    pub(crate) fn emit_static_archive(
+        &self,
+        path: &Path,
+        exports: &[(String, String)],
+    ) -> Result<()> {
+        // The copy is declared last so that it is dropped before its context.
+        let context = LLContext::new();
+        let module = self.copy_for_linking(&context, exports)?;
+
+        let object = LLTargetMachine::new()?.emit_object(&module)?;
+        let symbols = module.external_definitions();
+
+        let mut file = BufWriter::new(File::create(path)?);
+        archive::write_archive(&mut file, "module.o", &object, &symbols)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// Generates an object file for the module and writes it to `path`.
+    ///
+    /// The object is for `triple` if given, or the host's target triple if not. Symbols are exported like in
+    /// [`emit_static_archive`](#method.emit_static_archive).
+    pub(crate) fn emit_object_file(
+        &self,
+        path: &Path,
+        triple: Option<&str>,
+        exports: &[(String, String)],
+    ) -> Result<()> {
+        let target_machine = match triple {
+            Some(triple) => LLTargetMachine::with_triple(triple)?,
+            None => LLTargetMachine::new()?,
+        };
+
+        // The copy is declared last so that it is dropped before its context.
+        let context = LLContext::new();
+        let module = self.copy_for_linking(&context, exports)?;
+
+        fs::write(path, target_machine.emit_object(&module)?)?;
+
+        Ok(())
+    }
+
+    /// Copies the module into `context` with internal functions, except for the aliases it gets for `exports`.
+    fn copy_for_linking(
+        &self,
+        context: &LLContext,
+        exports: &[(String, String)],
+    ) -> Result<LLModule> {
+        let module = self.module.as_ref().unwrap().copy_into(context)?;
+
+        module.internalize_functions();
+        for (function, name) in exports {
+            module.add_function_alias(function, name)?;
+        }
+
+        Ok(module)
     }
 }
@@ -1,13 +1,141 @@
-use std::{ffi::CString, rc::Rc};
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fs::{self, File},
+    io::{self, Write},
+    process, ptr,
+    rc::Rc,
+    slice,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use anyhow::Result;
 
 use llvm_sys::{
-    core::{LLVMDumpModule, LLVMModuleCreateWithNameInContext},
+    analysis::{LLVMVerifierFailureAction, LLVMVerifyModule},
+    bit_writer::LLVMWriteBitcodeToMemoryBuffer,
+    core::{
+        LLVMAddFunction, LLVMAddGlobal, LLVMConstAddrSpaceCast, LLVMConstArray, LLVMConstBitCast,
+        LLVMConstInt, LLVMConstStringInContext, LLVMConstStructInContext,
+        LLVMCreateMemoryBufferWithMemoryRangeCopy, LLVMCreatePassManager, LLVMDeleteGlobal,
+        LLVMDisposeMemoryBuffer, LLVMDisposeMessage, LLVMDisposeModule, LLVMDisposePassManager,
+        LLVMGetAsString, LLVMGetBufferSize, LLVMGetBufferStart, LLVMGetFirstFunction,
+        LLVMGetFirstGlobal, LLVMGetFirstGlobalAlias, LLVMGetInitializer,
+        LLVMGetIntrinsicDeclaration, LLVMGetLinkage, LLVMGetModuleContext, LLVMGetNamedFunction,
+        LLVMGetNamedGlobal, LLVMGetNextFunction, LLVMGetNextGlobal, LLVMGetNextGlobalAlias,
+        LLVMGetTarget, LLVMGetValueName2, LLVMGlobalGetValueType, LLVMInt64TypeInContext,
+        LLVMInt8TypeInContext, LLVMIntrinsicGetType, LLVMIsDeclaration, LLVMLookupIntrinsicID,
+        LLVMModuleCreateWithNameInContext, LLVMPointerType, LLVMPrintModuleToFile,
+        LLVMPrintModuleToString, LLVMRunPassManager, LLVMSetInitializer, LLVMSetLinkage,
+        LLVMSetSection, LLVMTypeOf,
+    },
     prelude::LLVMModuleRef,
+    prelude::LLVMTypeRef,
+    transforms::pass_manager_builder::{
+        LLVMPassManagerBuilderCreate, LLVMPassManagerBuilderDispose,
+        LLVMPassManagerBuilderPopulateModulePassManager, LLVMPassManagerBuilderSetOptLevel,
+        LLVMPassManagerBuilderSetSizeLevel,
+    },
+    LLVMLinkage,
+};
+
+use crate::{errors::CompilerError, OptLevel};
+
+use super::{
+    context::LLContext,
+    function::{LLFunction, LLHostFunction},
+    intrinsic::LLIntrinsic,
+    types::{LLArrayType, LLFunctionType, LLNumType},
+    values::{LLConstArray, LLGlobal, LLValue},
 };
 
-use super::{context::LLContext, function::LLFunction};
+/// The symbol of the global holding the base address of memory 0.
+///
+/// It is null until the engine running the module patches it with the address of the instance's memory.
+pub(crate) const MEMORY_BASE_SYMBOL: &str = "__wasmo_memory_base";
+
+/// The symbol of the global holding the size of memory 0 in bytes, as an `i64`.
+///
+/// It is zero until the engine running the module patches it along with the base address, so every access traps until then.
+pub(crate) const MEMORY_SIZE_SYMBOL: &str = "__wasmo_memory_size";
+
+/// The symbol of the global holding the address of the host memory 0, which `memory.grow` grows.
+pub(crate) const MEMORY_SYMBOL: &str = "__wasmo_memory";
+
+/// The symbol of the global holding the base address of the slots of table 0.
+///
+/// It is null until the engine running the module patches it with the address of the slots.
+pub(crate) const TABLE_BASE_SYMBOL: &str = "__wasmo_table_base";
+
+/// The symbol of the global holding the number of slots of table 0, as an `i64`.
+///
+/// It is zero until the engine running the module patches it along with the base address, so every access traps until then.
+pub(crate) const TABLE_SIZE_SYMBOL: &str = "__wasmo_table_size";
+
+/// The symbol of the global holding the address of the host vector of the slots of table 0, which `table.grow` grows.
+pub(crate) const TABLE_SLOTS_SYMBOL: &str = "__wasmo_table_slots";
+
+/// The symbol of the global holding the address of the host functions the function imports are resolved to.
+///
+/// It is null until the engine running the module patches it, so every call to an imported function traps until then.
+pub(crate) const IMPORTED_FUNCTIONS_SYMBOL: &str = "__wasmo_imported_functions";
+
+/// The prefix of the symbols of the globals holding initial memory images, which is followed by the memory index.
+pub(crate) const MEMORY_IMAGE_SYMBOL_PREFIX: &str = "__wasmo_memory_image_";
+
+/// The prefix of the symbols of the constant globals holding the bytes of data segments, which is followed by the
+/// segment index.
+pub(crate) const DATA_BYTES_SYMBOL_PREFIX: &str = "__wasmo_data_bytes_";
+
+/// The prefix of the symbols of the globals holding the address of the bytes of data segments, which is followed by
+/// the segment index.
+///
+/// The data section comes after the code, so the address is only filled in once the segment is compiled.
+pub(crate) const DATA_BASE_SYMBOL_PREFIX: &str = "__wasmo_data_base_";
+
+/// The prefix of the symbols of the globals holding the size of data segments in bytes as an `i64`, which is followed
+/// by the segment index.
+///
+/// It is zero for active segments and segments that are dropped, since `memory.init` can then only copy nothing.
+pub(crate) const DATA_SIZE_SYMBOL_PREFIX: &str = "__wasmo_data_size_";
+
+/// The prefix of the symbols of the constant globals holding the references of element segments, which is followed
+/// by the segment index.
+pub(crate) const ELEM_ITEMS_SYMBOL_PREFIX: &str = "__wasmo_elem_items_";
+
+/// The prefix of the symbols of the globals holding the address of the references of element segments, which is
+/// followed by the segment index.
+pub(crate) const ELEM_BASE_SYMBOL_PREFIX: &str = "__wasmo_elem_base_";
+
+/// The prefix of the symbols of the globals holding the number of references of element segments as an `i64`, which
+/// is followed by the segment index.
+///
+/// It is zero for active and declared segments and segments that are dropped, since `table.init` can then only copy
+/// nothing.
+pub(crate) const ELEM_SIZE_SYMBOL_PREFIX: &str = "__wasmo_elem_size_";
+
+/// Gets the symbol of the function copying active data segment `data_index` into memory `memory_index`.
+pub(crate) fn data_initializer_symbol(memory_index: u32, data_index: u32) -> String {
+    format!("initialize_mem_{}_data_{}", memory_index, data_index)
+}
+
+/// Gets the symbol of the constant global funcrefs to the function at `function_index` point to.
+///
+/// It holds the address of the function followed by the `i64` id of its type, which indirect calls compare against
+/// the id of the type they expect, as given by `ModuleInfo::type_id`.
+pub(crate) fn function_reference_symbol(function_index: u32) -> String {
+    format!("__wasmo_func_ref_{}", function_index)
+}
+
+/// Gets the symbol of the function copying active element segment `elem_index` into table `table_index`.
+pub(crate) fn element_initializer_symbol(table_index: u32, elem_index: u32) -> String {
+    format!("initialize_table_{}_elem_{}", table_index, elem_index)
+}
+
+/// Gets the symbol of the trampoline the host calls exported function `function_index` through.
+pub(crate) fn trampoline_symbol(function_index: u32) -> String {
+    format!("trampoline_func_{}", function_index)
+}
 
 /// A wrapper for LLVM Module.
 ///
@@ -15,9 +143,9 @@ use super::{context::LLContext, function::LLFunction};
 ///
 /// When a Module references a Context, the Context frees it when it gets dropped.
 ///
-/// We leverage this behavior by not disposing the Module explicitly on drop, letting associated Context do the job.
+/// A Context can be shared by many modules over its lifetime, so a Module is still disposed of explicitly on drop.
 ///
-/// WARNING: This is safe only if we can only create a Module from a Context.
+/// WARNING: This is safe only if the Module is dropped before its Context.
 ///
 /// NOTE: We can't use lifetime parameter since it leads to unresolvable self-referential structs when an `LLModule` is stored in the same struct as the associated `LLContext`.
 ///
@@ -36,7 +164,7 @@ pub(crate) struct LLModule {
 }
 
 impl LLModule {
-    /// This is the only way to create an LLModule to ensure it has an associated Context that can dispose it.
+    /// This is the only way to create an LLModule to ensure it has an associated Context.
     ///
     /// # Safety
     /// A temporary `CString` name is safe to use here because it is copied into the LLVM Module.
@@ -61,13 +189,668 @@ impl LLModule {
         self.functions.push(function)
     }
 
+    /// Declares a global of type `ty` defined outside the module, to be resolved by name when linking.
+    pub(crate) fn add_external_global(&self, ty: &LLNumType, name: &str) -> Result<LLGlobal> {
+        Ok(LLGlobal(unsafe {
+            LLVMAddGlobal(self.module_ref, ty.as_ptr(), CString::new(name)?.as_ptr())
+        }))
+    }
+
+    /// Gets the global holding the base address of memory 0, defining it if needed.
+    ///
+    /// See [`MEMORY_BASE_SYMBOL`](constant.MEMORY_BASE_SYMBOL.html)
+    pub(crate) fn add_or_get_memory_base(&self, ty: &LLNumType) -> Result<LLGlobal> {
+        self.add_or_get_zeroed_global(MEMORY_BASE_SYMBOL, ty)
+    }
+
+    /// Gets the global holding the size of memory 0, defining it if needed.
+    ///
+    /// See [`MEMORY_SIZE_SYMBOL`](constant.MEMORY_SIZE_SYMBOL.html)
+    pub(crate) fn add_or_get_memory_size(&self, ty: &LLNumType) -> Result<LLGlobal> {
+        self.add_or_get_zeroed_global(MEMORY_SIZE_SYMBOL, ty)
+    }
+
+    /// Gets the global holding the address of the host memory 0, defining it if needed.
+    ///
+    /// See [`MEMORY_SYMBOL`](constant.MEMORY_SYMBOL.html)
+    pub(crate) fn add_or_get_memory(&self, ty: &LLNumType) -> Result<LLGlobal> {
+        self.add_or_get_zeroed_global(MEMORY_SYMBOL, ty)
+    }
+
+    /// Gets the global holding the base address of the slots of table 0, defining it if needed.
+    ///
+    /// See [`TABLE_BASE_SYMBOL`](constant.TABLE_BASE_SYMBOL.html)
+    pub(crate) fn add_or_get_table_base(&self, ty: &LLNumType) -> Result<LLGlobal> {
+        self.add_or_get_zeroed_global(TABLE_BASE_SYMBOL, ty)
+    }
+
+    /// Gets the global holding the number of slots of table 0, defining it if needed.
+    ///
+    /// See [`TABLE_SIZE_SYMBOL`](constant.TABLE_SIZE_SYMBOL.html)
+    pub(crate) fn add_or_get_table_size(&self, ty: &LLNumType) -> Result<LLGlobal> {
+        self.add_or_get_zeroed_global(TABLE_SIZE_SYMBOL, ty)
+    }
+
+    /// Gets the global holding the address of the host vector of the slots of table 0, defining it if needed.
+    ///
+    /// See [`TABLE_SLOTS_SYMBOL`](constant.TABLE_SLOTS_SYMBOL.html)
+    pub(crate) fn add_or_get_table_slots(&self, ty: &LLNumType) -> Result<LLGlobal> {
+        self.add_or_get_zeroed_global(TABLE_SLOTS_SYMBOL, ty)
+    }
+
+    /// Gets the global holding the address of the host functions the function imports are resolved to, defining it if needed.
+    ///
+    /// See [`IMPORTED_FUNCTIONS_SYMBOL`](constant.IMPORTED_FUNCTIONS_SYMBOL.html)
+    pub(crate) fn add_or_get_imported_functions(&self, ty: &LLNumType) -> Result<LLGlobal> {
+        self.add_or_get_zeroed_global(IMPORTED_FUNCTIONS_SYMBOL, ty)
+    }
+
+    /// Gets the globals holding the base address and size of data segment `index`, defining them if needed.
+    ///
+    /// See [`DATA_BASE_SYMBOL_PREFIX`](constant.DATA_BASE_SYMBOL_PREFIX.html) and
+    /// [`DATA_SIZE_SYMBOL_PREFIX`](constant.DATA_SIZE_SYMBOL_PREFIX.html)
+    pub(crate) fn add_or_get_data_segment(
+        &self,
+        index: u32,
+        base_ty: &LLNumType,
+        size_ty: &LLNumType,
+    ) -> Result<(LLGlobal, LLGlobal)> {
+        Ok((
+            self.add_or_get_zeroed_global(
+                &format!("{}{}", DATA_BASE_SYMBOL_PREFIX, index),
+                base_ty,
+            )?,
+            self.add_or_get_zeroed_global(
+                &format!("{}{}", DATA_SIZE_SYMBOL_PREFIX, index),
+                size_ty,
+            )?,
+        ))
+    }
+
+    /// Embeds the bytes of passive data segment `index` in the module, if code refers to the segment.
+    ///
+    /// The globals `add_or_get_data_segment` defines are initialized with the address and size of the bytes.
+    pub(crate) fn define_data_segment(&self, index: u32, bytes: &[u8]) -> Result<()> {
+        let base = match self.get_global(&format!("{}{}", DATA_BASE_SYMBOL_PREFIX, index))? {
+            Some(base) => base,
+            None => return Ok(()),
+        };
+        let size = self
+            .get_global(&format!("{}{}", DATA_SIZE_SYMBOL_PREFIX, index))?
+            .ok_or_else(|| CompilerError::IndexOutOfBounds(format!("data {}", index)))?;
+
+        let data =
+            self.add_constant_data(&format!("{}{}", DATA_BYTES_SYMBOL_PREFIX, index), bytes)?;
+        unsafe {
+            LLVMSetInitializer(
+                base.as_ptr(),
+                LLVMConstBitCast(data.as_ptr(), LLVMGlobalGetValueType(base.as_ptr())),
+            );
+            LLVMSetInitializer(
+                size.as_ptr(),
+                LLVMConstInt(LLVMGlobalGetValueType(size.as_ptr()), bytes.len() as u64, 0),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Gets the globals holding the base address and size of element segment `index`, defining them if needed.
+    ///
+    /// See [`ELEM_BASE_SYMBOL_PREFIX`](constant.ELEM_BASE_SYMBOL_PREFIX.html) and
+    /// [`ELEM_SIZE_SYMBOL_PREFIX`](constant.ELEM_SIZE_SYMBOL_PREFIX.html)
+    pub(crate) fn add_or_get_element_segment(
+        &self,
+        index: u32,
+        base_ty: &LLNumType,
+        size_ty: &LLNumType,
+    ) -> Result<(LLGlobal, LLGlobal)> {
+        Ok((
+            self.add_or_get_zeroed_global(
+                &format!("{}{}", ELEM_BASE_SYMBOL_PREFIX, index),
+                base_ty,
+            )?,
+            self.add_or_get_zeroed_global(
+                &format!("{}{}", ELEM_SIZE_SYMBOL_PREFIX, index),
+                size_ty,
+            )?,
+        ))
+    }
+
+    /// Embeds the references of passive element segment `index` in the module.
+    ///
+    /// The globals `add_or_get_element_segment` gets are initialized with the address and number of the references.
+    pub(crate) fn define_element_segment(
+        &self,
+        index: u32,
+        ty: &LLNumType,
+        base_ty: &LLNumType,
+        size_ty: &LLNumType,
+        references: &[Option<LLGlobal>],
+    ) -> Result<()> {
+        let items = self.add_constant_references(
+            &format!("{}{}", ELEM_ITEMS_SYMBOL_PREFIX, index),
+            ty,
+            references,
+        )?;
+        let (base, size) = self.add_or_get_element_segment(index, base_ty, size_ty)?;
+        unsafe {
+            LLVMSetInitializer(
+                base.as_ptr(),
+                LLVMConstBitCast(items.as_ptr(), LLVMGlobalGetValueType(base.as_ptr())),
+            );
+            LLVMSetInitializer(
+                size.as_ptr(),
+                LLVMConstInt(size_ty.as_ptr(), references.len() as u64, 0),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Adds the constant global funcrefs to `function`, the function at `index`, point to.
+    ///
+    /// See [`function_reference_symbol`](fn.function_reference_symbol.html)
+    pub(crate) fn add_function_reference(
+        &self,
+        index: u32,
+        function: &LLFunction,
+        type_id: u32,
+    ) -> Result<LLGlobal> {
+        let name = function_reference_symbol(index);
+        unsafe {
+            let context = LLVMGetModuleContext(self.module_ref);
+            let byte_pointer_type = LLVMPointerType(LLVMInt8TypeInContext(context), 0);
+            let i64_type = LLVMInt64TypeInContext(context);
+            let mut fields = [
+                LLVMConstBitCast(function.as_ptr(), byte_pointer_type),
+                LLVMConstInt(i64_type, type_id as u64, 0),
+            ];
+            let constant = LLVMConstStructInContext(context, fields.as_mut_ptr(), 2, 0);
+
+            let global = LLGlobal(LLVMAddGlobal(
+                self.module_ref,
+                LLVMTypeOf(constant),
+                CString::new(name)?.as_ptr(),
+            ));
+            LLVMSetInitializer(global.as_ptr(), constant);
+            global.set_constant(true);
+
+            Ok(global)
+        }
+    }
+
+    /// Adds a constant global `name` holding an array of references of type `ty` to `references`.
+    ///
+    /// `None` is a null reference, which is all an externref segment can hold.
+    pub(crate) fn add_constant_references(
+        &self,
+        name: &str,
+        ty: &LLNumType,
+        references: &[Option<LLGlobal>],
+    ) -> Result<LLGlobal> {
+        unsafe {
+            let byte_pointer_type = LLVMPointerType(
+                LLVMInt8TypeInContext(LLVMGetModuleContext(self.module_ref)),
+                0,
+            );
+            let mut references = references
+                .iter()
+                .map(|reference| match reference {
+                    Some(reference) => LLVMConstAddrSpaceCast(
+                        LLVMConstBitCast(reference.as_ptr(), byte_pointer_type),
+                        ty.as_ptr(),
+                    ),
+                    None => ty.zero().as_ptr(),
+                })
+                .collect::<Vec<_>>();
+            let constant = LLConstArray(LLVMConstArray(
+                ty.as_ptr(),
+                references.as_mut_ptr(),
+                references.len() as u32,
+            ));
+
+            self.add_constant_global(name, &constant)
+        }
+    }
+
+    /// Gets the global with the given name, defining it as a zero of type `ty` if needed.
+    fn add_or_get_zeroed_global(&self, name: &str, ty: &LLNumType) -> Result<LLGlobal> {
+        if let Some(global) = self.get_global(name)? {
+            return Ok(global);
+        }
+
+        let name = CString::new(name)?;
+        unsafe {
+            let global_ref = LLVMAddGlobal(self.module_ref, ty.as_ptr(), name.as_ptr());
+            LLVMSetInitializer(global_ref, ty.zero().as_ptr());
+
+            Ok(LLGlobal(global_ref))
+        }
+    }
+
+    /// Gets the global with the given name if the module has one.
+    pub(crate) fn get_global(&self, name: &str) -> Result<Option<LLGlobal>> {
+        let global_ref =
+            unsafe { LLVMGetNamedGlobal(self.module_ref, CString::new(name)?.as_ptr()) };
+        if global_ref.is_null() {
+            return Ok(None);
+        }
+
+        Ok(Some(LLGlobal(global_ref)))
+    }
+
+    /// Gets the declaration of the host function `name`, adding it to the module if needed.
+    ///
+    /// The engine running the module links the declaration against the host function.
+    pub(crate) fn add_or_get_host_function(
+        &self,
+        name: &str,
+        function_type: &LLFunctionType,
+    ) -> Result<LLHostFunction> {
+        let name = CString::new(name)?;
+        let mut function_ref = unsafe { LLVMGetNamedFunction(self.module_ref, name.as_ptr()) };
+        if function_ref.is_null() {
+            function_ref =
+                unsafe { LLVMAddFunction(self.module_ref, name.as_ptr(), function_type.as_ptr()) };
+        }
+
+        Ok(LLHostFunction {
+            function_ref,
+            function_type_ref: unsafe { function_type.as_ptr() },
+        })
+    }
+
+    /// Gets the declaration of an intrinsic overloaded on `types`, adding it to the module if needed.
+    ///
+    /// Returns `None` if the linked LLVM does not know the intrinsic or the target cannot lower it.
+    /// Callers are expected to fall back to an equivalent sequence of instructions.
+    pub(crate) fn add_or_get_intrinsic_function(
+        &self,
+        name: &str,
+        types: &[LLNumType],
+    ) -> Option<LLIntrinsic> {
+        if !LLIntrinsic::is_supported(name, &self.target_triple()) {
+            return None;
+        }
+
+        let id = unsafe { LLVMLookupIntrinsicID(name.as_ptr() as *const _, name.len()) };
+        if id == 0 {
+            return None;
+        }
+
+        let mut types = types
+            .iter()
+            .map(|ty| unsafe { ty.as_ptr() })
+            .collect::<Vec<LLVMTypeRef>>();
+
+        unsafe {
+            Some(LLIntrinsic {
+                function_ref: LLVMGetIntrinsicDeclaration(
+                    self.module_ref,
+                    id,
+                    types.as_mut_ptr(),
+                    types.len(),
+                ),
+                function_type_ref: LLVMIntrinsicGetType(
+                    LLVMGetModuleContext(self.module_ref),
+                    id,
+                    types.as_mut_ptr(),
+                    types.len(),
+                ),
+            })
+        }
+    }
+
+    /// Gets the target triple of the module, which is empty if it has not been set.
+    pub(crate) fn target_triple(&self) -> String {
+        unsafe {
+            CStr::from_ptr(LLVMGetTarget(self.module_ref))
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    /// Copies the module into `context` through bitcode, leaving this one untouched.
+    pub(crate) fn copy_into(&self, context: &LLContext) -> Result<LLModule> {
+        Self::from_bitcode(&self.to_bitcode(), context)
+    }
+
+    /// Parses a module from `bitcode` into `context`.
+    pub(crate) fn from_bitcode(bitcode: &[u8], context: &LLContext) -> Result<LLModule> {
+        unsafe {
+            let name = CString::new("bitcode")?;
+            let buffer = LLVMCreateMemoryBufferWithMemoryRangeCopy(
+                bitcode.as_ptr() as *const _,
+                bitcode.len(),
+                name.as_ptr(),
+            );
+
+            // The non-deprecated parser reports errors through the context's diagnostic handler, which exits the
+            // process by default, so we use the one that returns a message instead.
+            let mut module_ref = ptr::null_mut();
+            let mut message = ptr::null_mut();
+            #[allow(deprecated)]
+            let failed = llvm_sys::bit_reader::LLVMParseBitcodeInContext(
+                context.as_ptr(),
+                buffer,
+                &mut module_ref,
+                &mut message,
+            ) != 0;
+            LLVMDisposeMemoryBuffer(buffer);
+
+            if failed {
+                let description = if message.is_null() {
+                    "cannot parse bitcode".to_string()
+                } else {
+                    let description = CStr::from_ptr(message).to_string_lossy().into_owned();
+                    LLVMDisposeMessage(message);
+                    description
+                };
+
+                return Err(CompilerError::InvalidModule(description).into());
+            }
+
+            Ok(Self {
+                module_ref,
+                functions: vec![],
+            })
+        }
+    }
+
+    /// Writes the module as bitcode.
+    pub(crate) fn to_bitcode(&self) -> Vec<u8> {
+        unsafe {
+            let buffer = LLVMWriteBitcodeToMemoryBuffer(self.module_ref);
+            let bitcode = slice::from_raw_parts(
+                LLVMGetBufferStart(buffer) as *const u8,
+                LLVMGetBufferSize(buffer),
+            )
+            .to_vec();
+            LLVMDisposeMemoryBuffer(buffer);
+
+            bitcode
+        }
+    }
+
+    /// Adds a constant global `name` holding `content` as an array of bytes.
+    pub(crate) fn add_constant_data(&self, name: &str, content: &[u8]) -> Result<LLGlobal> {
+        let constant = unsafe {
+            LLConstArray(LLVMConstStringInContext(
+                LLVMGetModuleContext(self.module_ref),
+                content.as_ptr() as *const _,
+                content.len() as u32,
+                1,
+            ))
+        };
+
+        self.add_constant_global(name, &constant)
+    }
+
+    /// Adds a global `name` of type `ty`, which is only a declaration until it is given an initializer.
+    pub(crate) fn add_global(&self, ty: &LLArrayType, name: &str) -> Result<LLGlobal> {
+        Ok(LLGlobal(unsafe {
+            LLVMAddGlobal(self.module_ref, ty.as_ptr(), CString::new(name)?.as_ptr())
+        }))
+    }
+
+    /// Adds a constant global `name` initialized with `constant`.
+    fn add_constant_global(&self, name: &str, constant: &LLConstArray) -> Result<LLGlobal> {
+        let global = self.add_global(&constant.array_type(), name)?;
+        global.set_initializer(constant);
+        global.set_constant(true);
+
+        Ok(global)
+    }
+
+    /// Embeds `content` in the module as a constant global `name` placed in the object file section `section`.
+    ///
+    /// The global has private linkage so that it never clashes with the globals of other modules.
+    pub(crate) fn add_section_data(&self, name: &str, section: &str, content: &[u8]) -> Result<()> {
+        let global = self.add_constant_data(name, content)?;
+        unsafe {
+            LLVMSetLinkage(global.as_ptr(), LLVMLinkage::LLVMPrivateLinkage);
+            LLVMSetSection(global.as_ptr(), CString::new(section)?.as_ptr());
+        }
+
+        Ok(())
+    }
+
+    /// Removes the global `name` added by `add_section_data`, returning its content.
+    pub(crate) fn take_section_data(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        unsafe {
+            let global = LLVMGetNamedGlobal(self.module_ref, CString::new(name)?.as_ptr());
+            if global.is_null() || LLVMGetInitializer(global).is_null() {
+                return Ok(None);
+            }
+
+            let mut length = 0;
+            let content = LLVMGetAsString(LLVMGetInitializer(global), &mut length);
+            let content = slice::from_raw_parts(content as *const u8, length).to_vec();
+            LLVMDeleteGlobal(global);
+
+            Ok(Some(content))
+        }
+    }
+
+    /// Gives every function defined in the module internal linkage, hiding it from code linked against the module.
+    pub(crate) fn internalize_functions(&self) {
+        unsafe {
+            let mut function = LLVMGetFirstFunction(self.module_ref);
+            while !function.is_null() {
+                if LLVMIsDeclaration(function) == 0 {
+                    LLVMSetLinkage(function, LLVMLinkage::LLVMInternalLinkage);
+                }
+
+                function = LLVMGetNextFunction(function);
+            }
+        }
+    }
+
+    /// Adds an externally visible alias `name` for the function `aliasee`.
+    pub(crate) fn add_function_alias(&self, aliasee: &str, name: &str) -> Result<()> {
+        unsafe {
+            let function = LLVMGetNamedFunction(self.module_ref, CString::new(aliasee)?.as_ptr());
+            if function.is_null() {
+                return Err(
+                    CompilerError::IndexOutOfBounds(format!("function {:?}", aliasee)).into(),
+                );
+            }
+
+            // A function named like its export is made visible itself, since an alias cannot take its name.
+            if aliasee == name {
+                LLVMSetLinkage(function, LLVMLinkage::LLVMExternalLinkage);
+                return Ok(());
+            }
+
+            // `LLVMAddAlias2` is not available in every LLVM version this supports.
+            #[allow(deprecated)]
+            llvm_sys::core::LLVMAddAlias(
+                self.module_ref,
+                LLVMTypeOf(function),
+                function,
+                CString::new(name)?.as_ptr(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Gets the names of the functions, globals and aliases the module defines that are visible outside of it.
+    pub(crate) fn external_definitions(&self) -> Vec<String> {
+        let mut names = vec![];
+        unsafe {
+            let is_external = |value| {
+                LLVMIsDeclaration(value) == 0
+                    && !matches!(
+                        LLVMGetLinkage(value),
+                        LLVMLinkage::LLVMInternalLinkage | LLVMLinkage::LLVMPrivateLinkage
+                    )
+            };
+
+            let mut push_name = |value| {
+                let mut length = 0;
+                let name = LLVMGetValueName2(value, &mut length);
+                names.push(
+                    String::from_utf8_lossy(slice::from_raw_parts(name as *const u8, length))
+                        .into_owned(),
+                );
+            };
+
+            let mut function = LLVMGetFirstFunction(self.module_ref);
+            while !function.is_null() {
+                if is_external(function) {
+                    push_name(function);
+                }
+
+                function = LLVMGetNextFunction(function);
+            }
+
+            let mut global = LLVMGetFirstGlobal(self.module_ref);
+            while !global.is_null() {
+                if is_external(global) {
+                    push_name(global);
+                }
+
+                global = LLVMGetNextGlobal(global);
+            }
+
+            // Aliases are never declarations.
+            let mut alias = LLVMGetFirstGlobalAlias(self.module_ref);
+            while !alias.is_null() {
+                if is_external(alias) {
+                    push_name(alias);
+                }
+
+                alias = LLVMGetNextGlobalAlias(alias);
+            }
+        }
+
+        names
+    }
+
     pub(crate) unsafe fn as_ptr(&self) -> LLVMModuleRef {
         self.module_ref
     }
 
-    pub(crate) fn print(&self) {
+    /// Checks that the module is well-formed.
+    pub(crate) fn verify(&self) -> Result<()> {
+        let mut message = ptr::null_mut();
+        let failed = unsafe {
+            LLVMVerifyModule(
+                self.module_ref,
+                LLVMVerifierFailureAction::LLVMReturnStatusAction,
+                &mut message,
+            ) != 0
+        };
+
+        let description = if message.is_null() {
+            String::new()
+        } else {
+            unsafe {
+                let description = CStr::from_ptr(message).to_string_lossy().into_owned();
+                LLVMDisposeMessage(message);
+                description
+            }
+        };
+
+        if failed {
+            return Err(CompilerError::InvalidModule(description).into());
+        }
+
+        Ok(())
+    }
+
+    /// Runs the standard optimization pipeline for `opt_level` over the module.
+    ///
+    /// The pass manager is created and disposed here since nothing else runs passes.
+    pub(crate) fn optimize(&self, opt_level: OptLevel) {
+        let (opt, size) = match opt_level {
+            OptLevel::None => return,
+            OptLevel::Less => (1, 0),
+            OptLevel::Default => (2, 0),
+            OptLevel::Aggressive => (3, 0),
+            OptLevel::Size => (2, 1),
+            OptLevel::SizeMin => (2, 2),
+        };
+
+        unsafe {
+            let builder = LLVMPassManagerBuilderCreate();
+            LLVMPassManagerBuilderSetOptLevel(builder, opt);
+            LLVMPassManagerBuilderSetSizeLevel(builder, size);
+
+            let pass_manager = LLVMCreatePassManager();
+            LLVMPassManagerBuilderPopulateModulePassManager(builder, pass_manager);
+            LLVMRunPassManager(pass_manager, self.module_ref);
+
+            LLVMDisposePassManager(pass_manager);
+            LLVMPassManagerBuilderDispose(builder);
+        }
+    }
+
+    /// Writes the textual IR of the module to `writer`.
+    ///
+    /// The IR is streamed through a temporary file so the whole module is never buffered in memory.
+    pub(crate) fn write_ir<W: Write>(&self, writer: &mut W) -> Result<()> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = env::temp_dir().join(format!(
+            "wasmo-{}-{}.ll",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = self.print_to_file(&path.to_string_lossy()).and_then(|_| {
+            io::copy(&mut File::open(&path)?, writer)?;
+            Ok(())
+        });
+
+        let _ = fs::remove_file(&path);
+
+        result
+    }
+
+    /// Writes the textual IR of the module to the file at `path`.
+    fn print_to_file(&self, path: &str) -> Result<()> {
+        let mut message = ptr::null_mut();
+        let failed = unsafe {
+            LLVMPrintModuleToFile(self.module_ref, CString::new(path)?.as_ptr(), &mut message) != 0
+        };
+
+        let description = if message.is_null() {
+            String::new()
+        } else {
+            unsafe {
+                let description = CStr::from_ptr(message).to_string_lossy().into_owned();
+                LLVMDisposeMessage(message);
+                description
+            }
+        };
+
+        if failed {
+            return Err(CompilerError::IRPrintFailed(description).into());
+        }
+
+        Ok(())
+    }
+
+    /// Gets the textual IR of the module.
+    pub(crate) fn print_to_string(&self) -> String {
+        unsafe {
+            let message = LLVMPrintModuleToString(self.module_ref);
+            let ir = CStr::from_ptr(message).to_string_lossy().into_owned();
+            LLVMDisposeMessage(message);
+
+            ir
+        }
+    }
+}
+
+impl Drop for LLModule {
+    fn drop(&mut self) {
+        // Dispose of the LLVM module, which also frees its functions and globals.
         unsafe {
-            LLVMDumpModule(self.module_ref);
+            LLVMDisposeModule(self.module_ref);
         }
     }
 }
@@ -1 +1,525 @@
+use std::{
+    ffi::{c_void, CStr, CString},
+    process, ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
+use anyhow::Result;
+use llvm_sys::{
+    bit_reader::LLVMParseBitcodeInContext2,
+    bit_writer::LLVMWriteBitcodeToMemoryBuffer,
+    core::{
+        LLVMAddFunction, LLVMAddGlobal, LLVMAppendBasicBlockInContext, LLVMBuildCall2,
+        LLVMBuildLoad2, LLVMBuildRet, LLVMBuildRetVoid, LLVMCountParams,
+        LLVMCreateBuilderInContext, LLVMDeleteBasicBlock, LLVMDisposeBuilder,
+        LLVMDisposeMemoryBuffer, LLVMGetFirstBasicBlock, LLVMGetFirstFunction, LLVMGetFirstGlobal,
+        LLVMGetFirstInstruction, LLVMGetFirstUse, LLVMGetModuleContext, LLVMGetNamedFunction,
+        LLVMGetNextBasicBlock, LLVMGetNextFunction, LLVMGetNextGlobal, LLVMGetNextInstruction,
+        LLVMGetParam, LLVMGetReturnType, LLVMGetTypeKind, LLVMGetUndef, LLVMGetValueName2,
+        LLVMGlobalGetValueType, LLVMInstructionEraseFromParent, LLVMIsDeclaration,
+        LLVMPositionBuilderAtEnd, LLVMReplaceAllUsesWith, LLVMSetInitializer, LLVMSetTailCall,
+        LLVMSetValueName2, LLVMTypeOf,
+    },
+    error::{LLVMDisposeErrorMessage, LLVMErrorRef, LLVMGetErrorMessage},
+    orc2::{
+        lljit::{
+            LLVMOrcCreateLLJIT, LLVMOrcDisposeLLJIT, LLVMOrcLLJITAddLLVMIRModule,
+            LLVMOrcLLJITGetExecutionSession, LLVMOrcLLJITGetIRTransformLayer,
+            LLVMOrcLLJITGetMainJITDylib, LLVMOrcLLJITGetTripleString, LLVMOrcLLJITLookup,
+            LLVMOrcLLJITMangleAndIntern, LLVMOrcLLJITRef,
+        },
+        LLVMJITEvaluatedSymbol, LLVMJITSymbolFlags, LLVMJITSymbolGenericFlags,
+        LLVMOrcAbsoluteSymbols, LLVMOrcCSymbolAliasMapEntry, LLVMOrcCSymbolAliasMapPair,
+        LLVMOrcCSymbolMapPair, LLVMOrcCreateLocalIndirectStubsManager,
+        LLVMOrcCreateLocalLazyCallThroughManager, LLVMOrcCreateNewThreadSafeContext,
+        LLVMOrcCreateNewThreadSafeModule, LLVMOrcDisposeIndirectStubsManager,
+        LLVMOrcDisposeLazyCallThroughManager, LLVMOrcDisposeThreadSafeContext,
+        LLVMOrcIRTransformLayerSetTransform, LLVMOrcIndirectStubsManagerRef, LLVMOrcJITDylibDefine,
+        LLVMOrcLazyCallThroughManagerRef, LLVMOrcLazyReexports,
+        LLVMOrcMaterializationResponsibilityRef, LLVMOrcThreadSafeContextGetContext,
+        LLVMOrcThreadSafeModuleRef,
+    },
+    prelude::{LLVMBuilderRef, LLVMMemoryBufferRef, LLVMModuleRef, LLVMValueRef},
+    LLVMTypeKind,
+};
+
+use crate::errors::CompilerError;
+
+use super::{module::LLModule, LLVM};
+
+/// Suffix of the symbols function bodies are renamed to when they are called through a lazy stub or a trampoline.
+const BODY_SUFFIX: &str = ".body";
+
+/// Suffix of the globals holding the address patchable functions jump to.
+const PATCHABLE_SLOT_SUFFIX: &str = ".slot";
+
+/// A wrapper for the LLVM ORC LLJIT.
+///
+/// # Ownership
+/// Owns the modules added to it and the symbols defined in its main JITDylib.
+///
+/// The stubs and call-through managers used for lazy compilation are referenced by the JIT so they are freed after it.
+///
+/// - https://llvm.org/docs/ORCv2.html
+#[derive(Debug)]
+pub(crate) struct LLJit {
+    jit_ref: LLVMOrcLLJITRef,
+    stubs_manager: LLVMOrcIndirectStubsManagerRef,
+    call_through_manager: LLVMOrcLazyCallThroughManagerRef,
+    /// Number of modules compiled so far. It is boxed so the JIT can keep pointing to it.
+    compile_count: Box<AtomicUsize>,
+}
+
+impl LLJit {
+    /// Creates an LLJIT for the host.
+    pub(crate) fn new() -> Result<Self> {
+        LLVM::initialize_native_target();
+
+        let mut jit_ref = ptr::null_mut();
+        unsafe { check(LLVMOrcCreateLLJIT(&mut jit_ref, ptr::null_mut()))? };
+
+        // Dropping `this` on error disposes whatever has been created.
+        let mut this = Self {
+            jit_ref,
+            stubs_manager: ptr::null_mut(),
+            call_through_manager: ptr::null_mut(),
+            compile_count: Box::new(AtomicUsize::new(0)),
+        };
+
+        unsafe {
+            let triple = LLVMOrcLLJITGetTripleString(jit_ref);
+
+            this.stubs_manager = LLVMOrcCreateLocalIndirectStubsManager(triple);
+            check(LLVMOrcCreateLocalLazyCallThroughManager(
+                triple,
+                LLVMOrcLLJITGetExecutionSession(jit_ref),
+                lazy_compile_failed as *const () as usize as _,
+                &mut this.call_through_manager,
+            ))?;
+
+            // Every module passes through the transform layer right before it is compiled.
+            LLVMOrcIRTransformLayerSetTransform(
+                LLVMOrcLLJITGetIRTransformLayer(jit_ref),
+                count_compile,
+                this.compile_count.as_ref() as *const AtomicUsize as *mut c_void,
+            );
+        }
+
+        Ok(this)
+    }
+
+    /// Gets the number of modules compiled so far.
+    pub(crate) fn compile_count(&self) -> usize {
+        self.compile_count.load(Ordering::SeqCst)
+    }
+
+    /// Defines a symbol at an absolute `address` that code added later can link against.
+    ///
+    /// # Safety
+    /// The JIT does not check what lives at `address`. It must stay valid for as long as code referencing it runs.
+    pub(crate) fn define_absolute_symbol(&self, name: &str, address: usize) -> Result<()> {
+        unsafe {
+            let name = CString::new(name)?;
+            let mut pair = LLVMOrcCSymbolMapPair {
+                Name: LLVMOrcLLJITMangleAndIntern(self.jit_ref, name.as_ptr()),
+                Sym: LLVMJITEvaluatedSymbol {
+                    Address: address as _,
+                    Flags: LLVMJITSymbolFlags {
+                        GenericFlags: LLVMJITSymbolGenericFlags::LLVMJITSymbolGenericFlagsExported
+                            as u8,
+                        TargetFlags: 0,
+                    },
+                },
+            };
+
+            // The materialization unit takes ownership of the interned name.
+            let unit = LLVMOrcAbsoluteSymbols(&mut pair, 1);
+            check(LLVMOrcJITDylibDefine(
+                LLVMOrcLLJITGetMainJITDylib(self.jit_ref),
+                unit,
+            ))
+        }
+    }
+
+    /// Adds a copy of `module` to the JIT.
+    ///
+    /// The JIT needs a module it can own in a context it can lock so the module is copied over through bitcode.
+    /// This leaves the compiler's module untouched.
+    pub(crate) fn add_module(&self, module: &LLModule) -> Result<()> {
+        unsafe {
+            let buffer = LLVMWriteBitcodeToMemoryBuffer(module.as_ptr());
+            let result = copy_module(buffer, |_| Ok(())).and_then(|module| self.add(module));
+            LLVMDisposeMemoryBuffer(buffer);
+
+            result
+        }
+    }
+
+    /// Adds a copy of each function defined in `module` to the JIT in a module of its own.
+    ///
+    /// Functions are called through stubs that compile their module on the first call.
+    /// Function `name` is defined by the stub and its body is renamed to `name.body`.
+    /// Calls between functions go through the stubs as well so nothing is compiled before it is needed.
+    ///
+    /// Global variables are defined in a module of their own that is compiled along with the first function using them.
+    pub(crate) fn add_lazy_module(&self, module: &LLModule) -> Result<()> {
+        unsafe {
+            let buffer = LLVMWriteBitcodeToMemoryBuffer(module.as_ptr());
+            let result = defined_functions(module.as_ptr())
+                .iter()
+                .try_for_each(|name| {
+                    let body = format!("{}{}", name, BODY_SUFFIX);
+                    let module =
+                        copy_module(buffer, |module_ref| split_function(module_ref, name, &body))?;
+
+                    self.add(module)
+                })
+                .and_then(|_| {
+                    self.add(copy_module(buffer, |module_ref| split_globals(module_ref))?)
+                })
+                .and_then(|_| self.add_lazy_reexports(&defined_functions(module.as_ptr())));
+
+            LLVMDisposeMemoryBuffer(buffer);
+
+            result
+        }
+    }
+
+    /// Adds a copy of `module` to the JIT whose functions can be replaced with `replace_function`.
+    ///
+    /// Function `name` is defined by a trampoline that jumps to the address held by global `name.slot`,
+    /// which initially is that of its body renamed to `name.body`.
+    /// Calls between functions go through the trampolines as well so they see replacements too.
+    pub(crate) fn add_patchable_module(&self, module: &LLModule) -> Result<()> {
+        unsafe {
+            let buffer = LLVMWriteBitcodeToMemoryBuffer(module.as_ptr());
+            let result = copy_module(buffer, |module_ref| add_trampolines(module_ref))
+                .and_then(|module| self.add(module));
+            LLVMDisposeMemoryBuffer(buffer);
+
+            result
+        }
+    }
+
+    /// Redirects patchable function `name` to a copy of function `replacement` of `module`.
+    ///
+    /// The copy is added in a module of its own as `name.v{generation}`, where everything else is a declaration
+    /// linked against the symbols already in the JIT. So `module` has to reference them by the same names.
+    pub(crate) fn replace_function(
+        &self,
+        module: &LLModule,
+        replacement: &str,
+        name: &str,
+        generation: usize,
+    ) -> Result<()> {
+        let body = format!("{}.v{}", name, generation);
+        unsafe {
+            let buffer = LLVMWriteBitcodeToMemoryBuffer(module.as_ptr());
+            let result = copy_module(buffer, |module_ref| {
+                split_function(module_ref, replacement, &body)
+            })
+            .and_then(|module| self.add(module));
+            LLVMDisposeMemoryBuffer(buffer);
+
+            result?;
+        }
+
+        let address = self.lookup(&body)?;
+        let slot = self.lookup(&format!("{}{}", name, PATCHABLE_SLOT_SUFFIX))?;
+        unsafe { *(slot as *mut usize) = address };
+
+        Ok(())
+    }
+
+    /// Defines stubs for `names` that compile and call the renamed bodies.
+    unsafe fn add_lazy_reexports(&self, names: &[String]) -> Result<()> {
+        let generic_flags = LLVMJITSymbolGenericFlags::LLVMJITSymbolGenericFlagsExported as u8
+            | LLVMJITSymbolGenericFlags::LLVMJITSymbolGenericFlagsCallable as u8;
+
+        let mut aliases = vec![];
+        for name in names {
+            let stub = CString::new(name.as_str())?;
+            let body = CString::new(format!("{}{}", name, BODY_SUFFIX))?;
+            aliases.push(LLVMOrcCSymbolAliasMapPair {
+                Name: LLVMOrcLLJITMangleAndIntern(self.jit_ref, stub.as_ptr()),
+                Entry: LLVMOrcCSymbolAliasMapEntry {
+                    Name: LLVMOrcLLJITMangleAndIntern(self.jit_ref, body.as_ptr()),
+                    Flags: LLVMJITSymbolFlags {
+                        GenericFlags: generic_flags,
+                        TargetFlags: 0,
+                    },
+                },
+            });
+        }
+
+        // The materialization unit takes ownership of the interned names.
+        let main = LLVMOrcLLJITGetMainJITDylib(self.jit_ref);
+        let unit = LLVMOrcLazyReexports(
+            self.call_through_manager,
+            self.stubs_manager,
+            main,
+            aliases.as_mut_ptr(),
+            aliases.len(),
+        );
+
+        check(LLVMOrcJITDylibDefine(main, unit))
+    }
+
+    /// Adds a thread-safe module to the main JITDylib.
+    ///
+    /// Modules are only compiled once one of their symbols is looked up.
+    unsafe fn add(&self, module: LLVMOrcThreadSafeModuleRef) -> Result<()> {
+        check(LLVMOrcLLJITAddLLVMIRModule(
+            self.jit_ref,
+            LLVMOrcLLJITGetMainJITDylib(self.jit_ref),
+            module,
+        ))
+    }
+
+    /// Gets the address of the symbol with the given name, materializing it if needed.
+    pub(crate) fn lookup(&self, name: &str) -> Result<usize> {
+        let name = CString::new(name)?;
+        let mut address = 0;
+        unsafe {
+            check(LLVMOrcLLJITLookup(
+                self.jit_ref,
+                &mut address,
+                name.as_ptr(),
+            ))?
+        };
+
+        Ok(address as usize)
+    }
+}
+
+impl Drop for LLJit {
+    fn drop(&mut self) {
+        unsafe {
+            // Errors from tearing down are consumed since there is no one to report them to.
+            let _ = check(LLVMOrcDisposeLLJIT(self.jit_ref));
+
+            if !self.call_through_manager.is_null() {
+                LLVMOrcDisposeLazyCallThroughManager(self.call_through_manager);
+            }
+
+            if !self.stubs_manager.is_null() {
+                LLVMOrcDisposeIndirectStubsManager(self.stubs_manager);
+            }
+        }
+    }
+}
+
+/// Converts an LLVM error into a `CompilerError`, consuming it.
+unsafe fn check(error: LLVMErrorRef) -> Result<()> {
+    if error.is_null() {
+        return Ok(());
+    }
+
+    let message = LLVMGetErrorMessage(error);
+    let description = CStr::from_ptr(message).to_string_lossy().into_owned();
+    LLVMDisposeErrorMessage(message);
+
+    Err(CompilerError::JitError(description).into())
+}
+
+/// Parses a copy of the bitcode in `buffer` into a context of its own and lets `edit` change it.
+unsafe fn copy_module(
+    buffer: LLVMMemoryBufferRef,
+    edit: impl FnOnce(LLVMModuleRef) -> Result<()>,
+) -> Result<LLVMOrcThreadSafeModuleRef> {
+    let context = LLVMOrcCreateNewThreadSafeContext();
+
+    let mut module_ref = ptr::null_mut();
+    let failed = LLVMParseBitcodeInContext2(
+        LLVMOrcThreadSafeContextGetContext(context),
+        buffer,
+        &mut module_ref,
+    ) != 0;
+
+    let result = if failed {
+        Err(CompilerError::JitError("cannot copy module".to_string()).into())
+    } else {
+        edit(module_ref)
+    };
+
+    if let Err(err) = result {
+        // The module is owned by the context.
+        LLVMOrcDisposeThreadSafeContext(context);
+        return Err(err);
+    }
+
+    // The thread-safe module keeps its own reference to the context.
+    let module = LLVMOrcCreateNewThreadSafeModule(module_ref, context);
+    LLVMOrcDisposeThreadSafeContext(context);
+
+    Ok(module)
+}
+
+/// Gets the names of the functions with a body in the module.
+unsafe fn defined_functions(module_ref: LLVMModuleRef) -> Vec<String> {
+    let mut names = vec![];
+    let mut function = LLVMGetFirstFunction(module_ref);
+    while !function.is_null() {
+        if LLVMIsDeclaration(function) == 0 {
+            let mut length = 0;
+            let name = LLVMGetValueName2(function, &mut length);
+            let name = std::slice::from_raw_parts(name as *const u8, length);
+            names.push(String::from_utf8_lossy(name).into_owned());
+        }
+
+        function = LLVMGetNextFunction(function);
+    }
+
+    names
+}
+
+/// Turns everything in the module except function `name` into declarations and renames `name` to `body`.
+unsafe fn split_function(module_ref: LLVMModuleRef, name: &str, body: &str) -> Result<()> {
+    let kept = LLVMGetNamedFunction(module_ref, CString::new(name)?.as_ptr());
+
+    let mut function = LLVMGetFirstFunction(module_ref);
+    while !function.is_null() {
+        if function != kept && LLVMIsDeclaration(function) == 0 {
+            delete_body(function);
+        }
+
+        function = LLVMGetNextFunction(function);
+    }
+
+    // A null initializer turns a global variable into a declaration.
+    let mut global = LLVMGetFirstGlobal(module_ref);
+    while !global.is_null() {
+        LLVMSetInitializer(global, ptr::null_mut());
+        global = LLVMGetNextGlobal(global);
+    }
+
+    LLVMSetValueName2(kept, body.as_ptr() as *const _, body.len());
+
+    Ok(())
+}
+
+/// Puts a trampoline jumping through a patchable slot in front of every function defined in the module.
+unsafe fn add_trampolines(module_ref: LLVMModuleRef) -> Result<()> {
+    let builder = LLVMCreateBuilderInContext(LLVMGetModuleContext(module_ref));
+    let result = defined_functions(module_ref)
+        .iter()
+        .try_for_each(|name| add_trampoline(module_ref, builder, name));
+    LLVMDisposeBuilder(builder);
+
+    result
+}
+
+/// Renames function `name` to its patchable body and defines `name` as a tail call through the body's slot.
+unsafe fn add_trampoline(
+    module_ref: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+    name: &str,
+) -> Result<()> {
+    let c_name = CString::new(name)?;
+    let body = LLVMGetNamedFunction(module_ref, c_name.as_ptr());
+    let function_type = LLVMGlobalGetValueType(body);
+    let pointer_type = LLVMTypeOf(body);
+
+    let body_name = format!("{}{}", name, BODY_SUFFIX);
+    LLVMSetValueName2(body, body_name.as_ptr() as *const _, body_name.len());
+
+    // Existing calls are redirected before the slot becomes a use of the body itself.
+    let trampoline = LLVMAddFunction(module_ref, c_name.as_ptr(), function_type);
+    LLVMReplaceAllUsesWith(body, trampoline);
+
+    let slot_name = CString::new(format!("{}{}", name, PATCHABLE_SLOT_SUFFIX))?;
+    let slot = LLVMAddGlobal(module_ref, pointer_type, slot_name.as_ptr());
+    LLVMSetInitializer(slot, body);
+
+    let context = LLVMGetModuleContext(module_ref);
+    let entry = LLVMAppendBasicBlockInContext(context, trampoline, CString::new("entry")?.as_ptr());
+    LLVMPositionBuilderAtEnd(builder, entry);
+
+    let unnamed = CString::new("")?;
+    let target = LLVMBuildLoad2(builder, pointer_type, slot, unnamed.as_ptr());
+    let mut args = (0..LLVMCountParams(trampoline))
+        .map(|index| LLVMGetParam(trampoline, index))
+        .collect::<Vec<_>>();
+    let call = LLVMBuildCall2(
+        builder,
+        function_type,
+        target,
+        args.as_mut_ptr(),
+        args.len() as u32,
+        unnamed.as_ptr(),
+    );
+    LLVMSetTailCall(call, 1);
+
+    if LLVMGetTypeKind(LLVMGetReturnType(function_type)) == LLVMTypeKind::LLVMVoidTypeKind {
+        LLVMBuildRetVoid(builder);
+    } else {
+        LLVMBuildRet(builder, call);
+    }
+
+    Ok(())
+}
+
+/// Turns every function of the module into a declaration, leaving only global variables defined.
+unsafe fn split_globals(module_ref: LLVMModuleRef) -> Result<()> {
+    let mut function = LLVMGetFirstFunction(module_ref);
+    while !function.is_null() {
+        if LLVMIsDeclaration(function) == 0 {
+            delete_body(function);
+        }
+
+        function = LLVMGetNextFunction(function);
+    }
+
+    Ok(())
+}
+
+/// Deletes the basic blocks of a function, leaving an external declaration.
+///
+/// Instructions can use each other across blocks so all uses are dropped before anything is erased.
+unsafe fn delete_body(function: LLVMValueRef) {
+    let mut block = LLVMGetFirstBasicBlock(function);
+    while !block.is_null() {
+        let mut instruction = LLVMGetFirstInstruction(block);
+        while !instruction.is_null() {
+            if !LLVMGetFirstUse(instruction).is_null() {
+                LLVMReplaceAllUsesWith(instruction, LLVMGetUndef(LLVMTypeOf(instruction)));
+            }
+
+            instruction = LLVMGetNextInstruction(instruction);
+        }
+
+        block = LLVMGetNextBasicBlock(block);
+    }
+
+    // Blocks are only used by terminators, which go first.
+    let mut block = LLVMGetFirstBasicBlock(function);
+    while !block.is_null() {
+        while !LLVMGetFirstInstruction(block).is_null() {
+            LLVMInstructionEraseFromParent(LLVMGetFirstInstruction(block));
+        }
+
+        block = LLVMGetNextBasicBlock(block);
+    }
+
+    while !LLVMGetFirstBasicBlock(function).is_null() {
+        LLVMDeleteBasicBlock(LLVMGetFirstBasicBlock(function));
+    }
+}
+
+/// Counts the modules the JIT compiles, leaving them unchanged.
+extern "C" fn count_compile(
+    count: *mut c_void,
+    _module: *mut LLVMOrcThreadSafeModuleRef,
+    _responsibility: LLVMOrcMaterializationResponsibilityRef,
+) -> LLVMErrorRef {
+    unsafe { (*(count as *const AtomicUsize)).fetch_add(1, Ordering::SeqCst) };
+    ptr::null_mut()
+}
+
+/// Called by a lazy stub when the function behind it cannot be compiled.
+///
+/// There is no caller to return an error to so this aborts.
+extern "C" fn lazy_compile_failed() {
+    eprintln!("wasmo: lazy compilation of a function failed");
+    process::abort();
+}
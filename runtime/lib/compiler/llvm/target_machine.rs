@@ -1,3 +1,139 @@
+use std::{
+    ffi::{CStr, CString},
+    ptr, slice,
+};
+
+use anyhow::Result;
+use llvm_sys::{
+    core::{
+        LLVMDisposeMemoryBuffer, LLVMDisposeMessage, LLVMGetBufferSize, LLVMGetBufferStart,
+        LLVMSetTarget,
+    },
+    target::{LLVMDisposeTargetData, LLVMSetModuleDataLayout, LLVMTargetDataRef},
+    target_machine::{
+        LLVMCodeGenFileType, LLVMCodeGenOptLevel, LLVMCodeModel, LLVMCreateTargetDataLayout,
+        LLVMCreateTargetMachine, LLVMDisposeTargetMachine, LLVMGetDefaultTargetTriple,
+        LLVMGetTargetFromTriple, LLVMRelocMode, LLVMTargetMachineEmitToMemoryBuffer,
+        LLVMTargetMachineRef,
+    },
+};
+
+use crate::errors::CompilerError;
+
+use super::{module::LLModule, LLVM};
+
+/// A wrapper for LLVM Target Machine, which generates object code ahead of time.
+///
+/// # Ownership
+/// Owns the target machine, which is disposed of on drop.
+///
+/// - https://llvm.org/doxygen/classllvm_1_1TargetMachine.html
+#[derive(Debug)]
 pub(crate) struct LLTargetMachine {
     target_machine: LLVMTargetMachineRef,
+    triple: CString,
+}
+
+impl LLTargetMachine {
+    /// Creates a target machine for the host's target triple.
+    ///
+    /// The code it generates is position-independent and runs on any CPU of the triple so that it can be distributed.
+    pub(crate) fn new() -> Result<Self> {
+        let triple = unsafe {
+            let triple = LLVMGetDefaultTargetTriple();
+            let owned_triple = CStr::from_ptr(triple).to_string_lossy().into_owned();
+            LLVMDisposeMessage(triple);
+            owned_triple
+        };
+
+        Self::with_triple(&triple)
+    }
+
+    /// Creates a target machine for `triple`, like `i686-unknown-linux-gnu` for 32-bit code on an x86-64 host.
+    ///
+    /// Only the target of the host is initialized, so the triple must be for the host's architecture family.
+    pub(crate) fn with_triple(triple: &str) -> Result<Self> {
+        LLVM::initialize_native_target();
+
+        let owned_triple = CString::new(triple)?;
+        unsafe {
+            let mut target = ptr::null_mut();
+            let mut message = ptr::null_mut();
+            if LLVMGetTargetFromTriple(owned_triple.as_ptr(), &mut target, &mut message) != 0 {
+                return Err(take_error(message));
+            }
+
+            let empty = CString::new("")?;
+            let target_machine = LLVMCreateTargetMachine(
+                target,
+                owned_triple.as_ptr(),
+                empty.as_ptr(),
+                empty.as_ptr(),
+                LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault,
+                LLVMRelocMode::LLVMRelocPIC,
+                LLVMCodeModel::LLVMCodeModelDefault,
+            );
+
+            Ok(Self {
+                target_machine,
+                triple: owned_triple,
+            })
+        }
+    }
+
+    /// Generates an object file for `module`, setting the module's target to that of the machine first.
+    pub(crate) fn emit_object(&self, module: &LLModule) -> Result<Vec<u8>> {
+        unsafe {
+            LLVMSetTarget(module.as_ptr(), self.triple.as_ptr());
+
+            let data_layout = self.create_data_layout();
+            LLVMSetModuleDataLayout(module.as_ptr(), data_layout);
+            LLVMDisposeTargetData(data_layout);
+
+            let mut buffer = ptr::null_mut();
+            let mut message = ptr::null_mut();
+            if LLVMTargetMachineEmitToMemoryBuffer(
+                self.target_machine,
+                module.as_ptr(),
+                LLVMCodeGenFileType::LLVMObjectFile,
+                &mut message,
+                &mut buffer,
+            ) != 0
+            {
+                return Err(take_error(message));
+            }
+
+            let object = slice::from_raw_parts(
+                LLVMGetBufferStart(buffer) as *const u8,
+                LLVMGetBufferSize(buffer),
+            )
+            .to_vec();
+            LLVMDisposeMemoryBuffer(buffer);
+
+            Ok(object)
+        }
+    }
+}
+
+impl LLTargetMachine {
+    /// Creates the layout of data on the target, which the caller must dispose of.
+    pub(super) unsafe fn create_data_layout(&self) -> LLVMTargetDataRef {
+        LLVMCreateTargetDataLayout(self.target_machine)
+    }
+}
+
+impl Drop for LLTargetMachine {
+    fn drop(&mut self) {
+        unsafe {
+            LLVMDisposeTargetMachine(self.target_machine);
+        }
+    }
+}
+
+/// Converts an error message from LLVM into a `CompilerError`, disposing of the message.
+unsafe fn take_error(message: *mut libc::c_char) -> anyhow::Error {
+    let description = CStr::from_ptr(message).to_string_lossy().into_owned();
+    LLVMDisposeMessage(message);
+
+    CompilerError::CodegenFailed(description).into()
 }
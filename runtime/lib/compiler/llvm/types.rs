@@ -1,29 +1,61 @@
+use std::{ffi::CString, mem};
+
+use anyhow::Result;
+
 use llvm_sys::{
     core::{
-        LLVMDoubleTypeInContext, LLVMFloatTypeInContext, LLVMFunctionType, LLVMInt128TypeInContext,
-        LLVMInt32TypeInContext, LLVMInt64TypeInContext, LLVMStructType, LLVMVoidTypeInContext,
+        LLVMArrayType, LLVMConstInt, LLVMConstNull, LLVMConstReal, LLVMDoubleTypeInContext,
+        LLVMFloatTypeInContext, LLVMFunctionType, LLVMGetElementType, LLVMGetIntTypeWidth,
+        LLVMGetTypeKind, LLVMInt128TypeInContext, LLVMInt16TypeInContext, LLVMInt1TypeInContext,
+        LLVMInt32TypeInContext, LLVMInt64TypeInContext, LLVMInt8TypeInContext, LLVMPointerType,
+        LLVMStructCreateNamed, LLVMStructSetBody, LLVMStructTypeInContext, LLVMVectorType,
+        LLVMVoidTypeInContext,
     },
     prelude::LLVMTypeRef,
+    LLVMTypeKind,
 };
 
-use super::context::LLContext;
+use super::{
+    context::LLContext,
+    values::{LLConstFloat, LLConstInt, LLConstNull},
+};
 
-/// This is based on wasm num and vector types.
+/// This is based on wasm num, vector and reference types.
 pub(crate) enum LLNumTypeKind {
+    I1,
+    I8,
+    I16,
     I32,
     I64,
     I128,
     F32,
     F64,
+    FuncRef,
+    ExternRef,
 }
 
-/// Wrapper for LLVM number types (e.g. i64, f32) which is based on wasm num and vector types.
+/// Address space of externref pointers.
+///
+/// Reference types are opaque pointers that are never dereferenced.
+/// They live in different address spaces so that a funcref and an externref never have the same LLVM type.
+/// The address spaces are the ones used by the LLVM WebAssembly backend.
+///
+/// - https://llvm.org/doxygen/WebAssembly_8h_source.html
+pub(crate) const EXTERNREF_ADDRESS_SPACE: u32 = 10;
+
+/// Address space of funcref pointers.
+///
+/// See [`EXTERNREF_ADDRESS_SPACE`](constant.EXTERNREF_ADDRESS_SPACE.html)
+pub(crate) const FUNCREF_ADDRESS_SPACE: u32 = 20;
+
+/// Wrapper for LLVM number types (e.g. i64, f32) which is based on wasm num, vector and reference types.
 ///
 /// # Safety
 /// Only a kind of each `LLVMTypeRef` is ever created. They are singletons and are never freed.
 ///
 /// - https://llvm.org/doxygen/classllvm_1_1Type.html#details
 /// - https://llvm.org/docs/LangRef.html#integer-type
+#[derive(Debug, Clone, Copy)]
 pub(crate) struct LLNumType(LLVMTypeRef);
 
 /// Wrapper for LLVM pointer types (e.g. i64*, [2 x double]*).
@@ -84,6 +116,7 @@ pub(crate) enum LLResultType {
     Void(LLVoidType),
     Num(LLNumType),
     Struct(LLStructType),
+    Vector(LLVectorType),
 }
 
 impl LLNumType {
@@ -96,17 +129,113 @@ impl LLNumType {
         let context_ref = unsafe { context.as_ptr() };
         let type_ref = unsafe {
             match kind {
+                I1 => LLVMInt1TypeInContext(context_ref),
+                I8 => LLVMInt8TypeInContext(context_ref),
+                I16 => LLVMInt16TypeInContext(context_ref),
                 I32 => LLVMInt32TypeInContext(context_ref),
                 I64 => LLVMInt64TypeInContext(context_ref),
                 I128 => LLVMInt128TypeInContext(context_ref),
                 F32 => LLVMFloatTypeInContext(context_ref),
                 F64 => LLVMDoubleTypeInContext(context_ref),
+                FuncRef => LLPointerType::new(&context.i8_type(), FUNCREF_ADDRESS_SPACE).0,
+                ExternRef => LLPointerType::new(&context.i8_type(), EXTERNREF_ADDRESS_SPACE).0,
             }
         };
 
         Self(type_ref)
     }
 
+    /// Creates an integer constant of this type.
+    ///
+    /// `value` is truncated to the width of the type.
+    pub(crate) fn const_int(&self, value: u64) -> LLConstInt {
+        LLConstInt(unsafe { LLVMConstInt(self.0, value, 0) })
+    }
+
+    /// Creates a floating-point constant of this type.
+    pub(crate) fn const_float(&self, value: f64) -> LLConstFloat {
+        LLConstFloat(unsafe { LLVMConstReal(self.0, value) })
+    }
+
+    /// Gets the type of pointers to values of this type in the default address space.
+    pub(crate) fn pointer_type(&self) -> LLNumType {
+        LLPointerType::new(self, 0).into()
+    }
+
+    /// Gets the bit width of this type, which must be an integer type.
+    pub(crate) fn int_width(&self) -> u32 {
+        unsafe { LLVMGetIntTypeWidth(self.0) }
+    }
+
+    /// Gets the size of this type in bytes, which must be an integer, float or pointer type.
+    ///
+    /// Pointers are the size of those of the host, which the code is compiled for.
+    pub(crate) fn byte_size(&self) -> u64 {
+        match unsafe { LLVMGetTypeKind(self.0) } {
+            LLVMTypeKind::LLVMFloatTypeKind => 4,
+            LLVMTypeKind::LLVMDoubleTypeKind => 8,
+            LLVMTypeKind::LLVMPointerTypeKind => mem::size_of::<usize>() as u64,
+            _ => self.int_width() as u64 / 8,
+        }
+    }
+
+    /// Wraps a type that already exists, which must be an integer, float or pointer type.
+    pub(super) unsafe fn from_ptr(type_ref: LLVMTypeRef) -> Self {
+        Self(type_ref)
+    }
+
+    /// Creates a zero constant of this type.
+    pub(crate) fn zero(&self) -> LLConstNull {
+        LLConstNull(unsafe { LLVMConstNull(self.0) })
+    }
+
+    pub(crate) unsafe fn as_ptr(&self) -> LLVMTypeRef {
+        self.0
+    }
+}
+
+impl LLPointerType {
+    /// Creates the type of pointers to values of type `pointee` in `address_space`.
+    pub(crate) fn new(pointee: &LLNumType, address_space: u32) -> Self {
+        Self(unsafe { LLVMPointerType(pointee.0, address_space) })
+    }
+
+    /// Gets the type of the values the pointers point to.
+    pub(crate) fn pointee_type(&self) -> LLNumType {
+        LLNumType(unsafe { LLVMGetElementType(self.0) })
+    }
+}
+
+impl From<LLPointerType> for LLNumType {
+    fn from(ty: LLPointerType) -> Self {
+        Self(ty.0)
+    }
+}
+
+impl LLVectorType {
+    /// Creates the type of vectors of `count` elements of type `element`.
+    ///
+    /// `element` must be an integer, float or pointer type and `count` must not be zero.
+    pub(crate) fn new(element: &LLNumType, count: u32) -> Self {
+        Self(unsafe { LLVMVectorType(element.0, count) })
+    }
+
+    pub(crate) unsafe fn as_ptr(&self) -> LLVMTypeRef {
+        self.0
+    }
+}
+
+impl LLArrayType {
+    /// Creates the type of arrays of `count` elements of type `element`.
+    pub(crate) fn new(element: &LLNumType, count: u32) -> Self {
+        Self(unsafe { LLVMArrayType(element.0, count) })
+    }
+
+    /// Wraps a type that already exists, which must be an array type.
+    pub(super) unsafe fn from_ptr(type_ref: LLVMTypeRef) -> Self {
+        Self(type_ref)
+    }
+
     pub(crate) unsafe fn as_ptr(&self) -> LLVMTypeRef {
         self.0
     }
@@ -127,18 +256,19 @@ impl LLVoidType {
 }
 
 impl LLStructType {
-    /// Creates a new LLVM array type.
+    /// Creates a new LLVM struct type.
     ///
     /// # Safety
     /// See [LLStructType](struct.LLStructType.html) for safety.
-    pub(crate) fn new(types: &[LLNumType], is_packed: bool) -> Self {
+    pub(crate) fn new(context: &LLContext, types: &[LLNumType], is_packed: bool) -> Self {
         let types = types
             .iter()
             .map(|p| unsafe { p.as_ptr() })
             .collect::<Vec<_>>();
 
         Self(unsafe {
-            LLVMStructType(
+            LLVMStructTypeInContext(
+                context.as_ptr(),
                 types.as_ptr() as *mut LLVMTypeRef,
                 types.len() as u32,
                 is_packed as i32,
@@ -146,6 +276,40 @@ impl LLStructType {
         })
     }
 
+    /// Creates a new LLVM struct type named `name` without a body.
+    ///
+    /// The body can be set later with [`set_body`](#method.set_body), so the struct can have fields that point to it.
+    ///
+    /// # Safety
+    /// See [LLStructType](struct.LLStructType.html) for safety.
+    pub(crate) fn new_named(context: &LLContext, name: &str) -> Result<Self> {
+        Ok(Self(unsafe {
+            LLVMStructCreateNamed(context.as_ptr(), CString::new(name)?.as_ptr())
+        }))
+    }
+
+    /// Sets the fields of a named struct type created without a body.
+    pub(crate) fn set_body(&mut self, types: &[LLNumType], is_packed: bool) {
+        let types = types
+            .iter()
+            .map(|p| unsafe { p.as_ptr() })
+            .collect::<Vec<_>>();
+
+        unsafe {
+            LLVMStructSetBody(
+                self.0,
+                types.as_ptr() as *mut LLVMTypeRef,
+                types.len() as u32,
+                is_packed as i32,
+            )
+        }
+    }
+
+    /// Gets the type of pointers to structs of this type in the default address space.
+    pub(crate) fn pointer_type(&self) -> LLNumType {
+        LLNumType(unsafe { LLVMPointerType(self.0, 0) })
+    }
+
     pub(super) unsafe fn as_ptr(&self) -> LLVMTypeRef {
         self.0
     }
@@ -171,6 +335,11 @@ impl LLFunctionType {
         })
     }
 
+    /// Gets the type of pointers to functions of this type, which indirect calls go through.
+    pub(crate) fn pointer_type(&self) -> LLNumType {
+        LLNumType(unsafe { LLVMPointerType(self.0, 0) })
+    }
+
     pub(super) unsafe fn as_ptr(&self) -> LLVMTypeRef {
         self.0
     }
@@ -183,6 +352,7 @@ impl LLResultType {
             Void(v) => v.as_ptr(),
             Num(n) => n.as_ptr(),
             Struct(s) => s.as_ptr(),
+            Vector(v) => v.as_ptr(),
         }
     }
 }
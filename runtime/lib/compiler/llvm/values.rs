@@ -0,0 +1,398 @@
+use llvm_sys::{
+    core::{
+        LLVMAddCase, LLVMAddIncoming, LLVMGetTypeKind, LLVMSetAlignment, LLVMSetGlobalConstant,
+        LLVMSetInitializer, LLVMTypeOf,
+    },
+    prelude::LLVMValueRef,
+    LLVMTypeKind,
+};
+
+use super::{
+    basic_block::LLBasicBlock,
+    types::{LLArrayType, LLNumType},
+};
+
+/// Common interface of the LLVM value wrappers.
+///
+/// # Safety
+/// Values are owned by the basic block, function or context they are created in.
+/// Wrappers only hold a reference to them and never free them.
+///
+/// - https://llvm.org/doxygen/classllvm_1_1Value.html#details
+pub(crate) trait LLValue {
+    /// Gets the underlying LLVM value.
+    ///
+    /// # Safety
+    /// The pointer must not outlive the context the value is created in.
+    unsafe fn as_ptr(&self) -> LLVMValueRef;
+
+    /// Checks if the value has a pointer type.
+    fn is_pointer_type(&self) -> bool {
+        unsafe { LLVMGetTypeKind(LLVMTypeOf(self.as_ptr())) == LLVMTypeKind::LLVMPointerTypeKind }
+    }
+
+    /// Checks if the value is of type `ty`.
+    ///
+    /// LLVM types are uniqued within a context so they can be compared by address.
+    fn has_type(&self, ty: &LLNumType) -> bool {
+        unsafe { LLVMTypeOf(self.as_ptr()) == ty.as_ptr() }
+    }
+}
+
+/// Implements a value trait for a list of single-field value wrappers.
+macro_rules! impl_trait {
+    ($trait:ident { $($ty:ident),* $(,)? }) => {
+        $(
+            impl $trait for $ty {
+                unsafe fn as_ptr(&self) -> LLVMValueRef {
+                    self.0
+                }
+            }
+        )*
+    };
+}
+
+/// Wrapper for LLVM integer constants.
+///
+/// - https://llvm.org/docs/LangRef.html#simple-constants
+pub(crate) struct LLConstInt(pub(super) LLVMValueRef);
+
+/// Wrapper for LLVM floating-point constants.
+///
+/// - https://llvm.org/docs/LangRef.html#simple-constants
+pub(crate) struct LLConstFloat(pub(super) LLVMValueRef);
+
+/// Wrapper for LLVM zero-initialized constants of any type.
+///
+/// - https://llvm.org/docs/LangRef.html#complex-constants
+pub(crate) struct LLConstNull(pub(super) LLVMValueRef);
+
+/// Wrapper for LLVM constant arrays, which include constant strings of bytes.
+///
+/// - https://llvm.org/docs/LangRef.html#complex-constants
+pub(crate) struct LLConstArray(pub(super) LLVMValueRef);
+
+/// Wrapper for LLVM function parameters.
+pub(crate) struct LLParam(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `alloca` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#alloca-instruction
+pub(crate) struct LLAlloca(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `load` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#load-instruction
+pub(crate) struct LLLoad(pub(super) LLVMValueRef);
+
+/// Wrapper for an LLVM `store` instruction, which has no result.
+///
+/// - https://llvm.org/docs/LangRef.html#store-instruction
+pub(crate) struct LLStore(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `icmp` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#icmp-instruction
+pub(crate) struct LLIntCmp(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fcmp` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fcmp-instruction
+pub(crate) struct LLFloatCmp(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `zext` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#zext-to-instruction
+pub(crate) struct LLIntZExt(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `sext` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#sext-to-instruction
+pub(crate) struct LLIntSExt(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `trunc` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#trunc-to-instruction
+pub(crate) struct LLIntTrunc(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fptosi` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fptosi-to-instruction
+pub(crate) struct LLFPToSI(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fptoui` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fptoui-to-instruction
+pub(crate) struct LLFPToUI(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `sitofp` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#sitofp-to-instruction
+pub(crate) struct LLSIToFP(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `uitofp` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#uitofp-to-instruction
+pub(crate) struct LLUIToFP(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fadd` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fadd-instruction
+pub(crate) struct LLFloatAdd(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fsub` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fsub-instruction
+pub(crate) struct LLFloatSub(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fmul` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fmul-instruction
+pub(crate) struct LLFloatMul(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fdiv` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fdiv-instruction
+pub(crate) struct LLFloatDiv(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `fneg` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#fneg-instruction
+pub(crate) struct LLFloatNeg(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `sub` instruction subtracting from zero, which is what `LLVMBuildNeg` builds.
+///
+/// - https://llvm.org/docs/LangRef.html#sub-instruction
+pub(crate) struct LLIntNeg(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `xor` instruction with all ones, which is what `LLVMBuildNot` builds.
+///
+/// - https://llvm.org/docs/LangRef.html#xor-instruction
+pub(crate) struct LLIntNot(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `add` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#add-instruction
+pub(crate) struct LLIntAdd(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `sub` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#sub-instruction
+pub(crate) struct LLIntSub(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `mul` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#mul-instruction
+pub(crate) struct LLIntMul(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `sdiv` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#sdiv-instruction
+pub(crate) struct LLSDiv(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `udiv` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#udiv-instruction
+pub(crate) struct LLUDiv(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `srem` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#srem-instruction
+pub(crate) struct LLSRem(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `urem` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#urem-instruction
+pub(crate) struct LLURem(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `and` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#and-instruction
+pub(crate) struct LLAnd(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `or` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#or-instruction
+pub(crate) struct LLOr(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `xor` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#xor-instruction
+pub(crate) struct LLXor(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `shl` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#shl-instruction
+pub(crate) struct LLShl(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `ashr` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#ashr-instruction
+pub(crate) struct LLAShr(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `lshr` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#lshr-instruction
+pub(crate) struct LLLShr(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `select` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#select-instruction
+pub(crate) struct LLSelect(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `call` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#call-instruction
+pub(crate) struct LLCall(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `extractvalue` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#extractvalue-instruction
+pub(crate) struct LLExtractValue(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `insertvalue` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#insertvalue-instruction
+pub(crate) struct LLInsertValue(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `getelementptr` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#getelementptr-instruction
+pub(crate) struct LLGep(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `addrspacecast` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#addrspacecast-to-instruction
+pub(crate) struct LLAddrSpaceCast(pub(super) LLVMValueRef);
+
+/// Wrapper for the result of an LLVM `bitcast` instruction.
+///
+/// - https://llvm.org/docs/LangRef.html#bitcast-to-instruction
+pub(crate) struct LLBitCast(pub(super) LLVMValueRef);
+
+/// Wrapper for an LLVM `switch` instruction, which terminates its block.
+///
+/// - https://llvm.org/docs/LangRef.html#switch-instruction
+pub(crate) struct LLSwitch(pub(super) LLVMValueRef);
+
+/// Wrapper for an LLVM `phi` instruction, which takes the value coming from the block control flow came from.
+///
+/// - https://llvm.org/docs/LangRef.html#phi-instruction
+pub(crate) struct LLPhi(pub(super) LLVMValueRef);
+
+impl LLLoad {
+    /// Sets the alignment in bytes the loaded address is assumed to have.
+    pub(crate) fn set_alignment(&self, bytes: u32) {
+        unsafe { LLVMSetAlignment(self.0, bytes) }
+    }
+}
+
+impl LLStore {
+    /// Sets the alignment in bytes the stored address is assumed to have.
+    pub(crate) fn set_alignment(&self, bytes: u32) {
+        unsafe { LLVMSetAlignment(self.0, bytes) }
+    }
+}
+
+impl LLPhi {
+    /// Adds the values the phi takes when coming from each of the blocks they are paired with.
+    ///
+    /// A predecessor branching to the phi's block in several ways, like a switch with several cases, needs an entry
+    /// for each of them.
+    pub(crate) fn add_incoming(&mut self, values: &[(&dyn LLValue, &LLBasicBlock)]) {
+        let (mut values, mut blocks): (Vec<_>, Vec<_>) = values
+            .iter()
+            .map(|(value, block)| unsafe { (value.as_ptr(), block.as_ptr()) })
+            .unzip();
+
+        unsafe {
+            LLVMAddIncoming(
+                self.0,
+                values.as_mut_ptr(),
+                blocks.as_mut_ptr(),
+                values.len() as u32,
+            )
+        }
+    }
+}
+
+impl LLSwitch {
+    /// Adds a case jumping to `destination` when the condition is `value`.
+    pub(crate) fn add_case(&self, value: &LLConstInt, destination: &LLBasicBlock) {
+        unsafe { LLVMAddCase(self.0, value.0, destination.as_ptr()) }
+    }
+}
+
+/// Wrapper for LLVM global variables, which are pointers to their contents.
+///
+/// - https://llvm.org/docs/LangRef.html#global-variables
+pub(crate) struct LLGlobal(pub(super) LLVMValueRef);
+
+impl LLConstArray {
+    /// Gets the array type of the constant.
+    pub(crate) fn array_type(&self) -> LLArrayType {
+        unsafe { LLArrayType::from_ptr(LLVMTypeOf(self.0)) }
+    }
+}
+
+impl LLGlobal {
+    /// Sets the value the global holds when the module is loaded, which defines the global.
+    pub(crate) fn set_initializer(&self, value: &dyn LLValue) {
+        unsafe { LLVMSetInitializer(self.0, value.as_ptr()) }
+    }
+
+    /// Sets whether the global is never written to.
+    pub(crate) fn set_constant(&self, is_constant: bool) {
+        unsafe { LLVMSetGlobalConstant(self.0, is_constant as i32) }
+    }
+}
+
+impl_trait! {
+    LLValue {
+        LLConstInt,
+        LLConstFloat,
+        LLConstNull,
+        LLConstArray,
+        LLParam,
+        LLAlloca,
+        LLLoad,
+        LLIntCmp,
+        LLFloatCmp,
+        LLIntZExt,
+        LLIntSExt,
+        LLIntTrunc,
+        LLFPToSI,
+        LLFPToUI,
+        LLSIToFP,
+        LLUIToFP,
+        LLIntAdd,
+        LLIntSub,
+        LLIntMul,
+        LLSDiv,
+        LLUDiv,
+        LLSRem,
+        LLURem,
+        LLAnd,
+        LLOr,
+        LLXor,
+        LLShl,
+        LLAShr,
+        LLLShr,
+        LLFloatAdd,
+        LLFloatSub,
+        LLFloatMul,
+        LLFloatDiv,
+        LLFloatNeg,
+        LLIntNeg,
+        LLIntNot,
+        LLSelect,
+        LLCall,
+        LLExtractValue,
+        LLInsertValue,
+        LLGep,
+        LLBitCast,
+        LLAddrSpaceCast,
+        LLPhi,
+        LLGlobal,
+    }
+}
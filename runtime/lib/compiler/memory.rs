@@ -1,18 +1,19 @@
-use crate::types::Limits;
+use crate::types::{Limits, NumType};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Memory {
-    pub is_memory_64: bool, // TODO(appcypher): Wasmo does not support memory64 proposal yet.
+    /// The type of addresses into the memory, `I64` for memories of the memory64 proposal and `I32` otherwise.
+    pub index_type: NumType,
     pub is_shared: bool,
     pub limits: Limits,
 }
 
 impl Memory {
-    pub fn new(limits: Limits, is_shared: bool) -> Self {
+    pub fn new(limits: Limits, is_shared: bool, index_type: NumType) -> Self {
         Self {
-            is_memory_64: false,
+            index_type,
             is_shared,
             limits,
         }
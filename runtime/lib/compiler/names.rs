@@ -0,0 +1,130 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use wasmparser::{ExportSectionReader, ExternalKind, Name, NameSectionReader, Parser, Payload};
+
+use crate::intrinsics::libc_symbols;
+
+/// Prefixes of the symbols the compiler generates, which names from the name section must not take.
+const RESERVED_PREFIXES: [&str; 5] = ["__", "llvm", "func_", "trampoline_", "initialize_"];
+
+/// Readable names of functions and locals from the `name` custom section, which the generated code is named after.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Names {
+    /// Symbols of the functions the section names, by function index.
+    ///
+    /// Names that are not plain identifiers or that could clash with other symbols are left out.
+    pub functions: HashMap<u32, String>,
+    /// Names of params and locals, by function index and then local index.
+    pub locals: HashMap<u32, HashMap<u32, String>>,
+}
+
+impl Names {
+    /// Gets the names from the name section of `wasm`, if it has one.
+    ///
+    /// The section comes after the code, so it is looked up before anything gets compiled. Like other custom sections,
+    /// a malformed one has no effect on the module, so it is ignored with a warning.
+    pub(crate) fn parse(wasm: &[u8]) -> Self {
+        let mut names = Self::default();
+        let mut exports = HashMap::new();
+
+        for payload in Parser::new(0).parse_all(wasm) {
+            let result = match payload {
+                Ok(Payload::ExportSection(reader)) => read_exports(reader, &mut exports),
+                Ok(Payload::CustomSection {
+                    name: "name",
+                    data,
+                    data_offset,
+                    ..
+                }) => names.read_section(data, data_offset, &exports),
+                Ok(_) => Ok(()),
+                // The error surfaces again when the module gets compiled.
+                Err(_) => return names,
+            };
+
+            if let Err(err) = result {
+                warn!("ignoring malformed name section: {}", err);
+                return Self::default();
+            }
+        }
+
+        names
+    }
+
+    /// Reads the function and local names of the name section in `data`.
+    ///
+    /// `exports` maps the names of exported functions to their indices, since exports get symbols of their own.
+    fn read_section(
+        &mut self,
+        data: &[u8],
+        offset: usize,
+        exports: &HashMap<String, u32>,
+    ) -> Result<()> {
+        let mut reader = NameSectionReader::new(data, offset)?;
+        while !reader.eof() {
+            match reader.read()? {
+                Name::Function(map) => {
+                    let mut taken = HashSet::new();
+                    let mut map = map.get_map()?;
+                    for _ in 0..map.get_count() {
+                        let naming = map.read()?;
+                        let is_exported_elsewhere = exports
+                            .get(naming.name)
+                            .is_some_and(|index| *index != naming.index);
+
+                        if is_symbol(naming.name)
+                            && !is_exported_elsewhere
+                            && taken.insert(naming.name)
+                        {
+                            self.functions.insert(naming.index, naming.name.to_string());
+                        }
+                    }
+                }
+                Name::Local(map) => {
+                    let mut map = map.get_indirect_map()?;
+                    for _ in 0..map.get_indirect_count() {
+                        let indirect = map.read()?;
+                        let locals = self.locals.entry(indirect.indirect_index).or_default();
+
+                        let mut map = indirect.get_map()?;
+                        for _ in 0..map.get_count() {
+                            let naming = map.read()?;
+                            locals.insert(naming.index, naming.name.to_string());
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the names and indices of the exported functions into `exports`.
+fn read_exports(reader: ExportSectionReader, exports: &mut HashMap<String, u32>) -> Result<()> {
+    for export in reader {
+        let export = export?;
+        if let ExternalKind::Function = export.kind {
+            exports.insert(export.field.to_string(), export.index);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks if `name` can be the symbol of a function, which takes an identifier no other symbol can have.
+fn is_symbol(name: &str) -> bool {
+    let is_identifier = name
+        .chars()
+        .all(|char| char.is_ascii_alphanumeric() || char == '_')
+        && name.starts_with(|char: char| !char.is_ascii_digit());
+
+    is_identifier
+        && !RESERVED_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        && !libc_symbols().iter().any(|(symbol, _)| *symbol == name)
+}
@@ -7,10 +7,10 @@ pub mod convert {
                 context::LLContext,
                 types::{LLFunctionType, LLNumType, LLResultType},
             },
-            DataKind, ElementKind,
+            DataKind, ElementKind, InitExpr, Memory,
         },
         errors::CompilerError,
-        types::{FuncType, NumType, RefType, ValType},
+        types::{FuncType, Limits, NumType, RefType, ValType},
     };
     use anyhow::Result;
 
@@ -45,24 +45,83 @@ pub mod convert {
         }
     }
 
+    /// Converts `wasmparser` `MemoryType` to `wasmo` `Memory`.
+    pub fn to_wasmo_memory(ty: &wasmparser::MemoryType) -> Memory {
+        let index_type = if ty.memory64 {
+            NumType::I64
+        } else {
+            NumType::I32
+        };
+
+        Memory::new(Limits::new(ty.initial, ty.maximum), ty.shared, index_type)
+    }
+
     /// Converts `wasmparser` `DataKind` to `wasmo` `DataKind`.
-    pub fn to_wasmo_data_kind(ty: &wasmparser::DataKind) -> DataKind {
-        match ty {
+    pub fn to_wasmo_data_kind(ty: &wasmparser::DataKind) -> Result<DataKind> {
+        Ok(match ty {
             wasmparser::DataKind::Passive => DataKind::Passive,
-            wasmparser::DataKind::Active { memory_index, .. } => DataKind::Active {
+            wasmparser::DataKind::Active {
+                memory_index,
+                init_expr,
+            } => DataKind::Active {
                 memory_index: *memory_index,
+                offset: to_wasmo_init_expr(init_expr)?,
             },
-        }
+        })
     }
 
     /// Converts `wasmparser` `ElementKind` to `wasmo` `ElementKind`.
-    pub fn to_wasmo_element_kind(ty: &wasmparser::ElementKind) -> ElementKind {
-        match ty {
+    pub fn to_wasmo_element_kind(ty: &wasmparser::ElementKind) -> Result<ElementKind> {
+        Ok(match ty {
             wasmparser::ElementKind::Passive => ElementKind::Passive,
             wasmparser::ElementKind::Declared => ElementKind::Declared,
-            wasmparser::ElementKind::Active { table_index, .. } => ElementKind::Active {
+            wasmparser::ElementKind::Active {
+                table_index,
+                init_expr,
+            } => ElementKind::Active {
                 table_index: *table_index,
+                offset: to_wasmo_init_expr(init_expr)?,
             },
+        })
+    }
+
+    /// Converts a `wasmparser` `ElementItem` to the index of the function it references, or `None` for a null reference.
+    pub fn to_wasmo_element_item(item: &wasmparser::ElementItem) -> Result<Option<u32>> {
+        let expr = match item {
+            wasmparser::ElementItem::Func(index) => return Ok(Some(*index)),
+            wasmparser::ElementItem::Expr(expr) => expr,
+        };
+
+        let mut reader = expr.get_operators_reader();
+        let index = match reader.read()? {
+            wasmparser::Operator::RefFunc { function_index } => Some(function_index),
+            wasmparser::Operator::RefNull { .. } => None,
+            operator => {
+                return Err(CompilerError::UnsupportedInitExpr(format!("{:?}", operator)).into())
+            }
+        };
+
+        match reader.read()? {
+            wasmparser::Operator::End => Ok(index),
+            operator => Err(CompilerError::UnsupportedInitExpr(format!("{:?}", operator)).into()),
+        }
+    }
+
+    /// Converts a `wasmparser` `InitExpr` computing a segment offset to `wasmo` `InitExpr`.
+    pub fn to_wasmo_init_expr(expr: &wasmparser::InitExpr) -> Result<InitExpr> {
+        let mut reader = expr.get_operators_reader();
+        let init_expr = match reader.read()? {
+            wasmparser::Operator::I32Const { value } => InitExpr::I32Const(value),
+            wasmparser::Operator::I64Const { value } => InitExpr::I64Const(value),
+            wasmparser::Operator::GlobalGet { global_index } => InitExpr::GlobalGet(global_index),
+            operator => {
+                return Err(CompilerError::UnsupportedInitExpr(format!("{:?}", operator)).into())
+            }
+        };
+
+        match reader.read()? {
+            wasmparser::Operator::End => Ok(init_expr),
+            operator => Err(CompilerError::UnsupportedInitExpr(format!("{:?}", operator)).into()),
         }
     }
 
@@ -74,7 +133,8 @@ pub mod convert {
             Num(NumType::I64) => ctx.i64_type(),
             Num(NumType::F32) => ctx.f32_type(),
             Num(NumType::F64) => ctx.f64_type(),
-            Ref(_) => ctx.i64_type(), // TODO(appcypher): Use ctx.target_ptr_type()
+            Ref(RefType::FuncRef) => ctx.funcref_type(),
+            Ref(RefType::ExternRef) => ctx.externref_type(),
             Vec => ctx.i128_type(),
         }
     }
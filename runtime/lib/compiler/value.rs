@@ -1,13 +1,15 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::types::{NumType, ValType};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum Value {
     Num(NumVal),
     Ref(RefVal),
     Vec(i128),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum NumVal {
     I32(i32),
     I64(i64),
@@ -15,8 +17,49 @@ pub enum NumVal {
     F64(f64),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum RefVal {
     FuncAddr(i32),
     ExternAddr(i64),
 }
+
+impl Value {
+    /// Gets the 8-byte slot a trampoline reads the value from, with the value at its start.
+    ///
+    /// Only numbers of type `ty` fit in a slot.
+    pub(crate) fn to_slot(self, ty: &ValType) -> Option<u64> {
+        let mut slot = [0; 8];
+        match (self, ty) {
+            (Value::Num(NumVal::I32(value)), ValType::Num(NumType::I32)) => {
+                slot[..4].copy_from_slice(&value.to_ne_bytes())
+            }
+            (Value::Num(NumVal::I64(value)), ValType::Num(NumType::I64)) => {
+                slot.copy_from_slice(&value.to_ne_bytes())
+            }
+            (Value::Num(NumVal::F32(value)), ValType::Num(NumType::F32)) => {
+                slot[..4].copy_from_slice(&value.to_ne_bytes())
+            }
+            (Value::Num(NumVal::F64(value)), ValType::Num(NumType::F64)) => {
+                slot.copy_from_slice(&value.to_ne_bytes())
+            }
+            _ => return None,
+        }
+
+        Some(u64::from_ne_bytes(slot))
+    }
+
+    /// Reads a value of type `ty` from the 8-byte slot a trampoline wrote it to.
+    pub(crate) fn from_slot(slot: u64, ty: &ValType) -> Option<Self> {
+        let bytes = slot.to_ne_bytes();
+        let low = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        let value = match ty {
+            ValType::Num(NumType::I32) => NumVal::I32(i32::from_ne_bytes(low)),
+            ValType::Num(NumType::I64) => NumVal::I64(i64::from_ne_bytes(bytes)),
+            ValType::Num(NumType::F32) => NumVal::F32(f32::from_ne_bytes(low)),
+            ValType::Num(NumType::F64) => NumVal::F64(f64::from_ne_bytes(bytes)),
+            _ => return None,
+        };
+
+        Some(Value::Num(value))
+    }
+}
@@ -6,8 +6,34 @@ pub enum CompilerError {
     UnsupportedExportSectionEntry(String),
     UnsupportedImportSectionEntry(String),
     UnsupportedWasmoValType(String),
-    UnsupportedMemory64Proposal,
     UnsupportedSection(String),
+    LimitExceeded(String),
+    UnresolvedImport(String),
+    IncompatibleImport(String),
+    ValueStackUnderflow,
+    IndexOutOfBounds(String),
+    InvalidModule(String),
+    TypeMismatch(String),
+    ModuleNotCompiled,
+    IRPrintFailed(String),
+    UnsupportedIntrinsic(String),
+    UnsupportedInitExpr(String),
+    UnsupportedDataSegment(String),
+    UnsupportedElementSegment(String),
+    JitError(String),
+    CodegenFailed(String),
+    IncompatiblePrecompiled(String),
+    UnknownExport(String),
+    ModuleNotPatchable,
+    UnbalancedControl(String),
+    AtomicOnUnsharedMemory(String),
+    SnapshotMismatch,
+    /// An error generating the operator at byte `offset` of the wasm binary, in the function at `function_index`.
+    AtOperator {
+        function_index: u32,
+        offset: usize,
+        error: Box<CompilerError>,
+    },
 }
 
 impl std::error::Error for CompilerError {}
@@ -17,3 +43,96 @@ impl Display for CompilerError {
         write!(f, "{:?}", self)
     }
 }
+
+/// The reasons a running WebAssembly function can trap.
+///
+/// Compiled code raises a trap by passing the discriminant of its code to the trap intrinsic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum TrapCode {
+    /// A host function returned an error or panicked.
+    HostError,
+    /// A memory access was out of the bounds of the memory.
+    OutOfBoundsMemory,
+    /// A table access was out of the bounds of the table.
+    OutOfBoundsTable,
+    /// An externref handle did not refer to a host object in the store.
+    InvalidExternRef,
+    /// An integer division overflowed, which only happens dividing the minimum signed integer by -1,
+    /// or a float converted to an integer was out of its range.
+    IntegerOverflow,
+    /// An integer division or remainder had a divisor of zero.
+    IntegerDivideByZero,
+    /// A float converted to an integer was NaN.
+    InvalidConversionToInteger,
+    /// An `unreachable` operator was executed.
+    Unreachable,
+    /// An indirect call went through a null table slot.
+    UndefinedElement,
+    /// An indirect call went through a table slot holding a function of another type than the call expects.
+    IndirectCallTypeMismatch,
+    /// The module called WASI `proc_exit`, which records its exit code in the `WasiCtx`.
+    Exit,
+}
+
+/// A trap raised by compiled code, along with where it was raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trap {
+    pub code: TrapCode,
+    /// Index of the function the trap was raised in, in the function index space.
+    pub function_index: u32,
+    /// Byte offset of the trapping operator in the wasm binary.
+    pub offset: usize,
+    /// The address of an out-of-bounds memory access, which is the address operand plus the static offset.
+    pub address: Option<u64>,
+}
+
+impl TryFrom<u32> for TrapCode {
+    type Error = u32;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        use TrapCode::*;
+        [
+            HostError,
+            OutOfBoundsMemory,
+            OutOfBoundsTable,
+            InvalidExternRef,
+            IntegerOverflow,
+            IntegerDivideByZero,
+            InvalidConversionToInteger,
+            Unreachable,
+            UndefinedElement,
+            IndirectCallTypeMismatch,
+            Exit,
+        ]
+        .into_iter()
+        .find(|trap_code| *trap_code as u32 == code)
+        .ok_or(code)
+    }
+}
+
+impl std::error::Error for TrapCode {}
+
+impl Display for TrapCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Trap {}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} in function {} at offset {}",
+            self.code, self.function_index, self.offset
+        )?;
+
+        if let Some(address) = self.address {
+            write!(f, " accessing address {}", address)?;
+        }
+
+        Ok(())
+    }
+}
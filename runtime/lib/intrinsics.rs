@@ -1,3 +1,9 @@
+mod function;
 mod memory;
+mod table;
+mod trap;
 
-pub use memory::*;
+pub(crate) use function::*;
+pub(crate) use memory::*;
+pub(crate) use table::*;
+pub use trap::*;
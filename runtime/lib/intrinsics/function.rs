@@ -0,0 +1,133 @@
+use std::{cell::Cell, ptr};
+
+use log::warn;
+
+use super::raise_trap;
+use crate::{compiler::value::Value, errors::TrapCode, types::FuncType, HostFunction, Memory};
+
+thread_local! {
+    /// Memory 0 of the instance whose code is running on this thread, or null if there is none.
+    static CALLER_MEMORY: Cell<*mut Memory> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// The symbol of the intrinsic compiled code calls imported functions through.
+///
+/// It takes the address of the `ImportedFunctions` of the instance, the `i32` index of the function and pointers to
+/// arrays of 8-byte slots, one holding the arguments and one receiving the results, laid out like those of a trampoline.
+pub(crate) const CALL_IMPORT_SYMBOL: &str = "__wasmo_call_import";
+
+/// The host functions an instance resolved its function imports to, in import order, along with their types.
+#[derive(Debug, Default)]
+pub(crate) struct ImportedFunctions<'a> {
+    functions: Vec<(&'a HostFunction, FuncType)>,
+}
+
+impl<'a> ImportedFunctions<'a> {
+    pub(crate) fn new(functions: Vec<(&'a HostFunction, FuncType)>) -> Self {
+        Self { functions }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Calls the function at `index` with arguments read from `args`, and gets the slots of its results.
+    ///
+    /// Returns the code to trap with if the function fails, which is `HostError` unless it gave one, or if its
+    /// results do not match its type.
+    ///
+    /// # Safety
+    /// `args` must point to a slot for each param of the function.
+    unsafe fn call(&self, index: u32, args: *const u64) -> Result<Vec<u64>, TrapCode> {
+        let (function, ty) = self
+            .functions
+            .get(index as usize)
+            .ok_or(TrapCode::HostError)?;
+        let args = ty
+            .params
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| Value::from_slot(*args.add(index), ty))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(TrapCode::HostError)?;
+
+        let results = match function.call(&args) {
+            Ok(results) => results,
+            Err(err) => {
+                warn!("imported function {} failed: {:?}", index, err);
+                return Err(err
+                    .downcast_ref::<TrapCode>()
+                    .copied()
+                    .unwrap_or(TrapCode::HostError));
+            }
+        };
+
+        if results.len() != ty.results.len() {
+            return Err(TrapCode::HostError);
+        }
+
+        results
+            .into_iter()
+            .zip(&ty.results)
+            .map(|(result, ty)| result.to_slot(ty))
+            .collect::<Option<_>>()
+            .ok_or(TrapCode::HostError)
+    }
+}
+
+/// Calls the imported function at `index` for compiled code, writing its results to `results`.
+///
+/// Errors of the host function and results that do not match the type of the import trap with `HostError`, unless the
+/// error is a `TrapCode` to trap with.
+///
+/// # Safety
+/// `functions` must be null or point to the `ImportedFunctions` of the running instance.
+/// `args` and `results` must point to a slot for each param and result of the function.
+pub(crate) unsafe extern "C-unwind" fn call_import(
+    functions: *const ImportedFunctions,
+    index: u32,
+    args: *const u64,
+    results: *mut u64,
+) {
+    let slots = match functions.as_ref() {
+        Some(functions) => functions.call(index, args),
+        None => Err(TrapCode::HostError),
+    };
+
+    match slots {
+        Ok(slots) => {
+            for (offset, slot) in slots.into_iter().enumerate() {
+                *results.add(offset) = slot;
+            }
+        }
+        Err(code) => raise_trap(code as u32, index, 0, 0),
+    }
+}
+
+/// Calls `f` with `memory` as the memory host functions it calls get from `with_caller_memory`.
+///
+/// The previous memory is restored afterwards, so that instances can be called from host functions.
+///
+/// # Safety
+/// `memory` must be null or valid for as long as `f` runs.
+pub(crate) unsafe fn call_with_memory<T>(memory: *mut Memory, f: impl FnOnce() -> T) -> T {
+    /// Restores the previous memory even if `f` unwinds.
+    struct Restore(*mut Memory);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CALLER_MEMORY.with(|caller_memory| caller_memory.set(self.0));
+        }
+    }
+
+    let _restore = Restore(CALLER_MEMORY.with(|caller_memory| caller_memory.replace(memory)));
+    f()
+}
+
+/// Calls `f` with memory 0 of the instance calling the host function, if it has one.
+///
+/// Host functions like those of WASI use it to read and write the buffers guests pass them by address.
+pub(crate) fn with_caller_memory<T>(f: impl FnOnce(Option<&mut Memory>) -> T) -> T {
+    let memory = CALLER_MEMORY.with(Cell::get);
+    f(unsafe { memory.as_mut() })
+}
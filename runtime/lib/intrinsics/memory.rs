@@ -1 +1,45 @@
+use crate::{types::PAGE_SIZE, Memory};
 
+/// The symbol of the intrinsic compiled code calls to grow memory 0.
+///
+/// It takes the address of the host memory, the `i64` number of pages to add, followed by the addresses of the globals
+/// holding the base address and size of the memory, which it updates. It returns the old size of the memory in pages,
+/// or -1 if the memory cannot grow.
+pub(crate) const MEMORY_GROW_SYMBOL: &str = "__wasmo_memory_grow";
+
+/// The C library functions LLVM lowers `llvm.memset`, `llvm.memcpy` and `llvm.memmove` to when it does not inline
+/// them, and their addresses in the host.
+///
+/// The JIT does not search the host process for symbols, so they have to be defined for it.
+pub(crate) fn libc_symbols() -> [(&'static str, usize); 3] {
+    [
+        ("memset", libc::memset as *const () as usize),
+        ("memcpy", libc::memcpy as *const () as usize),
+        ("memmove", libc::memmove as *const () as usize),
+    ]
+}
+
+/// Grows a memory by `delta` pages, like `memory.grow` does, calling the grow hook of the memory if it has one.
+///
+/// # Safety
+/// `memory` must be null or point to the memory the memory base and size globals at `base` and `size` describe.
+pub(crate) unsafe extern "C-unwind" fn memory_grow(
+    memory: *mut Memory,
+    delta: u64,
+    base: *mut *mut u8,
+    size: *mut u64,
+) -> u64 {
+    let memory = match memory.as_mut() {
+        Some(memory) => memory,
+        None => return u64::MAX,
+    };
+
+    match memory.grow(delta) {
+        Some(old_size) => {
+            *base = memory.as_mut_ptr();
+            *size = memory.size() * PAGE_SIZE as u64;
+            old_size
+        }
+        None => u64::MAX,
+    }
+}
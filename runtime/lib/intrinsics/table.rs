@@ -0,0 +1,63 @@
+use std::slice;
+
+/// The symbol of the intrinsic compiled code calls to grow table 0.
+///
+/// It takes the address of the slots of the table, the `i32` number of slots to add, the reference to fill them with
+/// and the `i64` maximum size of the table, followed by the addresses of the globals holding the base address and size
+/// of the table, which it updates. It returns the old size of the table, or -1 if the table cannot grow.
+pub(crate) const TABLE_GROW_SYMBOL: &str = "__wasmo_table_grow";
+
+/// The symbol of the intrinsic compiled code calls to fill slots of table 0.
+///
+/// It takes the base address of the table, the `i32` index of the first slot, the reference to fill the slots with
+/// and the `i32` number of slots. Compiled code checks that the slots are in bounds beforehand.
+pub(crate) const TABLE_FILL_SYMBOL: &str = "__wasmo_table_fill";
+
+/// Grows the slots of a table by `delta` slots set to `init`, like `table.grow` does.
+///
+/// # Safety
+/// `slots` must be null or point to the slots the table base and size globals at `base` and `size` describe.
+pub(crate) unsafe extern "C" fn table_grow(
+    slots: *mut Vec<usize>,
+    delta: u32,
+    init: usize,
+    max: u64,
+    base: *mut *mut usize,
+    size: *mut u64,
+) -> u32 {
+    let slots = match slots.as_mut() {
+        Some(slots) => slots,
+        None => return u32::MAX,
+    };
+
+    // The size is an `i32` that -1 has to stay distinct from.
+    let old_size = slots.len();
+    let new_size = old_size as u64 + delta as u64;
+    if new_size > max.min(u32::MAX as u64 - 1) {
+        return u32::MAX;
+    }
+
+    // `resize` aborts when allocation fails, whereas a failed grow has to be reported to the guest. The new size is
+    // below `u32::MAX`, so it fits in a `usize`.
+    if slots.try_reserve_exact(delta as usize).is_err() {
+        return u32::MAX;
+    }
+
+    slots.resize(old_size + delta as usize, init);
+    *base = slots.as_mut_ptr();
+    *size = new_size;
+
+    old_size as u32
+}
+
+/// Sets `count` slots of a table from `index` on to `value`, like `table.fill` does.
+///
+/// # Safety
+/// The slots must be in bounds of the table at `base`.
+pub(crate) unsafe extern "C" fn table_fill(base: *mut usize, index: u32, value: usize, count: u32) {
+    if count == 0 {
+        return;
+    }
+
+    slice::from_raw_parts_mut(base.add(index as usize), count as usize).fill(value);
+}
@@ -0,0 +1,44 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use anyhow::Result;
+
+use crate::errors::{Trap, TrapCode};
+
+/// The symbol of the intrinsic compiled code calls to trap.
+///
+/// It takes the `TrapCode`, the function index and the operator offset as `i32` arguments, followed by the `i64` address
+/// of an out-of-bounds memory access, which is ignored for other traps.
+pub(crate) const RAISE_TRAP_SYMBOL: &str = "__wasmo_raise_trap";
+
+/// Unwinds from compiled code to the closest `catch_traps` with the `Trap` as payload.
+///
+/// Compiled functions are not marked `nounwind`, so LLVM emits the unwind tables this needs.
+pub(crate) extern "C-unwind" fn raise_trap(
+    code: u32,
+    function_index: u32,
+    offset: u32,
+    address: u64,
+) -> ! {
+    let code = TrapCode::try_from(code).unwrap_or(TrapCode::HostError);
+    let trap = Trap {
+        code,
+        function_index,
+        offset: offset as usize,
+        address: (code == TrapCode::OutOfBoundsMemory).then_some(address),
+    };
+
+    panic::resume_unwind(Box::new(trap))
+}
+
+/// Calls `f`, returning a trap raised by compiled code it calls as an error.
+///
+/// The error downcasts to the `Trap` as well as to its `TrapCode`.
+///
+/// Compiled functions have to be called through `extern "C-unwind"` function pointers for traps to get here.
+/// Other panics are resumed.
+pub fn catch_traps<T>(f: impl FnOnce() -> T) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| match payload.downcast::<Trap>() {
+        Ok(trap) => anyhow::Error::new(trap.code).context(*trap),
+        Err(payload) => panic::resume_unwind(payload),
+    })
+}
@@ -6,3 +6,7 @@ mod intrinsics;
 mod types;
 
 pub use api::*;
+pub use compiler::value::*;
+pub use errors::{CompilerError, Trap, TrapCode};
+pub use intrinsics::catch_traps;
+pub use types::NumType;
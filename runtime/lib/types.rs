@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 /// WebAssembly function type as defined in the spec.
 ///
 /// https://webassembly.github.io/spec/core/syntax/types.html#syntax-functype
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct FuncType {
     pub params: Vec<ValType>,
     pub results: Vec<ValType>,
@@ -12,7 +12,7 @@ pub struct FuncType {
 /// WebAssembly value types as defined in the spec.
 ///
 /// https://webassembly.github.io/spec/core/syntax/types.html#syntax-valtype
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ValType {
     Num(NumType), // i32, i64, f32, f64
     Ref(RefType), // funcref, externref
@@ -22,7 +22,7 @@ pub enum ValType {
 /// WebAssembly num types as defined in the spec.
 ///
 /// https://webassembly.github.io/spec/core/syntax/types.html#syntax-numtype
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum NumType {
     I32,
     I64,
@@ -33,7 +33,7 @@ pub enum NumType {
 /// WebAssembly num types as defined in the spec.
 ///
 /// https://webassembly.github.io/spec/core/syntax/types.html#syntax-reftype
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RefType {
     FuncRef,
     ExternRef,
@@ -41,7 +41,7 @@ pub enum RefType {
 
 /// WebAssembly limits almost as defined in the spec.
 ///
-/// A slight deviation from the current spec. Wasmo uses 64-bit types as there will be support for memory64 in the future.
+/// A slight deviation from the current spec. Wasmo uses 64-bit types so they can hold the limits of memory64 memories.
 ///
 /// https://webassembly.github.io/spec/core/syntax/types.html#syntax-limits
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -54,10 +54,29 @@ pub struct Limits {
 
 /// Webassembly memory and table page size.
 /// 64KiB.
-pub const _PAGE_SIZE: u32 = 65536;
+pub const PAGE_SIZE: u32 = 65536;
+
+/// Maximum page count of a 32-bit memory.
+/// 4GiB.
+pub const MAX_PAGES: u64 = 65536;
 
 impl Limits {
     pub fn new(min: u64, max: Option<u64>) -> Self {
         Self { min, max }
     }
+
+    /// Checks if an object with these limits can be provided where `expected` limits are declared.
+    ///
+    /// https://webassembly.github.io/spec/core/valid/types.html#match-limits
+    pub fn matches(&self, expected: &Limits) -> bool {
+        if self.min < expected.min {
+            return false;
+        }
+
+        match (self.max, expected.max) {
+            (_, None) => true,
+            (Some(max), Some(expected_max)) => max <= expected_max,
+            (None, Some(_)) => false,
+        }
+    }
 }
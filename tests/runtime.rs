@@ -1 +1,8 @@
+mod engine;
+mod imports;
+mod instance;
+mod memory;
 mod module;
+mod operator;
+mod store;
+mod wasi;
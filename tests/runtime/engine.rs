@@ -0,0 +1,1027 @@
+mod test {
+    use std::mem;
+
+    use wasmo_runtime::{catch_traps, Engine, Memory, Module, Options, Trap, TrapCode};
+
+    static CONFIG: i32 = 42;
+
+    #[test]
+    fn test_read_host_global_symbol() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "host" "config" (global i32))
+                (func (export "get") (result i32)
+                    global.get 0
+                )
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine
+            .define_symbol("host.config", &CONFIG as *const i32 as usize)
+            .unwrap();
+        engine.load(&module).unwrap();
+
+        let address = engine.get_function(&module, "get").unwrap();
+        let get: extern "C" fn() -> i32 = unsafe { mem::transmute(address) };
+
+        assert_eq!(get(), 42);
+    }
+
+    #[test]
+    fn test_lazy_compilation() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "one") (result i32)
+                    i32.const 1
+                )
+                (func (export "two") (result i32)
+                    i32.const 2
+                )
+            )
+            "#,
+        )
+        .unwrap();
+
+        let options = Options {
+            lazy_compilation: true,
+            ..Default::default()
+        };
+
+        let module = Module::new(&wasm, options).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let one: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "one").unwrap()) };
+        let two: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "two").unwrap()) };
+
+        // Looking functions up only creates their stubs.
+        assert_eq!(engine.compile_count(), 0);
+
+        assert_eq!(one(), 1);
+        assert_eq!(one(), 1);
+        assert_eq!(engine.compile_count(), 1);
+
+        assert_eq!(two(), 2);
+        assert_eq!(engine.compile_count(), 2);
+    }
+
+    #[test]
+    fn test_memory_base_store_load() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "round_trip") (param i32) (result i32)
+                    (i32.store offset=4 (i32.const 8) (local.get 0))
+                    (i32.load (i32.const 12))
+                )
+            )
+            "#,
+        )
+        .unwrap();
+
+        for lazy_compilation in [false, true] {
+            let options = Options {
+                lazy_compilation,
+                ..Default::default()
+            };
+
+            let module = Module::new(&wasm, options).unwrap();
+            let mut memory = Memory::new(1, None, false).unwrap();
+
+            let engine = Engine::new().unwrap();
+            engine.load(&module).unwrap();
+            engine.set_memory_base(&mut memory).unwrap();
+
+            let round_trip: extern "C" fn(i32) -> i32 =
+                unsafe { mem::transmute(engine.get_function(&module, "round_trip").unwrap()) };
+
+            assert_eq!(round_trip(0x1234_5678), 0x1234_5678);
+            assert_eq!(memory.data()[12..16], 0x1234_5678_i32.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_memory_loads() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "i32") (param i32) (result i32)
+                    (i32.load offset=4 (local.get 0)))
+                (func (export "i64") (param i32) (result i64)
+                    (i64.load offset=4 align=4 (local.get 0)))
+                (func (export "f32") (param i32) (result f32)
+                    (f32.load offset=4 (local.get 0)))
+                (func (export "f64") (param i32) (result f64)
+                    (f64.load offset=4 (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // The offset is added to the address before indexing into the memory base.
+        let ir = String::from_utf8(ir).unwrap();
+        let body = &ir[ir.find("@func_0(").unwrap()..ir.find("@func_1(").unwrap()];
+        assert!(body.contains("%3 = add i64 %2, 4\n"));
+        assert!(body.contains("getelementptr i8, i8* %memory_base, i64 %3\n"));
+        assert!(body
+            .lines()
+            .any(|line| line.contains("load i32, i32* ") && line.ends_with(", align 4")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains("load i64, i64* ") && line.ends_with(", align 4")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains("load double, double* ") && line.ends_with(", align 8")));
+
+        let mut memory = Memory::new(1, None, false).unwrap();
+        memory.data_mut()[8..16].copy_from_slice(&1.5_f64.to_le_bytes());
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let load_i32: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32").unwrap()) };
+        let load_i64: extern "C" fn(i32) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64").unwrap()) };
+        let load_f32: extern "C" fn(i32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32").unwrap()) };
+        let load_f64: extern "C" fn(i32) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64").unwrap()) };
+
+        assert_eq!(load_f64(4), 1.5);
+        assert_eq!(load_i64(4), 1.5_f64.to_bits() as i64);
+        assert_eq!(load_i32(8), (1.5_f64.to_bits() >> 32) as i32);
+        assert_eq!(
+            load_f32(8),
+            f32::from_bits((1.5_f64.to_bits() >> 32) as u32)
+        );
+    }
+
+    #[test]
+    fn test_memory_extending_loads() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "i32_8_s") (param i32) (result i32)
+                    (i32.load8_s (local.get 0)))
+                (func (export "i32_8_u") (param i32) (result i32)
+                    (i32.load8_u (local.get 0)))
+                (func (export "i32_16_s") (param i32) (result i32)
+                    (i32.load16_s (local.get 0)))
+                (func (export "i64_16_u") (param i32) (result i64)
+                    (i64.load16_u (local.get 0)))
+                (func (export "i64_32_s") (param i32) (result i64)
+                    (i64.load32_s (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir
+            .lines()
+            .any(|line| line.contains("load i8, i8* ") && line.ends_with(", align 1")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains(" = sext i8 ") && line.ends_with(" to i32")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains(" = zext i8 ") && line.ends_with(" to i32")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains("load i16, i16* ") && line.ends_with(", align 2")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains(" = zext i16 ") && line.ends_with(" to i64")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains(" = sext i32 ") && line.ends_with(" to i64")));
+
+        let mut memory = Memory::new(1, None, false).unwrap();
+        memory.data_mut()[..4].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0x80]);
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let i32_8_s: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_8_s").unwrap()) };
+        let i32_8_u: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_8_u").unwrap()) };
+        let i32_16_s: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_16_s").unwrap()) };
+        let i64_16_u: extern "C" fn(i32) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_16_u").unwrap()) };
+        let i64_32_s: extern "C" fn(i32) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_32_s").unwrap()) };
+
+        assert_eq!(i32_8_s(0), -1);
+        assert_eq!(i32_8_u(0), 255);
+        assert_eq!(i32_16_s(2), -32513);
+        assert_eq!(i64_16_u(2), 0x80FF);
+        assert_eq!(i64_32_s(0), -0x7F00_0001);
+    }
+
+    #[test]
+    fn test_memory_stores() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "store8") (param i32 i32)
+                    (i32.store8 (local.get 0) (local.get 1)))
+                (func (export "store16") (param i32 i64)
+                    (i64.store16 offset=2 (local.get 0) (local.get 1)))
+                (func (export "store_f64") (param i32 f64)
+                    (f64.store align=1 (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        let body = &ir[ir.find("@func_0(").unwrap()..ir.find("@func_1(").unwrap()];
+        assert!(body
+            .lines()
+            .any(|line| line.contains(" = trunc i32 ") && line.ends_with(" to i8")));
+        assert!(body
+            .lines()
+            .any(|line| line.contains("store i8 %") && line.ends_with(", align 1")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains("store i16 ") && line.ends_with(", align 2")));
+        assert!(ir
+            .lines()
+            .any(|line| line.contains("store double ") && line.ends_with(", align 1")));
+
+        let mut memory = Memory::new(1, None, false).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let store8: extern "C" fn(i32, i32) =
+            unsafe { mem::transmute(engine.get_function(&module, "store8").unwrap()) };
+        let store16: extern "C" fn(i32, i64) =
+            unsafe { mem::transmute(engine.get_function(&module, "store16").unwrap()) };
+        let store_f64: extern "C" fn(i32, f64) =
+            unsafe { mem::transmute(engine.get_function(&module, "store_f64").unwrap()) };
+
+        store8(0, 0x1234);
+        store16(0, 0x0102_0304);
+        store_f64(9, 1.5);
+
+        assert_eq!(memory.data()[..5], [0x34, 0, 0x04, 0x03, 0]);
+        assert_eq!(memory.data()[9..17], 1.5_f64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_memory_fill_copy() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "fill") (param i32 i32 i32)
+                    (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+                (func (export "copy") (param i32 i32 i32)
+                    (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("declare void @llvm.memset.p0i8.i64(i8* "));
+        assert!(ir.contains("declare void @llvm.memmove.p0i8.p0i8.i64(i8* "));
+        assert!(ir.lines().any(
+            |line| line.contains("call void @llvm.memset.p0i8.i64(i8* %")
+                && line.contains(", i8 %")
+                && line.ends_with(", i1 false)")
+        ));
+        assert!(ir.lines().any(|line| line
+            .contains("call void @llvm.memmove.p0i8.p0i8.i64(i8* %")
+            && line.ends_with(", i1 false)")));
+
+        let mut memory = Memory::new(1, None, false).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let fill: extern "C-unwind" fn(i32, i32, i32) =
+            unsafe { mem::transmute(engine.get_function(&module, "fill").unwrap()) };
+        let copy: extern "C-unwind" fn(i32, i32, i32) =
+            unsafe { mem::transmute(engine.get_function(&module, "copy").unwrap()) };
+
+        fill(1, 0x1AB, 3);
+        copy(2, 0, 4);
+        // The source overlaps the destination and is read before being overwritten.
+        assert_eq!(memory.data()[..7], [0, 0xAB, 0, 0xAB, 0xAB, 0xAB, 0]);
+
+        // Nothing is written when the range runs past the end of the memory.
+        let error = catch_traps(|| fill(65534, 0xFF, 3)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+        let error = catch_traps(|| copy(0, 65534, 3)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+        assert!(memory.data()[65534..].iter().all(|byte| *byte == 0));
+        assert_eq!(memory.data()[..3], [0, 0xAB, 0]);
+
+        // Empty ranges at the end of the memory are in bounds.
+        catch_traps(|| fill(65536, 0xFF, 0)).unwrap();
+    }
+
+    #[test]
+    fn test_memory_init_data_drop() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (data "unused")
+                (data $hello "hello")
+                (func (export "init") (param i32 i32 i32)
+                    (memory.init $hello (local.get 0) (local.get 1) (local.get 2)))
+                (func (export "drop")
+                    (data.drop $hello))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Only the bytes of segments that code refers to are embedded.
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("@__wasmo_data_bytes_1 = constant [5 x i8] c\"hello\""));
+        assert!(!ir.contains("__wasmo_data_bytes_0"));
+        assert!(ir.contains("@__wasmo_data_size_1 = global i64 5"));
+        assert!(ir.contains("call void @llvm.memcpy.p0i8.p0i8.i64(i8* "));
+
+        let mut memory = Memory::new(1, None, false).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let init: extern "C-unwind" fn(i32, i32, i32) =
+            unsafe { mem::transmute(engine.get_function(&module, "init").unwrap()) };
+        let drop: extern "C-unwind" fn() =
+            unsafe { mem::transmute(engine.get_function(&module, "drop").unwrap()) };
+
+        init(10, 1, 3);
+        assert_eq!(&memory.data()[9..14], b"\0ell\0");
+
+        // Nothing is written when the range runs past the end of the segment or the memory.
+        let error = catch_traps(|| init(0, 3, 3)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+        let error = catch_traps(|| init(65534, 0, 3)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+        assert_eq!(memory.data()[..2], [0, 0]);
+        assert_eq!(memory.data()[65534..], [0, 0]);
+
+        // A dropped segment is empty, so only copying nothing succeeds.
+        drop();
+        catch_traps(|| init(0, 0, 0)).unwrap();
+        let error = catch_traps(|| init(0, 0, 1)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+    }
+
+    #[test]
+    fn test_data_initializers() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (data (i32.const 8) "wasmo")
+                (data "passive")
+                (data (i32.const 10) "xy")
+                (func (export "load") (param i32) (result i32)
+                    (i32.load8_u (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("define i32 @initialize_mem_0_data_0()"));
+        assert!(ir.contains("define i32 @initialize_mem_0_data_2()"));
+        assert!(!ir.contains("@initialize_mem_0_data_1"));
+        assert!(ir.contains("@__wasmo_data_bytes_0 = constant [5 x i8] c\"wasmo\""));
+        assert!(ir.contains("icmp ugt i64 13, %memory_size"));
+        assert!(ir.contains("getelementptr i8, i8* %memory_base, i64 8\n"));
+
+        let mut memory = Memory::new(1, None, false).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+        engine.initialize_data(&module).unwrap();
+
+        // Later segments overwrite earlier ones where they overlap.
+        assert_eq!(&memory.data()[7..14], b"\0waxyo\0");
+
+        let load: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "load").unwrap()) };
+        assert_eq!(load(11), b'y' as i32);
+
+        let wasm =
+            wat::parse_str(r#"(module (memory 1) (data (i32.const 65535) "wasmo"))"#).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let mut memory = Memory::new(1, None, false).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let error = engine.initialize_data(&module).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+        assert_eq!(memory.data()[65535], 0);
+    }
+
+    #[test]
+    fn test_table_get_set() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (table 4 funcref)
+                (func (export "round_trip") (param i32 funcref) (result funcref)
+                    (table.set (local.get 0) (local.get 1))
+                    (table.get (local.get 0)))
+                (func (export "is_null") (param i32) (result i32)
+                    (ref.is_null (table.get (local.get 0))))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let mut slots = vec![0; 4];
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_table_base(&mut slots).unwrap();
+
+        let round_trip: extern "C-unwind" fn(i32, usize) -> usize =
+            unsafe { mem::transmute(engine.get_function(&module, "round_trip").unwrap()) };
+        let is_null: extern "C-unwind" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "is_null").unwrap()) };
+
+        let function = test_table_get_set as *const () as usize;
+        assert_eq!(round_trip(3, function), function);
+        assert_eq!(slots, [0, 0, 0, function]);
+        assert_eq!(is_null(0), 1);
+        assert_eq!(is_null(3), 0);
+
+        let error = catch_traps(|| round_trip(4, function)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsTable)
+        );
+        let error = catch_traps(|| is_null(-1)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsTable)
+        );
+    }
+
+    #[test]
+    fn test_table_grow_size_fill() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (table 2 6 externref)
+                (func (export "grow") (param externref i32) (result i32)
+                    (table.grow (local.get 0) (local.get 1)))
+                (func (export "size") (result i32)
+                    (table.size))
+                (func (export "fill") (param i32 externref i32)
+                    (table.fill (local.get 0) (local.get 1) (local.get 2)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("call i32 @__wasmo_table_grow("));
+        assert!(ir.contains("call void @__wasmo_table_fill("));
+
+        let mut slots = vec![0; 2];
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_table_base(&mut slots).unwrap();
+
+        let grow: extern "C-unwind" fn(usize, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "grow").unwrap()) };
+        let size: extern "C-unwind" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "size").unwrap()) };
+        let fill: extern "C-unwind" fn(i32, usize, i32) =
+            unsafe { mem::transmute(engine.get_function(&module, "fill").unwrap()) };
+
+        assert_eq!(size(), 2);
+        assert_eq!(grow(7, 3), 2);
+        assert_eq!(size(), 5);
+        assert_eq!(grow(0, 0), 5);
+
+        // Growing past the maximum fails without changing the table.
+        assert_eq!(grow(0, 2), -1);
+        assert_eq!(size(), 5);
+
+        catch_traps(|| fill(0, 9, 2)).unwrap();
+        assert_eq!(slots, [9, 9, 7, 7, 7]);
+
+        // A fill that does not fit traps before setting anything.
+        let error = catch_traps(|| fill(4, 1, 2)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsTable)
+        );
+        assert_eq!(slots, [9, 9, 7, 7, 7]);
+    }
+
+    #[test]
+    fn test_element_initializers() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (type $binary (func (param i32 i32) (result i32)))
+                (table 4 funcref)
+                (elem (i32.const 1) $add $sub)
+                (elem $passive func $sub)
+                (func $add (param i32 i32) (result i32)
+                    (i32.add (local.get 0) (local.get 1)))
+                (func $sub (param i32 i32) (result i32)
+                    (i32.sub (local.get 0) (local.get 1)))
+                (func (export "apply") (param i32 i32 i32) (result i32)
+                    (call_indirect (type $binary) (local.get 1) (local.get 2) (local.get 0)))
+                (func (export "init") (param i32 i32 i32)
+                    (table.init $passive (local.get 0) (local.get 1) (local.get 2)))
+                (func (export "drop")
+                    (elem.drop $passive))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let mut slots = vec![0; 4];
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_table_base(&mut slots).unwrap();
+        engine.initialize_elements(&module).unwrap();
+
+        let apply: extern "C-unwind" fn(i32, i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "apply").unwrap()) };
+        let init: extern "C-unwind" fn(i32, i32, i32) =
+            unsafe { mem::transmute(engine.get_function(&module, "init").unwrap()) };
+        let drop: extern "C-unwind" fn() =
+            unsafe { mem::transmute(engine.get_function(&module, "drop").unwrap()) };
+
+        assert_eq!(slots[0], 0);
+        assert_eq!(catch_traps(|| apply(1, 7, 2)).unwrap(), 9);
+        assert_eq!(catch_traps(|| apply(2, 7, 2)).unwrap(), 5);
+
+        let error = catch_traps(|| apply(0, 7, 2)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::UndefinedElement)
+        );
+        let error = catch_traps(|| apply(4, 7, 2)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsTable)
+        );
+
+        catch_traps(|| init(3, 0, 1)).unwrap();
+        assert_eq!(slots[3], slots[2]);
+        assert_eq!(catch_traps(|| apply(3, 7, 2)).unwrap(), 5);
+
+        // A dropped segment can only copy nothing.
+        drop();
+        catch_traps(|| init(0, 0, 0)).unwrap();
+        let error = catch_traps(|| init(0, 0, 1)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsTable)
+        );
+        assert_eq!(slots[0], 0);
+    }
+
+    #[test]
+    fn test_call_indirect_type_mismatch() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (type $binary (func (param i32 i32) (result i32)))
+                (type $same_binary (func (param i32 i32) (result i32)))
+                (table 3 funcref)
+                (elem (i32.const 0) $add $neg $mul)
+                (func $add (type $binary)
+                    (i32.add (local.get 0) (local.get 1)))
+                (func $neg (param i32) (result i32)
+                    (i32.sub (i32.const 0) (local.get 0)))
+                (func $mul (type $same_binary)
+                    (i32.mul (local.get 0) (local.get 1)))
+                (func (export "apply") (param i32 i32 i32) (result i32)
+                    (call_indirect (type $binary) (local.get 1) (local.get 2) (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let mut slots = vec![0; 3];
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_table_base(&mut slots).unwrap();
+        engine.initialize_elements(&module).unwrap();
+
+        let apply: extern "C-unwind" fn(i32, i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "apply").unwrap()) };
+
+        assert_eq!(catch_traps(|| apply(0, 6, 3)).unwrap(), 9);
+
+        // Types are compared by structure rather than by index.
+        assert_eq!(catch_traps(|| apply(2, 6, 3)).unwrap(), 18);
+
+        let error = catch_traps(|| apply(1, 6, 3)).unwrap_err();
+        let trap = *error.downcast_ref::<Trap>().unwrap();
+        assert_eq!(trap.code, TrapCode::IndirectCallTypeMismatch);
+        assert_eq!(trap.function_index, 3);
+        assert_eq!(wasm[trap.offset], 0x11); // call_indirect
+    }
+
+    #[test]
+    fn test_element_initializer_out_of_bounds() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (table 2 funcref)
+                (elem (i32.const 1) $f $f)
+                (func $f)
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let mut slots = vec![0; 2];
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_table_base(&mut slots).unwrap();
+
+        let error = engine.initialize_elements(&module).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsTable)
+        );
+        assert_eq!(slots, [0, 0]);
+    }
+
+    #[test]
+    fn test_memory_out_of_bounds_trap() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "load") (param i32) (result i32)
+                    (i32.load offset=8 (local.get 0)))
+                (func (export "trap") unreachable)
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let mut memory = Memory::new(1, None, false).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let load: extern "C-unwind" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "load").unwrap()) };
+        let trap: extern "C-unwind" fn() =
+            unsafe { mem::transmute(engine.get_function(&module, "trap").unwrap()) };
+
+        // The last four bytes of the memory are still in bounds.
+        assert_eq!(catch_traps(|| load(65524)).unwrap(), 0);
+
+        let error = catch_traps(|| load(65525)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+
+        let load_trap = *error.downcast_ref::<Trap>().unwrap();
+        assert_eq!(load_trap.function_index, 0);
+        assert_eq!(load_trap.address, Some(65533));
+        assert_eq!(wasm[load_trap.offset], 0x28); // i32.load
+
+        // An address past 2^31 is not taken as negative.
+        let error = catch_traps(|| load(-1)).unwrap_err();
+        let address = error.downcast_ref::<Trap>().unwrap().address;
+        assert_eq!(address, Some(u32::MAX as u64 + 8));
+
+        let error = catch_traps(|| trap()).unwrap_err();
+        let unreachable_trap = *error.downcast_ref::<Trap>().unwrap();
+        assert_eq!(unreachable_trap.code, TrapCode::Unreachable);
+        assert_eq!(unreachable_trap.function_index, 1);
+        assert_eq!(unreachable_trap.address, None);
+        assert_eq!(wasm[unreachable_trap.offset], 0x00); // unreachable
+    }
+
+    #[test]
+    fn test_loop_with_branches() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "sum_odd") (param i32) (result i32) (local i32)
+                    (block
+                        (loop
+                            (br_if 1 (i32.eqz (local.get 0)))
+                            (if (i32.and (local.get 0) (i32.const 1))
+                                (then (local.set 1 (i32.add (local.get 1) (local.get 0))))
+                                (else nop)
+                            )
+                            (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                            (br 0)
+                        )
+                    )
+                    (local.get 1)
+                )
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let sum_odd: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "sum_odd").unwrap()) };
+
+        assert_eq!(sum_odd(0), 0);
+        assert_eq!(sum_odd(10), 25);
+    }
+
+    #[test]
+    fn test_block_results() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "select") (param i32 i32 i32) (result i32)
+                    (if (result i32) (local.get 0)
+                        (then (local.get 1))
+                        (else (local.get 2))))
+                (func (export "classify") (param i32) (result i32)
+                    (block (result i32)
+                        (block (result i32)
+                            (br_table 0 1 (i32.const 10) (local.get 0)))
+                        (i32.add (i32.const 1))))
+                (func (export "is_small") (param i32) (result i32)
+                    (block (result i32)
+                        (br_if 0 (i32.lt_u (local.get 0) (i32.const 10)) (i32.const 1))
+                        (drop)
+                        (i32.const 0)))
+                (func (export "pair") (param i32) (result i32)
+                    (block (result i32 i64)
+                        (i32.const 2)
+                        (i64.const 3)
+                        (br_if 0 (local.get 0))
+                        (drop)
+                        (drop)
+                        (i32.const 4)
+                        (i64.const 5))
+                    (i32.wrap_i64)
+                    (i32.mul))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("phi i32"));
+        assert!(ir.contains("phi i64"));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let select: extern "C" fn(i32, i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "select").unwrap()) };
+        let classify: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "classify").unwrap()) };
+        let is_small: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "is_small").unwrap()) };
+        let pair: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "pair").unwrap()) };
+
+        assert_eq!(select(1, 7, 9), 7);
+        assert_eq!(select(0, 7, 9), 9);
+        assert_eq!(classify(0), 11);
+        assert_eq!(classify(1), 10);
+        assert_eq!(classify(5), 10);
+        assert_eq!(is_small(3), 1);
+        assert_eq!(is_small(30), 0);
+        assert_eq!(pair(1), 6);
+        assert_eq!(pair(0), 20);
+    }
+
+    #[test]
+    fn test_replace_function() {
+        let counter = |step| {
+            wat::parse_str(format!(
+                r#"
+                (module
+                    (memory 1)
+                    (func (export "count") (result i32)
+                        (i32.store (i32.const 0) (i32.add (i32.load (i32.const 0)) (i32.const {})))
+                        (i32.load (i32.const 0))
+                    )
+                )
+                "#,
+                step
+            ))
+            .unwrap()
+        };
+
+        let options = Options {
+            hot_patching: true,
+            ..Default::default()
+        };
+
+        let module = Module::new(&counter(1), options).unwrap();
+        let mut memory = Memory::new(1, None, false).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+        engine.set_memory_base(&mut memory).unwrap();
+
+        let count: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "count").unwrap()) };
+
+        assert_eq!(count(), 1);
+        assert_eq!(count(), 2);
+
+        engine
+            .replace_function(&module, "count", &counter(10))
+            .unwrap();
+        assert_eq!(count(), 12);
+
+        engine
+            .replace_function(&module, "count", &counter(100))
+            .unwrap();
+        assert_eq!(count(), 112);
+    }
+
+    #[test]
+    fn test_replace_function_not_patchable() {
+        let wasm = wat::parse_str(r#"(module (func (export "f")))"#).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let err = engine.replace_function(&module, "f", &wasm).unwrap_err();
+        assert!(err.to_string().contains("ModuleNotPatchable"));
+    }
+
+    #[test]
+    fn test_exec_buffer() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "add") (param i32 i32) (result i32)
+                    (i32.add (local.get 0) (local.get 1))
+                )
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let buffer = module.emit_to_executable_buffer().unwrap();
+        let add: extern "C" fn(i32, i32) -> i32 =
+            unsafe { mem::transmute(buffer.get_function(&module, "add").unwrap()) };
+
+        assert_eq!(add(2, 3), 5);
+        assert_eq!(add(i32::MAX, 1), i32::MIN);
+    }
+
+    #[test]
+    fn test_compile_in_pooled_context() {
+        let engine = Engine::new().unwrap();
+        let compile = |index: i32| {
+            let wasm = wat::parse_str(format!(
+                r#"(module (func (export "get") (result i32) (i32.const {})))"#,
+                index
+            ))
+            .unwrap();
+            engine.compile(&wasm, Options::default()).unwrap()
+        };
+
+        // Warm up so that allocations LLVM makes once are not counted as growth.
+        for index in 0..10 {
+            compile(index);
+        }
+
+        // Resident memory is only read on Linux.
+        if cfg!(target_os = "linux") {
+            let resident_before = resident_memory();
+            for index in 0..100 {
+                compile(index);
+            }
+
+            // A context kept alive per module would take about 2 MiB here.
+            let growth = resident_memory() - resident_before;
+            assert!(growth < 1 << 20, "grew by {} bytes", growth);
+        }
+
+        let module = compile(7);
+        engine.load(&module).unwrap();
+
+        let get: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "get").unwrap()) };
+        assert_eq!(get(), 7);
+    }
+
+    /// Gets the resident memory of the process in bytes, assuming pages of 4 KiB.
+    fn resident_memory() -> isize {
+        let statm = std::fs::read_to_string("/proc/self/statm").unwrap();
+        let pages: isize = statm.split_whitespace().nth(1).unwrap().parse().unwrap();
+        pages * 4096
+    }
+}
@@ -0,0 +1,38 @@
+mod test {
+    use std::io;
+
+    use wasmo_runtime::{HostFunction, Imports, NumVal, TrapCode, Value};
+
+    #[test]
+    fn test_host_function_error_traps() {
+        let mut imports = Imports::default();
+        imports.define_function(
+            "env",
+            "fail",
+            HostFunction::new(|_| Err(io::Error::other("out of coffee").into())),
+        );
+
+        let err = imports
+            .get_function("env", "fail")
+            .unwrap()
+            .call(&[])
+            .unwrap_err();
+
+        assert_eq!(err.downcast_ref::<TrapCode>(), Some(&TrapCode::HostError));
+        assert_eq!(err.root_cause().to_string(), "out of coffee");
+    }
+
+    #[test]
+    fn test_host_function_panic_traps() {
+        let function = HostFunction::new(|args| match args {
+            [Value::Num(NumVal::I32(value))] => Ok(vec![Value::Num(NumVal::I32(value + 1))]),
+            _ => panic!("unexpected args"),
+        });
+
+        let results = function.call(&[Value::Num(NumVal::I32(1))]).unwrap();
+        assert!(matches!(results[..], [Value::Num(NumVal::I32(2))]));
+
+        let err = function.call(&[]).unwrap_err();
+        assert_eq!(err.downcast_ref::<TrapCode>(), Some(&TrapCode::HostError));
+    }
+}
@@ -0,0 +1,755 @@
+mod test {
+    use std::{
+        cell::{Cell, RefCell},
+        io,
+        rc::Rc,
+    };
+
+    use wasmo_runtime::{
+        CompilerError, HostFunction, Imports, Instance, Memory, MemoryGrowHook, Module, NumVal,
+        Options, Trap, TrapCode, Value,
+    };
+
+    #[test]
+    fn test_data_segment_targets_memory_index() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (memory 1)
+                (data (memory 1) (i32.const 8) "wasmo")
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let instance = Instance::new(&module, &imports).unwrap();
+
+        assert_eq!(&instance.memory(1).unwrap().data()[8..13], b"wasmo");
+        assert!(instance
+            .memory(0)
+            .unwrap()
+            .data()
+            .iter()
+            .all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_data_segment_out_of_bounds() {
+        let wasm =
+            wat::parse_str(r#"(module (memory 1) (data (i32.const 65535) "wasmo"))"#).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+
+        let err = Instance::new(&module, &imports).unwrap_err();
+        assert!(err.to_string().contains("OutOfBoundsMemory"));
+    }
+
+    #[test]
+    fn test_element_segment_targets_other_table() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (table 1 funcref)
+                (table 1 funcref)
+                (func)
+                (elem (table 1) (i32.const 0) func 0)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+
+        let err = Instance::new(&module, &imports).unwrap_err();
+        assert!(err.to_string().contains("UnsupportedElementSegment"));
+    }
+
+    #[test]
+    fn test_imported_memory() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "host" "mem" (memory 1 2))
+                (data (i32.const 16) "wasmo")
+                (func (export "load") (param i32) (result i32)
+                    (i32.load8_u (local.get 0)))
+                (func (export "store_grown") (param i32)
+                    (drop (memory.grow (i32.const 1)))
+                    (i32.store8 (i32.const 65536) (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut memory = Memory::new(1, Some(2), false).unwrap();
+        memory.data_mut()[8] = 42;
+        let mut imports = Imports::default();
+        imports.define_memory("host", "mem", memory);
+
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let byte = |byte: i32| [Value::Num(NumVal::I32(byte))];
+
+        // The guest reads what the host wrote, and the host sees the data segment and writes of the guest.
+        assert_eq!(instance.call("load", &byte(8)).unwrap(), byte(42));
+        assert_eq!(instance.call("load", &byte(16)).unwrap(), byte(b'w' as i32));
+
+        instance.call("store_grown", &byte(7)).unwrap();
+        let memory = imports.get_memory("host", "mem").unwrap().borrow();
+        assert_eq!(memory.size(), 2);
+        assert_eq!(&memory.data()[16..21], b"wasmo");
+        assert_eq!(memory.data()[65536], 7);
+    }
+
+    #[test]
+    fn test_imported_start_function() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "host" "init" (func $init))
+                (func)
+                (start $init)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let err = Instance::new(&module, &Imports::default()).unwrap_err();
+        assert!(err.to_string().contains("UnresolvedImport"));
+
+        let calls = Rc::new(Cell::new(0));
+        let mut imports = Imports::default();
+        imports.define_function("host", "init", {
+            let calls = Rc::clone(&calls);
+            HostFunction::new(move |_| {
+                calls.set(calls.get() + 1);
+                Ok(vec![])
+            })
+        });
+
+        Instance::new(&module, &imports).unwrap();
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_memory_grow_hook() {
+        let wasm = wat::parse_str("(module (memory 1 4))").unwrap();
+
+        let grows = Rc::new(RefCell::new(vec![]));
+        let options = Options {
+            on_memory_grow: Some(MemoryGrowHook({
+                let grows = Rc::clone(&grows);
+                Rc::new(move |old, new| grows.borrow_mut().push((old, new)))
+            })),
+            ..Default::default()
+        };
+        let module = Module::new(&wasm, options).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        let memory = instance.memory_mut(0).unwrap();
+        assert_eq!(memory.grow(1), Some(1));
+        assert_eq!(memory.grow(2), Some(2));
+
+        // Failed grows leave the memory as is and are not reported.
+        assert_eq!(memory.grow(1), None);
+        assert_eq!(*grows.borrow(), [(1, 2), (2, 4)]);
+    }
+
+    #[test]
+    fn test_memory_grow_hook_from_wasm() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1 4)
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let grows = Rc::new(RefCell::new(vec![]));
+        let options = Options {
+            on_memory_grow: Some(MemoryGrowHook({
+                let grows = Rc::clone(&grows);
+                Rc::new(move |old, new| grows.borrow_mut().push((old, new)))
+            })),
+            ..Default::default()
+        };
+        let module = Module::new(&wasm, options).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let pages = |pages: i32| [Value::Num(NumVal::I32(pages))];
+
+        assert_eq!(instance.call("grow", &pages(1)).unwrap(), pages(1));
+        assert_eq!(instance.call("grow", &pages(2)).unwrap(), pages(2));
+
+        // The grow past the maximum fails in the builtin before reaching the hook.
+        assert_eq!(instance.call("grow", &pages(1)).unwrap(), pages(-1));
+        assert_eq!(*grows.borrow(), [(1, 2), (2, 4)]);
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let wasm = wat::parse_str("(module (memory 1 4))").unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        instance.memory_mut(0).unwrap().data_mut()[..5].copy_from_slice(b"wasmo");
+        let snapshot = instance.snapshot();
+        assert_eq!(snapshot.size(), 65536 + module.store_layout().size);
+
+        let memory = instance.memory_mut(0).unwrap();
+        memory.data_mut()[..5].copy_from_slice(b"xxxxx");
+        memory.grow(1).unwrap();
+        memory.data_mut()[65536] = 1;
+
+        // Restoring undoes the grow as well as the writes, and can be done more than once.
+        for _ in 0..2 {
+            instance.restore(&snapshot).unwrap();
+
+            let memory = instance.memory(0).unwrap();
+            assert_eq!(memory.size(), 1);
+            assert_eq!(&memory.data()[..5], b"wasmo");
+
+            instance.memory_mut(0).unwrap().data_mut()[0] = 0;
+        }
+
+        let wasm = wat::parse_str("(module)").unwrap();
+        let other_module = Module::new(&wasm, Options::default()).unwrap();
+        let other_instance = Instance::new(&other_module, &imports).unwrap();
+        let err = instance.restore(&other_instance.snapshot()).unwrap_err();
+        assert!(err.to_string().contains("SnapshotMismatch"));
+    }
+
+    #[test]
+    fn test_static_memory_image() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (data (i32.const 4) "wasmo")
+                (data (i32.const 6) "xy")
+            )
+            "#,
+        )
+        .unwrap();
+        let options = Options {
+            static_memory_image: true,
+            ..Default::default()
+        };
+        let module = Module::new(&wasm, options).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // The later segment overwrites the earlier one where they overlap.
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains(r#"@__wasmo_memory_image_0 = constant [9 x i8] c"\00\00\00\00waxyo""#));
+
+        let imports = Imports::default();
+        let instance = Instance::new(&module, &imports).unwrap();
+        assert_eq!(
+            &instance.memory(0).unwrap().data()[..10],
+            b"\0\0\0\0waxyo\0"
+        );
+    }
+
+    #[test]
+    fn test_call() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "add") (param i32 i32) (result i32)
+                    local.get 0
+                    local.get 1
+                    i32.add)
+                (func (export "split") (param f64 f32) (result i64 f32)
+                    (i64.trunc_f64_s (local.get 0))
+                    (f32.neg (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        let results = instance
+            .call(
+                "add",
+                &[Value::Num(NumVal::I32(2)), Value::Num(NumVal::I32(3))],
+            )
+            .unwrap();
+        assert_eq!(results, [Value::Num(NumVal::I32(5))]);
+
+        let results = instance
+            .call(
+                "split",
+                &[Value::Num(NumVal::F64(-2.5)), Value::Num(NumVal::F32(2.5))],
+            )
+            .unwrap();
+        assert_eq!(
+            results,
+            [Value::Num(NumVal::I64(-2)), Value::Num(NumVal::F32(-2.5))]
+        );
+
+        let err = instance
+            .call(
+                "add",
+                &[Value::Num(NumVal::I64(2)), Value::Num(NumVal::I32(3))],
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CompilerError>(),
+            Some(CompilerError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_call_accesses_memory_and_traps() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (data (i32.const 16) "\2a")
+                (func (export "load") (param i32) (result i32)
+                    (i32.load8_u (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        let results = instance
+            .call("load", &[Value::Num(NumVal::I32(16))])
+            .unwrap();
+        assert_eq!(results, [Value::Num(NumVal::I32(42))]);
+
+        let err = instance
+            .call("load", &[Value::Num(NumVal::I32(65536))])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::OutOfBoundsMemory)
+        );
+
+        // Growing the memory may move it, so the base is looked up again on the next call.
+        instance.memory_mut(0).unwrap().grow(1).unwrap();
+        let results = instance
+            .call("load", &[Value::Num(NumVal::I32(65536))])
+            .unwrap();
+        assert_eq!(results, [Value::Num(NumVal::I32(0))]);
+    }
+
+    #[test]
+    fn test_memory_grow_then_load() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1 3)
+                (data (i32.const 0) "\2a")
+                (func (export "grow_and_load") (param i32) (result i32)
+                    (drop (memory.grow (local.get 0)))
+                    (i32.add
+                        (i32.load8_u (i32.const 0))
+                        (i32.load8_u (i32.const 65536))))
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0)))
+                (func (export "size") (result i32)
+                    (memory.size))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let pages = |pages: i32| [Value::Num(NumVal::I32(pages))];
+
+        let err = instance.call("grow_and_load", &pages(0)).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Trap>().unwrap().code,
+            TrapCode::OutOfBoundsMemory
+        );
+
+        // The load after the grow sees the new page and the data that moved along with the memory.
+        assert_eq!(
+            instance.call("grow_and_load", &pages(1)).unwrap(),
+            pages(42)
+        );
+        assert_eq!(instance.call("size", &[]).unwrap(), pages(2));
+
+        assert_eq!(instance.call("grow", &pages(2)).unwrap(), pages(-1));
+        assert_eq!(instance.call("grow", &pages(1)).unwrap(), pages(2));
+        assert_eq!(instance.memory_mut(0).unwrap().size(), 3);
+    }
+
+    #[test]
+    fn test_local_start_function_writes_memory() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func $init
+                    (i32.store (i32.const 8) (i32.const 0x6f6d7361)))
+                (func (export "read") (param i32) (result i32)
+                    (i32.load8_u (local.get 0)))
+                (start $init)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        assert_eq!(&instance.memory(0).unwrap().data()[8..12], b"asmo");
+
+        let mut read = instance.get_func("read").unwrap();
+        assert_eq!(
+            read.call(&[Value::Num(NumVal::I32(11))]).unwrap(),
+            [Value::Num(NumVal::I32(b'o' as i32))]
+        );
+
+        let err = instance.get_func("init").unwrap_err();
+        assert!(err.to_string().contains("UnknownExport"));
+    }
+
+    #[test]
+    fn test_typed_func() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "add") (param i32 i32) (result i32)
+                    (i32.add (local.get 0) (local.get 1)))
+                (func (export "swap") (param i64 f64) (result f64 i64)
+                    (local.get 1)
+                    (local.get 0))
+                (func (export "nop"))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        let mut add = instance.get_typed_func::<(i32, i32), i32>("add").unwrap();
+        assert_eq!(add.call((2, 3)).unwrap(), 5);
+
+        let mut swap = instance
+            .get_typed_func::<(i64, f64), (f64, i64)>("swap")
+            .unwrap();
+        assert_eq!(swap.call((7, 0.5)).unwrap(), (0.5, 7));
+
+        let mut nop = instance.get_typed_func::<(), ()>("nop").unwrap();
+        nop.call(()).unwrap();
+    }
+
+    #[test]
+    fn test_typed_func_signature_mismatch() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "add") (param i32 i32) (result i32)
+                    (i32.add (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        for err in [
+            instance
+                .get_typed_func::<(i32, i64), i32>("add")
+                .unwrap_err(),
+            instance
+                .get_typed_func::<(i32, i32), ()>("add")
+                .unwrap_err(),
+            instance.get_typed_func::<i32, i32>("add").unwrap_err(),
+        ] {
+            assert!(matches!(
+                err.downcast_ref::<CompilerError>(),
+                Some(CompilerError::TypeMismatch(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_call_imported_function() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "log" (func $log (param i32)))
+                (func (export "run") (param i32) (result i32)
+                    (call $log (local.get 0))
+                    (call $log (i32.add (local.get 0) (i32.const 1)))
+                    (local.get 0))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let logged = Rc::new(RefCell::new(vec![]));
+        let mut imports = Imports::default();
+        imports.define_func("env", "log", {
+            let logged = Rc::clone(&logged);
+            move |args| {
+                logged.borrow_mut().push(args[0]);
+                Ok(vec![])
+            }
+        });
+
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let mut run = instance.get_typed_func::<i32, i32>("run").unwrap();
+        assert_eq!(run.call(41).unwrap(), 41);
+
+        assert_eq!(
+            *logged.borrow(),
+            [Value::Num(NumVal::I32(41)), Value::Num(NumVal::I32(42))]
+        );
+    }
+
+    #[test]
+    fn test_call_indirect_imported_function() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "double" (func $double (param i32) (result i32)))
+                (table 2 funcref)
+                (elem (i32.const 0) $double $negate)
+                (func $negate (param i32) (result i32)
+                    (i32.sub (i32.const 0) (local.get 0)))
+                (func (export "apply") (param i32 i32) (result i32)
+                    (call_indirect (param i32) (result i32) (local.get 1) (local.get 0)))
+                (func (export "apply_binary") (param i32) (result i32)
+                    (call_indirect (param i32 i32) (result i32)
+                        (i32.const 1) (i32.const 2) (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut imports = Imports::default();
+        imports.define_func("env", "double", |args| match args {
+            [Value::Num(NumVal::I32(value))] => Ok(vec![Value::Num(NumVal::I32(value * 2))]),
+            _ => unreachable!(),
+        });
+
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let mut apply = instance.get_typed_func::<(i32, i32), i32>("apply").unwrap();
+        assert_eq!(apply.call((0, 21)).unwrap(), 42);
+        assert_eq!(apply.call((1, 5)).unwrap(), -5);
+
+        // The imported function is checked against the expected type like local ones.
+        let err = instance
+            .call("apply_binary", &[Value::Num(NumVal::I32(0))])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Trap>().unwrap().code,
+            TrapCode::IndirectCallTypeMismatch
+        );
+    }
+
+    #[test]
+    fn test_imported_function_error_traps() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "check" (func $check (param i64) (result i64)))
+                (func (export "run") (param i64) (result i64)
+                    (call $check (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut imports = Imports::default();
+        imports.define_func("env", "check", |args| match args {
+            [Value::Num(NumVal::I64(value))] if *value >= 0 => Ok(vec![args[0]]),
+            [Value::Num(NumVal::I64(_))] => Err(io::Error::other("negative value").into()),
+            _ => Ok(vec![]),
+        });
+
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let mut run = instance.get_typed_func::<i64, i64>("run").unwrap();
+        assert_eq!(run.call(7).unwrap(), 7);
+
+        let err = run.call(-1).unwrap_err();
+        assert_eq!(err.downcast_ref::<TrapCode>(), Some(&TrapCode::HostError));
+    }
+
+    #[test]
+    fn test_call_unreachable_returns_trap() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "check") (param i32) (result i32)
+                    (if (i32.eqz (local.get 0))
+                        (then unreachable))
+                    (local.get 0))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        assert_eq!(
+            instance
+                .call("check", &[Value::Num(NumVal::I32(3))])
+                .unwrap(),
+            [Value::Num(NumVal::I32(3))]
+        );
+
+        let err = instance
+            .call("check", &[Value::Num(NumVal::I32(0))])
+            .unwrap_err();
+        let trap = *err.downcast_ref::<Trap>().unwrap();
+        assert_eq!(trap.code, TrapCode::Unreachable);
+        assert_eq!(trap.function_index, 0);
+        assert_eq!(wasm[trap.offset], 0x00); // unreachable
+
+        // The instance is still usable after a trap.
+        assert_eq!(
+            instance
+                .call("check", &[Value::Num(NumVal::I32(5))])
+                .unwrap(),
+            [Value::Num(NumVal::I32(5))]
+        );
+    }
+
+    #[test]
+    fn test_memory64_addresses() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory i64 1)
+                (func (export "store") (param i64 i32)
+                    (i32.store (local.get 0) (local.get 1)))
+                (func (export "load") (param i64) (result i32)
+                    (i32.load (local.get 0)))
+                (func (export "load_offset") (param i64) (result i32)
+                    (i32.load offset=12 (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let ir = module.ir_to_string().unwrap();
+        assert!(ir.contains("define i32 @func_1(i64 %0)"));
+
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let address = |address: i64| Value::Num(NumVal::I64(address));
+
+        instance
+            .call("store", &[address(8), Value::Num(NumVal::I32(42))])
+            .unwrap();
+        assert_eq!(
+            instance.call("load", &[address(8)]).unwrap(),
+            [Value::Num(NumVal::I32(42))]
+        );
+
+        // Neither the address nor its sum with the offset is truncated or wraps around to 8.
+        for (name, address) in [("load", address(1 << 32 | 8)), ("load_offset", address(-4))] {
+            let err = instance.call(name, &[address]).unwrap_err();
+            let trap = *err.downcast_ref::<Trap>().unwrap();
+            assert_eq!(trap.code, TrapCode::OutOfBoundsMemory);
+        }
+    }
+
+    #[test]
+    fn test_memory64_size_and_grow() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory i64 1 4)
+                (func (export "size") (result i64)
+                    (memory.size))
+                (func (export "grow") (param i64) (result i64)
+                    (memory.grow (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let pages = |pages: i64| Value::Num(NumVal::I64(pages));
+
+        assert_eq!(instance.call("size", &[]).unwrap(), [pages(1)]);
+        assert_eq!(instance.call("grow", &[pages(2)]).unwrap(), [pages(1)]);
+        assert_eq!(instance.call("size", &[]).unwrap(), [pages(3)]);
+
+        // A delta past the maximum, or one that would not fit in 32 bits, fails without growing.
+        assert_eq!(instance.call("grow", &[pages(2)]).unwrap(), [pages(-1)]);
+        assert_eq!(
+            instance.call("grow", &[pages(1 << 32)]).unwrap(),
+            [pages(-1)]
+        );
+        assert_eq!(instance.call("size", &[]).unwrap(), [pages(3)]);
+    }
+
+    #[test]
+    fn test_memory64_data_segment() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory i64 1)
+                (data (i64.const 8) "wasmo")
+                (func (export "load") (param i64) (result i32)
+                    (i32.load8_u (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        assert_eq!(&instance.memory(0).unwrap().data()[8..13], b"wasmo");
+        assert_eq!(
+            instance
+                .call("load", &[Value::Num(NumVal::I64(8))])
+                .unwrap(),
+            [Value::Num(NumVal::I32(b'w' as i32))]
+        );
+    }
+
+    #[test]
+    fn test_memory64_data_segment_out_of_bounds() {
+        let wasm =
+            wat::parse_str(r#"(module (memory i64 1) (data (i64.const -1) "wasmo"))"#).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+
+        let err = Instance::new(&module, &imports).unwrap_err();
+        assert!(err.to_string().contains("OutOfBoundsMemory"));
+    }
+
+    #[test]
+    fn test_memory64_minimum_too_large() {
+        let wasm = wat::parse_str("(module (memory i64 0x1_0000_0000))").unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+
+        let err = Instance::new(&module, &imports).unwrap_err();
+        assert!(err.to_string().contains("LimitExceeded"));
+    }
+}
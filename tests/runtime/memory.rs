@@ -0,0 +1,46 @@
+mod test {
+    use wasmo_runtime::{Memory, POISON_BYTE};
+
+    const PAGE_SIZE: usize = 65536;
+
+    #[test]
+    fn test_memory_grow() {
+        let mut memory = Memory::new(1, Some(32), false).unwrap();
+        memory.data_mut()[100] = 42;
+
+        assert_eq!(memory.grow(16), Some(1));
+        assert_eq!(memory.size(), 17);
+
+        // Contents survive the grow even if the memory got reallocated.
+        let data = unsafe { std::slice::from_raw_parts(memory.as_ptr(), memory.data().len()) };
+        assert_eq!(data[100], 42);
+        assert_eq!(data.len(), 17 * PAGE_SIZE);
+        assert!(data[PAGE_SIZE..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_memory_grow_past_maximum() {
+        let mut memory = Memory::new(1, Some(2), false).unwrap();
+
+        assert_eq!(memory.grow(2), None);
+        assert_eq!(memory.size(), 1);
+        assert_eq!(memory.grow(1), Some(1));
+        assert_eq!(memory.grow(0), Some(2));
+    }
+
+    #[test]
+    fn test_memory_new_past_max_pages() {
+        let err = Memory::new(65537, None, false).unwrap_err();
+        assert!(err.to_string().contains("LimitExceeded"));
+
+        assert!(Memory::new(u64::MAX, None, false).is_err());
+    }
+
+    #[test]
+    fn test_poisoned_memory_grow() {
+        let mut memory = Memory::new(1, None, false).unwrap().poisoned();
+
+        assert_eq!(memory.grow(1), Some(1));
+        assert!(memory.data().iter().all(|byte| *byte == POISON_BYTE));
+    }
+}
@@ -1,5 +1,10 @@
 mod test {
-    use wasmo_runtime::{Module, Options};
+    use std::{env, fs, mem, process};
+
+    use wasmo_runtime::{
+        Dependency, Engine, FunctionStats, Imports, Instance, Memory, Module, NumType, NumVal,
+        OptLevel, Options, ResourceLimits, TrapCode, Value,
+    };
 
     #[test]
     fn test_parser() {
@@ -8,4 +13,797 @@ mod test {
         let _module = Module::new(&wasm, Options::default()).unwrap();
         assert!(true)
     }
+
+    #[test]
+    fn test_max_locals_exceeded() {
+        let wasm = wat::parse_str("(module (func (local i32 i64 f32)))").unwrap();
+        let options = Options {
+            limits: ResourceLimits {
+                max_locals: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let err = Module::new(&wasm, options).unwrap_err();
+        assert!(err.to_string().contains("LimitExceeded"));
+    }
+
+    #[test]
+    fn test_max_locals_exceeded_without_allocating() {
+        // A single local entry declaring u32::MAX i32 locals.
+        let wasm = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section: () -> ()
+            0x03, 0x02, 0x01, 0x00, // function section
+            0x0a, 0x0a, 0x01, 0x08, // code section
+            0x01, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x7f, 0x0b, // body: (local i32 * u32::MAX)
+        ];
+
+        let err = Module::new(&wasm, Options::default()).unwrap_err();
+        assert!(err.to_string().contains("LimitExceeded"));
+    }
+
+    #[test]
+    fn test_max_control_depth_exceeded() {
+        let wasm = wat::parse_str(format!(
+            "(module (func {}{}))",
+            "(block (loop (if (i32.const 1) (then ".repeat(4),
+            "))))".repeat(4)
+        ))
+        .unwrap();
+
+        // Each repetition nests a block, a loop and an if.
+        for (max_control_depth, is_ok) in [(11, false), (12, true)] {
+            let options = Options {
+                limits: ResourceLimits {
+                    max_control_depth,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let result = Module::new(&wasm, options);
+            assert_eq!(result.is_ok(), is_ok);
+
+            if let Err(err) = result {
+                assert!(err.to_string().contains("LimitExceeded"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_end_is_unbalanced_control() {
+        let header = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section: () -> ()
+            0x03, 0x02, 0x01, 0x00, // function section
+        ];
+
+        let bodies: [&[u8]; 2] = [
+            &[0x0a, 0x05, 0x01, 0x03, 0x00, 0x02, 0x40], // body: (block
+            &[0x0a, 0x06, 0x01, 0x04, 0x00, 0x02, 0x40, 0x0b], // body: (block) without its own end
+        ];
+
+        for body in bodies {
+            let wasm = [&header[..], body].concat();
+            let err = Module::new(&wasm, Options::default()).unwrap_err();
+            assert!(err.to_string().contains("UnbalancedControl"));
+        }
+    }
+
+    #[test]
+    fn test_abandoned_controls_release_blocks() {
+        // Each if opens a then block and leaves its else and end blocks to be appended at its end.
+        let module = |body: &str| {
+            wat::parse_str(format!(
+                "(module (func (param i32) {}{}{}))",
+                "(if (local.get 0) (then ".repeat(50),
+                body,
+                "))".repeat(50)
+            ))
+            .unwrap()
+        };
+
+        // Run under a leak checker, the blocks of the ifs left open by the error must not be reported.
+        let wasm = module("(drop (i64.add (i32.const 1) (i64.const 1)))");
+        for _ in 0..10 {
+            let err = Module::new(&wasm, Options::default()).unwrap_err();
+            assert!(err.to_string().contains("TypeMismatch"));
+        }
+
+        let wasm = module("(drop (i64.add (i64.const 1) (i64.const 1)))");
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        assert_eq!(module.function_stats(0).unwrap().basic_blocks, 1 + 3 * 50);
+    }
+
+    #[test]
+    fn test_same_signature_types_share_function_type() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (type $a (func (param i32) (result i32)))
+                (type $b (func (param i64)))
+                (type $c (func (param i32) (result i32)))
+                (func (type $a) (local.get 0))
+                (func (type $c) (local.get 0))
+                (func (type $b))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Functions of distinct type entries with the same signature are declared alike.
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("define i32 @func_0(i32 %0)"));
+        assert!(ir.contains("define i32 @func_1(i32 %0)"));
+        assert!(ir.contains("define void @func_2(i64 %0)"));
+    }
+
+    #[test]
+    fn test_empty_function_bodies() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "empty"))
+                (func (export "default") (result i32 f64))
+            )
+            "#,
+        )
+        .unwrap();
+
+        // Verification fails if an entry block is left without a terminator.
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("ret void"));
+        assert!(ir.contains("ret <{ i32, double }> zeroinitializer"));
+    }
+
+    #[test]
+    fn test_call_graph() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (table 1 funcref)
+                (func $f0 (call $f1))
+                (func $f1 (call_indirect (i32.const 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let call_graph = module.call_graph();
+
+        assert_eq!(call_graph.dependencies(0), &[Dependency::Function(1)]);
+        assert_eq!(call_graph.dependencies(1), &[Dependency::AnyInTable(0)]);
+    }
+
+    #[test]
+    fn test_imported_memory_with_insufficient_minimum() {
+        let wasm = wat::parse_str(r#"(module (import "host" "mem" (memory 2 10)))"#).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut imports = Imports::default();
+        imports.define_memory("host", "mem", Memory::new(1, Some(10), false).unwrap());
+
+        let err = Instance::new(&module, &imports).unwrap_err();
+        assert!(err.to_string().contains("IncompatibleImport"));
+    }
+
+    #[test]
+    fn test_imported_memory_with_mismatched_index_type() {
+        let wasm = wat::parse_str(r#"(module (import "host" "mem" (memory i64 1)))"#).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut imports = Imports::default();
+        imports.define_memory("host", "mem", Memory::new(1, None, false).unwrap());
+
+        let err = Instance::new(&module, &imports).unwrap_err();
+        assert!(err.to_string().contains("IncompatibleImport"));
+
+        let mut imports = Imports::default();
+        let memory = Memory::new(1, None, false)
+            .unwrap()
+            .with_index_type(NumType::I64);
+        imports.define_memory("host", "mem", memory);
+
+        Instance::new(&module, &imports).unwrap();
+    }
+
+    #[test]
+    fn test_write_ir() {
+        let wasm = wat::parse_str(
+            "(module (func (param i32) (result i32) (local.get 0)) (func (result i64) (i64.const 1)))",
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("define i32 @func_0(i32 %0)"));
+        assert!(ir.contains("define i64 @func_1()"));
+    }
+
+    #[test]
+    fn test_ir_to_string() {
+        let wasm = wat::parse_str("(module (func (result i32) (i32.const 7)))").unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let ir = module.ir_to_string().unwrap();
+        assert!(ir.contains("define i32 @func_0()"));
+
+        let mut written = vec![];
+        module.write_ir(&mut written).unwrap();
+        assert_eq!(ir, String::from_utf8(written).unwrap());
+    }
+
+    #[test]
+    fn test_size_opt_level_shrinks_loops() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "fill") (local i32)
+                    (loop
+                        (i32.store (i32.shl (local.get 0) (i32.const 2)) (local.get 0))
+                        (local.set 0 (i32.add (local.get 0) (i32.const 1)))
+                        (br_if 0 (i32.lt_u (local.get 0) (i32.const 16)))
+                    )
+                )
+            )
+            "#,
+        )
+        .unwrap();
+
+        let instruction_count = |opt_level| {
+            let options = Options {
+                opt_level,
+                ..Default::default()
+            };
+
+            let module = Module::new(&wasm, options).unwrap();
+
+            let mut ir = vec![];
+            module.write_ir(&mut ir).unwrap();
+
+            let ir = String::from_utf8(ir).unwrap();
+            let function = ir.split("define").nth(1).unwrap();
+            function
+                .lines()
+                .filter(|line| line.starts_with("  "))
+                .count()
+        };
+
+        assert!(instruction_count(OptLevel::SizeMin) < instruction_count(OptLevel::Aggressive));
+    }
+
+    #[test]
+    fn test_default_opt_level_folds_constants() {
+        // The local keeps the builder from folding the addition itself while generating code.
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "five") (result i32) (local i32)
+                    (local.set 0 (i32.const 2))
+                    (i32.add (local.get 0) (i32.const 3)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let ir = |opt_level| {
+            let options = Options {
+                opt_level,
+                ..Default::default()
+            };
+
+            Module::new(&wasm, options).unwrap().ir_to_string().unwrap()
+        };
+
+        assert!(ir(OptLevel::None).contains("add i32"));
+
+        let optimized = ir(OptLevel::Default);
+        assert!(!optimized.contains("add i32"));
+        assert!(optimized.contains("ret i32 5"));
+    }
+
+    #[test]
+    fn test_function_stats() {
+        let wasm = wat::parse_str(r#"(module (import "host" "func" (func)) (func))"#).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let stats = module.function_stats(1).unwrap();
+        assert_eq!(
+            stats,
+            FunctionStats {
+                basic_blocks: 1,
+                instructions: 1,
+            }
+        );
+
+        let err = module.function_stats(0).unwrap_err();
+        assert!(err.to_string().contains("IndexOutOfBounds"));
+    }
+
+    #[test]
+    fn test_function_cfg_loop_back_edge() {
+        let wasm = wat::parse_str("(module (func (loop (br 0))))").unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let cfg = module.function_cfg(0).unwrap();
+        assert!(cfg.blocks.iter().all(|block| block.is_terminated));
+
+        let header = cfg.block("loop_begin").unwrap();
+        assert!(header.successors.iter().any(|name| name == "loop_begin"));
+        let predecessors = cfg.predecessors("loop_begin");
+        let names = predecessors.iter().map(|block| &block.name[..]);
+        assert_eq!(names.collect::<Vec<_>>(), ["entry", "loop_begin"]);
+    }
+
+    #[test]
+    fn test_memory_access_branches_to_trap() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (param i32) (result i32)
+                    (i32.load offset=4 (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let cfg = module.function_cfg(0).unwrap();
+        assert_eq!(
+            cfg.block("entry").unwrap().successors,
+            ["trap", "trap_next"]
+        );
+        assert!(cfg.block("trap").unwrap().successors.is_empty());
+
+        // The end of the access is checked against the size of the memory, and the trap reports the address.
+        let ir = module.ir_to_string().unwrap();
+        assert!(ir.contains("icmp ugt i64 %"));
+        assert!(ir.contains(&format!(
+            "call void @__wasmo_raise_trap(i32 {}, i32 0",
+            TrapCode::OutOfBoundsMemory as u32
+        )));
+    }
+
+    #[test]
+    fn test_single_result_returns_once() {
+        let wasm = wat::parse_str("(module (func (result i32) (i32.const 7)))").unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("define i32 @func_0() {\nentry:\n  ret i32 7\n}"));
+        assert_eq!(module.function_stats(0).unwrap().instructions, 1);
+    }
+
+    #[test]
+    fn test_body_end_returns_once() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (param i32) (result i32)
+                    (block
+                        (br_if 0 (local.get 0))
+                        (if (local.get 0) (then nop) (else nop)))
+                    (local.get 0))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert_eq!(ir.matches("ret i32 ").count(), 1);
+
+        // The return terminates the block the body continued in, which leaves no other block without successors.
+        let cfg = module.function_cfg(0).unwrap();
+        assert!(cfg.blocks.iter().all(|block| block.is_terminated));
+
+        let exits = cfg
+            .blocks
+            .iter()
+            .filter(|block| block.successors.is_empty());
+        assert_eq!(
+            exits.map(|block| &block.name[..]).collect::<Vec<_>>(),
+            ["block_end"]
+        );
+
+        for block in &cfg.blocks[1..] {
+            assert!(!cfg.predecessors(&block.name).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_counting_loop_branches_to_header() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "count") (param i32) (result i32) (local i32)
+                    (block
+                        (loop
+                            (br_if 1 (i32.eqz (local.get 0)))
+                            (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                            (local.set 1 (i32.add (local.get 1) (i32.const 1)))
+                            (br 0)))
+                    (local.get 1))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        // Only the entry and the `br 0` enter the loop. Nothing follows the `br 0`, so the end of the loop is never reached.
+        let cfg = module.function_cfg(0).unwrap();
+        let names = |name| {
+            let predecessors = cfg.predecessors(name);
+            predecessors
+                .iter()
+                .map(|block| block.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("loop_begin"), ["entry", "br_if_next"]);
+        assert!(names("loop_end").is_empty());
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let count: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "count").unwrap()) };
+
+        assert_eq!(count(0), 0);
+        assert_eq!(count(5), 5);
+    }
+
+    #[test]
+    fn test_emit_static_archive() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "host" "log" (func $log (param i32)))
+                (func $hidden (result i32)
+                    (i32.const 0))
+                (func (export "increment") (param i32) (result i32)
+                    (i32.add (local.get 0) (i32.const 1)))
+                (export "inc" (func 2))
+                (export "log" (func $log))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let path = env::temp_dir().join(format!("wasmo-test-{}.a", process::id()));
+        module.emit_static_archive(&path).unwrap();
+        let archive = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(&archive[..8], b"!<arch>\n");
+
+        // The symbol table is the first member, right after the archive's magic and its own 60-byte header.
+        assert_eq!(&archive[8..24], b"/               ");
+        let count = u32::from_be_bytes(archive[68..72].try_into().unwrap()) as usize;
+        let names = archive[72 + 4 * count..]
+            .split(|byte| *byte == 0)
+            .take(count)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect::<Vec<_>>();
+
+        // Exports of the same function are aliases of it, and the imported function has no code to export.
+        assert_eq!(names, ["inc", "increment"]);
+    }
+
+    #[test]
+    fn test_emit_object_file() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "increment") (param i32) (result i32)
+                    (i32.add (local.get 0) (i32.const 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let path = env::temp_dir().join(format!("wasmo-test-{}.o", process::id()));
+        module.emit_object_file(&path, None).unwrap();
+        let object = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let is_elf = object.starts_with(b"\x7fELF");
+        let is_mach_o = object.starts_with(&[0xcf, 0xfa, 0xed, 0xfe]);
+        assert!(is_elf || is_mach_o);
+        assert!(object.windows(9).any(|name| name == b"increment"));
+    }
+
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    #[test]
+    fn test_emit_object_file_for_triple() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "increment") (param i32) (result i32)
+                    (i32.add (local.get 0) (i32.const 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let path = env::temp_dir().join(format!("wasmo-test-{}-i686.o", process::id()));
+        module
+            .emit_object_file(&path, Some("i686-unknown-linux-gnu"))
+            .unwrap();
+        let object = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The fifth byte of the ELF identification is the class, which is 1 for 32-bit objects.
+        assert!(object.starts_with(b"\x7fELF"));
+        assert_eq!(object[4], 1);
+    }
+
+    #[test]
+    fn test_precompiled_round_trip() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "add") (param i32 i32) (result i32)
+                    (i32.add (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let precompiled = Module::new(&wasm, Options::default())
+            .unwrap()
+            .to_precompiled()
+            .unwrap();
+
+        let module = Module::from_precompiled(&precompiled).unwrap();
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let add: extern "C" fn(i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "add").unwrap()) };
+        assert_eq!(add(2, 3), 5);
+
+        let err = engine.get_function(&module, "sub").unwrap_err();
+        assert!(err.to_string().contains("UnknownExport"));
+
+        let err = Module::from_precompiled(b"not bitcode").unwrap_err();
+        assert!(err.to_string().contains("InvalidModule"));
+    }
+
+    #[test]
+    fn test_precompiled_file_round_trip() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (data (i32.const 0) "\07")
+                (func $load (result i32)
+                    (i32.load8_u (i32.const 0)))
+                (export "load" (func $load))
+                (export "read" (func $load))
+            )
+            "#,
+        )
+        .unwrap();
+
+        // The original module is dropped before the file is read back, so nothing is shared with it.
+        let path = env::temp_dir().join(format!("wasmo-test-{}.wasmo", process::id()));
+        {
+            let module = Module::new(&wasm, Options::default()).unwrap();
+            fs::write(&path, module.to_precompiled().unwrap()).unwrap();
+        }
+
+        let precompiled = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let module = Module::from_precompiled(&precompiled).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+
+        for name in ["load", "read"] {
+            let results = instance.call(name, &[]).unwrap();
+            assert_eq!(results, [Value::Num(NumVal::I32(7))]);
+        }
+
+        let err = instance.call("store", &[]).unwrap_err();
+        assert!(err.to_string().contains("UnknownExport"));
+    }
+
+    #[test]
+    fn test_preserve_frame_pointer() {
+        let wasm = wat::parse_str("(module (func))").unwrap();
+
+        for preserve_frame_pointer in [false, true] {
+            let options = Options {
+                preserve_frame_pointer,
+                ..Default::default()
+            };
+            let module = Module::new(&wasm, options).unwrap();
+
+            let mut ir = vec![];
+            module.write_ir(&mut ir).unwrap();
+
+            let ir = String::from_utf8(ir).unwrap();
+            assert_eq!(
+                ir.contains(r#""frame-pointer"="all""#),
+                preserve_frame_pointer
+            );
+        }
+    }
+
+    #[test]
+    fn test_debug_info() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func $load (export "load") (param i32) (result i32)
+                    (i32.load (local.get 0))))
+            "#,
+        )
+        .unwrap();
+
+        for debug_info in [false, true] {
+            let options = Options {
+                debug_info,
+                ..Default::default()
+            };
+            let module = Module::new(&wasm, options).unwrap();
+
+            let ir = module.ir_to_string().unwrap();
+            assert_eq!(ir.contains("!llvm.dbg.cu"), debug_info);
+            assert_eq!(ir.contains(r#"!DISubprogram(name: "load""#), debug_info);
+
+            let imports = Imports::default();
+            let mut instance = Instance::new(&module, &imports).unwrap();
+            let results = instance
+                .call("load", &[Value::Num(NumVal::I32(0))])
+                .unwrap();
+            assert_eq!(results, [Value::Num(NumVal::I32(0))]);
+        }
+    }
+
+    #[test]
+    fn test_compile_to_ir_only_many_functions() {
+        let function = r#"
+            (func (param i32 i64) (result i64) (local i32)
+                (loop
+                    (local.set 2 (i32.add (local.get 2) (i32.mul (local.get 0) (i32.const 3))))
+                    (br_if 0 (i32.lt_u (local.get 2) (i32.const 1000))))
+                (i64.add (local.get 1) (i64.extend_i32_u (local.get 2))))
+        "#;
+        let wasm = wat::parse_str(format!("(module {})", function.repeat(500))).unwrap();
+
+        Module::compile_to_ir_only(&wasm, Options::default()).unwrap();
+    }
+
+    #[test]
+    fn test_name_section_names_functions_and_locals() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func $memcpy)
+                (func $func_0)
+                (func)
+                (func $my_func (export "run") (param $count i32) (result i32) (local $total i32)
+                    (local.set $total (i32.mul (local.get $count) (i32.const 3)))
+                    (local.get $total))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let ir = module.ir_to_string().unwrap();
+        assert!(ir.contains("define i32 @my_func(i32 %0)"));
+        assert!(!ir.contains("@func_3"));
+        assert!(ir.contains("%count = alloca i32"));
+        assert!(ir.contains("%total = alloca i32"));
+
+        // Names that could clash with other symbols are not used.
+        assert!(ir.contains("define void @func_0()"));
+        assert!(ir.contains("define void @func_1()"));
+        assert!(ir.contains("define void @func_2()"));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let run: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "run").unwrap()) };
+        assert_eq!(run(5), 15);
+    }
+
+    #[test]
+    fn test_direct_call() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func $double (param i32) (result i32)
+                    (i32.mul (local.get 0) (i32.const 2)))
+                (func $pair (param i32) (result i32 i32)
+                    (local.get 0)
+                    (i32.add (local.get 0) (i32.const 1)))
+                (func (export "call") (param i32) (result i32)
+                    (call $pair (call $double (local.get 0)))
+                    i32.sub)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("call i32 @double("));
+        assert!(ir.contains("call <{ i32, i32 }> @pair("));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let call: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "call").unwrap()) };
+
+        assert_eq!(call(5), -1);
+    }
+
+    #[test]
+    fn test_multi_value_return() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func $pair (param i32) (result i32 i64)
+                    (local.get 0)
+                    (i64.extend_i32_s (local.get 0)))
+                (func (export "call") (param i32) (result i64) (local i64)
+                    (call $pair (local.get 0))
+                    (local.set 1)
+                    (i64.extend_i32_s)
+                    (i64.add (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("insertvalue <{ i32, i64 }>"));
+        assert!(ir.contains("extractvalue <{ i32, i64 }>"));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let call: extern "C" fn(i32) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "call").unwrap()) };
+
+        assert_eq!(call(-3), -6);
+    }
 }
@@ -0,0 +1,1320 @@
+mod test {
+    use std::mem;
+
+    use wasmo_runtime::{catch_traps, CompilerError, Engine, Module, Options, TrapCode};
+
+    #[test]
+    fn test_comparisons_produce_i32() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (param i32 i32) (result i32)
+                    (i32.eq (local.get 0) (local.get 1)))
+
+                (func (param i64 i64) (result i32)
+                    (i64.lt_u (local.get 0) (local.get 1)))
+
+                (func (param f32 f32) (result i32)
+                    (f32.ge (local.get 0) (local.get 1)))
+
+                (func (param i64) (result i32)
+                    (i64.eqz (local.get 0)))
+
+                (func (param i32 i32) (result i32) (local i32)
+                    (local.set 2 (i32.lt_s (local.get 0) (local.get 1)))
+                    (i32.add (local.get 2) (i32.ne (local.get 0) (local.get 1))))
+            )
+            "#,
+        )
+        .unwrap();
+
+        // The module fails verification if a comparison leaves an `i1` where an `i32` is expected.
+        Module::new(&wasm, Options::default()).unwrap();
+    }
+
+    #[test]
+    fn test_ref_is_null() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (param externref) (result i32)
+                    (ref.is_null (local.get 0)))
+
+                (func (result i32)
+                    (ref.is_null (ref.null func)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        Module::new(&wasm, Options::default()).unwrap();
+    }
+
+    #[test]
+    fn test_table_set_ref_type_mismatch() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (table 1 funcref)
+                (func (param externref)
+                    (table.set 0 (i32.const 0) (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let err = Module::new(&wasm, Options::default()).unwrap_err();
+        assert!(err.to_string().contains("TypeMismatch"));
+    }
+
+    #[test]
+    fn test_element_segment_ref_type_mismatch() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (table 1 externref)
+                (func)
+                (elem (table 0) (i32.const 0) func 0)
+            )
+            "#,
+        )
+        .unwrap();
+
+        let err = Module::new(&wasm, Options::default()).unwrap_err();
+        assert!(err.to_string().contains("TypeMismatch"));
+    }
+
+    #[test]
+    fn test_float_operand_type_mismatch() {
+        for body in [
+            "(drop (f32.lt (f32.const 1) (f64.const 1)))",
+            "(drop (f64.eq (i64.const 1) (i64.const 1)))",
+            "(drop (f64.nearest (f32.const 1)))",
+        ] {
+            let wasm = wat::parse_str(format!("(module (func {}))", body)).unwrap();
+            let err = Module::new(&wasm, Options::default()).unwrap_err();
+            assert!(err.to_string().contains("TypeMismatch"));
+        }
+    }
+
+    #[test]
+    fn test_nearest_without_native_roundeven() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (param f32) (result f32)
+                    (f32.nearest (local.get 0)))
+
+                (func (param f64) (result f64)
+                    (f64.nearest (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Modules have no target triple so `llvm.roundeven` is not assumed to be lowered natively.
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(!ir.contains("llvm.roundeven"));
+        assert!(ir.contains("llvm.copysign.f32"));
+        assert!(ir.contains("llvm.copysign.f64"));
+    }
+
+    #[test]
+    fn test_i64_eqz() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "eqz_large") (result i32)
+                    (i64.eqz (i64.const 0x1_0000_0000)))
+
+                (func (export "eqz_zero") (result i32)
+                    (i64.eqz (i64.const 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        // The module fails verification if the operand is compared against a zero of another type.
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let eqz_large: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "eqz_large").unwrap()) };
+        let eqz_zero: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "eqz_zero").unwrap()) };
+
+        assert_eq!(eqz_large(), 0);
+        assert_eq!(eqz_zero(), 1);
+    }
+
+    #[test]
+    fn test_int_binop_width_mismatch() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (param i64) (result i64)
+                    (i64.add (i32.const 1) (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let err = Module::new(&wasm, Options::default()).unwrap_err();
+        assert!(err.to_string().contains("TypeMismatch"));
+    }
+
+    #[test]
+    fn test_operator_error_offset() {
+        let wasm = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+            0x01, 0x06, 0x01, 0x60, 0x01, 0x7e, 0x01, 0x7e, // type section: (i64) -> i64
+            0x03, 0x03, 0x02, 0x00, 0x00, // function section
+            0x0a, 0x0e, 0x02, // code section
+            0x04, 0x00, 0x20, 0x00, 0x0b, // body 0: (local.get 0)
+            0x07, 0x00, 0x41, 0x01, 0x20, 0x00, 0x7c,
+            0x0b, // body 1: (i64.add (i32.const 1) (local.get 0))
+        ];
+
+        let err = Module::new(&wasm, Options::default()).unwrap_err();
+        match err.downcast_ref::<CompilerError>().unwrap() {
+            CompilerError::AtOperator {
+                function_index,
+                offset,
+                error,
+            } => {
+                assert_eq!(*function_index, 1);
+                assert_eq!(*offset, 35);
+                assert!(matches!(**error, CompilerError::TypeMismatch(_)));
+            }
+            err => panic!("unexpected error {}", err),
+        }
+    }
+
+    #[test]
+    fn test_int_shift_count_wraps() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "shl") (result i32)
+                    (i32.shl (i32.const 1) (i32.const 33)))
+
+                (func (export "shr_s") (result i64)
+                    (i64.shr_s (i64.const -8) (i64.const 65)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let shl: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "shl").unwrap()) };
+        let shr_s: extern "C" fn() -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "shr_s").unwrap()) };
+
+        assert_eq!(shl(), 2);
+        assert_eq!(shr_s(), -4);
+    }
+
+    #[test]
+    fn test_int_rotate() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "i32_rotl") (param i32 i32) (result i32)
+                    (i32.rotl (local.get 0) (local.get 1)))
+                (func (export "i32_rotr") (param i32 i32) (result i32)
+                    (i32.rotr (local.get 0) (local.get 1)))
+                (func (export "i64_rotl") (param i64 i64) (result i64)
+                    (i64.rotl (local.get 0) (local.get 1)))
+                (func (export "i64_rotr") (param i64 i64) (result i64)
+                    (i64.rotr (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // The value is funnel-shifted with itself by the count.
+        let ir = String::from_utf8(ir).unwrap();
+        for (ty, name) in [
+            ("i32", "fshl"),
+            ("i32", "fshr"),
+            ("i64", "fshl"),
+            ("i64", "fshr"),
+        ] {
+            let call = format!("call {} @llvm.{}.{}(", ty, name, ty);
+            let line = ir.lines().find(|line| line.contains(&call)).unwrap();
+            let args = line
+                .split(&call)
+                .nth(1)
+                .unwrap()
+                .trim_end_matches(')')
+                .split(", ")
+                .collect::<Vec<_>>();
+
+            assert_eq!(args.len(), 3);
+            assert!(args.iter().all(|arg| arg.starts_with(&format!("{} %", ty))));
+            assert_eq!(args[0], args[1]);
+            assert_ne!(args[1], args[2]);
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let i32_rotl: extern "C" fn(i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_rotl").unwrap()) };
+        let i32_rotr: extern "C" fn(i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_rotr").unwrap()) };
+        let i64_rotl: extern "C" fn(i64, i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_rotl").unwrap()) };
+        let i64_rotr: extern "C" fn(i64, i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_rotr").unwrap()) };
+
+        assert_eq!(i32_rotl(0x8000_0001_u32 as i32, 1), 3);
+        assert_eq!(i32_rotr(3, 1), 0x8000_0001_u32 as i32);
+        assert_eq!(i32_rotl(0x1234_5678, 36), 0x2345_6781);
+        assert_eq!(i64_rotl(i64::MIN | 1, 1), 3);
+        assert_eq!(i64_rotr(3, 65), i64::MIN | 1);
+        assert_eq!(i64_rotr(0x1234, 0), 0x1234);
+    }
+
+    #[test]
+    fn test_count_zeros() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "i32_clz") (param i32) (result i32)
+                    (i32.clz (local.get 0)))
+                (func (export "i32_ctz") (param i32) (result i32)
+                    (i32.ctz (local.get 0)))
+                (func (export "i64_clz") (param i64) (result i64)
+                    (i64.clz (local.get 0)))
+                (func (export "i64_ctz") (param i64) (result i64)
+                    (i64.ctz (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Zero operands are not poison.
+        let ir = String::from_utf8(ir).unwrap();
+        for name in ["ctlz.i32", "cttz.i32", "ctlz.i64", "cttz.i64"] {
+            let call = format!("@llvm.{}(", name);
+            assert!(ir
+                .lines()
+                .any(|line| line.contains(&call) && line.ends_with(", i1 false)")));
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let i32_clz: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_clz").unwrap()) };
+        let i32_ctz: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_ctz").unwrap()) };
+        let i64_clz: extern "C" fn(i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_clz").unwrap()) };
+        let i64_ctz: extern "C" fn(i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_ctz").unwrap()) };
+
+        assert_eq!(i32_clz(0), 32);
+        assert_eq!(i32_clz(1), 31);
+        assert_eq!(i32_ctz(0), 32);
+        assert_eq!(i32_ctz(8), 3);
+        assert_eq!(i64_clz(0), 64);
+        assert_eq!(i64_clz(-1), 0);
+        assert_eq!(i64_ctz(0), 64);
+        assert_eq!(i64_ctz(i64::MIN), 63);
+    }
+
+    #[test]
+    fn test_drop() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "second") (result i32)
+                    (i32.const 1)
+                    drop
+                    (i32.const 2))
+                (func (export "first") (param i32) (result i32)
+                    (local.get 0)
+                    (i32.eqz (local.get 0))
+                    drop)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let second: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "second").unwrap()) };
+        let first: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "first").unwrap()) };
+
+        assert_eq!(second(), 2);
+        assert_eq!(first(5), 5);
+
+        let wasm = wat::parse_str("(module (func drop))").unwrap();
+        let err = Module::new(&wasm, Options::default()).unwrap_err();
+        assert!(err.to_string().contains("ValueStackUnderflow"));
+    }
+
+    #[test]
+    fn test_nop_emits_nothing() {
+        let wasm = wat::parse_str("(module (func nop nop nop))").unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("define void @func_0() {\nentry:\n  ret void\n}"));
+    }
+
+    #[test]
+    fn test_int_comparison_matrix() {
+        // Each predicate with its expected results for the operands (-1, 1), (1, 1) and (1, -1).
+        let matrix = [
+            ("eq", [0, 1, 0]),
+            ("ne", [1, 0, 1]),
+            ("lt_s", [1, 0, 0]),
+            ("lt_u", [0, 0, 1]),
+            ("gt_s", [0, 0, 1]),
+            ("gt_u", [1, 0, 0]),
+            ("le_s", [1, 1, 0]),
+            ("le_u", [0, 1, 1]),
+            ("ge_s", [0, 1, 1]),
+            ("ge_u", [1, 1, 0]),
+        ];
+
+        let mut functions = String::new();
+        for (predicate, _) in matrix {
+            for ty in ["i32", "i64"] {
+                functions += &format!(
+                    r#"(func (export "{ty}.{predicate}") (param {ty} {ty}) (result i32)
+                        ({ty}.{predicate} (local.get 0) (local.get 1)))"#,
+                );
+            }
+        }
+
+        let wasm = wat::parse_str(format!("(module {})", functions)).unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        for (predicate, expected) in matrix {
+            let name = format!("i32.{}", predicate);
+            let cmp_i32: extern "C" fn(i32, i32) -> i32 =
+                unsafe { mem::transmute(engine.get_function(&module, &name).unwrap()) };
+
+            let name = format!("i64.{}", predicate);
+            let cmp_i64: extern "C" fn(i64, i64) -> i32 =
+                unsafe { mem::transmute(engine.get_function(&module, &name).unwrap()) };
+
+            let actual = [cmp_i32(-1, 1), cmp_i32(1, 1), cmp_i32(1, -1)];
+            assert_eq!(actual, expected, "i32.{}", predicate);
+
+            let actual = [cmp_i64(-1, 1), cmp_i64(1, 1), cmp_i64(1, -1)];
+            assert_eq!(actual, expected, "i64.{}", predicate);
+        }
+    }
+
+    #[test]
+    fn test_int_div_traps() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "div_s") (param i32 i32) (result i32)
+                    (i32.div_s (local.get 0) (local.get 1)))
+
+                (func (export "rem_s") (param i32 i32) (result i32)
+                    (i32.rem_s (local.get 0) (local.get 1)))
+
+                (func (export "div_u") (param i64 i64) (result i64)
+                    (i64.div_u (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        // Traps unwind through compiled code so it has to be called as `C-unwind`.
+        let div_s: extern "C-unwind" fn(i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "div_s").unwrap()) };
+        let rem_s: extern "C-unwind" fn(i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "rem_s").unwrap()) };
+        let div_u: extern "C-unwind" fn(i64, i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "div_u").unwrap()) };
+
+        assert_eq!(catch_traps(|| div_s(-7, 2)).unwrap(), -3);
+        assert_eq!(catch_traps(|| rem_s(-7, 2)).unwrap(), -1);
+        assert_eq!(catch_traps(|| div_u(-1, 2)).unwrap(), i64::MAX);
+
+        let error = catch_traps(|| div_s(i32::MIN, -1)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::IntegerOverflow)
+        );
+
+        assert_eq!(catch_traps(|| rem_s(i32::MIN, -1)).unwrap(), 0);
+
+        let error = catch_traps(|| rem_s(1, 0)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::IntegerDivideByZero)
+        );
+
+        let error = catch_traps(|| div_u(1, 0)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::IntegerDivideByZero)
+        );
+    }
+
+    #[test]
+    fn test_early_return() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "return") (result i32)
+                    (return (i32.const 1))
+                    (i32.const 2))
+
+                (func (export "return_in_if") (param i32) (result i32)
+                    (i32.const 7)
+                    (if (local.get 0)
+                        (then
+                            (return (i32.const 1))
+                            (drop)
+                            (block (drop (i32.const 3))))))
+            )
+            "#,
+        )
+        .unwrap();
+
+        // Operators after a return would underflow the value stack if they were not skipped.
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let early_return: extern "C" fn() -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "return").unwrap()) };
+        let return_in_if: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "return_in_if").unwrap()) };
+
+        assert_eq!(early_return(), 1);
+        assert_eq!(return_in_if(1), 1);
+        assert_eq!(return_in_if(0), 7);
+    }
+
+    #[test]
+    fn test_local_get_loads_value() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "double") (param i32) (result i32)
+                    local.get 0
+                    local.get 0
+                    i32.add)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Both operands of the add are loaded from the local's alloca rather than being the alloca itself.
+        let ir = String::from_utf8(ir).unwrap();
+        let loaded = ir
+            .lines()
+            .filter(|line| line.contains(" = load i32, i32* "))
+            .map(|line| line.trim().split(' ').next().unwrap().to_string())
+            .collect::<Vec<_>>();
+        let add = ir
+            .lines()
+            .find(|line| line.contains(" = add i32 "))
+            .unwrap();
+        let operands = add.split(" = add i32 ").nth(1).unwrap().split(", ");
+        for operand in operands {
+            assert!(loaded.iter().any(|name| name == operand));
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let double: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "double").unwrap()) };
+
+        assert_eq!(double(21), 42);
+    }
+
+    #[test]
+    fn test_i32_wrap_i64() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (param i64) (result i32)
+                    local.get 0
+                    i32.wrap_i64)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir
+            .lines()
+            .any(|line| line.contains("trunc i64 ") && line.ends_with(" to i32")));
+    }
+
+    #[test]
+    fn test_comparison_condition_stays_i1() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "min") (param i32 i32) (result i32)
+                    (if (i32.lt_s (local.get 0) (local.get 1))
+                        (then (return (local.get 0))))
+                    (local.get 1))
+
+                (func (export "is_less") (param i64 i64) (result i32)
+                    (i64.lt_u (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Only the returned comparison is extended to an i32, and no condition is compared against zero again.
+        let ir = String::from_utf8(ir).unwrap();
+        assert_eq!(ir.matches("zext i1").count(), 1);
+        assert!(!ir.contains("icmp ne i32"));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let min: extern "C" fn(i32, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "min").unwrap()) };
+        let is_less: extern "C" fn(i64, i64) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "is_less").unwrap()) };
+
+        assert_eq!(min(-1, 2), -1);
+        assert_eq!(min(3, 2), 2);
+        assert_eq!(is_less(1, 2), 1);
+        assert_eq!(is_less(2, 1), 0);
+    }
+
+    #[test]
+    fn test_float_comparison_widened_for_arithmetic() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "count_less") (param f64 f64 i32) (result i32)
+                    (i32.add (f64.lt (local.get 0) (local.get 1)) (local.get 2)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        let fcmp = ir.find("fcmp olt double").unwrap();
+        let zext = ir.find("zext i1").unwrap();
+        let add = ir.find("add i32").unwrap();
+        assert!(fcmp < zext && zext < add);
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let count_less: extern "C" fn(f64, f64, i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "count_less").unwrap()) };
+
+        assert_eq!(count_less(1.0, 2.0, 5), 6);
+        assert_eq!(count_less(2.0, 1.0, 5), 5);
+        assert_eq!(count_less(f64::NAN, 1.0, 5), 5);
+    }
+
+    #[test]
+    fn test_i64_extend_i32() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "extend_sum") (param i32) (result i64)
+                    (i64.add
+                        (i64.extend_i32_s (local.get 0))
+                        (i64.extend_i32_u (local.get 0))))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("sext i32 "));
+        assert!(ir.contains("zext i32 "));
+
+        // The add only finds both operands if each extension leaves exactly one value on the stack.
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let extend_sum: extern "C" fn(i32) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "extend_sum").unwrap()) };
+
+        assert_eq!(extend_sum(1), 2);
+        assert_eq!(extend_sum(-1), u32::MAX as i64 - 1);
+    }
+
+    #[test]
+    fn test_sign_extension() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "i32_extend8_s") (param i32) (result i32)
+                    (i32.extend8_s (local.get 0)))
+                (func (export "i32_extend16_s") (param i32) (result i32)
+                    (i32.extend16_s (local.get 0)))
+                (func (export "i64_extend8_s") (param i64) (result i64)
+                    (i64.extend8_s (local.get 0)))
+                (func (export "i64_extend16_s") (param i64) (result i64)
+                    (i64.extend16_s (local.get 0)))
+                (func (export "i64_extend32_s") (param i64) (result i64)
+                    (i64.extend32_s (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        for (wide, narrow) in [
+            ("i32", "i8"),
+            ("i32", "i16"),
+            ("i64", "i8"),
+            ("i64", "i16"),
+            ("i64", "i32"),
+        ] {
+            let trunc = format!("trunc {} ", wide);
+            let trunc_suffix = format!(" to {}", narrow);
+            let sext = format!("sext {} ", narrow);
+            let sext_suffix = format!(" to {}", wide);
+            assert!(ir
+                .lines()
+                .any(|line| line.contains(&trunc) && line.ends_with(&trunc_suffix)));
+            assert!(ir
+                .lines()
+                .any(|line| line.contains(&sext) && line.ends_with(&sext_suffix)));
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let i32_extend8_s: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_extend8_s").unwrap()) };
+        let i32_extend16_s: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_extend16_s").unwrap()) };
+        let i64_extend8_s: extern "C" fn(i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_extend8_s").unwrap()) };
+        let i64_extend16_s: extern "C" fn(i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_extend16_s").unwrap()) };
+        let i64_extend32_s: extern "C" fn(i64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_extend32_s").unwrap()) };
+
+        assert_eq!(i32_extend8_s(0xFF), -1);
+        assert_eq!(i32_extend8_s(0x7F), 0x7F);
+        assert_eq!(i32_extend8_s(0x1_80), -0x80);
+        assert_eq!(i32_extend16_s(0xFFFF), -1);
+        assert_eq!(i32_extend16_s(0x1_7FFF), 0x7FFF);
+        assert_eq!(i64_extend8_s(0xFF), -1);
+        assert_eq!(i64_extend16_s(0x8000), -0x8000);
+        assert_eq!(i64_extend32_s(0xFFFF_FFFF), -1);
+        assert_eq!(i64_extend32_s(0x1_7FFF_FFFF), 0x7FFF_FFFF);
+    }
+
+    #[test]
+    fn test_atomic_on_unshared_memory() {
+        let module = |memory: &str| {
+            wat::parse_str(format!(
+                r#"
+                (module
+                    {}
+                    (func (param i32 i32) (result i32)
+                        (i32.atomic.rmw.add (local.get 0) (local.get 1)))
+                )
+                "#,
+                memory
+            ))
+            .unwrap()
+        };
+
+        let err = Module::new(&module("(memory 1)"), Options::default()).unwrap_err();
+        assert!(err.to_string().contains("AtomicOnUnsharedMemory"));
+
+        Module::new(&module("(memory 1 1 shared)"), Options::default()).unwrap();
+    }
+
+    #[test]
+    fn test_float_to_int_truncation() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "i32_trunc_f32_s") (param f32) (result i32)
+                    (i32.trunc_f32_s (local.get 0)))
+                (func (param f32) (result i32) (i32.trunc_f32_u (local.get 0)))
+                (func (param f64) (result i32) (i32.trunc_f64_s (local.get 0)))
+                (func (export "i32_trunc_f64_u") (param f64) (result i32)
+                    (i32.trunc_f64_u (local.get 0)))
+                (func (param f32) (result i64) (i64.trunc_f32_s (local.get 0)))
+                (func (param f32) (result i64) (i64.trunc_f32_u (local.get 0)))
+                (func (export "i64_trunc_f64_s") (param f64) (result i64)
+                    (i64.trunc_f64_s (local.get 0)))
+                (func (param f64) (result i64) (i64.trunc_f64_u (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        for instruction in ["fptosi", "fptoui"] {
+            for (float, int) in [
+                ("float", "i32"),
+                ("double", "i32"),
+                ("float", "i64"),
+                ("double", "i64"),
+            ] {
+                let pattern = format!("{} {} ", instruction, float);
+                let suffix = format!(" to {}", int);
+                assert!(ir
+                    .lines()
+                    .any(|line| line.contains(&pattern) && line.ends_with(&suffix)));
+            }
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        // Traps unwind through compiled code so it has to be called as `C-unwind`.
+        let i32_trunc_f32_s: extern "C-unwind" fn(f32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_trunc_f32_s").unwrap()) };
+        let i32_trunc_f64_u: extern "C-unwind" fn(f64) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_trunc_f64_u").unwrap()) };
+        let i64_trunc_f64_s: extern "C-unwind" fn(f64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_trunc_f64_s").unwrap()) };
+
+        assert_eq!(catch_traps(|| i32_trunc_f32_s(-2.9)).unwrap(), -2);
+        assert_eq!(
+            catch_traps(|| i32_trunc_f32_s(-2147483648.0)).unwrap(),
+            i32::MIN
+        );
+        assert_eq!(catch_traps(|| i32_trunc_f64_u(-0.9)).unwrap(), 0);
+        assert_eq!(catch_traps(|| i32_trunc_f64_u(4294967295.9)).unwrap(), -1);
+        assert_eq!(
+            catch_traps(|| i64_trunc_f64_s(i64::MIN as f64)).unwrap(),
+            i64::MIN
+        );
+
+        let error = catch_traps(|| i32_trunc_f32_s(f32::NAN)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::InvalidConversionToInteger)
+        );
+
+        for error in [
+            catch_traps(|| i32_trunc_f32_s(2147483648.0)).unwrap_err(),
+            catch_traps(|| i32_trunc_f64_u(-1.0)).unwrap_err(),
+            catch_traps(|| i32_trunc_f64_u(4294967296.0)).unwrap_err(),
+            catch_traps(|| i64_trunc_f64_s(-(i64::MIN as f64))).unwrap_err(),
+        ] {
+            assert_eq!(
+                error.downcast_ref::<TrapCode>(),
+                Some(&TrapCode::IntegerOverflow)
+            );
+        }
+    }
+
+    #[test]
+    fn test_saturating_float_to_int_truncation() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "i32_trunc_sat_f64_u") (param f64) (result i32)
+                    (i32.trunc_sat_f64_u (local.get 0)))
+                (func (export "i64_trunc_sat_f32_s") (param f32) (result i64)
+                    (i64.trunc_sat_f32_s (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("call i32 @llvm.fptoui.sat.i32.f64(double"));
+        assert!(ir.contains("call i64 @llvm.fptosi.sat.i64.f32(float"));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let i32_trunc_sat_f64_u: extern "C" fn(f64) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "i32_trunc_sat_f64_u").unwrap()) };
+        let i64_trunc_sat_f32_s: extern "C" fn(f32) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "i64_trunc_sat_f32_s").unwrap()) };
+
+        assert_eq!(i32_trunc_sat_f64_u(f64::NAN), 0);
+        assert_eq!(i32_trunc_sat_f64_u(-1.5), 0);
+        assert_eq!(i32_trunc_sat_f64_u(7.9), 7);
+        assert_eq!(i32_trunc_sat_f64_u(1e10), u32::MAX as i32);
+        assert_eq!(i64_trunc_sat_f32_s(f32::NAN), 0);
+        assert_eq!(i64_trunc_sat_f32_s(f32::NEG_INFINITY), i64::MIN);
+        assert_eq!(i64_trunc_sat_f32_s(-7.9), -7);
+    }
+
+    #[test]
+    fn test_br_table() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "classify") (param i32) (result i32)
+                    (block
+                        (block
+                            (block
+                                (block
+                                    (br_table 0 1 2 3 (local.get 0)))
+                                (return (i32.const 10)))
+                            (return (i32.const 11)))
+                        (return (i32.const 12)))
+                    (i32.const 13))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Each target but the default is a case.
+        let ir = String::from_utf8(ir).unwrap();
+        let switch = ir.split("switch i32 ").nth(1).unwrap();
+        let cases = switch.split("\n  ]").next().unwrap().lines().skip(1);
+        let cases = cases.map(|case| case.trim().split(',').next().unwrap());
+        assert_eq!(cases.collect::<Vec<_>>(), ["i32 0", "i32 1", "i32 2"]);
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let classify: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "classify").unwrap()) };
+
+        assert_eq!(classify(0), 10);
+        assert_eq!(classify(1), 11);
+        assert_eq!(classify(2), 12);
+        assert_eq!(classify(3), 13);
+        assert_eq!(classify(-1), 13);
+    }
+
+    #[test]
+    fn test_unreachable_in_block() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "trap")
+                    (block (unreachable)))
+
+                (func (param i32) (result i32)
+                    (if (local.get 0)
+                        (then (unreachable) (i32.const 3) (drop))
+                        (else (nop)))
+                    (loop (block (unreachable) (br 0)))
+                    (i32.const 2))
+
+                (func (export "no_trap") (param i32) (result i32)
+                    (if (local.get 0)
+                        (then (unreachable)))
+                    (i32.const 2))
+            )
+            "#,
+        )
+        .unwrap();
+
+        // Verification fails if an end expects the block holding the `unreachable` to fall through.
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        // Traps unwind through compiled code so it has to be called as `C-unwind`.
+        let trap: extern "C-unwind" fn() =
+            unsafe { mem::transmute(engine.get_function(&module, "trap").unwrap()) };
+        let no_trap: extern "C-unwind" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "no_trap").unwrap()) };
+
+        let error = catch_traps(|| trap()).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<TrapCode>(),
+            Some(&TrapCode::Unreachable)
+        );
+
+        assert_eq!(catch_traps(|| no_trap(0)).unwrap(), 2);
+        assert!(catch_traps(|| no_trap(1)).is_err());
+    }
+
+    #[test]
+    fn test_stack_polymorphic_after_br() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "first") (param i32) (result i32)
+                    (block (result i32)
+                        (br 0 (local.get 0))
+                        (i32.add)
+                        (drop)
+                        (i32.const 1)))
+
+                (func (export "second") (param i32) (result i32)
+                    (block (result i32)
+                        (block (result i32)
+                            (br_table 0 1 (i32.const 10) (local.get 0))
+                            (i32.add))
+                        (i32.const 20)
+                        (i32.add)))
+            )
+            "#,
+        )
+        .unwrap();
+
+        // Operators after an unconditional branch pop values the stack does not have, which is only valid because
+        // they are never reached.
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let first: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "first").unwrap()) };
+        let second: extern "C" fn(i32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "second").unwrap()) };
+
+        assert_eq!(first(7), 7);
+        assert_eq!(second(0), 30);
+        assert_eq!(second(1), 10);
+    }
+
+    #[test]
+    fn test_int_to_float_conversion() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "f64_convert_i64_u") (param i64) (result f64)
+                    local.get 0
+                    f64.convert_i64_u)
+                (func (export "f32_convert_i32_s") (param i32) (result f32)
+                    local.get 0
+                    f32.convert_i32_s)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("uitofp i64 "));
+        assert!(ir.contains("sitofp i32 "));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let f64_convert_i64_u: extern "C" fn(i64) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_convert_i64_u").unwrap()) };
+        let f32_convert_i32_s: extern "C" fn(i32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_convert_i32_s").unwrap()) };
+
+        assert_eq!(f64_convert_i64_u(-1), u64::MAX as f64);
+        assert_eq!(f32_convert_i32_s(-3), -3.0);
+    }
+
+    #[test]
+    fn test_reinterpret() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "f32_bits_plus_one") (param f32) (result i32)
+                    (i32.add (i32.reinterpret_f32 (local.get 0)) (i32.const 1)))
+                (func (export "f64_bits") (param f64) (result i64)
+                    (i64.reinterpret_f64 (local.get 0)))
+                (func (export "f32_from_bits") (param i32) (result f32)
+                    (f32.reinterpret_i32 (local.get 0)))
+                (func (export "f64_from_bits") (param i64) (result f64)
+                    (f64.reinterpret_i64 (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        for (from, to) in [
+            ("float", "i32"),
+            ("double", "i64"),
+            ("i32", "float"),
+            ("i64", "double"),
+        ] {
+            let pattern = format!("bitcast {} ", from);
+            let suffix = format!(" to {}", to);
+            assert!(ir
+                .lines()
+                .any(|line| line.contains(&pattern) && line.ends_with(&suffix)));
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        // The add only finds both operands if the reinterpretation leaves exactly one value on the stack.
+        let f32_bits_plus_one: extern "C" fn(f32) -> i32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_bits_plus_one").unwrap()) };
+        let f64_bits: extern "C" fn(f64) -> i64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_bits").unwrap()) };
+        let f32_from_bits: extern "C" fn(i32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_from_bits").unwrap()) };
+        let f64_from_bits: extern "C" fn(i64) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_from_bits").unwrap()) };
+
+        assert_eq!(f32_bits_plus_one(1.0), 1.0f32.to_bits() as i32 + 1);
+        assert_eq!(f64_bits(-2.5), (-2.5f64).to_bits() as i64);
+        assert_eq!(f32_from_bits(0x4049_0fdb), f32::from_bits(0x4049_0fdb));
+        assert_eq!(f64_from_bits(1), f64::from_bits(1));
+    }
+
+    #[test]
+    fn test_float_min_max() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "f32_min") (param f32 f32) (result f32)
+                    (f32.min (local.get 0) (local.get 1)))
+                (func (export "f32_max") (param f32 f32) (result f32)
+                    (f32.max (local.get 0) (local.get 1)))
+                (func (export "f64_min") (param f64 f64) (result f64)
+                    (f64.min (local.get 0) (local.get 1)))
+                (func (export "f64_max") (param f64 f64) (result f64)
+                    (f64.max (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let f32_min: extern "C" fn(f32, f32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_min").unwrap()) };
+        let f32_max: extern "C" fn(f32, f32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_max").unwrap()) };
+        let f64_min: extern "C" fn(f64, f64) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_min").unwrap()) };
+        let f64_max: extern "C" fn(f64, f64) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_max").unwrap()) };
+
+        // NaN in either operand is propagated, unlike with `minnum` and `maxnum`.
+        for (lhs, rhs) in [(f32::NAN, 1.0), (1.0, f32::NAN), (f32::NAN, f32::NAN)] {
+            assert!(f32_min(lhs, rhs).is_nan());
+            assert!(f32_max(lhs, rhs).is_nan());
+        }
+
+        for (lhs, rhs) in [(f64::NAN, 1.0), (1.0, f64::NAN), (f64::NAN, f64::NAN)] {
+            assert!(f64_min(lhs, rhs).is_nan());
+            assert!(f64_max(lhs, rhs).is_nan());
+        }
+
+        // -0.0 is ordered below +0.0 whichever side it is on.
+        for (lhs, rhs) in [(-0.0, 0.0), (0.0, -0.0)] {
+            assert_eq!(f32_min(lhs, rhs).to_bits(), (-0.0f32).to_bits());
+            assert_eq!(f32_max(lhs, rhs).to_bits(), 0.0f32.to_bits());
+            assert_eq!(
+                f64_min(lhs as f64, rhs as f64).to_bits(),
+                (-0.0f64).to_bits()
+            );
+            assert_eq!(f64_max(lhs as f64, rhs as f64).to_bits(), 0.0f64.to_bits());
+        }
+
+        assert_eq!(f32_min(-1.5, 2.0), -1.5);
+        assert_eq!(f32_max(-1.5, 2.0), 2.0);
+        assert_eq!(f32_min(3.0, 3.0), 3.0);
+        assert_eq!(f32_max(f32::NEG_INFINITY, -1.0), -1.0);
+        assert_eq!(f64_min(2.0, -1.5), -1.5);
+        assert_eq!(f64_max(2.0, -1.5), 2.0);
+        assert_eq!(f64_min(f64::INFINITY, 1.0), 1.0);
+        assert_eq!(f64_max(-0.0, f64::INFINITY), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_float_copysign() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "f32_copysign") (param f32 f32) (result f32)
+                    (f32.copysign (local.get 0) (local.get 1)))
+                (func (export "f64_copysign") (param f64 f64) (result f64)
+                    (f64.copysign (local.get 0) (local.get 1)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        // Both operands are passed to the intrinsic.
+        let ir = String::from_utf8(ir).unwrap();
+        for (ty, suffix) in [("float", "f32"), ("double", "f64")] {
+            let call = format!("call {} @llvm.copysign.{}(", ty, suffix);
+            let line = ir.lines().find(|line| line.contains(&call)).unwrap();
+            assert_eq!(line.matches(&format!("{} %", ty)).count(), 2);
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let f32_copysign: extern "C" fn(f32, f32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_copysign").unwrap()) };
+        let f64_copysign: extern "C" fn(f64, f64) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_copysign").unwrap()) };
+
+        assert_eq!(f32_copysign(1.5, -2.0), -1.5);
+        assert_eq!(f32_copysign(-1.5, 0.0), 1.5);
+        assert_eq!(f32_copysign(0.0, -0.0).to_bits(), (-0.0f32).to_bits());
+        assert!(f32_copysign(f32::NAN, -1.0).is_sign_negative());
+        assert_eq!(f64_copysign(2.5, -0.0), -2.5);
+        assert_eq!(f64_copysign(-2.5, 1.0), 2.5);
+        assert_eq!(f64_copysign(f64::INFINITY, -1.0), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_float_neg() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "f32_neg") (param f32) (result f32)
+                    (f32.neg (local.get 0)))
+                (func (export "f64_neg") (param f64) (result f64)
+                    (f64.neg (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("fneg float "));
+        assert!(ir.contains("fneg double "));
+        assert!(!ir.contains("@llvm.fneg"));
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let f32_neg: extern "C" fn(f32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_neg").unwrap()) };
+        let f64_neg: extern "C" fn(f64) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_neg").unwrap()) };
+
+        assert_eq!(f32_neg(1.5), -1.5);
+        assert_eq!(f32_neg(0.0).to_bits(), (-0.0f32).to_bits());
+        assert_eq!(f64_neg(-2.5), 2.5);
+        assert_eq!(f64_neg(f64::NAN).to_bits(), (-f64::NAN).to_bits());
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (func (export "f64_sub") (param f64 f64) (result f64)
+                    (f64.sub (local.get 0) (local.get 1)))
+                (func (export "f32_arithmetic") (param f32 f32) (result f32)
+                    (f32.div
+                        (f32.mul (f32.add (local.get 0) (local.get 1)) (local.get 1))
+                        (f32.sub (local.get 0) (local.get 1))))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let mut ir = vec![];
+        module.write_ir(&mut ir).unwrap();
+
+        let ir = String::from_utf8(ir).unwrap();
+        assert!(ir.contains("fsub double "));
+        for instruction in ["fadd", "fsub", "fmul", "fdiv"] {
+            assert!(ir.contains(&format!("{} float ", instruction)));
+        }
+
+        let engine = Engine::new().unwrap();
+        engine.load(&module).unwrap();
+
+        let f64_sub: extern "C" fn(f64, f64) -> f64 =
+            unsafe { mem::transmute(engine.get_function(&module, "f64_sub").unwrap()) };
+        let f32_arithmetic: extern "C" fn(f32, f32) -> f32 =
+            unsafe { mem::transmute(engine.get_function(&module, "f32_arithmetic").unwrap()) };
+
+        assert_eq!(f64_sub(0.5, 2.25), -1.75);
+        assert_eq!(f32_arithmetic(3.0, 1.0), 2.0);
+        assert_eq!(f32_arithmetic(1.0, 1.0), f32::INFINITY);
+    }
+}
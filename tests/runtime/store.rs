@@ -0,0 +1,113 @@
+mod test {
+    use std::{mem, rc::Rc};
+
+    use wasmo_runtime::{
+        Imports, Instance, Module, NumVal, Options, Store, StoreLayout, Table, Value,
+    };
+
+    #[test]
+    fn test_patch_memory_base() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (table 1 funcref)
+                (global i32 (i32.const 0))
+                (func)
+                (func)
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let layout = module.store_layout();
+        assert!(layout.functions < layout.memories);
+        assert!(layout.memory_base(0) < layout.tables);
+        assert!(layout.global(0) < layout.size);
+
+        let mut store = Store::new(layout.clone());
+        assert_eq!(
+            store.data_section()[layout.memories..][..4],
+            1_u32.to_ne_bytes()
+        );
+        assert_eq!(store.memory_base(0), Some(0));
+
+        let base = 0xdead_beef_usize;
+        let offset = layout.memory_base(0);
+        store.data_section_mut()[offset..offset + mem::size_of::<usize>()]
+            .copy_from_slice(&base.to_ne_bytes());
+
+        assert_eq!(store.memory_base(0), Some(base));
+        assert_eq!(store.memory_base(1), None);
+    }
+
+    #[test]
+    fn test_patched_memory_base_is_loaded_from() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (export "load") (param i32) (result i32)
+                    (i32.load8_u (local.get 0)))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+        let imports = Imports::default();
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        instance.memory_mut(0).unwrap().data_mut()[8] = 1;
+
+        // A buffer of the size of the memory, which the guest loads from once its address is patched in.
+        let mut buffer = vec![0_u8; 65536];
+        buffer[8] = 42;
+
+        let offset = module.store_layout().memory_base(0);
+        let patch = |instance: &mut Instance, base: usize| {
+            instance.store_mut().unwrap().data_section_mut()
+                [offset..offset + mem::size_of::<usize>()]
+                .copy_from_slice(&base.to_ne_bytes())
+        };
+        patch(&mut instance, buffer.as_mut_ptr() as usize);
+        let address = [Value::Num(NumVal::I32(8))];
+        assert_eq!(
+            instance.call("load", &address).unwrap(),
+            [Value::Num(NumVal::I32(42))]
+        );
+
+        // Clearing the patch points the guest back at its own memory.
+        patch(&mut instance, 0);
+        assert_eq!(
+            instance.call("load", &address).unwrap(),
+            [Value::Num(NumVal::I32(1))]
+        );
+    }
+
+    #[test]
+    fn test_table_keeps_externref_alive() {
+        let mut store = Store::new(StoreLayout::new(0, 0, 0, 0, 0));
+        let mut table = Table::new(2, None);
+
+        let object = Rc::new(String::from("host object"));
+        let weak = Rc::downgrade(&object);
+        let handle = store.externrefs_mut().insert(object);
+
+        table.set(&store, 0, Some(handle)).unwrap();
+        assert_eq!(table.get(0).unwrap(), Some(handle));
+
+        // The table is all that references the object now.
+        store.externrefs_mut().remove(handle);
+        assert!(weak.upgrade().is_some());
+
+        table.set(&store, 0, None).unwrap();
+        assert_eq!(table.get(0).unwrap(), None);
+        assert!(weak.upgrade().is_none());
+
+        let err = table.set(&store, 1, Some(handle)).unwrap_err();
+        assert!(err.to_string().contains("InvalidExternRef"));
+
+        let err = table.get(2).unwrap_err();
+        assert!(err.to_string().contains("OutOfBoundsTable"));
+    }
+}
@@ -0,0 +1,92 @@
+mod test {
+    use std::{cell::RefCell, io::Write, rc::Rc};
+
+    use wasmo_runtime::{
+        Errno, Imports, Instance, Memory, Module, NumVal, Options, TrapCode, Value, WasiCtx,
+    };
+
+    /// A sink that can still be read after being boxed into a `WasiCtx`.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_wasi_fd_write() {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+                (memory (export "memory") 1)
+                ;; An iovec at 0 pointing to "hello world\n" at 16. The byte count is written at 8.
+                (data (i32.const 0) "\10\00\00\00\0c\00\00\00")
+                (data (i32.const 16) "hello world\n")
+                (func (export "write") (param i32) (result i32)
+                    (call $fd_write (local.get 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+                (func (export "exit")
+                    (call $proc_exit (i32.const 3))
+                    (unreachable))
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&wasm, Options::default()).unwrap();
+
+        let stdout = SharedBuffer::default();
+        let mut imports = Imports::default();
+        imports.define_wasi(WasiCtx {
+            stdout: Box::new(stdout.clone()),
+            ..Default::default()
+        });
+
+        let mut instance = Instance::new(&module, &imports).unwrap();
+        let value = |value: i32| [Value::Num(NumVal::I32(value))];
+
+        assert_eq!(
+            instance.call("write", &value(1)).unwrap(),
+            value(Errno::Success as i32)
+        );
+        assert_eq!(
+            instance.call("write", &value(3)).unwrap(),
+            value(Errno::Badf as i32)
+        );
+        assert_eq!(stdout.0.borrow().as_slice(), b"hello world\n");
+        assert_eq!(
+            instance.memory(0).unwrap().data()[8..12],
+            12_u32.to_le_bytes()
+        );
+
+        // Exiting stops the module before it reaches `unreachable`.
+        let err = instance.call("exit", &[]).unwrap_err();
+        assert_eq!(err.downcast_ref::<TrapCode>(), Some(&TrapCode::Exit));
+        drop(instance);
+        assert_eq!(imports.wasi_mut().unwrap().exit_code, Some(3));
+    }
+
+    #[test]
+    fn test_wasi_environ_get() {
+        let wasi = WasiCtx {
+            env: vec![("KEY".into(), "VALUE".into())],
+            ..Default::default()
+        };
+
+        let mut memory = Memory::new(1, None, false).unwrap();
+        assert_eq!(wasi.environ_sizes_get(&mut memory, 0, 4), Errno::Success);
+        assert_eq!(memory.data()[0..4], 1_u32.to_le_bytes());
+        assert_eq!(memory.data()[4..8], 10_u32.to_le_bytes());
+
+        assert_eq!(wasi.environ_get(&mut memory, 8, 16), Errno::Success);
+        assert_eq!(memory.data()[8..12], 16_u32.to_le_bytes());
+        assert_eq!(&memory.data()[16..26], b"KEY=VALUE\0");
+    }
+}